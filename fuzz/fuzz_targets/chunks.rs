@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use miband4::protocol::{encode_chunks, CHUNKED_ALERT};
+
+// the chunks put back together are the payload, and only the last one says it's the last
+fuzz_target!(|payload: &[u8]| {
+    let chunks = encode_chunks(CHUNKED_ALERT, payload);
+    let joined: Vec<u8> = chunks.iter().flat_map(|chunk| chunk[3..].to_vec()).collect();
+    assert_eq!(joined, payload);
+    for (i, chunk) in chunks.iter().enumerate() {
+        assert!(chunk.len() <= 20);
+        assert_eq!(chunk[1] & 0x80 != 0, i == chunks.len() - 1);
+    }
+});
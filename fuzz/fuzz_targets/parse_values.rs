@@ -0,0 +1,23 @@
+#![no_main]
+
+use chrono::Local;
+use libfuzzer_sys::fuzz_target;
+use miband4::protocol::{
+    parse_activity, parse_auth_response, parse_battery, parse_current_activity, parse_device_event,
+    parse_fetch_response, parse_heart_rate, parse_time, parse_workout_summary, response_status
+};
+
+// whatever the band sends, none of these can panic
+fuzz_target!(|value: &[u8]| {
+    parse_time(value);
+    parse_auth_response(value);
+    parse_battery(value);
+    parse_current_activity(value);
+    parse_heart_rate(value);
+    parse_device_event(value);
+    parse_fetch_response(value);
+    parse_workout_summary(value);
+    response_status(value);
+    let samples = parse_activity(Local::now(), value);
+    assert_eq!(samples.len(), value.len() / 4);
+});
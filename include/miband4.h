@@ -0,0 +1,89 @@
+/* libmiband4 - the miband4-gtk protocol stack as GObject types
+ *
+ * Everything runs on the thread-default main context, so a GLib main loop has to be running.
+ * The band has to have been connected to in the app first, so its auth key is saved.
+ */
+
+#ifndef MIBAND4_H
+#define MIBAND4_H
+
+#include <glib-object.h>
+
+G_BEGIN_DECLS
+
+#define MIBAND4_TYPE_SESSION (miband4_session_get_type ())
+G_DECLARE_FINAL_TYPE (MiBand4Session, miband4_session, MIBAND4, SESSION, GObject)
+
+#define MIBAND4_TYPE_DEVICE (miband4_device_get_type ())
+G_DECLARE_FINAL_TYPE (MiBand4Device, miband4_device, MIBAND4, DEVICE, GObject)
+
+#define MIBAND4_TYPE_METRICS (miband4_metrics_get_type ())
+G_DECLARE_FINAL_TYPE (MiBand4Metrics, miband4_metrics, MIBAND4, METRICS, GObject)
+
+/**
+ * miband4_session_new:
+ *
+ * Returns: (transfer full): a session that isn't loaded yet
+ */
+MiBand4Session *miband4_session_new (void);
+
+/**
+ * miband4_session_load:
+ * @session: a #MiBand4Session
+ *
+ * Opens the app's store and connects to BlueZ. #MiBand4Session:ready is set when it's done,
+ * or #MiBand4Session::error is emitted.
+ */
+void miband4_session_load (MiBand4Session *session);
+
+/**
+ * miband4_session_get_saved_bands:
+ * @session: a #MiBand4Session
+ *
+ * Returns: (transfer full) (array zero-terminated=1): the mac addresses of the saved bands
+ */
+char **miband4_session_get_saved_bands (MiBand4Session *session);
+
+/**
+ * miband4_session_get_device:
+ * @session: a #MiBand4Session
+ * @query: a mac address or alias
+ *
+ * Returns: (transfer full) (nullable): the band, or %NULL if the session isn't ready or there's no band called @query
+ */
+MiBand4Device *miband4_session_get_device (MiBand4Session *session, const char *query);
+
+/**
+ * miband4_device_connect_band:
+ * @device: a #MiBand4Device
+ *
+ * Connects to and authenticates with the band. #MiBand4Device:connected and
+ * #MiBand4Device:authenticated change as it goes, and #MiBand4Device::error is emitted if it fails.
+ */
+void miband4_device_connect_band (MiBand4Device *device);
+
+/**
+ * miband4_device_refresh:
+ * @device: a #MiBand4Device
+ *
+ * Reads the battery and current activity into #MiBand4Device:metrics.
+ */
+void miband4_device_refresh (MiBand4Device *device);
+
+/**
+ * miband4_device_send_alert:
+ * @device: a #MiBand4Device
+ * @title: the alert's title
+ * @message: the alert's body
+ */
+void miband4_device_send_alert (MiBand4Device *device, const char *title, const char *message);
+
+/**
+ * miband4_device_disconnect_band:
+ * @device: a #MiBand4Device
+ */
+void miband4_device_disconnect_band (MiBand4Device *device);
+
+G_END_DECLS
+
+#endif
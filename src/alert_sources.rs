@@ -0,0 +1,160 @@
+use std::{error::Error, fmt::Display, time::Duration};
+
+use async_io::Timer;
+use chrono::{DateTime, Local, TimeDelta};
+use futures::{future::LocalBoxFuture, stream::{self, LocalBoxStream}, FutureExt, StreamExt};
+
+use crate::{band::{Alert, AlertType}, calendar::next_event, i18n::{gettext, i18n_f}, notifications::{is_monitor_denied, stream_notifications}};
+
+/// reminders are sent this long before an event starts
+const CALENDAR_REMINDER_LEAD: TimeDelta = TimeDelta::minutes(10);
+/// how often the calendar is checked for events that are about to start
+const CALENDAR_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// an alert produced by an `AlertSource`
+#[derive(Debug, Clone)]
+pub struct SourcedAlert {
+    pub alert_type: AlertType,
+    pub title: String,
+    pub message: String,
+    /// the desktop notification it came from, so it can be closed when it's dismissed on the band
+    pub notification_id: Option<u32>
+}
+
+impl SourcedAlert {
+    pub fn as_alert(&self) -> Alert<'_> {
+        Alert { alert_type: self.alert_type, title: &self.title, message: &self.message }
+    }
+}
+
+#[derive(Debug)]
+pub enum AlertSourceError {
+    /// the source isn't allowed to listen (D-Bus policy, sandboxing, etc)
+    /// retrying won't help until the user changes something
+    Denied(String),
+    /// what the source listens to isn't there on this desktop (no calendar server, etc)
+    /// it's retried quietly in case it shows up later
+    Unavailable(String),
+    Failed(String)
+}
+
+impl Display for AlertSourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Denied(err) => write!(f, "Permission denied: {err}"),
+            Self::Unavailable(err) => write!(f, "Unavailable: {err}"),
+            Self::Failed(err) => write!(f, "{err}")
+        }
+    }
+}
+
+impl Error for AlertSourceError {}
+
+/// something that produces alerts to show on the band (desktop notifications and calls, calendar reminders, ...)
+///
+/// sources are registered with the window, which restarts them with a backoff when they fail
+/// and forwards everything they produce to the current band
+pub trait AlertSource {
+    /// unique - a source is only registered once
+    /// it's also shown to the user, lowercased in the middle of sentences
+    fn name(&self) -> &'static str;
+
+    /// Pango markup explaining why the source might not be allowed to listen and how to fix it
+    /// shown when it fails with `AlertSourceError::Denied`
+    fn denied_help(&self) -> String {
+        i18n_f(
+            "The session bus refused to let this app listen for {}. This usually happens when the app runs inside a sandbox (Flatpak, Snap) or when a D-Bus policy restricts it.",
+            &[&self.name().to_lowercase()]
+        )
+    }
+
+    /// start listening for alerts
+    /// the stream ending means the source lost its connection, and it will be started again
+    fn start(&self) -> LocalBoxFuture<'static, Result<LocalBoxStream<'static, SourcedAlert>, AlertSourceError>>;
+}
+
+/// desktop notifications sent through org.freedesktop.Notifications
+pub struct NotificationSource;
+
+impl AlertSource for NotificationSource {
+    fn name(&self) -> &'static str {
+        "Notifications"
+    }
+
+    fn denied_help(&self) -> String {
+        [
+            gettext("The session bus refused to let this app monitor desktop notifications, so they can't be forwarded to your band. This usually happens when the app runs inside a sandbox (Flatpak, Snap) or when a D-Bus policy restricts <tt>BecomeMonitor</tt>."),
+            gettext("<b>To fix this, try one of the following:</b>\n\
+                • Run the app outside of its sandbox, or grant it access to the session bus (<tt>flatpak override --user --socket=session-bus me.grimsteel.miband4-gtk</tt>)\n\
+                • Make sure your session bus policy allows <tt>org.freedesktop.DBus.Monitoring.BecomeMonitor</tt> for your user\n\
+                • Restart your session after changing the D-Bus configuration")
+        ].join("\n\n")
+    }
+
+    fn start(&self) -> LocalBoxFuture<'static, Result<LocalBoxStream<'static, SourcedAlert>, AlertSourceError>> {
+        async {
+            match stream_notifications().await {
+                Ok(stream) => Ok(stream.map(|notif| SourcedAlert {
+                    // calls vibrate like calls on the band
+                    alert_type: match notif.category.as_deref() {
+                        Some("call.incoming") => AlertType::Call,
+                        Some("call.unanswered") => AlertType::MissedCall,
+                        _ => AlertType::Message
+                    },
+                    title: notif.app,
+                    message: format!("{} - {}", notif.summary, notif.body),
+                    notification_id: Some(notif.id)
+                }).boxed_local()),
+                Err(err) if is_monitor_denied(&err) => Err(AlertSourceError::Denied(err.to_string())),
+                Err(err) => Err(AlertSourceError::Failed(err.to_string()))
+            }
+        }.boxed_local()
+    }
+}
+
+/// reminders for the events in the desktop calendar (through GNOME Shell's calendar server), shortly before they start
+pub struct CalendarSource;
+
+fn calendar_error(err: zbus::Error) -> AlertSourceError {
+    match &err {
+        _ if is_monitor_denied(&err) => AlertSourceError::Denied(err.to_string()),
+        zbus::Error::MethodError(name, _, _) if name.as_str() == "org.freedesktop.DBus.Error.ServiceUnknown" => {
+            AlertSourceError::Unavailable(err.to_string())
+        },
+        _ => AlertSourceError::Failed(err.to_string())
+    }
+}
+
+impl AlertSource for CalendarSource {
+    fn name(&self) -> &'static str {
+        "Calendar reminders"
+    }
+
+    fn start(&self) -> LocalBoxFuture<'static, Result<LocalBoxStream<'static, SourcedAlert>, AlertSourceError>> {
+        async {
+            // make sure the calendar server is there, so a missing one isn't retried every minute
+            next_event(Local::now()).await.map_err(calendar_error)?;
+
+            // (summary, start) of the last event a reminder was sent for
+            let reminded: Option<(String, DateTime<Local>)> = None;
+            Ok(stream::unfold(reminded, |mut reminded| async move {
+                loop {
+                    Timer::after(CALENDAR_CHECK_INTERVAL).await;
+                    // ending the stream gets the source started again
+                    let event = next_event(Local::now() + CALENDAR_REMINDER_LEAD).await.ok()?;
+                    let Some(event) = event else { continue };
+                    let key = Some((event.summary.clone(), event.start));
+                    if reminded == key { continue }
+                    reminded = key;
+                    let alert = SourcedAlert {
+                        alert_type: AlertType::Message,
+                        title: gettext("Calendar"),
+                        message: i18n_f("{} at {}", &[&event.summary, &event.start.format("%H:%M").to_string()]),
+                        notification_id: None
+                    };
+                    return Some((alert, reminded));
+                }
+            }).boxed_local())
+        }.boxed_local()
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use gtk::glib::{self, ChecksumType};
+use pbkdf2::pbkdf2_hmac;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zbus::{proxy, zvariant::Value, Connection};
+
+/// installed from me.grimsteel.miband4-gtk.policy
+const POLKIT_ACTION: &'static str = "me.grimsteel.miband4-gtk.unlock";
+/// POLKIT_CHECK_AUTHORIZATION_FLAGS_ALLOW_USER_INTERACTION - show the password dialog
+const ALLOW_USER_INTERACTION: u32 = 0x01;
+/// PBKDF2-HMAC-SHA256 rounds for the PIN (OWASP's recommendation)
+/// a 4-6 digit PIN has so few possibilities that every guess has to be slow, or the config file gives it away
+const PIN_HASH_ITERATIONS: u32 = 600_000;
+
+#[proxy(default_service = "org.freedesktop.PolicyKit1", default_path = "/org/freedesktop/PolicyKit1/Authority", interface = "org.freedesktop.PolicyKit1.Authority", gen_blocking = false)]
+trait Authority {
+    /// returns (is authorized, is challenge, details)
+    fn check_authorization(
+        &self,
+        subject: &(&str, HashMap<&str, Value<'_>>),
+        action_id: &str,
+        details: &HashMap<&str, &str>,
+        flags: u32,
+        cancellation_id: &str
+    ) -> zbus::Result<(bool, bool, HashMap<String, String>)>;
+}
+
+/// a salted hash of the app PIN - the PIN itself is never stored
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PinHash {
+    salt: String,
+    hash: String,
+    /// PBKDF2 rounds - 0 for hashes from older versions, which are a single salted SHA-256
+    #[serde(default)]
+    iterations: u32
+}
+
+impl PinHash {
+    /// slow on purpose - run it off the main thread
+    pub fn new(pin: &str) -> Self {
+        let salt = glib::uuid_string_random().to_string();
+        Self { hash: hash_pin(&salt, pin, PIN_HASH_ITERATIONS), salt, iterations: PIN_HASH_ITERATIONS }
+    }
+
+    /// slow on purpose - run it off the main thread
+    pub fn verify(&self, pin: &str) -> bool {
+        let hash = hash_pin(&self.salt, pin, self.iterations);
+        // don't give away how much of it matched
+        hash.len() == self.hash.len() && hash.bytes().zip(self.hash.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+
+    /// whether it should be hashed again (once the PIN is known) because it's weaker than new hashes
+    pub fn is_outdated(&self) -> bool {
+        self.iterations < PIN_HASH_ITERATIONS
+    }
+}
+
+/// hex encoded
+fn hash_pin(salt: &str, pin: &str, iterations: u32) -> String {
+    if iterations == 0 {
+        return glib::compute_checksum_for_string(ChecksumType::Sha256, &format!("{salt}{pin}"))
+            .map(|hash| hash.to_string())
+            .unwrap_or_default();
+    }
+    let mut key = [0; 32];
+    pbkdf2_hmac::<Sha256>(pin.as_bytes(), salt.as_bytes(), iterations, &mut key);
+    key.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// ask polkit to authenticate the user (password, fingerprint, etc)
+/// returns whether they were authenticated
+pub async fn authenticate_system() -> zbus::Result<bool> {
+    let conn = Connection::system().await?;
+    let authority = AuthorityProxy::new(&conn).await?;
+
+    // polkit looks up which user/session we are from our bus name
+    let name = conn.unique_name().map(|name| name.to_string()).unwrap_or_default();
+    let subject = ("system-bus-name", HashMap::from([("name", Value::from(name))]));
+    let (authorized, _challenge, _details) = authority
+        .check_authorization(&subject, POLKIT_ACTION, &HashMap::new(), ALLOW_USER_INTERACTION, "")
+        .await?;
+    Ok(authorized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the real iteration count takes too long in debug builds
+    fn quick_hash(pin: &str, iterations: u32) -> PinHash {
+        let salt = "salt".to_string();
+        PinHash { hash: hash_pin(&salt, pin, iterations), salt, iterations }
+    }
+
+    #[test]
+    fn verify() {
+        let hash = quick_hash("1234", 1000);
+        assert!(hash.verify("1234"));
+        assert!(!hash.verify("1243"));
+        assert!(!hash.verify(""));
+        assert!(hash.is_outdated());
+    }
+
+    #[test]
+    fn older_hashes() {
+        // what older versions stored - SHA-256 of the salt and PIN
+        let legacy: PinHash = serde_json::from_str(
+            r#"{"salt": "salt", "hash": "ea32961dbd579ef5697c367f9267921ee07f14d77fb2d4fb9500d4221d615695"}"#
+        ).unwrap();
+        assert_eq!(legacy.iterations, 0);
+        assert!(legacy.is_outdated());
+        assert!(legacy.verify("1234"));
+        assert!(!legacy.verify("4321"));
+    }
+}
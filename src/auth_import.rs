@@ -0,0 +1,91 @@
+use serde_json::Value;
+
+use crate::utils::is_hex_string;
+
+/// field names other apps use for the mac address and auth key
+const MAC_FIELDS: [&'static str; 4] = ["mac", "macAddress", "mac_address", "address"];
+const KEY_FIELDS: [&'static str; 4] = ["auth_key", "authKey", "authkey", "key"];
+
+/// a mac address at the start of `chars`, with `:`, `_`, `-` or nothing between the bytes
+fn mac_at(chars: &[char]) -> Option<String> {
+    let separator = chars.get(2).copied().filter(|c| matches!(c, ':' | '_' | '-'));
+    let step = if separator.is_some() { 3 } else { 2 };
+    let mut bytes = vec![];
+    for i in 0..6 {
+        let byte = chars.get(i * step..i * step + 2)?;
+        if !byte.iter().all(|c| c.is_ascii_hexdigit()) { return None }
+        if i < 5 && separator.is_some() && chars.get(i * step + 2) != separator.as_ref() { return None }
+        bytes.push(byte.iter().collect::<String>().to_uppercase());
+    }
+    Some(bytes.join(":"))
+}
+
+/// a whole string that's a mac address, in the AA:BB:CC:DD:EE:FF form BlueZ uses
+fn parse_mac(value: &str) -> Option<String> {
+    let chars: Vec<char> = value.trim().chars().collect();
+    // 12 digits, or 17 with separators
+    if chars.len() != 12 && chars.len() != 17 { return None }
+    mac_at(&chars)
+}
+
+/// the last mac address somewhere in `text` (e.g. a file name)
+fn find_mac(text: &str) -> Option<String> {
+    let chars: Vec<char> = text.chars().collect();
+    (0..chars.len()).rev().find_map(|start| mac_at(&chars[start..]))
+}
+
+/// 32 hex digits, with or without a 0x
+pub fn parse_key(value: &str) -> Option<String> {
+    let value = value.trim();
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    (value.len() == 32 && is_hex_string(value)).then(|| value.to_lowercase())
+}
+
+/// any object with both a mac address and an auth key, wherever it is in the file
+fn keys_from_json(value: &Value, keys: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) => {
+            let mac = MAC_FIELDS.iter().find_map(|field| map.get(*field)?.as_str().and_then(parse_mac));
+            let key = KEY_FIELDS.iter().find_map(|field| map.get(*field)?.as_str().and_then(parse_key))
+                // the Zepp API has it in JSON in a string
+                .or_else(|| {
+                    let info: Value = serde_json::from_str(map.get("additionalInfo")?.as_str()?).ok()?;
+                    info["auth_key"].as_str().and_then(parse_key)
+                });
+            if let (Some(mac), Some(key)) = (mac, key) {
+                keys.push((mac, key));
+            }
+            map.values().for_each(|value| keys_from_json(value, keys));
+        },
+        Value::Array(items) => items.iter().for_each(|value| keys_from_json(value, keys)),
+        _ => {}
+    }
+}
+
+/// Gadgetbridge keeps the key in the band's shared preferences, which are named after its mac address
+fn key_from_preferences(contents: &str) -> Option<String> {
+    let start = contents.find("name=\"authkey\"")?;
+    let value = &contents[start..];
+    let value = &value[value.find('>')? + 1..];
+    parse_key(&value[..value.find('<')?])
+}
+
+/// the (mac address, auth key) pair from a Gadgetbridge preferences export
+pub fn parse_gadgetbridge_keys(file_name: &str, contents: &str) -> Vec<(String, String)> {
+    match (find_mac(file_name), key_from_preferences(contents)) {
+        (Some(mac), Some(key)) => vec![(mac, key)],
+        _ => vec![]
+    }
+}
+
+/// (mac address, auth key) pairs from a Gadgetbridge preferences export or a JSON file
+/// (freemybuddy, huami-token, or anything else with mac addresses and keys next to each other)
+pub fn parse_auth_keys(file_name: &str, contents: &str) -> Vec<(String, String)> {
+    if let Ok(json) = serde_json::from_str::<Value>(contents) {
+        let mut keys = vec![];
+        keys_from_json(&json, &mut keys);
+        keys.dedup();
+        return keys;
+    }
+    parse_gadgetbridge_keys(file_name, contents)
+}
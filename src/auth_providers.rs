@@ -0,0 +1,214 @@
+use std::{error::Error, fmt::Display, rc::Rc};
+
+use futures::{future::LocalBoxFuture, FutureExt};
+
+use crate::{auth_import::{parse_auth_keys, parse_gadgetbridge_keys, parse_key}, huami::{fetch_auth_key, Credentials}, i18n::{gettext, i18n_f, n_}, secrets::SecretStore, store::SECRET_AUTH_KEY};
+
+/// what an `AuthProvider` needs the user to fill in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthInputKind {
+    /// a key typed or pasted in
+    Key,
+    /// a Zepp account's email and password, or an access token
+    Account,
+    /// a file to read keys from
+    File,
+    Nothing
+}
+
+/// what the user filled in - matches the provider's `AuthInputKind`
+pub enum AuthInput {
+    Key(String),
+    Account(Credentials),
+    File { name: String, contents: String },
+    Nothing
+}
+
+#[derive(Debug)]
+pub enum AuthProviderError {
+    /// what was filled in can't be used (e.g. a key that isn't 32 hex digits)
+    Invalid(String),
+    /// it worked, but there wasn't a key for the band
+    NotFound,
+    Failed(String)
+}
+
+impl Display for AuthProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Invalid(err) => write!(f, "{err}"),
+            Self::NotFound => write!(f, "{}", gettext("No auth key was found for this band")),
+            Self::Failed(err) => write!(f, "{err}")
+        }
+    }
+}
+
+impl Error for AuthProviderError {}
+
+pub type AuthKeys = Vec<(String, String)>;
+
+/// somewhere to get a band's auth key from
+///
+/// the auth key dialog lists every provider and asks for its input;
+/// the window saves whatever keys it returns the same way, whichever provider it was
+pub trait AuthProvider {
+    /// shown in the list of providers (marked with `n_` - the dialog translates it)
+    fn name(&self) -> &'static str;
+    /// shown under the list when it's chosen
+    fn description(&self) -> &'static str;
+    fn input_kind(&self) -> AuthInputKind;
+
+    /// (mac address, auth key) pairs - these can be for other bands too, and the window saves the ones it knows about
+    fn fetch(&self, address: String, input: AuthInput) -> LocalBoxFuture<'static, Result<AuthKeys, AuthProviderError>>;
+}
+
+fn unexpected_input() -> AuthProviderError {
+    AuthProviderError::Failed(gettext("The provider was given the wrong kind of input"))
+}
+
+fn non_empty(keys: AuthKeys) -> Result<AuthKeys, AuthProviderError> {
+    if keys.is_empty() { Err(AuthProviderError::NotFound) } else { Ok(keys) }
+}
+
+/// typed in
+pub struct ManualProvider;
+
+impl AuthProvider for ManualProvider {
+    fn name(&self) -> &'static str {
+        n_("Enter it")
+    }
+
+    fn description(&self) -> &'static str {
+        n_("Paste the 32 hex digit key (e.g. from Gadgetbridge's device settings)")
+    }
+
+    fn input_kind(&self) -> AuthInputKind {
+        AuthInputKind::Key
+    }
+
+    fn fetch(&self, address: String, input: AuthInput) -> LocalBoxFuture<'static, Result<AuthKeys, AuthProviderError>> {
+        let result = match input {
+            AuthInput::Key(key) => parse_key(&key)
+                .map(|key| vec![(address, key)])
+                .ok_or_else(|| AuthProviderError::Invalid(gettext("The auth key has to be 32 hex digits"))),
+            _ => Err(unexpected_input())
+        };
+        async move { result }.boxed_local()
+    }
+}
+
+/// saved in the Secret Service before (e.g. the band was forgotten, or bands.json was deleted)
+pub struct KeyringProvider;
+
+impl AuthProvider for KeyringProvider {
+    fn name(&self) -> &'static str {
+        n_("Keyring")
+    }
+
+    fn description(&self) -> &'static str {
+        n_("Use the key saved in the login keyring the last time this band was set up")
+    }
+
+    fn input_kind(&self) -> AuthInputKind {
+        AuthInputKind::Nothing
+    }
+
+    fn fetch(&self, address: String, _input: AuthInput) -> LocalBoxFuture<'static, Result<AuthKeys, AuthProviderError>> {
+        async move {
+            let secrets = SecretStore::connect().await
+                .map_err(|err| AuthProviderError::Failed(i18n_f("The keyring isn't available: {}", &[&err.to_string()])))?;
+            match secrets.get(SECRET_AUTH_KEY, &address).await {
+                Ok(Some(key)) => Ok(vec![(address, key)]),
+                Ok(None) => Err(AuthProviderError::NotFound),
+                Err(err) => Err(AuthProviderError::Failed(err.to_string()))
+            }
+        }.boxed_local()
+    }
+}
+
+/// signs in to the Zepp account the band is paired with
+pub struct HuamiProvider;
+
+impl AuthProvider for HuamiProvider {
+    fn name(&self) -> &'static str {
+        n_("Zepp account")
+    }
+
+    fn description(&self) -> &'static str {
+        n_("Sign in to the Zepp (Amazfit) account the band is paired with. Your email and password are only sent to Zepp and aren't saved.")
+    }
+
+    fn input_kind(&self) -> AuthInputKind {
+        AuthInputKind::Account
+    }
+
+    fn fetch(&self, address: String, input: AuthInput) -> LocalBoxFuture<'static, Result<AuthKeys, AuthProviderError>> {
+        async move {
+            let AuthInput::Account(credentials) = input else { return Err(unexpected_input()) };
+            fetch_auth_key(credentials, address.clone()).await
+                .map(|key| vec![(address, key)])
+                .map_err(|err| AuthProviderError::Failed(err.to_string()))
+        }.boxed_local()
+    }
+}
+
+/// the shared preferences Gadgetbridge exports for each band
+pub struct GadgetbridgeProvider;
+
+impl AuthProvider for GadgetbridgeProvider {
+    fn name(&self) -> &'static str {
+        n_("Gadgetbridge export")
+    }
+
+    fn description(&self) -> &'static str {
+        n_("Choose the band's preferences file (named after its mac address) from a Gadgetbridge export")
+    }
+
+    fn input_kind(&self) -> AuthInputKind {
+        AuthInputKind::File
+    }
+
+    fn fetch(&self, _address: String, input: AuthInput) -> LocalBoxFuture<'static, Result<AuthKeys, AuthProviderError>> {
+        let result = match input {
+            AuthInput::File { name, contents } => non_empty(parse_gadgetbridge_keys(&name, &contents)),
+            _ => Err(unexpected_input())
+        };
+        async move { result }.boxed_local()
+    }
+}
+
+/// a JSON file from another tool (freemybuddy, huami-token, ...)
+pub struct FileProvider;
+
+impl AuthProvider for FileProvider {
+    fn name(&self) -> &'static str {
+        n_("File")
+    }
+
+    fn description(&self) -> &'static str {
+        n_("Choose a JSON file with mac addresses and auth keys - keys for other bands in it are saved too")
+    }
+
+    fn input_kind(&self) -> AuthInputKind {
+        AuthInputKind::File
+    }
+
+    fn fetch(&self, _address: String, input: AuthInput) -> LocalBoxFuture<'static, Result<AuthKeys, AuthProviderError>> {
+        let result = match input {
+            AuthInput::File { name, contents } => non_empty(parse_auth_keys(&name, &contents)),
+            _ => Err(unexpected_input())
+        };
+        async move { result }.boxed_local()
+    }
+}
+
+/// every provider, in the order they're listed
+pub fn auth_providers() -> Vec<Rc<dyn AuthProvider>> {
+    vec![
+        Rc::new(ManualProvider),
+        Rc::new(HuamiProvider),
+        Rc::new(KeyringProvider),
+        Rc::new(GadgetbridgeProvider),
+        Rc::new(FileProvider)
+    ]
+}
@@ -1,39 +1,73 @@
-use std::{error::Error, fmt::Display, io, pin::Pin, task::{Context, Poll}};
+use std::{cell::{Cell, RefCell}, error::Error, fmt::Display, future::Future, io, pin::Pin, task::{Context, Poll}, time::Duration};
 
-use async_net::unix::UnixStream;
-use chrono::{DateTime, Datelike, Local, TimeZone, Timelike};
-use futures::{stream::select, AsyncRead, AsyncReadExt, AsyncWriteExt, Stream, StreamExt};
+use async_io::Timer;
+use chrono::{DateTime, Local, TimeDelta};
+use futures::{future::pending, pin_mut, select, stream::{select, BoxStream}, AsyncWriteExt, FutureExt, Stream, StreamExt};
+use log::warn;
 use zbus::zvariant::{ObjectPath, OwnedObjectPath};
 
-use crate::{bluez::{BluezSession, DeviceProxy, DiscoveredDevice, DiscoveredDeviceEvent, DiscoveryFilter, GattCharacteristicProxy}, mpris::{MediaInfo, MediaState}, store::{self, ActivityGoal, BandLock}, utils::encrypt_value};
+use crate::{bluez::{bluez_error_name, BluezSession, DeviceProxy, DiscoveredDevice, DeviceServiceChars, DeviceServicePaths, DiscoveredDeviceEvent, DiscoveryFilter, GattCharacteristicProxy, GattTransport, NotifySubscription}, mpris::MediaInfo, protocol::{encode_activity_goal, encode_auth_answer, encode_band_lock, encode_chunks, encode_config_query, encode_date_display, encode_distance_unit, encode_do_not_disturb, encode_fetch_request, encode_media_info, encode_time, encode_time_format, is_config_response, parse_activity, parse_auth_response, parse_band_lock, parse_config_value, parse_date_display, parse_distance_unit, parse_goal_notifications, parse_step_goal, parse_time_format, parse_fetch_response, parse_device_event, parse_heart_rate, parse_time, parse_workout_summary, response_status, AuthResponse, FetchResponse, ALERT_WRITE_LIMIT, AUTH_REQUEST_CHALLENGE, CHUNKED_ALERT, CHUNKED_MUSIC, COMMAND_FACTORY_RESET, COMMAND_FETCH_START, COMMAND_FETCH_STOP, CONFIG_BAND_LOCK, CONFIG_DATE_DISPLAY, CONFIG_DISTANCE_UNIT, CONFIG_GOAL_NOTIFICATIONS, CONFIG_TIME_FORMAT, COMMAND_REBOOT, STATUS_BUSY, STATUS_LOW_BATTERY, STATUS_NOT_AUTHORIZED, STATUS_SUCCESS, TIME_LENGTH}, store::{self, ActivityGoal, BandLock, DateDisplay, DisplayFormat, DistanceUnit, IoPolicy, TimeFormat}, trace::{self, TraceKind}};
+
+mod model;
+
+pub use model::{BandModel, BandProtocol};
 
 const SERVICE_BAND_0: &'static str = "0000fee0-0000-1000-8000-00805f9b34fb";
 const SERVICE_BAND_1: &'static str = "0000fee1-0000-1000-8000-00805f9b34fb";
 const SERVICE_DEVICE_INFO: &'static str = "0000180a-0000-1000-8000-00805f9b34fb";
 const SERVICE_NOTIFICATION: &'static str = "00001811-0000-1000-8000-00805f9b34fb";
-const CHAR_BATTERY: &'static str = "00000006-0000-3512-2118-0009af100700";
+const SERVICE_HEART_RATE: &'static str = "0000180d-0000-1000-8000-00805f9b34fb";
+const SERVICE_FIRMWARE: &'static str = "00001530-0000-3512-2118-0009af100700";
+pub const CHAR_BATTERY: &'static str = "00000006-0000-3512-2118-0009af100700";
 const CHAR_STEPS: &'static str = "00000007-0000-3512-2118-0009af100700";
-const CHAR_AUTH: &'static str = "00000009-0000-3512-2118-0009af100700";
+pub const CHAR_AUTH: &'static str = "00000009-0000-3512-2118-0009af100700";
 const CHAR_SOFT_REV: &'static str = "00002a28-0000-1000-8000-00805f9b34fb";
-const CHAR_TIME: &'static str = "00002a2b-0000-1000-8000-00805f9b34fb";
+const CHAR_SERIAL: &'static str = "00002a25-0000-1000-8000-00805f9b34fb";
+pub const CHAR_TIME: &'static str = "00002a2b-0000-1000-8000-00805f9b34fb";
 const CHAR_CONFIG: &'static str = "00000003-0000-3512-2118-0009af100700";
 const CHAR_SETTINGS: &'static str = "00000008-0000-3512-2118-0009af100700";
 const CHAR_ALERT: &'static str = "00002a46-0000-1000-8000-00805f9b34fb";
 const CHAR_CHUNKED_TRANSFER: &'static str = "00000020-0000-3512-2118-0009af100700";
 const CHAR_MUSIC_NOTIFICATIONS: &'static str = "00000010-0000-3512-2118-0009af100700";
+const CHAR_HEART_RATE_MEASURE: &'static str = "00002a37-0000-1000-8000-00805f9b34fb";
+const CHAR_HEART_RATE_CONTROL: &'static str = "00002a39-0000-1000-8000-00805f9b34fb";
+pub const CHAR_FETCH: &'static str = "00000004-0000-3512-2118-0009af100700";
+pub const CHAR_ACTIVITY_DATA: &'static str = "00000005-0000-3512-2118-0009af100700";
+const CHAR_FIRMWARE: &'static str = "00001531-0000-3512-2118-0009af100700";
+
+/// data types for the fetch characteristic
+const FETCH_ACTIVITY: u8 = 0x01;
+const FETCH_SPORTS_SUMMARIES: u8 = 0x05;
+/// give up on a fetch if the band goes quiet for this long
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// wait this long before trying a characteristic operation again
+const IO_RETRY_DELAY: Duration = Duration::from_millis(500);
+/// stop after this many workouts in one go
+const MAX_WORKOUTS_PER_FETCH: usize = 100;
+
+/// continuous heart rate measurement stops unless the band is pinged this often
+pub const HEART_RATE_PING_INTERVAL: Duration = Duration::from_secs(12);
 
 #[derive(Debug)]
-struct BandChars<'a> {
-    battery: GattCharacteristicProxy<'a>,
-    steps: GattCharacteristicProxy<'a>,
-    firm_rev: GattCharacteristicProxy<'a>,
-    time: GattCharacteristicProxy<'a>,
-    auth: GattCharacteristicProxy<'a>,
-    config: GattCharacteristicProxy<'a>,
-    settings: GattCharacteristicProxy<'a>,
-    alert: GattCharacteristicProxy<'a>,
-    chunked_transfer: GattCharacteristicProxy<'a>,
-    music_notifs: GattCharacteristicProxy<'a>
+struct BandChars<C: GattTransport> {
+    battery: C,
+    steps: C,
+    firm_rev: C,
+    /// not every firmware has it, so it isn't required
+    serial: Option<C>,
+    time: C,
+    auth: C,
+    config: C,
+    settings: C,
+    alert: C,
+    chunked_transfer: C,
+    music_notifs: C,
+    heart_rate_measure: C,
+    heart_rate_control: C,
+    fetch: C,
+    activity_data: C,
+    /// only used for rebooting, so it isn't required either
+    firmware: Option<C>
 }
 
 #[derive(Debug)]
@@ -47,7 +81,24 @@ pub enum BandError {
     Utf8Error,
     RequiresAuth,
     InvalidAuthKey,
-    InvalidLockPin,
+    /// a step goal the band can't store
+    InvalidGoal,
+    /// a band lock PIN the band wouldn't accept
+    InvalidPin,
+    SettingNotAdoptable,
+    FetchFailed,
+    /// a value from the band was too short to parse (the whole value is kept)
+    MalformedResponse(Vec<u8>),
+    /// the band is in the middle of something else - trying again later usually works
+    Busy,
+    LowBattery,
+    NotAuthorized,
+    /// the band answered with a status we don't know (the whole response is kept)
+    ErrorResponse(Vec<u8>),
+    /// a read/write/notify didn't finish in time (the band is probably out of range)
+    Timeout,
+    /// the band was closed (disconnected or replaced) while this was running
+    Cancelled,
     //Failed,
     //UnknownError
 }
@@ -83,7 +134,23 @@ impl Display for BandError {
             Self::Utf8Error => write!(f, "Device sent invalid UTF-8 text"),
             Self::RequiresAuth => write!(f, "The operation requires authentication"),
             Self::InvalidAuthKey => write!(f, "Invalid auth key"),
-            Self::InvalidLockPin => write!(f, "Invalid band lock PIN (must be 4 digits from 1-4)"),
+            Self::InvalidGoal => write!(f, "The step goal is too high for the band"),
+            Self::InvalidPin => write!(f, "The band lock PIN has to be 4 digits from 1 to 4"),
+            Self::SettingNotAdoptable => write!(f, "This setting can't be read back from the band"),
+            Self::FetchFailed => write!(f, "The band stopped sending data partway through"),
+            Self::MalformedResponse(raw) => write!(
+                f, "Device sent an incomplete value ({})",
+                raw.iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>().join(" ")
+            ),
+            Self::Busy => write!(f, "The band is busy - try again in a moment"),
+            Self::LowBattery => write!(f, "The band's battery is too low - charge it and try again"),
+            Self::NotAuthorized => write!(f, "The band refused the request - try entering the auth key again"),
+            Self::ErrorResponse(raw) => write!(
+                f, "The band responded with an error ({})",
+                raw.iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>().join(" ")
+            ),
+            Self::Timeout => write!(f, "The band didn't respond in time - move it closer and try again"),
+            Self::Cancelled => write!(f, "The band was closed before the operation finished"),
             //Self::Failed => write!(f, "The operation failed"),
             //Self::UnknownError => write!(f, "An unknown error occurred")
         }
@@ -92,18 +159,104 @@ impl Display for BandError {
 
 impl Error for BandError {}
 
+impl BandError {
+    /// the error for a 0x10 <command> <status> response, or `None` if it was successful (or isn't a response)
+    pub fn from_response(raw: &[u8]) -> Option<Self> {
+        match response_status(raw)? {
+            STATUS_SUCCESS => None,
+            STATUS_BUSY => Some(Self::Busy),
+            STATUS_LOW_BATTERY => Some(Self::LowBattery),
+            STATUS_NOT_AUTHORIZED => Some(Self::NotAuthorized),
+            _ => Some(Self::ErrorResponse(raw.to_vec()))
+        }
+    }
+
+    /// whether trying the operation again might work
+    /// a write that timed out may still have reached the band, so it's only repeated if `repeatable`
+    fn is_transient(&self, repeatable: bool) -> bool {
+        match self {
+            Self::Timeout => repeatable,
+            // BlueZ is still busy with the previous operation - this one never started
+            Self::DBusError(err) => bluez_error_name(err) == Some("InProgress"),
+            _ => false
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, BandError>;
 
+/// how much of a history fetch has arrived
+#[derive(Debug, Copy, Clone)]
+pub struct FetchProgress {
+    /// when the first sample was recorded
+    pub start: DateTime<Local>,
+    pub bytes: usize
+}
+
+impl FetchProgress {
+    /// activity samples are 4 bytes each
+    pub fn activity_samples(&self) -> usize {
+        self.bytes / 4
+    }
+
+    /// when the most recently received activity sample was recorded
+    pub fn activity_time(&self) -> DateTime<Local> {
+        self.start + TimeDelta::minutes(self.activity_samples() as i64)
+    }
+}
+
+/// what the band is doing, for showing why things can't be used yet
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum BandState {
+    #[default]
+    Disconnected,
+    Connecting,
+    Authenticating,
+    Connected,
+    /// downloading history
+    Syncing
+}
+
+impl BandState {
+    /// whether it's in the middle of something that other operations have to wait for
+    pub fn is_busy(self) -> bool {
+        matches!(self, Self::Connecting | Self::Authenticating | Self::Syncing)
+    }
+}
+
+/// the pairing state at the BlueZ level - separate from the band's own auth
+#[derive(Debug, Copy, Clone)]
+pub struct BondState {
+    pub paired: bool,
+    pub bonded: bool,
+    pub trusted: bool
+}
+
+/// `C` is how the characteristics are reached - the BlueZ proxies, unless it's running against `MemoryCharacteristic`s
 #[derive(Debug)]
-pub struct MiBand<'a> {
+pub struct MiBand<'a, C: GattTransport = GattCharacteristicProxy<'a>> {
     session: BluezSession<'a>,
     device: DeviceProxy<'a>,
     pub authenticated: bool,
-    chars: Option<BandChars<'a>>,
+    chars: Option<BandChars<C>>,
+    /// where the characteristics were found, so they don't have to be looked up again next time
+    gatt_paths: Option<DeviceServicePaths>,
+    io_policy: IoPolicy,
+    simplify_alerts: bool,
+    model: BandModel,
+    /// receives something (or closes) once the band is closed, to stop whatever is still running
+    cancel: Option<async_channel::Receiver<()>>,
+    /// closed by `cancel_pending` to stop what's running now, and replaced with a new one for what comes after
+    pending: RefCell<(async_channel::Sender<()>, async_channel::Receiver<()>)>,
+    state: Cell<BandState>,
+    /// set while a fetch is running - the band can only send one thing at a time
+    fetching: Cell<bool>,
+    /// everything from `stream_state` - they end when the band is dropped
+    state_listeners: RefCell<Vec<async_channel::Sender<BandState>>>,
     pub address: String
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BatteryStatus {
     pub battery_level: u8,
     //pub last_off: DateTime<Local>,
@@ -111,20 +264,114 @@ pub struct BatteryStatus {
     pub charging: bool
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CurrentActivity {
     pub steps: u16,
     pub calories: u16,
     pub meters: u16
 }
 
+#[derive(Debug, Copy, Clone)]
+pub struct HeartRate {
+    pub bpm: u16
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WorkoutKind {
+    OutdoorRun,
+    Treadmill,
+    Walking,
+    OutdoorCycling,
+    Exercise,
+    PoolSwimming,
+    OpenWaterSwimming,
+    IndoorCycling,
+    Elliptical,
+    Unknown(u16)
+}
+
+impl From<u16> for WorkoutKind {
+    fn from(value: u16) -> Self {
+        match value {
+            0x01 => Self::OutdoorRun,
+            0x02 => Self::Treadmill,
+            0x03 => Self::Walking,
+            0x04 => Self::OutdoorCycling,
+            0x05 => Self::Exercise,
+            0x06 => Self::PoolSwimming,
+            0x07 => Self::OpenWaterSwimming,
+            0x08 => Self::IndoorCycling,
+            0x09 => Self::Elliptical,
+            other => Self::Unknown(other)
+        }
+    }
+}
+
+impl From<WorkoutKind> for u16 {
+    fn from(value: WorkoutKind) -> Self {
+        match value {
+            WorkoutKind::OutdoorRun => 0x01,
+            WorkoutKind::Treadmill => 0x02,
+            WorkoutKind::Walking => 0x03,
+            WorkoutKind::OutdoorCycling => 0x04,
+            WorkoutKind::Exercise => 0x05,
+            WorkoutKind::PoolSwimming => 0x06,
+            WorkoutKind::OpenWaterSwimming => 0x07,
+            WorkoutKind::IndoorCycling => 0x08,
+            WorkoutKind::Elliptical => 0x09,
+            WorkoutKind::Unknown(code) => code
+        }
+    }
+}
+
+impl Display for WorkoutKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OutdoorRun => write!(f, "Outdoor Run"),
+            Self::Treadmill => write!(f, "Treadmill"),
+            Self::Walking => write!(f, "Walking"),
+            Self::OutdoorCycling => write!(f, "Outdoor Cycling"),
+            Self::Exercise => write!(f, "Exercise"),
+            Self::PoolSwimming => write!(f, "Pool Swimming"),
+            Self::OpenWaterSwimming => write!(f, "Open Water Swimming"),
+            Self::IndoorCycling => write!(f, "Indoor Cycling"),
+            Self::Elliptical => write!(f, "Elliptical"),
+            Self::Unknown(code) => write!(f, "Workout (0x{code:02x})")
+        }
+    }
+}
+
+/// a workout recorded on the band itself
+#[derive(Debug, Clone)]
+pub struct WorkoutSummary {
+    pub kind: WorkoutKind,
+    pub start: DateTime<Local>,
+    /// active time (excludes pauses)
+    pub duration: Duration,
+    /// in meters
+    pub distance: f32,
+    pub calories: u16,
+    pub avg_hr: Option<u16>
+}
+
+/// one minute of recorded activity
+#[derive(Debug, Clone)]
+pub struct ActivitySample {
+    pub time: DateTime<Local>,
+    /// raw activity kind (walking, sleeping, not worn, etc.)
+    pub kind: u8,
+    pub intensity: u8,
+    pub steps: u8,
+    pub heart_rate: Option<u8>
+}
+
 #[derive(Debug)]
 pub enum BandChangeEvent {
     RSSI(Option<i16>),
     Connected(bool)
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum AlertType {
     Mail = 0x01,
     Call = 0x03,
@@ -144,100 +391,281 @@ pub enum MusicEvent {
     Open,
     Close,
 
-    PlayPause,
+    Play,
+    Pause,
     Next,
     Previous,
     VolumeUp,
-    VolumeDown
+    VolumeDown,
+    /// control the next player instead (the band's button was held down)
+    SwitchPlayer
 }
 
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DeviceEvent {
+    Music(MusicEvent),
+    /// the band stopped detecting a wrist
+    RemovedFromWrist,
+    FellAsleep,
+    WokeUp,
+    /// the daily step goal was reached
+    GoalReached,
+    ButtonPressed,
+    ButtonLongPressed,
+    /// the alert being shown was dismissed with its ignore button
+    /// the band doesn't say which alert it was
+    AlertDismissed
+}
 
-/// A `Stream` implementation for music events from a band
-#[derive(Debug)]
-pub struct MusicEventListener {
-    notify_stream: UnixStream,
-    mtu: usize
+/// A `Stream` implementation for music and other device events from a band
+pub struct DeviceEventListener {
+    notifications: BoxStream<'static, Vec<u8>>
 }
 
-impl Stream for MusicEventListener {
-    type Item = Option<MusicEvent>;
+impl Stream for DeviceEventListener {
+    type Item = Option<DeviceEvent>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let mut buf = vec![0; self.mtu];
-        let result = Pin::new(&mut self.get_mut().notify_stream).poll_read(cx, &mut buf);
-        let result = result.map(move |value| -> io::Result<Option<MusicEvent>> {
-            // when this function returns an Err, that means the stream must end
-            let size = value?;
-            let buf = &buf[..size];
-            // Ok(None) means we don't recognize the data it gave us
-            if size < 2 { return Ok(None) }
-            Ok(match buf[1] {
-                0xe0 => Some(MusicEvent::Open),
-                0xe1 => Some(MusicEvent::Close),
-                0x00 | 0x01 => Some(MusicEvent::PlayPause),
-                0x03 => Some(MusicEvent::Next),
-                0x04 => Some(MusicEvent::Previous),
-                0x05 => Some(MusicEvent::VolumeUp),
-                0x06 => Some(MusicEvent::VolumeDown),
-                _ => None
-            })
-        });
+        // the stream ends when the notifications do
+        // `Some(None)` means the band sent data we don't recognize, but it's not fatal
+        self.get_mut().notifications.poll_next_unpin(cx)
+            .map(|value| value.map(|buf| parse_device_event(&buf)))
+    }
+}
 
-        match result {
-            // fatal - stream must end
-            Poll::Ready(Err(_)) => Poll::Ready(None),
-            // the band sent data we don't recognize, but it's not fatal
-            // we just don't have data to send
-            Poll::Ready(Ok(a)) => Poll::Ready(Some(a)),
-            Poll::Pending => Poll::Pending
-        }
+/// A `Stream` implementation for heart rate measurements from a band
+pub struct HeartRateListener {
+    notifications: BoxStream<'static, Vec<u8>>
+}
+
+impl Stream for HeartRateListener {
+    type Item = Option<HeartRate>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().notifications.poll_next_unpin(cx)
+            .map(|value| value.map(|buf| parse_heart_rate(&buf)))
     }
 }
 
-/// parse a time out of a 7 byte array
-fn parse_time(value: &[u8]) -> Option<DateTime<Local>> {
-    if value.len() < 7 { return None }
-    
-    let year = (value[0] as u16) | ((value[1] as u16) << 8);
-    let month = value[2];
-    let day = value[3];
-    let hour = value[4];
-    let minute = value[5];
-    let second = value[6];
+/// A `Stream` implementation for battery updates from a band
+pub struct BatteryListener {
+    notifications: BoxStream<'static, Vec<u8>>,
+    protocol: &'static dyn BandProtocol
+}
 
-    let time = Local.with_ymd_and_hms(year.into(), month.into(), day.into(), hour.into(), minute.into(), second.into());
-    match time {
-        chrono::offset::LocalResult::Single(time) => Some(time),
-        _ => None
+impl Stream for BatteryListener {
+    type Item = Option<BatteryStatus>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.notifications.poll_next_unpin(cx)
+            .map(|value| value.map(|buf| this.protocol.parse_battery(&buf)))
     }
 }
 
-impl<'a> MiBand<'a> {
-    pub async fn from_discovered_device<'b>(session: BluezSession<'a>, device: DiscoveredDevice) -> Result<Self> {
-        let device_proxy = session.proxy_from_discovered_device(device.path).await?;
-        Ok(Self {
-            device: device_proxy,
+/// A `Stream` implementation for step count/distance/calorie updates from a band
+pub struct ActivityListener {
+    notifications: BoxStream<'static, Vec<u8>>,
+    protocol: &'static dyn BandProtocol
+}
+
+impl Stream for ActivityListener {
+    type Item = Option<CurrentActivity>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.notifications.poll_next_unpin(cx)
+            .map(|value| value.map(|buf| this.protocol.parse_current_activity(&buf)))
+    }
+}
+
+/// clears the fetching flag when the fetch ends, however it ends
+struct FetchGuard<'b>(&'b Cell<bool>);
+
+impl<'b> FetchGuard<'b> {
+    /// `None` if another fetch is already running
+    fn acquire(fetching: &'b Cell<bool>) -> Option<Self> {
+        if fetching.replace(true) { None } else { Some(Self(fetching)) }
+    }
+}
+
+impl Drop for FetchGuard<'_> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+/// read a single notification, failing if the band doesn't send anything for `FETCH_TIMEOUT`
+async fn read_with_timeout(notifications: &NotifySubscription) -> Result<Vec<u8>> {
+    select! {
+        value = notifications.recv().fuse() => Ok(value?),
+        _ = Timer::after(FETCH_TIMEOUT).fuse() => Err(BandError::FetchFailed)
+    }
+}
+
+/// fail with `BandError::Timeout` if `op` doesn't finish within `timeout`
+pub async fn with_timeout<T, E: Into<BandError>>(timeout: Duration, op: impl Future<Output = std::result::Result<T, E>>) -> Result<T> {
+    let op = op.fuse();
+    let timer = Timer::after(timeout).fuse();
+    pin_mut!(op, timer);
+    select! {
+        result = op => result.map_err(Into::into),
+        _ = timer => Err(BandError::Timeout)
+    }
+}
+
+// #region Characteristic I/O
+
+impl<'a, C: GattTransport> MiBand<'a, C> {
+    fn io_timeout(&self) -> Duration {
+        Duration::from_secs(self.io_policy.timeout_secs)
+    }
+
+    /// finishes once the band has been closed, or once what's running now is cancelled with `cancel_pending`
+    async fn cancelled(&self) {
+        let pending_cancel = self.pending.borrow().1.clone();
+        let closed = async {
+            match &self.cancel {
+                Some(cancel) => { let _ = cancel.recv().await; },
+                None => pending::<()>().await
+            }
+        };
+        select! {
+            _ = pending_cancel.recv().fuse() => {},
+            _ = closed.fuse() => {}
+        }
+    }
+
+    /// fail with `BandError::Cancelled` if the band is closed (or `cancel_pending` is called) before `op` finishes
+    async fn cancellable<T>(&self, op: impl Future<Output = Result<T>>) -> Result<T> {
+        let op = op.fuse();
+        let cancelled = self.cancelled().fuse();
+        pin_mut!(op, cancelled);
+        select! {
+            result = op => result,
+            _ = cancelled => Err(BandError::Cancelled)
+        }
+    }
+
+    /// run `op` with the timeout, trying it again (up to the policy's attempts) if it fails in a way that might go away
+    async fn with_retries<T, F, Fut>(&self, repeatable: bool, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = zbus::Result<T>>
+    {
+        let mut attempt = 1;
+        loop {
+            match self.cancellable(with_timeout(self.io_timeout(), op())).await {
+                Err(err) if attempt < self.io_policy.attempts && err.is_transient(repeatable) => {
+                    attempt += 1;
+                    Timer::after(IO_RETRY_DELAY).await;
+                },
+                result => return result
+            }
+        }
+    }
+
+    async fn read(&self, characteristic: &impl GattTransport) -> Result<Vec<u8>> {
+        self.with_retries(true, move || characteristic.read()).await
+    }
+
+    async fn write_request(&self, characteristic: &impl GattTransport, value: &[u8]) -> Result<()> {
+        self.with_retries(false, move || characteristic.write_request(value)).await
+    }
+
+    async fn write_command(&self, characteristic: &impl GattTransport, value: &[u8]) -> Result<()> {
+        self.with_retries(false, move || characteristic.write_command(value)).await
+    }
+
+    /// shares the notify session with anything else that's already listening to `characteristic`
+    async fn subscribe(&self, characteristic: &impl GattTransport) -> Result<NotifySubscription> {
+        self.with_retries(true, move || self.session.subscribe_notify(characteristic)).await
+    }
+}
+
+// #endregion
+
+impl<'a, C: GattTransport> MiBand<'a, C> {
+    fn new(session: BluezSession<'a>, device: DeviceProxy<'a>, address: String, model: BandModel, chars: Option<BandChars<C>>) -> Self {
+        Self {
+            device,
             session,
             authenticated: false,
-            chars: None,
-            address: device.address
-        })
+            chars,
+            gatt_paths: None,
+            io_policy: IoPolicy::default(),
+            simplify_alerts: false,
+            model,
+            cancel: None,
+            pending: RefCell::new(async_channel::bounded(1)),
+            state: Cell::new(BandState::Disconnected),
+            fetching: Cell::new(false),
+            state_listeners: RefCell::new(vec![]),
+            address
+        }
     }
 
-    pub async fn initialize<'b>(&'b mut self) -> Result<()> {
-        // first connect if needed
-        let was_connected = self.is_connected().await;
-        if !was_connected {
-            self.device.connect().await?;
-        }
+    /// the current state, followed by every change to it
+    /// the stream ends when the band is dropped
+    pub fn stream_state(&self) -> async_channel::Receiver<BandState> {
+        let (tx, rx) = async_channel::unbounded();
+        let _ = tx.try_send(self.state.get());
+        self.state_listeners.borrow_mut().push(tx);
+        rx
+    }
 
-        // if we weren't connected of if we don't have the chars, fetch them
-        if !was_connected || self.chars.is_none() {
-            let chars = self.fetch_chars().await?;
-            self.chars = Some(chars);
+    /// BlueZ said the band disconnected
+    pub fn set_disconnected(&self) {
+        self.set_state(BandState::Disconnected);
+    }
+
+    fn set_state(&self, state: BandState) {
+        if self.state.replace(state) == state { return }
+        self.state_listeners.borrow_mut().retain(|tx| tx.try_send(state).is_ok());
+    }
+
+    /// back to connected once `busy` is done - unless it disconnected in the meantime
+    fn finish_state(&self, busy: BandState) {
+        if self.state.get() == busy {
+            self.set_state(BandState::Connected);
         }
+    }
 
-        Ok(())
+    /// use characteristic paths from an earlier connection instead of looking them all up
+    pub fn set_gatt_paths(&mut self, paths: Option<DeviceServicePaths>) {
+        self.gatt_paths = paths;
+    }
+
+    /// the timeout and retries for characteristic reads/writes
+    pub fn set_io_policy(&mut self, policy: IoPolicy) {
+        self.io_policy = policy;
+    }
+
+    /// replace punctuation and emoji parts the band can't show before sending alerts
+    pub fn set_simplify_alerts(&mut self, simplify: bool) {
+        self.simplify_alerts = simplify;
+    }
+
+    /// reads, writes and fetches stop with `BandError::Cancelled` once `cancel` receives something or closes
+    pub fn set_cancel(&mut self, cancel: Option<async_channel::Receiver<()>>) {
+        self.cancel = cancel;
+    }
+
+    /// stop the reads, writes and fetches that are running now with `BandError::Cancelled`
+    /// unlike closing the band, anything started afterwards runs normally
+    pub fn cancel_pending(&self) {
+        let (last_tx, _last_rx) = self.pending.replace(async_channel::bounded(1));
+        last_tx.close();
+    }
+
+    /// which band this is, from what it advertised
+    pub fn model(&self) -> BandModel {
+        self.model
+    }
+
+    /// the characteristic paths from the current connection
+    pub fn gatt_paths(&self) -> Option<&DeviceServicePaths> {
+        self.gatt_paths.as_ref()
     }
 
     pub async fn is_connected(&self) -> bool {
@@ -248,117 +676,103 @@ impl<'a> MiBand<'a> {
         self.device.path()
     }
 
-    /// iterate through all the services and characteristics in order to find the ones we need
-    /// Note: device must be connected here
-    async fn fetch_chars<'b>(&self) -> Result<BandChars<'b>> {
-        let services_resolved = self.device.services_resolved().await.unwrap_or(false);
+    pub async fn disconnect(&mut self) -> Result<()> {
+        self.device.disconnect().await?;
+        self.authenticated = false;
+        Ok(())
+    }
 
-        if !services_resolved {
-            // wait for services to resolve
-            let mut services_resolved_stream = self.device.receive_services_resolved_changed().await;
-            while let Some(value) = services_resolved_stream.next().await {
-                if let Ok(true) = value.get().await { break; }
-            }
-        };
-        
-        // get the services
-        let mut services = self.session.get_device_characteristics(self.device.path()).await?;
-        match (
-            services.remove(SERVICE_BAND_0),
-            services.remove(SERVICE_BAND_1),
-            services.remove(SERVICE_DEVICE_INFO),
-            services.remove(SERVICE_NOTIFICATION)
-        ) {
-            
-            (Some(mut band_0), Some(mut band_1), Some(mut device_info), Some(mut notification)) => {
-                // get the characteristics from their respective services
-                match (
-                    band_0.remove(CHAR_BATTERY),
-                    band_0.remove(CHAR_STEPS),
-                    band_0.remove(CHAR_TIME),
-                    band_0.remove(CHAR_CONFIG),
-                    band_0.remove(CHAR_SETTINGS),
-                    band_0.remove(CHAR_CHUNKED_TRANSFER),
-                    band_0.remove(CHAR_MUSIC_NOTIFICATIONS),
-                    device_info.remove(CHAR_SOFT_REV),
-                    band_1.remove(CHAR_AUTH),
-                    notification.remove(CHAR_ALERT)
-                ) {
-                    (
-                        Some(battery),
-                        Some(steps),
-                        Some(time),
-                        Some(config),
-                        Some(settings),
-                        Some(chunked_transfer),
-                        Some(music_notifs),
-                        Some(firm_rev),
-                        Some(auth),
-                        Some(alert)
-                    ) => {
-                        let chars = BandChars {
-                            battery, steps, time, config, firm_rev, auth, settings, alert, chunked_transfer, music_notifs
-                        };
+    pub async fn get_bond_state(&self) -> Result<BondState> {
+        let paired = self.device.paired().await?;
+        Ok(BondState {
+            paired,
+            // older versions of BlueZ always bonded when pairing
+            bonded: self.device.bonded().await.unwrap_or(paired),
+            trusted: self.device.trusted().await?
+        })
+    }
 
-                        return Ok(chars);
-                    },
-                    _ => {}
-                }
-            },
-            _ => {}
+    /// pair + bond with the band and trust it so BlueZ lets it reconnect by itself
+    pub async fn bond(&self) -> Result<()> {
+        if !self.device.paired().await? {
+            self.device.pair().await?;
         }
-
-        return Err(BandError::MissingServicesOrChars);
+        self.device.set_trusted(true).await?;
+        Ok(())
     }
 
-    pub async fn disconnect(&mut self) -> Result<()> {
-        self.device.disconnect().await?;
+    /// remove the band from BlueZ, along with the stored bond
+    /// a stale bond (after pairing the band with a phone again) makes authentication fail
+    /// the band has to be discovered again afterwards
+    pub async fn remove_bond(&mut self) -> Result<()> {
+        self.session.adapter.remove_device(self.device.path()).await?;
         self.authenticated = false;
         Ok(())
     }
 
+    /// restart the band - it disconnects, but nothing on it is lost
+    pub async fn reboot(&self) -> Result<()> {
+        if !self.authenticated { return Err(BandError::RequiresAuth) }
+
+        if let Some(BandChars { firmware, .. }) = &self.chars {
+            let firmware = firmware.as_ref().ok_or(BandError::MissingServicesOrChars)?;
+            self.write_command(firmware, &COMMAND_REBOOT).await
+        } else { Err(BandError::NotInitialized) }
+    }
+
+    /// erase everything on the band and restart it
+    /// it has to be paired with the official app again afterwards, which gives it a new auth key
+    pub async fn factory_reset(&self) -> Result<()> {
+        if !self.authenticated { return Err(BandError::RequiresAuth) }
+
+        if let Some(BandChars { config, .. }) = &self.chars {
+            self.write_command(config, &COMMAND_FACTORY_RESET).await
+        } else { Err(BandError::NotInitialized) }
+    }
+
     /// Authenticate with the band
     pub async fn authenticate(&mut self, auth_key: &[u8]) -> Result<()> {
+        self.set_state(BandState::Authenticating);
+        let result = self.auth_handshake(auth_key).await;
+        self.finish_state(BandState::Authenticating);
+        result
+    }
+
+    async fn auth_handshake(&mut self, auth_key: &[u8]) -> Result<()> {
         if let Some(BandChars { auth, ..}) = &self.chars {
 
             // note: it's important that we start the notify session before writing
-            let (mut notify, notify_mtu) = auth.acquire_notify_stream().await?;
-            let (mut write, _) = auth.acquire_write_stream().await?;
+            let notify = self.subscribe(auth).await?;
+            let (mut write, _) = with_timeout(self.io_timeout(), auth.write_stream()).await?;
 
             // signal the band to start auth
-            write.write(&[0x02, 0x00]).await?;
-            let mut buf = vec![0; notify_mtu as usize];
+            trace::record(TraceKind::Write, CHAR_AUTH, &AUTH_REQUEST_CHALLENGE);
+            write.write(&AUTH_REQUEST_CHALLENGE).await?;
             loop {
-                let len = notify.read(&mut buf).await?;
-                if len >= 3 && buf[0] == 0x10 {
-                    match &buf[1..3] {
-                        &[0x01, 0x01] => {
-                            // signal to start again
-                            write.write(&[0x02, 0x00]).await?;
-                        },
-                        &[0x02, 0x01] => {
-                            // the band has sent us a 16 byte value to encrypt
-                            let value = &buf[3..19];
-                            if let Some(encrypted) = encrypt_value(&auth_key, value) {
-                                // 0x03 0x00 <first 16 bytes of encrypted value>
-                                let response = [&[0x03, 0x00], &encrypted[0..16]].concat();
-                                write.write(&response).await?;
-                            }
-                        },
-                        &[0x03, 0x01] => {
-                            // success
-                            self.authenticated = true;
-                            return Ok(());
-                        },
-                        &[0x03, 0x08] => {
-                            self.authenticated = false;
-                            // invalid auth key
-                            return Err(BandError::InvalidAuthKey);
-                        },
-                        
-                        buf => {
-                            println!("unknown authentication response {buf:?}");
+                // the band answers each step right away, so don't wait forever on a weak link
+                let buf = self.cancellable(with_timeout(self.io_timeout(), notify.recv())).await?;
+                match parse_auth_response(&buf) {
+                    AuthResponse::Restart => {
+                        // signal to start again
+                        trace::record(TraceKind::Write, CHAR_AUTH, &AUTH_REQUEST_CHALLENGE);
+                        write.write(&AUTH_REQUEST_CHALLENGE).await?;
+                    },
+                    AuthResponse::Challenge(challenge) => {
+                        if let Some(response) = encode_auth_answer(auth_key, &challenge) {
+                            trace::record(TraceKind::Write, CHAR_AUTH, &response);
+                            write.write(&response).await?;
                         }
+                    },
+                    AuthResponse::Success => {
+                        self.authenticated = true;
+                        return Ok(());
+                    },
+                    AuthResponse::InvalidKey => {
+                        self.authenticated = false;
+                        return Err(BandError::InvalidAuthKey);
+                    },
+                    AuthResponse::Unknown => {
+                        warn!("Unknown authentication response {buf:?}");
                     }
                 }
             }
@@ -367,28 +781,10 @@ impl<'a> MiBand<'a> {
 
     /// chunked data transfer for longer payloads
     async fn write_chunked(&self, message_type: u8, payload: &[u8]) -> Result<()> {
-        const CHUNK_LENGTH: usize = 17;
         if let Some(BandChars { chunked_transfer, .. }) = &self.chars {
-            let chunks = payload.chunks(CHUNK_LENGTH).enumerate();
-            let num_chunks = chunks.len();
-            let processed_chunks: Vec<_> = chunks.map(|(i, chunk)| {
-                let flag = match (i == 0, i == num_chunks - 1) {
-                    // first and last chunk
-                    (true, true) => 0x40 | 0x80,
-                    // first chunk
-                    (true, false) => 0,
-                    // last chunk
-                    (false, true) => 0x80,
-                    // middle chunk
-                    (false, false) => 0x40
-                } | message_type;
-                // 0x00 <flag> <num chunks> <data...>
-                [&[0x00, flag, (i & 0xff) as u8], chunk].concat()
-            }).collect();
-
             // write all of the chunks
-            for chunk in processed_chunks {
-                chunked_transfer.write_value_command(&chunk).await?;
+            for chunk in encode_chunks(message_type, payload) {
+                self.write_command(chunked_transfer, &chunk).await?;
             }
             Ok(())
         } else { Err(BandError::NotInitialized) }
@@ -397,26 +793,16 @@ impl<'a> MiBand<'a> {
     /// get the battery level and status
     pub async fn get_battery(&self) -> Result<BatteryStatus> {
         if let Some(BandChars { battery, .. }) = &self.chars {
-            let value = battery.read_value_default().await?;
-            let battery_level = value[1];
-            let charging = value[2] != 0;
-
-            //let last_off = parse_time(&value[3..]).ok_or(BandError::InvalidTime)?;
-            let last_charge = parse_time(&value[11..]).ok_or(BandError::InvalidTime)?;
-
-            Ok(BatteryStatus {
-                battery_level,
-                charging,
-                //last_off,
-                last_charge
-            })
+            let value = self.read(battery).await?;
+            self.model.protocol().parse_battery(&value).ok_or(BandError::MalformedResponse(value))
         } else { Err(BandError::NotInitialized) }
     }
 
     /// get the current time on the band
     pub async fn get_band_time(&self) -> Result<DateTime<Local>> {
         if let Some(BandChars { time, .. }) = &self.chars {
-            let value = time.read_value_default().await?;
+            let value = self.read(time).await?;
+            if value.len() < TIME_LENGTH { return Err(BandError::MalformedResponse(value)) }
             parse_time(&value).ok_or(BandError::InvalidTime)
         } else { Err(BandError::NotInitialized) }
     }
@@ -426,11 +812,7 @@ impl<'a> MiBand<'a> {
         if !self.authenticated { return Err(BandError::RequiresAuth) }
         
         if let Some(BandChars { time, .. }) = &self.chars {
-            let year = new_time.year();
-            let day_of_week = new_time.weekday().num_days_from_sunday() as u8;
-            // year (two bytes), month, day, hour, minute, second, day of week, 3 zeros? (could be timezone)
-            let value = vec![(year & 0xff) as u8, (year >> 8) as u8, new_time.month() as u8, new_time.day() as u8, new_time.hour() as u8, new_time.minute() as u8, new_time.second() as u8, day_of_week, 0, 0, 0];
-            time.write_value_request(&value).await?;
+            self.write_request(time, &encode_time(&new_time)).await?;
             Ok(())
         } else { Err(BandError::NotInitialized) }
     }
@@ -440,13 +822,8 @@ impl<'a> MiBand<'a> {
         if !self.authenticated { return Err(BandError::RequiresAuth) }
         
         if let Some(BandChars { steps, .. }) = &self.chars {
-            let value = steps.read_value_default().await?;
-            let steps = (value[1] as u16) | ((value[2] as u16) << 8);
-            let meters = (value[5] as u16) | ((value[6] as u16) << 8);
-            let calories = (value[9] as u16) | ((value[10] as u16) << 8);
-            Ok(CurrentActivity {
-                steps, meters, calories
-            })
+            let value = self.read(steps).await?;
+            self.model.protocol().parse_current_activity(&value).ok_or(BandError::MalformedResponse(value))
         } else { Err(BandError::NotInitialized) }
     }
 
@@ -455,105 +832,469 @@ impl<'a> MiBand<'a> {
         if !self.authenticated { return Err(BandError::RequiresAuth) }
         
         if let Some(BandChars { config, settings, .. }) = &self.chars {
+            let (notifications_payload, goal_payload) = encode_activity_goal(goal);
             // enable/disable notifications
-            let notifs_enabled_byte = if goal.notifications { 0x01 } else { 0x00 };
-            config.write_value_command(&vec![0x06, 0x06, 0x00, notifs_enabled_byte]).await?;
-
+            self.write_command(config, &notifications_payload).await?;
             // set the actual goal
-            let goal_payload = vec![0x10, 0x00, 0x00, (goal.steps & 0xff) as u8, (goal.steps >> 8) as u8, 0x00, 0x00];
-            settings.write_value_request(&goal_payload).await?;
+            self.write_request(settings, &goal_payload).await?;
+            Ok(())
+        } else { Err(BandError::NotInitialized) }
+    }
+
+    /// set the unit the band displays distances in
+    pub async fn set_distance_unit(&self, unit: DistanceUnit) -> Result<()> {
+        if !self.authenticated { return Err(BandError::RequiresAuth) }
+
+        if let Some(BandChars { config, .. }) = &self.chars {
+            self.write_command(config, &encode_distance_unit(unit)).await?;
+            Ok(())
+        } else { Err(BandError::NotInitialized) }
+    }
+
+    /// set whether the band shows the time in 12 or 24 hour format
+    pub async fn set_time_format(&self, time_format: TimeFormat) -> Result<()> {
+        if !self.authenticated { return Err(BandError::RequiresAuth) }
+
+        if let Some(BandChars { config, .. }) = &self.chars {
+            self.write_command(config, &encode_time_format(time_format)).await?;
+            Ok(())
+        } else { Err(BandError::NotInitialized) }
+    }
+
+    /// set whether the watchface shows the date along with the time
+    pub async fn set_date_display(&self, date_display: DateDisplay) -> Result<()> {
+        if !self.authenticated { return Err(BandError::RequiresAuth) }
+
+        if let Some(BandChars { config, .. }) = &self.chars {
+            self.write_command(config, &encode_date_display(date_display)).await?;
             Ok(())
         } else { Err(BandError::NotInitialized) }
     }
 
+    /// ask the band for the value it has for a config key
+    /// `None` if the band refuses or doesn't answer the query
+    async fn read_config(&self, key: [u8; 2]) -> Result<Option<Vec<u8>>> {
+        if !self.authenticated { return Err(BandError::RequiresAuth) }
+
+        if let Some(BandChars { config, .. }) = &self.chars {
+            // note: the notify session has to be started before the query is written
+            let notify = self.subscribe(config).await?;
+            self.write_request(config, &encode_config_query(key)).await?;
+            loop {
+                let buf = match self.cancellable(with_timeout(self.io_timeout(), notify.recv())).await {
+                    Ok(buf) => buf,
+                    // older firmware doesn't answer at all
+                    Err(BandError::Timeout) => return Ok(None),
+                    Err(err) => return Err(err)
+                };
+                if let Some(value) = parse_config_value(key, &buf) {
+                    return Ok(Some(value.to_vec()));
+                }
+                if is_config_response(&buf) { return Ok(None) }
+            }
+        } else { Err(BandError::NotInitialized) }
+    }
+
+    /// the step goal and goal notifications set on the band
+    /// `None` if the band can't report them
+    pub async fn get_activity_goal(&self) -> Result<Option<ActivityGoal>> {
+        if !self.authenticated { return Err(BandError::RequiresAuth) }
+
+        if let Some(BandChars { settings, .. }) = &self.chars {
+            let Some(steps) = parse_step_goal(&self.read(settings).await?) else { return Ok(None) };
+            let notifications = self.read_config(CONFIG_GOAL_NOTIFICATIONS).await?
+                .and_then(|value| parse_goal_notifications(&value));
+            Ok(notifications.map(|notifications| ActivityGoal { notifications, steps }))
+        } else { Err(BandError::NotInitialized) }
+    }
+
+    /// the distance unit, time format and watchface set on the band
+    /// `None` if the band can't report all of them
+    pub async fn get_display_format(&self) -> Result<Option<DisplayFormat>> {
+        let distance_unit = self.read_config(CONFIG_DISTANCE_UNIT).await?.and_then(|value| parse_distance_unit(&value));
+        let time_format = self.read_config(CONFIG_TIME_FORMAT).await?.and_then(|value| parse_time_format(&value));
+        let date_display = self.read_config(CONFIG_DATE_DISPLAY).await?.and_then(|value| parse_date_display(&value));
+        Ok(match (distance_unit, time_format, date_display) {
+            (Some(distance_unit), Some(time_format), Some(date_display)) => Some(DisplayFormat { distance_unit, time_format, date_display }),
+            _ => None
+        })
+    }
+
+    /// the lock set on the band
+    /// `None` if the band can't report it
+    pub async fn get_band_lock(&self) -> Result<Option<BandLock>> {
+        Ok(self.read_config(CONFIG_BAND_LOCK).await?.and_then(|value| parse_band_lock(&value)))
+    }
+
     /// firmware revision (software revision string)
     pub async fn get_firmware_revision(&self) -> Result<String> {
         if let Some(BandChars { firm_rev, .. }) = &self.chars {
-            let value = firm_rev.read_value_default().await?;
+            let value = self.read(firm_rev).await?;
             String::from_utf8(value).map_err(|_e| BandError::Utf8Error)
         } else { Err(BandError::NotInitialized) }
     }
 
+    /// serial number (serial number string) - it stays the same when the band's address changes
+    /// `None` if the band doesn't have one
+    pub async fn get_serial_number(&self) -> Result<Option<String>> {
+        if let Some(BandChars { serial, .. }) = &self.chars {
+            let Some(serial) = serial else { return Ok(None) };
+            let value = self.read(serial).await?;
+            let serial = String::from_utf8(value).map_err(|_e| BandError::Utf8Error)?;
+            let serial = serial.trim_end_matches('\0').trim();
+            Ok(if serial.is_empty() { None } else { Some(serial.to_string()) })
+        } else { Err(BandError::NotInitialized) }
+    }
+
     /// show a notification on the band
     pub async fn send_alert(&self, alert_data: &Alert<'_>) -> Result<()> {
         if let Some(BandChars { alert, .. }) = &self.chars {
-            let type_byte = alert_data.alert_type as u8;
-            let data = [
-                &[type_byte, 0x01],
-                alert_data.title.as_bytes(),
-                &[0x00],
-                alert_data.message.as_bytes(),
-                &[0x00]
-            ].concat();
-            alert.write_value_request(&data).await?;
+            let payload = self.model.protocol().encode_alert(alert_data, self.simplify_alerts);
+            // longer alerts would be cut off by the write
+            if payload.len() > ALERT_WRITE_LIMIT {
+                self.write_chunked(CHUNKED_ALERT, &payload).await
+            } else {
+                self.write_request(alert, &payload).await
+            }
+        } else { Err(BandError::NotInitialized) }
+    }
+
+    pub async fn set_do_not_disturb(&self, enabled: bool) -> Result<()> {
+        if let Some(BandChars { config, .. }) = &self.chars {
+            self.write_command(config, &encode_do_not_disturb(enabled)).await?;
             Ok(())
         } else { Err(BandError::NotInitialized) }
     }
 
     pub async fn set_band_lock(&self, lock: &BandLock) -> Result<()> {
+        // the keypad only allows valid PINs, but the saved one might not have come from it
+        if !lock.is_valid() { return Err(BandError::InvalidPin) }
         if let Some(BandChars { config, .. }) = &self.chars {
-            // make sure all digits are between 1-4
-            if lock.pin.len() != 4 || !lock.pin.chars().all(|i| i >= '1' && i <= '4') { return Err(BandError::InvalidLockPin); }
-            let data = [
-                &[0x06, 0x21, 0x00, if lock.enabled { 0x01 } else { 0x00 }],
-                &lock.pin.bytes().collect::<Vec<u8>>()[..],
-                &[0x00]
-            ].concat();
-            config.write_value_command(&data).await?;
+            self.write_command(config, &encode_band_lock(lock)).await?;
             Ok(())
         } else { Err(BandError::NotInitialized) }
     }
 
     pub async fn set_media_info(&self, media: &Option<MediaInfo>) -> Result<()> {
-        if let Some(media) = media {
-            let pos = media.duration.zip(media.position).map(|(dur, pos)| {
-                let ratio = (pos as f64) / (dur as f64);
-                (ratio * (0xff as f64)) as u8
-            });
-            let pos_bytes = pos.as_ref()
-                .map(|&p| vec![p, 0])
-                .unwrap_or_else(|| vec![0x00, 0x00]);
-            let all_fields = [
-                // always include the position (even if it's just [0x00, 0x00])
-                (0x00u8, Some(pos_bytes)),
-                // track + null term
-                (0x08u8, media.track.as_ref().map(|b| [b.as_bytes(), &[0x00]].concat())),
-                // 0xffff - we scale position and duration to a full u16
-                (0x10u8, pos.map(|_d| vec![0xff, 0x0])),
-                // single byte volume
-                (0x40u8, media.volume.map(|d| vec![d]))
-            ];
-            let (flags, bufs): (Vec<u8>, Vec<Vec<u8>>) = all_fields.into_iter()
-                .filter_map(|(flag, buf)| {
-                    // basically filter out the `None`s
-                    Some((flag, buf?))
-                })
-                .unzip();
-
-            // OR all of the flags together with 0x01
-            let flag = flags.into_iter().fold(0x01, |acc, f| acc | f);
-            let buf = bufs.concat();
-
-            let buf = [
-                &[flag, if media.state == MediaState::Playing { 1 } else { 0 }, 0x00],
-                &buf[..]
-            ].concat();
-
-            self.write_chunked(0x03, &buf).await
-        } else {
-            // no music is playing
-            self.write_chunked(0x03, &[0x40 | 0x20, 0x00]).await
-        }
+        self.write_chunked(CHUNKED_MUSIC, &encode_media_info(media)).await
     }
 
-    /// listen for the media button presses
-    pub async fn stream_media_button_events(&self) -> Result<MusicEventListener> {
+    /// listen for the media button presses and other events (the band being taken off, etc)
+    pub async fn stream_device_events(&self) -> Result<DeviceEventListener> {
         if let Some(BandChars { music_notifs, .. }) = &self.chars {
-            let (notify_stream, mtu) = music_notifs.acquire_notify_stream().await?;
-            Ok(MusicEventListener { notify_stream, mtu: mtu as usize })
+            let notifications = self.subscribe(music_notifs).await?.into_stream();
+            Ok(DeviceEventListener { notifications })
         } else { Err(BandError::NotInitialized) }
     }
 
+    /// listen for the battery level changing (and charging starting or stopping)
+    pub async fn stream_battery(&self) -> Result<BatteryListener> {
+        if let Some(BandChars { battery, .. }) = &self.chars {
+            let notifications = self.subscribe(battery).await?.into_stream();
+            Ok(BatteryListener { notifications, protocol: self.model.protocol() })
+        } else { Err(BandError::NotInitialized) }
+    }
+
+    /// listen for the step count, distance and calories changing as they're walked
+    pub async fn stream_activity(&self) -> Result<ActivityListener> {
+        if !self.authenticated { return Err(BandError::RequiresAuth) }
+
+        if let Some(BandChars { steps, .. }) = &self.chars {
+            let notifications = self.subscribe(steps).await?.into_stream();
+            Ok(ActivityListener { notifications, protocol: self.model.protocol() })
+        } else { Err(BandError::NotInitialized) }
+    }
+
+    /// start continuous heart rate monitoring and listen for the measurements
+    /// the band stops measuring unless `ping_heart_rate` is called every `HEART_RATE_PING_INTERVAL`
+    pub async fn stream_heart_rate(&self) -> Result<HeartRateListener> {
+        if !self.authenticated { return Err(BandError::RequiresAuth) }
+
+        if let Some(BandChars { heart_rate_measure, heart_rate_control, .. }) = &self.chars {
+            // stop any manual or continuous measurements that are already running
+            self.write_request(heart_rate_control, &[0x15, 0x02, 0x00]).await?;
+            self.write_request(heart_rate_control, &[0x15, 0x01, 0x00]).await?;
+
+            let notifications = self.subscribe(heart_rate_measure).await?.into_stream();
+
+            // start continuous measurement
+            self.write_request(heart_rate_control, &[0x15, 0x01, 0x01]).await?;
+            Ok(HeartRateListener { notifications })
+        } else { Err(BandError::NotInitialized) }
+    }
+
+    /// keep continuous heart rate monitoring running
+    pub async fn ping_heart_rate(&self) -> Result<()> {
+        if let Some(BandChars { heart_rate_control, .. }) = &self.chars {
+            self.write_request(heart_rate_control, &[0x16]).await?;
+            Ok(())
+        } else { Err(BandError::NotInitialized) }
+    }
+
+    /// stop continuous heart rate monitoring
+    pub async fn stop_heart_rate(&self) -> Result<()> {
+        if let Some(BandChars { heart_rate_control, .. }) = &self.chars {
+            self.write_request(heart_rate_control, &[0x15, 0x01, 0x00]).await?;
+            Ok(())
+        } else { Err(BandError::NotInitialized) }
+    }
+
+    /// fetch stored data of `data_type` recorded after `since`
+    /// returns the time the data starts at and the raw data, or `None` if there's nothing new
+    /// if `cancel` receives anything, it stops early and returns what it has so far
+    /// fails with `BandError::Busy` if another fetch is still running
+    async fn fetch_data(
+        &self,
+        data_type: u8,
+        since: &DateTime<Local>,
+        progress: &mut dyn FnMut(FetchProgress),
+        cancel: Option<&async_channel::Receiver<()>>
+    ) -> Result<Option<(DateTime<Local>, Vec<u8>)>> {
+        if let Some(BandChars { fetch, activity_data, .. }) = &self.chars {
+            // a second fetch would have its data interleaved with this one's
+            let Some(_guard) = FetchGuard::acquire(&self.fetching) else { return Err(BandError::Busy) };
+
+            // note: the notify sessions have to be started before we ask for data
+            let fetch_notify = self.subscribe(fetch).await?;
+            let data_notify = self.subscribe(activity_data).await?;
+
+            // 0x01 <type> <time>
+            self.write_request(fetch, &encode_fetch_request(data_type, since)).await?;
+
+            // 0x10 0x01 0x01 <length (4 bytes)> <start time>
+            let metadata = &self.cancellable(read_with_timeout(&fetch_notify)).await?;
+            let start = match parse_fetch_response(metadata) {
+                FetchResponse::Started { length: 0, .. } => return Ok(None),
+                FetchResponse::Started { start, .. } => start.ok_or(BandError::InvalidTime)?,
+                // these are worth telling the user about - anything else just means there's nothing to send
+                FetchResponse::Rejected(STATUS_BUSY | STATUS_LOW_BATTERY | STATUS_NOT_AUTHORIZED) => {
+                    return Err(BandError::from_response(metadata).unwrap_or(BandError::FetchFailed))
+                },
+                FetchResponse::Unknown => return Err(BandError::MalformedResponse(metadata.clone())),
+                _ => return Ok(None)
+            };
+
+            // start sending the data
+            self.write_request(fetch, &COMMAND_FETCH_START).await?;
+
+            let mut data = vec![];
+            loop {
+                let cancelled = async {
+                    match cancel {
+                        Some(cancel) => { let _ = cancel.recv().await; },
+                        None => pending::<()>().await
+                    }
+                };
+                select! {
+                    _ = cancelled.fuse() => {
+                        // stop the band from sending the rest - it keeps the data, so it's all sent again next time
+                        if let Err(err) = self.write_request(fetch, &COMMAND_FETCH_STOP).await {
+                            warn!("Couldn't stop the fetch: {err}");
+                        }
+                        return Ok(Some((start, data)));
+                    },
+                    _ = self.cancelled().fuse() => {
+                        // if the band is still there, it shouldn't keep sending
+                        let _ = self.write_request(fetch, &COMMAND_FETCH_STOP).await;
+                        return Err(BandError::Cancelled);
+                    },
+                    packet = read_with_timeout(&data_notify).fuse() => {
+                        let packet = packet?;
+                        // the first byte is a packet counter
+                        if packet.len() > 1 {
+                            data.extend_from_slice(&packet[1..]);
+                            progress(FetchProgress { start, bytes: data.len() });
+                        }
+                    },
+                    response = read_with_timeout(&fetch_notify).fuse() => {
+                        let response = response?;
+                        match parse_fetch_response(&response) {
+                            FetchResponse::Finished => break,
+                            FetchResponse::Failed(_) => {
+                                return Err(BandError::from_response(&response).unwrap_or(BandError::FetchFailed))
+                            },
+                            _ => {}
+                        }
+                    }
+                }
+            }
+
+            // acknowledge the data, but keep it on the band so the official app still gets it
+            let _ = self.write_request(fetch, &COMMAND_FETCH_STOP).await;
+
+            Ok(Some((start, data)))
+        } else { Err(BandError::NotInitialized) }
+    }
+
+    /// run a bulk transfer, showing the band as syncing while it runs
+    async fn syncing<T>(&self, transfer: impl Future<Output = Result<T>>) -> Result<T> {
+        self.set_state(BandState::Syncing);
+        let result = transfer.await;
+        self.finish_state(BandState::Syncing);
+        result
+    }
+
+    /// download the per-minute activity recorded after `since`
+    /// if it's cancelled, the samples received so far are returned
+    pub async fn fetch_activity(
+        &self,
+        since: DateTime<Local>,
+        mut progress: impl FnMut(FetchProgress),
+        cancel: Option<&async_channel::Receiver<()>>
+    ) -> Result<Vec<ActivitySample>> {
+        if !self.authenticated { return Err(BandError::RequiresAuth) }
+
+        let fetched = self.syncing(self.fetch_data(FETCH_ACTIVITY, &since, &mut progress, cancel)).await?;
+        let Some((start, data)) = fetched else { return Ok(vec![]) };
+        Ok(parse_activity(start, &data))
+    }
+
+    /// download the summaries of workouts recorded on the band after `since`
+    pub async fn fetch_workouts(&self, since: DateTime<Local>) -> Result<Vec<WorkoutSummary>> {
+        if !self.authenticated { return Err(BandError::RequiresAuth) }
+
+        self.syncing(async {
+            let mut since = since;
+            let mut workouts = vec![];
+            // the band sends one summary at a time
+            while workouts.len() < MAX_WORKOUTS_PER_FETCH {
+                let Some((_start, data)) = self.fetch_data(FETCH_SPORTS_SUMMARIES, &since, &mut |_| {}, None).await? else { break };
+                let Some(workout) = parse_workout_summary(&data) else { break };
+                // make sure we're making progress
+                if workout.start < since { break }
+                since = workout.start + TimeDelta::minutes(1);
+                workouts.push(workout);
+            }
+            Ok(workouts)
+        }).await
+    }
+}
+
+// the BlueZ parts
+impl<'a> MiBand<'a> {
+    pub async fn from_discovered_device<'b>(session: BluezSession<'a>, device: DiscoveredDevice) -> Result<Self> {
+        let device_proxy = session.proxy_from_discovered_device(device.path).await?;
+        let name = device_proxy.name().await.ok();
+        let model = BandModel::detect(name.as_deref(), &device.services).unwrap_or_default();
+        Ok(Self::new(session, device_proxy, device.address, model, None))
+    }
+
+    pub async fn initialize<'b>(&'b mut self) -> Result<()> {
+        // first connect if needed
+        let was_connected = self.is_connected().await;
+        if !was_connected {
+            self.set_state(BandState::Connecting);
+            self.device.connect().await?;
+        }
+
+        // if we weren't connected of if we don't have the chars, fetch them
+        if !was_connected || self.chars.is_none() {
+            let chars = self.fetch_chars().await?;
+            self.chars = Some(chars);
+        }
+
+        self.set_state(BandState::Connected);
+        Ok(())
+    }
+
+    /// iterate through all the services and characteristics in order to find the ones we need
+    /// Note: device must be connected here
+    async fn fetch_chars<'b>(&mut self) -> Result<BandChars<GattCharacteristicProxy<'b>>> {
+        // BlueZ keeps the same paths for a known device, so the cached ones are usually still good
+        if let Some(paths) = &self.gatt_paths {
+            match self.session.characteristics_from_paths(paths).await {
+                Ok(services) => if let Some(chars) = Self::band_chars(services, self.model.protocol()) { return Ok(chars) },
+                // they're gone - fall back to finding them again
+                Err(err) => warn!("Cached characteristics are stale: {err}")
+            }
+            self.gatt_paths = None;
+        }
+
+        let services_resolved = self.device.services_resolved().await.unwrap_or(false);
+
+        if !services_resolved {
+            // wait for services to resolve
+            let mut services_resolved_stream = self.device.receive_services_resolved_changed().await;
+            while let Some(value) = services_resolved_stream.next().await {
+                if let Ok(true) = value.get().await { break; }
+            }
+        };
+        
+        // get the services
+        let services = self.session.get_device_characteristics(self.device.path()).await?;
+        // only the services we use are worth remembering
+        self.gatt_paths = Some(services.iter()
+            .filter(|(uuid, _)| [SERVICE_BAND_0, SERVICE_BAND_1, SERVICE_DEVICE_INFO, SERVICE_NOTIFICATION, SERVICE_HEART_RATE, SERVICE_FIRMWARE].contains(&uuid.as_str()))
+            .map(|(uuid, chars)| (
+                uuid.clone(),
+                chars.iter().map(|(char_uuid, proxy)| (char_uuid.clone(), proxy.path().to_string())).collect()
+            ))
+            .collect());
+
+        Self::band_chars(services, self.model.protocol()).ok_or(BandError::MissingServicesOrChars)
+    }
+
+    /// pick out the characteristics we need
+    fn band_chars<'b>(mut services: DeviceServiceChars<'b>, protocol: &dyn BandProtocol) -> Option<BandChars<GattCharacteristicProxy<'b>>> {
+        match (
+            services.remove(SERVICE_BAND_0),
+            services.remove(SERVICE_BAND_1),
+            services.remove(SERVICE_DEVICE_INFO),
+            services.remove(SERVICE_NOTIFICATION),
+            services.remove(SERVICE_HEART_RATE)
+        ) {
+            
+            (Some(mut band_0), Some(mut band_1), Some(mut device_info), Some(mut notification), Some(mut heart_rate)) => {
+                let serial = device_info.remove(CHAR_SERIAL);
+                let firmware = services.remove(SERVICE_FIRMWARE).and_then(|mut firmware| firmware.remove(CHAR_FIRMWARE));
+                // get the characteristics from their respective services
+                match (
+                    band_0.remove(protocol.char_battery()),
+                    band_0.remove(protocol.char_steps()),
+                    band_0.remove(CHAR_TIME),
+                    band_0.remove(CHAR_CONFIG),
+                    band_0.remove(CHAR_SETTINGS),
+                    band_0.remove(CHAR_CHUNKED_TRANSFER),
+                    band_0.remove(CHAR_MUSIC_NOTIFICATIONS),
+                    device_info.remove(CHAR_SOFT_REV),
+                    band_1.remove(CHAR_AUTH),
+                    notification.remove(CHAR_ALERT),
+                    heart_rate.remove(CHAR_HEART_RATE_MEASURE),
+                    heart_rate.remove(CHAR_HEART_RATE_CONTROL),
+                    band_0.remove(CHAR_FETCH),
+                    band_0.remove(CHAR_ACTIVITY_DATA)
+                ) {
+                    (
+                        Some(battery),
+                        Some(steps),
+                        Some(time),
+                        Some(config),
+                        Some(settings),
+                        Some(chunked_transfer),
+                        Some(music_notifs),
+                        Some(firm_rev),
+                        Some(auth),
+                        Some(alert),
+                        Some(heart_rate_measure),
+                        Some(heart_rate_control),
+                        Some(fetch),
+                        Some(activity_data)
+                    ) => {
+                        let chars = BandChars {
+                            battery, steps, time, config, firm_rev, serial, auth, settings, alert, chunked_transfer, music_notifs,
+                            heart_rate_measure, heart_rate_control, fetch, activity_data, firmware
+                        };
+
+                        return Some(chars);
+                    },
+                    _ => {}
+                }
+            },
+            _ => {}
+        }
+
+        None
+    }
 
     // ===== STATIC STRUCT METHODS ===== //
     
@@ -562,17 +1303,23 @@ impl<'a> MiBand<'a> {
         Ok(existing_devices.into_iter().filter(|device| device.services.contains(SERVICE_BAND_0)).collect())
     }
 
-    pub async fn start_filtered_discovery<'b>(session: BluezSession<'b>) -> Result<()> {
+    /// start discovering mi bands
+    /// discovery is shared by the whole app, so every call needs a matching `stop_filtered_discovery`
+    pub async fn start_filtered_discovery<'b>(session: &BluezSession<'b>) -> Result<()> {
         // filter to just the mi band service
         let filter = DiscoveryFilter {
             uuids: vec![SERVICE_BAND_0.into()],
             transport: "le".into(),
+            // only report advertisements that changed - discovery can stay on for a long time
             duplicate_data: false
         };
 
-        // start discovery
-        session.adapter.set_discovery_filter(filter).await?;
-        session.adapter.start_discovery().await?;
+        session.acquire_discovery(filter).await?;
+        Ok(())
+    }
+
+    pub async fn stop_filtered_discovery<'b>(session: &BluezSession<'b>) -> Result<()> {
+        session.release_discovery().await?;
         Ok(())
     }
 
@@ -612,3 +1359,187 @@ impl<'a> MiBand<'a> {
         Ok(select(rssi, connected))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net::UnixStream;
+
+    use async_io::block_on;
+    use futures::AsyncReadExt;
+    use zbus::{connection, proxy::CacheProperties, Connection, Guid};
+
+    use crate::{bluez::MemoryCharacteristic, protocol::encode_alert};
+    use super::*;
+
+    /// 2024-05-10 12:30:45
+    const TIME: [u8; TIME_LENGTH] = [0xe8, 0x07, 5, 10, 12, 30, 45];
+
+    /// a peer-to-peer connection with nothing answering on it - enough to make the proxies,
+    /// as long as nothing is called on them
+    async fn unused_connection() -> zbus::Result<(Connection, Connection)> {
+        let (ours, theirs) = UnixStream::pair()?;
+        futures::try_join!(
+            connection::Builder::unix_stream(ours).p2p().build(),
+            connection::Builder::unix_stream(theirs).server(Guid::generate())?.p2p().build()
+        )
+    }
+
+    fn memory_chars() -> BandChars<MemoryCharacteristic> {
+        BandChars {
+            battery: MemoryCharacteristic::new(CHAR_BATTERY),
+            steps: MemoryCharacteristic::new(CHAR_STEPS),
+            firm_rev: MemoryCharacteristic::new(CHAR_SOFT_REV),
+            serial: None,
+            time: MemoryCharacteristic::new(CHAR_TIME),
+            auth: MemoryCharacteristic::new(CHAR_AUTH),
+            config: MemoryCharacteristic::new(CHAR_CONFIG),
+            settings: MemoryCharacteristic::new(CHAR_SETTINGS),
+            alert: MemoryCharacteristic::new(CHAR_ALERT),
+            chunked_transfer: MemoryCharacteristic::new(CHAR_CHUNKED_TRANSFER),
+            music_notifs: MemoryCharacteristic::new(CHAR_MUSIC_NOTIFICATIONS),
+            heart_rate_measure: MemoryCharacteristic::new(CHAR_HEART_RATE_MEASURE),
+            heart_rate_control: MemoryCharacteristic::new(CHAR_HEART_RATE_CONTROL),
+            fetch: MemoryCharacteristic::new(CHAR_FETCH),
+            activity_data: MemoryCharacteristic::new(CHAR_ACTIVITY_DATA),
+            firmware: None
+        }
+    }
+
+    #[test]
+    fn unknown_device_events_dont_end_the_stream() {
+        let notifications = futures::stream::iter(vec![vec![0xfe, 0x42], vec![0x7f], vec![], vec![0xfe, 0x03]]).boxed();
+        let events: Vec<Option<DeviceEvent>> = block_on(DeviceEventListener { notifications }.collect());
+        assert_eq!(events, vec![None, None, None, Some(DeviceEvent::Music(MusicEvent::Next))]);
+    }
+
+    /// a band on `MemoryCharacteristic`s - keep the connection around for as long as the band is used
+    async fn memory_band() -> (MiBand<'static, MemoryCharacteristic>, Connection) {
+        let (conn, peer) = unused_connection().await.expect("can connect");
+        let session = BluezSession::with_connection(conn.clone()).await.expect("can make a session");
+        let device = DeviceProxy::builder(&conn)
+            .path("/org/bluez/hci0/dev_00_00_00_00_00_00").expect("is a valid path")
+            .cache_properties(CacheProperties::No)
+            .build().await.expect("can make a device proxy");
+        (MiBand::new(session, device, "00:00:00:00:00:00".into(), BandModel::default(), Some(memory_chars())), peer)
+    }
+
+    /// the band's ends of the (write, notify) streams `MiBand` opens on this characteristic, once it has opened them
+    async fn stream_peers(characteristic: &MemoryCharacteristic) -> (async_net::unix::UnixStream, async_net::unix::UnixStream) {
+        loop {
+            if let Some(write) = characteristic.take_write_peer() {
+                // notifications are always started before writing
+                let notify = characteristic.take_notify_peer().expect("notifications were started");
+                return (write, notify);
+            }
+            Timer::after(Duration::from_millis(1)).await;
+        }
+    }
+
+    #[test]
+    fn authenticate_in_memory() {
+        block_on(async {
+            let (mut band, _peer) = memory_band().await;
+            let auth = band.chars.as_ref().map(|chars| chars.auth.clone()).expect("has characteristics");
+            let key = [0x30; 16];
+            let challenge = [0x5a; 16];
+
+            let band_side = async {
+                let (mut written, mut notify) = stream_peers(&auth).await;
+                let mut request = [0; 2];
+                written.read_exact(&mut request).await.expect("a challenge is requested");
+                assert_eq!(request, AUTH_REQUEST_CHALLENGE);
+                // asking again starts over
+                notify.write_all(&[0x10, 0x01, 0x01]).await.expect("can restart");
+                written.read_exact(&mut request).await.expect("a challenge is requested again");
+                assert_eq!(request, AUTH_REQUEST_CHALLENGE);
+
+                notify.write_all(&[&[0x10, 0x02, 0x01][..], &challenge].concat()).await.expect("can send the challenge");
+                let mut answer = [0; 18];
+                written.read_exact(&mut answer).await.expect("the challenge is answered");
+                assert_eq!(answer.to_vec(), encode_auth_answer(&key, &challenge).expect("the key is valid"));
+                notify.write_all(&[0x10, 0x03, 0x01]).await.expect("can accept the answer");
+            };
+            let (result, ()) = futures::join!(band.authenticate(&key), band_side);
+            result.expect("authenticates");
+            assert!(band.authenticated);
+        });
+    }
+
+    #[test]
+    fn authenticate_with_the_wrong_key() {
+        block_on(async {
+            let (mut band, _peer) = memory_band().await;
+            let auth = band.chars.as_ref().map(|chars| chars.auth.clone()).expect("has characteristics");
+
+            let band_side = async {
+                let (mut written, mut notify) = stream_peers(&auth).await;
+                let mut request = [0; 2];
+                written.read_exact(&mut request).await.expect("a challenge is requested");
+                notify.write_all(&[&[0x10, 0x02, 0x01][..], &[0x5a; 16]].concat()).await.expect("can send the challenge");
+                let mut answer = [0; 18];
+                written.read_exact(&mut answer).await.expect("the challenge is answered");
+                notify.write_all(&[0x10, 0x03, 0x08]).await.expect("can reject the answer");
+            };
+            let (result, ()) = futures::join!(band.authenticate(&[0x31; 16]), band_side);
+            assert!(matches!(result, Err(BandError::InvalidAuthKey)));
+            assert!(!band.authenticated);
+        });
+    }
+
+    #[test]
+    fn long_alerts_are_chunked() {
+        block_on(async {
+            let (band, _peer) = memory_band().await;
+            let chars = band.chars.as_ref().expect("has characteristics");
+
+            let short = Alert { alert_type: AlertType::Message, title: "Hi", message: "" };
+            band.send_alert(&short).await.expect("can send a short alert");
+            assert_eq!(chars.alert.writes(), vec![encode_alert(&short, false)]);
+            assert!(chars.chunked_transfer.writes().is_empty());
+
+            let long = Alert {
+                alert_type: AlertType::Message,
+                title: "Someone",
+                message: "a message that is much too long to fit in a single write to the alert characteristic"
+            };
+            band.send_alert(&long).await.expect("can send a long alert");
+            let payload = encode_alert(&long, false);
+            let chunks = chars.chunked_transfer.writes();
+            assert!(chunks.len() > 1);
+            assert_eq!(chunks, encode_chunks(CHUNKED_ALERT, &payload));
+            // put back together, the chunks are the whole alert
+            assert_eq!(chunks.iter().flat_map(|chunk| chunk[3..].to_vec()).collect::<Vec<_>>(), payload);
+            // nothing more went to the alert characteristic
+            assert_eq!(chars.alert.writes().len(), 1);
+        });
+    }
+
+    #[test]
+    fn media_info_is_chunked() {
+        block_on(async {
+            let (band, _peer) = memory_band().await;
+            let chars = band.chars.as_ref().expect("has characteristics");
+            band.set_media_info(&None).await.expect("can send the media info");
+            assert_eq!(chars.chunked_transfer.writes(), encode_chunks(CHUNKED_MUSIC, &encode_media_info(&None)));
+        });
+    }
+
+    #[test]
+    fn read_and_notify_in_memory() {
+        block_on(async {
+            let (band, _peer) = memory_band().await;
+            let battery = band.chars.as_ref().map(|chars| &chars.battery).expect("has characteristics");
+
+            let mut value = [&[0x0f, 55, 0x01][..], &TIME, &[0x00], &TIME].concat();
+            battery.push_read(&value);
+            assert_eq!(band.get_battery().await.expect("can read the battery").battery_level, 55);
+
+            let mut listener = band.stream_battery().await.expect("can listen to the battery");
+            let mut notify = battery.take_notify_peer().expect("notifications were started");
+            value[1] = 54;
+            notify.write_all(&value).await.expect("can notify");
+            let status = listener.next().await.flatten().expect("the notification is a battery status");
+            assert_eq!((status.battery_level, status.charging), (54, true));
+        });
+    }
+}
@@ -0,0 +1,72 @@
+use std::{collections::HashSet, fmt::Display};
+
+use crate::protocol;
+
+use super::{Alert, BatteryStatus, CurrentActivity, CHAR_BATTERY, CHAR_STEPS, SERVICE_BAND_0};
+
+/// the bands that speak (close enough to) the Mi Band 4's protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BandModel {
+    #[default]
+    MiBand4,
+    MiBand5,
+    AmazfitBand5
+}
+
+impl BandModel {
+    /// from the services and name a device advertises
+    /// `None` if it isn't a band at all - bands with names we don't know are treated as a Mi Band 4
+    pub fn detect(name: Option<&str>, services: &HashSet<String>) -> Option<Self> {
+        if !services.is_empty() && !services.contains(SERVICE_BAND_0) { return None }
+        let name = name.unwrap_or_default().to_ascii_lowercase();
+        Some(if name.starts_with("amazfit") && name.contains("band 5") {
+            Self::AmazfitBand5
+        } else if name.contains("band 5") {
+            // "Mi Smart Band 5"
+            Self::MiBand5
+        } else {
+            Self::MiBand4
+        })
+    }
+
+    /// the protocol quirks of this model
+    pub fn protocol(self) -> &'static dyn BandProtocol {
+        match self {
+            // the Band 5s add things on top of the Mi Band 4's protocol (PAI, SpO2) that aren't used yet,
+            // so everything that is used is the same
+            Self::MiBand4 | Self::MiBand5 | Self::AmazfitBand5 => &MiBand4Protocol
+        }
+    }
+}
+
+impl Display for BandModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // product names, so they aren't translated
+        match self {
+            Self::MiBand4 => write!(f, "Mi Band 4"),
+            Self::MiBand5 => write!(f, "Mi Band 5"),
+            Self::AmazfitBand5 => write!(f, "Amazfit Band 5")
+        }
+    }
+}
+
+/// the parts of the protocol that can differ between models
+/// the defaults are the Mi Band 4's, so a model only overrides what it does differently
+pub trait BandProtocol: Sync {
+    fn char_battery(&self) -> &'static str { CHAR_BATTERY }
+    fn char_steps(&self) -> &'static str { CHAR_STEPS }
+
+    fn encode_alert(&self, alert: &Alert<'_>, simplify_text: bool) -> Vec<u8> {
+        protocol::encode_alert(alert, simplify_text)
+    }
+    fn parse_battery(&self, value: &[u8]) -> Option<BatteryStatus> {
+        protocol::parse_battery(value)
+    }
+    fn parse_current_activity(&self, value: &[u8]) -> Option<CurrentActivity> {
+        protocol::parse_current_activity(value)
+    }
+}
+
+struct MiBand4Protocol;
+
+impl BandProtocol for MiBand4Protocol {}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use futures::{channel::oneshot, future::LocalBoxFuture, FutureExt};
+use zbus::{connection, interface, object_server::{InterfaceRef, SignalContext}, zvariant::Value, Connection, ObjectServer};
+
+use crate::metric_sinks::{MetricSample, MetricSink};
+
+const BUS_NAME: &'static str = "me.grimsteel.MiBand4";
+const SERVICE_PATH: &'static str = "/me/grimsteel/MiBand4";
+/// matches me.grimsteel.miband4-gtk.search-provider.ini
+const SEARCH_PROVIDER_PATH: &'static str = "/me/grimsteel/MiBand4/SearchProvider";
+
+/// another app wants an alert shown on the band
+/// the window sends it and replies with how it went
+pub struct AlertRequest {
+    pub title: String,
+    pub body: String,
+    pub reply: oneshot::Sender<Result<(), String>>
+}
+
+/// the current band, for status bars and desktop extensions
+/// this app keeps the only connection to the band - everything else reads it from here
+struct BandService {
+    address: String,
+    connected: bool,
+    battery_level: u8,
+    steps: u32,
+    tx: async_channel::Sender<AlertRequest>
+}
+
+#[interface(name = "me.grimsteel.MiBand4")]
+impl BandService {
+    /// the mac address of the band that's open in the app (empty if there isn't one)
+    #[zbus(property)]
+    fn address(&self) -> &str {
+        &self.address
+    }
+
+    #[zbus(property)]
+    fn connected(&self) -> bool {
+        self.connected
+    }
+
+    /// percent
+    #[zbus(property)]
+    fn battery_level(&self) -> u8 {
+        self.battery_level
+    }
+
+    /// today's steps
+    #[zbus(property)]
+    fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// show a message on the band
+    async fn send_alert(&self, title: String, body: String) -> zbus::fdo::Result<()> {
+        let (reply, reply_rx) = oneshot::channel();
+        self.tx.send(AlertRequest { title, body, reply }).await
+            .map_err(|_| zbus::fdo::Error::Failed("The app isn't listening".into()))?;
+        match reply_rx.await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(err)) => Err(zbus::fdo::Error::Failed(err)),
+            Err(_) => Err(zbus::fdo::Error::Failed("The alert was dropped".into()))
+        }
+    }
+
+    /// a new measurement while the heart rate is being monitored continuously
+    #[zbus(signal)]
+    async fn heart_rate(ctxt: &SignalContext<'_>, bpm: u16) -> zbus::Result<()>;
+
+    /// fell asleep, woke up in the night, or got up (`sleep_tracking_started`, `awake` or `sleep_tracking_stopped`)
+    #[zbus(signal)]
+    async fn sleep(ctxt: &SignalContext<'_>, event: &str) -> zbus::Result<()>;
+}
+
+/// a result in the GNOME Shell overview - activating it opens the band on the card that shows it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchResult {
+    Battery,
+    Steps
+}
+
+impl SearchResult {
+    const ALL: [Self; 2] = [Self::Battery, Self::Steps];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Battery => "battery",
+            Self::Steps => "steps"
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|result| result.id() == id)
+    }
+
+    fn keywords(&self) -> &'static [&'static str] {
+        match self {
+            Self::Battery => &["battery", "charge", "charging", "band"],
+            Self::Steps => &["steps", "activity", "walked", "band"]
+        }
+    }
+
+    /// every term has to start one of the keywords (so "bat" matches while it's being typed)
+    fn matches(&self, terms: &[String]) -> bool {
+        !terms.is_empty() && terms.iter().all(|term| {
+            let term = term.to_lowercase();
+            self.keywords().iter().any(|keyword| keyword.starts_with(&term))
+        })
+    }
+}
+
+/// org.gnome.Shell.SearchProvider2 - shows the values `BandService` has in the overview
+struct SearchProvider {
+    tx: async_channel::Sender<Option<SearchResult>>
+}
+
+impl SearchProvider {
+    /// nothing is shown if no band is open in the app
+    async fn results(server: &ObjectServer, ids: impl Iterator<Item = SearchResult>, terms: &[String]) -> Vec<String> {
+        let Ok(iface) = server.interface::<_, BandService>(SERVICE_PATH).await else { return vec![] };
+        if iface.get().await.address.is_empty() { return vec![] }
+        ids.filter(|result| result.matches(terms)).map(|result| result.id().to_string()).collect()
+    }
+}
+
+#[interface(name = "org.gnome.Shell.SearchProvider2")]
+impl SearchProvider {
+    async fn get_initial_result_set(&self, #[zbus(object_server)] server: &ObjectServer, terms: Vec<String>) -> Vec<String> {
+        Self::results(server, SearchResult::ALL.into_iter(), &terms).await
+    }
+
+    async fn get_subsearch_result_set(&self, #[zbus(object_server)] server: &ObjectServer, previous_results: Vec<String>, terms: Vec<String>) -> Vec<String> {
+        Self::results(server, previous_results.iter().filter_map(|id| SearchResult::from_id(id)), &terms).await
+    }
+
+    async fn get_result_metas(&self, #[zbus(object_server)] server: &ObjectServer, ids: Vec<String>) -> zbus::fdo::Result<Vec<HashMap<String, Value<'static>>>> {
+        let iface = server.interface::<_, BandService>(SERVICE_PATH).await?;
+        let service = iface.get().await;
+        let description = if service.connected {
+            format!("Mi Band 4 ({})", service.address)
+        } else {
+            format!("Mi Band 4 ({}) - last read while connected", service.address)
+        };
+        Ok(ids.iter().filter_map(|id| SearchResult::from_id(id)).map(|result| {
+            let (name, icon) = match result {
+                SearchResult::Battery => (format!("Battery: {}%", service.battery_level), "battery-symbolic"),
+                SearchResult::Steps => (format!("Steps: {}", service.steps), "emoji-people-symbolic")
+            };
+            HashMap::from([
+                ("id".to_string(), Value::from(result.id())),
+                ("name".to_string(), Value::from(name)),
+                ("description".to_string(), Value::from(description.clone())),
+                ("gicon".to_string(), Value::from(icon))
+            ])
+        }).collect())
+    }
+
+    async fn activate_result(&self, id: String, _terms: Vec<String>, _timestamp: u32) -> zbus::fdo::Result<()> {
+        let result = SearchResult::from_id(&id).ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("Unknown result {id}")))?;
+        self.tx.send(Some(result)).await
+            .map_err(|_| zbus::fdo::Error::Failed("The app isn't listening".into()))
+    }
+
+    /// "show more results" - just bring up the app
+    async fn launch_search(&self, _terms: Vec<String>, _timestamp: u32) -> zbus::fdo::Result<()> {
+        self.tx.send(None).await
+            .map_err(|_| zbus::fdo::Error::Failed("The app isn't listening".into()))
+    }
+}
+
+/// updates what's served - cheap to clone
+#[derive(Clone)]
+pub struct BandServiceHandle {
+    connection: Connection
+}
+
+impl BandServiceHandle {
+    async fn interface(&self) -> zbus::Result<InterfaceRef<BandService>> {
+        self.connection.object_server().interface::<_, BandService>(SERVICE_PATH).await
+    }
+
+    /// only signals a change if it actually changed - this is called for every RSSI update
+    pub async fn set_connected(&self, address: String, connected: bool) -> zbus::Result<()> {
+        let iface = self.interface().await?;
+        let (address_changed, connected_changed) = {
+            let mut service = iface.get_mut().await;
+            let changed = (service.address != address, service.connected != connected);
+            service.address = address;
+            service.connected = connected;
+            changed
+        };
+        let service = iface.get().await;
+        if address_changed {
+            service.address_changed(iface.signal_context()).await?;
+        }
+        if connected_changed {
+            service.connected_changed(iface.signal_context()).await?;
+        }
+        Ok(())
+    }
+}
+
+impl MetricSink for BandServiceHandle {
+    fn name(&self) -> &'static str {
+        "D-Bus"
+    }
+
+    fn handle<'a>(&'a self, _band_mac: &'a str, sample: &'a MetricSample) -> LocalBoxFuture<'a, Result<(), String>> {
+        async move {
+            let iface = self.interface().await?;
+            let ctxt = iface.signal_context();
+            match sample {
+                MetricSample::HeartRate { bpm, .. } => BandService::heart_rate(ctxt, *bpm).await?,
+                MetricSample::Sleep { event, .. } => BandService::sleep(ctxt, event.name()).await?,
+                MetricSample::Battery { level, .. } => {
+                    iface.get_mut().await.battery_level = *level;
+                    iface.get().await.battery_level_changed(ctxt).await?;
+                },
+                MetricSample::Totals { totals, .. } => {
+                    iface.get_mut().await.steps = totals.steps as u32;
+                    iface.get().await.steps_changed(ctxt).await?;
+                },
+                // the history isn't served
+                MetricSample::HeartRates(_) | MetricSample::Activity(_) | MetricSample::Workouts(_) => {}
+            }
+            Ok(())
+        }.map(|result: zbus::Result<()>| result.map_err(|err| err.to_string())).boxed_local()
+    }
+}
+
+/// serve the band's state on the session bus, and search results for it to GNOME Shell
+/// the receivers get every alert other apps ask for, and the search results that were activated (`None` to just show the app)
+pub async fn serve_band_service() -> zbus::Result<(BandServiceHandle, async_channel::Receiver<AlertRequest>, async_channel::Receiver<Option<SearchResult>>)> {
+    let (tx, rx) = async_channel::unbounded();
+    let (search_tx, search_rx) = async_channel::unbounded();
+    let service = BandService { address: String::new(), connected: false, battery_level: 0, steps: 0, tx };
+    let connection = connection::Builder::session()?
+        .name(BUS_NAME)?
+        .serve_at(SERVICE_PATH, service)?
+        .serve_at(SEARCH_PROVIDER_PATH, SearchProvider { tx: search_tx })?
+        .build()
+        .await?;
+    Ok((BandServiceHandle { connection }, rx, search_rx))
+}
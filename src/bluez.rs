@@ -1,12 +1,15 @@
-use std::{collections::{HashMap, HashSet}, os::fd::OwnedFd};
+use std::{collections::{HashMap, HashSet}, io, os::fd::OwnedFd, sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex, Weak}, time::{Duration, Instant}};
 
+use async_channel::TrySendError;
 use async_net::unix::UnixStream;
-use zbus::{fdo::{InterfacesAdded, ObjectManagerProxy}, names::OwnedInterfaceName, proxy, zvariant::{DeserializeDict, ObjectPath, OwnedFd as ZOwnedFd, OwnedObjectPath, OwnedValue, SerializeDict, Type}, Connection};
+use zbus::{fdo::{InterfacesAdded, ObjectManagerProxy}, interface, message::Type as MessageType, names::OwnedInterfaceName, proxy, zvariant::{DeserializeDict, ObjectPath, OwnedFd as ZOwnedFd, OwnedObjectPath, OwnedValue, SerializeDict, Type}, Connection, DBusError, MatchRule, Message, MessageStream};
 
-use futures::stream::select;
+use futures::{channel::oneshot, future::{self, Either, LocalBoxFuture}, pin_mut, stream::{self, select, BoxStream}, AsyncReadExt, FutureExt};
 
 use futures_util::StreamExt;
 
+use crate::{metrics::{self, Operation}, trace::{self, TraceKind}};
+
 #[derive(DeserializeDict, SerializeDict, Type)]
 #[zvariant(signature = "dict")]
 pub struct DiscoveryFilter {
@@ -63,6 +66,8 @@ trait Adapter {
     fn set_discovery_filter(&self, filter: DiscoveryFilter) -> zbus::Result<()>;
     fn start_discovery(&self) -> zbus::Result<()>;
     fn stop_discovery(&self) -> zbus::Result<()>;
+    /// forgets the device along with its pairing keys
+    fn remove_device(&self, device: &ObjectPath<'_>) -> zbus::Result<()>;
 
     #[zbus(property)]
     fn powered(&self) -> zbus::Result<bool>;
@@ -74,15 +79,28 @@ trait Adapter {
 trait Device {
     fn connect(&self) -> zbus::Result<()>;
     fn disconnect(&self) -> zbus::Result<()>;
+    fn pair(&self) -> zbus::Result<()>;
 
     #[zbus(property)]
     fn address(&self) -> zbus::Result<String>;
     #[zbus(property)]
     fn connected(&self) -> zbus::Result<bool>;
+    /// the name the device advertises
+    #[zbus(property)]
+    fn name(&self) -> zbus::Result<String>;
     #[zbus(property)]
     fn services_resolved(&self) -> zbus::Result<bool>;
     #[zbus(property, name="RSSI")]
     fn rssi(&self) -> zbus::Result<i16>;
+    #[zbus(property)]
+    fn paired(&self) -> zbus::Result<bool>;
+    /// only in BlueZ 5.67 and up
+    #[zbus(property)]
+    fn bonded(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn trusted(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_trusted(&self, trusted: bool) -> zbus::Result<()>;
 }
 
 impl<'a> DeviceProxy<'a> {
@@ -111,15 +129,35 @@ trait GattCharacteristic {
 
 impl<'a> GattCharacteristicProxy<'a> {
     pub async fn read_value_default(&self) -> zbus::Result<Vec<u8>> {
-        self.read_value(&ReadOptions::default()).await
+        let op = metrics::start(Operation::Read);
+        let value = self.read_value(&ReadOptions::default()).await;
+        op.finish(value.is_ok());
+        let value = value?;
+        self.trace(TraceKind::Read, &value).await;
+        Ok(value)
     }
 
     pub async fn write_value_request(&self, value: &[u8]) -> zbus::Result<()> {
-        self.write_value(value, &WriteOptions::request()).await
+        self.trace(TraceKind::Write, value).await;
+        let op = metrics::start(Operation::Write);
+        let result = self.write_value(value, &WriteOptions::request()).await;
+        op.finish(result.is_ok());
+        result
     }
 
     pub async fn write_value_command(&self, value: &[u8]) -> zbus::Result<()> {
-        self.write_value(value, &WriteOptions::command()).await
+        self.trace(TraceKind::Write, value).await;
+        let op = metrics::start(Operation::Write);
+        let result = self.write_value(value, &WriteOptions::command()).await;
+        op.finish(result.is_ok());
+        result
+    }
+
+    /// add this operation to the protocol trace (if it's enabled)
+    async fn trace(&self, kind: TraceKind, value: &[u8]) {
+        if trace::is_enabled() {
+            trace::record(kind, &self.uuid().await.unwrap_or_default(), value);
+        }
     }
 
     pub async fn acquire_write_stream(&self) -> zbus::Result<(UnixStream, u16)> {
@@ -141,11 +179,453 @@ impl<'a> GattCharacteristicProxy<'a> {
     }
 }
 
+// #region GATT transport
+
+/// what the band code needs from a characteristic
+///
+/// the BlueZ proxy is the real one, and `MemoryCharacteristic` stands in for it
+/// so the protocol can be run without a band or BlueZ
+pub trait GattTransport {
+    /// identifies the characteristic (notify sessions are shared by this)
+    fn object_path(&self) -> String;
+    fn uuid(&self) -> LocalBoxFuture<'_, zbus::Result<String>>;
+
+    fn read(&self) -> LocalBoxFuture<'_, zbus::Result<Vec<u8>>>;
+    /// write with a response
+    fn write_request<'b>(&'b self, value: &'b [u8]) -> LocalBoxFuture<'b, zbus::Result<()>>;
+    /// write without a response
+    fn write_command<'b>(&'b self, value: &'b [u8]) -> LocalBoxFuture<'b, zbus::Result<()>>;
+
+    /// a socket to write values to, and the MTU
+    fn write_stream(&self) -> LocalBoxFuture<'_, zbus::Result<(UnixStream, u16)>>;
+    /// a socket the notifications come in on, and the MTU
+    fn notify_stream(&self) -> LocalBoxFuture<'_, zbus::Result<(UnixStream, u16)>>;
+}
+
+impl GattTransport for GattCharacteristicProxy<'_> {
+    fn object_path(&self) -> String {
+        self.inner().path().to_string()
+    }
+
+    fn uuid(&self) -> LocalBoxFuture<'_, zbus::Result<String>> {
+        GattCharacteristicProxy::uuid(self).boxed_local()
+    }
+
+    fn read(&self) -> LocalBoxFuture<'_, zbus::Result<Vec<u8>>> {
+        self.read_value_default().boxed_local()
+    }
+
+    fn write_request<'b>(&'b self, value: &'b [u8]) -> LocalBoxFuture<'b, zbus::Result<()>> {
+        self.write_value_request(value).boxed_local()
+    }
+
+    fn write_command<'b>(&'b self, value: &'b [u8]) -> LocalBoxFuture<'b, zbus::Result<()>> {
+        self.write_value_command(value).boxed_local()
+    }
+
+    fn write_stream(&self) -> LocalBoxFuture<'_, zbus::Result<(UnixStream, u16)>> {
+        self.acquire_write_stream().boxed_local()
+    }
+
+    fn notify_stream(&self) -> LocalBoxFuture<'_, zbus::Result<(UnixStream, u16)>> {
+        self.acquire_notify_stream().boxed_local()
+    }
+}
+
+#[cfg(test)]
+pub use memory::MemoryCharacteristic;
+
+/// stands in for the band in the tests
+#[cfg(test)]
+mod memory {
+    use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+    use async_net::unix::UnixStream;
+    use futures::{future::{self, LocalBoxFuture}, FutureExt};
+
+    use super::GattTransport;
+
+    /// the MTU `MemoryCharacteristic` reports (the smallest ATT MTU)
+    const MEMORY_MTU: u16 = 23;
+
+    /// an in-memory characteristic: reads come from a queue, and writes are kept
+    ///
+    /// the streams are socket pairs - the other ends are kept here, to send notifications and see what was written.
+    /// they're stream sockets rather than seqpacket ones like BlueZ's, so values written back to back can arrive together
+    ///
+    /// clones share everything, so a test can keep one to play the band while `MiBand` uses the other
+    #[derive(Clone)]
+    pub struct MemoryCharacteristic {
+        uuid: String,
+        reads: Rc<RefCell<VecDeque<Vec<u8>>>>,
+        writes: Rc<RefCell<Vec<Vec<u8>>>>,
+        write_peer: Rc<RefCell<Option<UnixStream>>>,
+        notify_peer: Rc<RefCell<Option<UnixStream>>>
+    }
+
+    impl MemoryCharacteristic {
+        pub fn new(uuid: &str) -> Self {
+            Self {
+                uuid: uuid.to_string(),
+                reads: Default::default(),
+                writes: Default::default(),
+                write_peer: Default::default(),
+                notify_peer: Default::default()
+            }
+        }
+
+        /// the value for the next read
+        pub fn push_read(&self, value: &[u8]) {
+            self.reads.borrow_mut().push_back(value.to_vec());
+        }
+
+        /// everything written with `write_request` and `write_command`, oldest first
+        pub fn writes(&self) -> Vec<Vec<u8>> {
+            self.writes.borrow().clone()
+        }
+
+        /// the other end of the last `write_stream`
+        pub fn take_write_peer(&self) -> Option<UnixStream> {
+            self.write_peer.take()
+        }
+
+        /// the other end of the last `notify_stream` - whatever is written to it comes in as notifications
+        pub fn take_notify_peer(&self) -> Option<UnixStream> {
+            self.notify_peer.take()
+        }
+
+        fn record_write(&self, value: &[u8]) -> LocalBoxFuture<'_, zbus::Result<()>> {
+            self.writes.borrow_mut().push(value.to_vec());
+            future::ready(Ok(())).boxed_local()
+        }
+
+        fn stream_pair<'b>(&'b self, peer: &'b RefCell<Option<UnixStream>>) -> LocalBoxFuture<'b, zbus::Result<(UnixStream, u16)>> {
+            async move {
+                let (stream, other) = UnixStream::pair()?;
+                *peer.borrow_mut() = Some(other);
+                Ok((stream, MEMORY_MTU))
+            }.boxed_local()
+        }
+    }
+
+    impl GattTransport for MemoryCharacteristic {
+        fn object_path(&self) -> String {
+            format!("/memory/{}", self.uuid)
+        }
+
+        fn uuid(&self) -> LocalBoxFuture<'_, zbus::Result<String>> {
+            future::ready(Ok(self.uuid.clone())).boxed_local()
+        }
+
+        fn read(&self) -> LocalBoxFuture<'_, zbus::Result<Vec<u8>>> {
+            let value = self.reads.borrow_mut().pop_front()
+                .ok_or_else(|| zbus::Error::Failure("nothing to read".into()));
+            future::ready(value).boxed_local()
+        }
+
+        fn write_request<'b>(&'b self, value: &'b [u8]) -> LocalBoxFuture<'b, zbus::Result<()>> {
+            self.record_write(value)
+        }
+
+        fn write_command<'b>(&'b self, value: &'b [u8]) -> LocalBoxFuture<'b, zbus::Result<()>> {
+            self.record_write(value)
+        }
+
+        fn write_stream(&self) -> LocalBoxFuture<'_, zbus::Result<(UnixStream, u16)>> {
+            self.stream_pair(&self.write_peer)
+        }
+
+        fn notify_stream(&self) -> LocalBoxFuture<'_, zbus::Result<(UnixStream, u16)>> {
+            self.stream_pair(&self.notify_peer)
+        }
+    }
+}
+
+// #endregion
+
+// #region Notify multiplexing
+
+/// how many notifications a subscriber can fall behind by before it starts missing them
+const NOTIFY_BACKLOG: usize = 64;
+
+/// the smallest ATT MTU - the buffer is never smaller than this, even if BlueZ reports something odd
+const MIN_ATT_MTU: usize = 23;
+
+/// one AcquireNotify session, shared by everything subscribed to the characteristic
+#[derive(Debug)]
+struct SharedNotify {
+    uuid: String,
+    mtu: usize,
+    /// whichever subscriber holds this reads the next notification for everyone
+    stream: async_lock::Mutex<UnixStream>,
+    subscribers: Mutex<Vec<async_channel::Sender<Vec<u8>>>>,
+    /// the fd stopped working (usually because the band disconnected), so it can't be shared anymore
+    closed: AtomicBool
+}
+
+impl SharedNotify {
+    /// read a single notification and hand it to every subscriber
+    async fn read_next(&self) -> io::Result<()> {
+        let mut stream = self.stream.lock().await;
+        // the fd is a seqpacket socket, so each read is one whole notification as long as the buffer can hold it
+        let mut buf = vec![0; self.mtu.max(MIN_ATT_MTU)];
+        let len = match stream.read(&mut buf).await {
+            Ok(0) => Err(io::ErrorKind::UnexpectedEof.into()),
+            result => result
+        }.inspect_err(|_| self.closed.store(true, Ordering::Relaxed))?;
+        buf.truncate(len);
+        // counted once, however many subscribers there are
+        metrics::record_notification();
+        trace::record(TraceKind::Notification, &self.uuid, &buf);
+
+        self.subscribers.lock().expect("can lock subscribers")
+            // a subscriber that's too far behind just misses this one
+            .retain(|tx| !matches!(tx.try_send(buf.clone()), Err(TrySendError::Closed(_))));
+        Ok(())
+    }
+}
+
+/// the notifications from a characteristic
+/// the notify session ends once every subscription to it is dropped
+#[derive(Debug)]
+pub struct NotifySubscription {
+    shared: Arc<SharedNotify>,
+    rx: async_channel::Receiver<Vec<u8>>
+}
+
+impl NotifySubscription {
+    /// the next notification, or an error if the band can't send any more
+    pub async fn recv(&self) -> io::Result<Vec<u8>> {
+        loop {
+            // either another subscriber already read one, or it's our turn to read it
+            let next = self.rx.recv();
+            let read = self.shared.read_next();
+            pin_mut!(next, read);
+            match future::select(next, read).await {
+                Either::Left((value, _)) => return value.map_err(|_| io::ErrorKind::BrokenPipe.into()),
+                Either::Right((result, _)) => result?
+            }
+        }
+    }
+
+    /// every notification, ending at the first error
+    pub fn into_stream(self) -> BoxStream<'static, Vec<u8>> {
+        stream::unfold(self, |subscription| async move {
+            let value = subscription.recv().await.ok()?;
+            Some((value, subscription))
+        }).boxed()
+    }
+}
+
+// #endregion
+
+#[proxy(default_service = "org.bluez", default_path = "/org/bluez", interface = "org.bluez.AgentManager1", gen_blocking = false)]
+trait AgentManager {
+    fn register_agent(&self, agent: &ObjectPath<'_>, capability: &str) -> zbus::Result<()>;
+    fn unregister_agent(&self, agent: &ObjectPath<'_>) -> zbus::Result<()>;
+}
+
+// #endregion
+
+// #region Pairing agent
+
+/// where our org.bluez.Agent1 lives
+const AGENT_PATH: &'static str = "/me/grimsteel/miband4gtk/agent";
+
+/// something BlueZ needs from the user while pairing
+/// requests with a `reply` are rejected if it's dropped
+#[derive(Debug)]
+pub enum AgentRequest {
+    /// check that this passkey matches the one on the device
+    Confirm { device: OwnedObjectPath, passkey: u32, reply: oneshot::Sender<bool> },
+    /// type in the passkey shown on the device
+    Passkey { device: OwnedObjectPath, reply: oneshot::Sender<Option<u32>> },
+    /// type in the PIN code shown on the device
+    PinCode { device: OwnedObjectPath, reply: oneshot::Sender<Option<String>> },
+    /// show this passkey (or PIN code) so it can be typed in on the device
+    Display { device: OwnedObjectPath, code: String },
+    /// let the device pair without any code
+    Authorize { device: OwnedObjectPath, reply: oneshot::Sender<bool> },
+    /// BlueZ gave up on the last request
+    Cancel
+}
+
+/// the name of an org.bluez.Error (without the prefix)
+pub(crate) fn bluez_error_name(err: &zbus::Error) -> Option<&str> {
+    match err {
+        zbus::Error::MethodError(name, _, _) => name.as_str().strip_prefix("org.bluez.Error."),
+        _ => None
+    }
+}
+
+#[derive(Debug, DBusError)]
+#[zbus(prefix = "org.bluez.Error")]
+enum AgentError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    Rejected(String),
+    Canceled(String)
+}
+
+/// forwards everything BlueZ asks about one device to the UI
+struct PairingAgent {
+    tx: async_channel::Sender<AgentRequest>,
+    /// the band being paired - requests for anything else are rejected
+    device: OwnedObjectPath
+}
+
+impl PairingAgent {
+    fn check_device(&self, device: &OwnedObjectPath) -> Result<(), AgentError> {
+        if *device == self.device { Ok(()) } else { Err(AgentError::Rejected("Not the band being paired".into())) }
+    }
+
+    /// send a request and wait for the UI to answer it
+    async fn ask<T>(&self, request: impl FnOnce(oneshot::Sender<T>) -> AgentRequest) -> Result<T, AgentError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.tx.send(request(reply_tx)).await.map_err(|_| AgentError::Canceled("The app isn't listening".into()))?;
+        reply_rx.await.map_err(|_| AgentError::Canceled("The request was dismissed".into()))
+    }
+
+    fn accepted(accepted: bool) -> Result<(), AgentError> {
+        if accepted { Ok(()) } else { Err(AgentError::Rejected("Rejected by the user".into())) }
+    }
+}
+
+#[interface(name = "org.bluez.Agent1")]
+impl PairingAgent {
+    fn release(&self) {}
+
+    async fn request_pin_code(&self, device: OwnedObjectPath) -> Result<String, AgentError> {
+        self.check_device(&device)?;
+        self.ask(|reply| AgentRequest::PinCode { device, reply }).await?
+            .ok_or_else(|| AgentError::Rejected("No PIN code entered".into()))
+    }
+
+    async fn display_pin_code(&self, device: OwnedObjectPath, pincode: String) {
+        if self.check_device(&device).is_err() { return }
+        let _ = self.tx.send(AgentRequest::Display { device, code: pincode }).await;
+    }
+
+    async fn request_passkey(&self, device: OwnedObjectPath) -> Result<u32, AgentError> {
+        self.check_device(&device)?;
+        self.ask(|reply| AgentRequest::Passkey { device, reply }).await?
+            .ok_or_else(|| AgentError::Rejected("No passkey entered".into()))
+    }
+
+    async fn display_passkey(&self, device: OwnedObjectPath, passkey: u32, _entered: u16) {
+        if self.check_device(&device).is_err() { return }
+        let _ = self.tx.send(AgentRequest::Display { device, code: format!("{passkey:06}") }).await;
+    }
+
+    async fn request_confirmation(&self, device: OwnedObjectPath, passkey: u32) -> Result<(), AgentError> {
+        self.check_device(&device)?;
+        Self::accepted(self.ask(|reply| AgentRequest::Confirm { device, passkey, reply }).await?)
+    }
+
+    async fn request_authorization(&self, device: OwnedObjectPath) -> Result<(), AgentError> {
+        self.check_device(&device)?;
+        Self::accepted(self.ask(|reply| AgentRequest::Authorize { device, reply }).await?)
+    }
+
+    /// the band being paired can use its services - nothing else is let in
+    fn authorize_service(&self, device: OwnedObjectPath, _uuid: String) -> Result<(), AgentError> {
+        self.check_device(&device)
+    }
+
+    async fn cancel(&self) {
+        let _ = self.tx.send(AgentRequest::Cancel).await;
+    }
+}
+
 // #endregion
 
+/// object path -> interface -> property -> value, as GetManagedObjects returns it
+type ManagedObjects = HashMap<OwnedObjectPath, HashMap<OwnedInterfaceName, HashMap<String, OwnedValue>>>;
+
+/// the signals are the source of truth, but if they pile up this much some might have been dropped
+const OBJECT_CACHE_BACKLOG: usize = 256;
+/// take a new snapshot at least this often anyway
+const OBJECT_CACHE_MAX_AGE: Duration = Duration::from_secs(30);
+
+/// a GetManagedObjects snapshot, kept up to date with the signals BlueZ has sent since
+#[derive(Debug)]
+struct ObjectCache {
+    objects: ManagedObjects,
+    signals: MessageStream,
+    taken: Instant
+}
+
+impl ObjectCache {
+    /// apply the signals that have arrived since the last call
+    /// returns false if the snapshot can't be trusted anymore
+    fn catch_up(&mut self) -> bool {
+        let mut applied = 0;
+        loop {
+            match self.signals.next().now_or_never() {
+                // nothing new
+                None => return true,
+                Some(Some(Ok(message))) => {
+                    applied += 1;
+                    if applied >= OBJECT_CACHE_BACKLOG || !self.apply(&message) { return false }
+                },
+                Some(_) => return false
+            }
+        }
+    }
+
+    fn apply(&mut self, message: &Message) -> bool {
+        let header = message.header();
+        let (Some(interface), Some(member)) = (header.interface(), header.member()) else { return true };
+        match (interface.as_str(), member.as_str()) {
+            ("org.freedesktop.DBus.ObjectManager", "InterfacesAdded") => {
+                let Ok((path, interfaces)) = message.body().deserialize::<(OwnedObjectPath, HashMap<OwnedInterfaceName, HashMap<String, OwnedValue>>)>() else { return false };
+                self.objects.entry(path).or_default().extend(interfaces);
+            },
+            ("org.freedesktop.DBus.ObjectManager", "InterfacesRemoved") => {
+                let Ok((path, interfaces)) = message.body().deserialize::<(OwnedObjectPath, Vec<String>)>() else { return false };
+                if let Some(object) = self.objects.get_mut(&path) {
+                    object.retain(|name, _| !interfaces.iter().any(|removed| removed == name.as_str()));
+                    if object.is_empty() {
+                        self.objects.remove(&path);
+                    }
+                }
+            },
+            ("org.freedesktop.DBus.Properties", "PropertiesChanged") => {
+                let Some(path) = header.path() else { return false };
+                let Ok((interface, changed, invalidated)) = message.body().deserialize::<(String, HashMap<String, OwnedValue>, Vec<String>)>() else { return false };
+                let properties = self.objects.get_mut(&OwnedObjectPath::from(path.to_owned()))
+                    .and_then(|object| object.iter_mut().find(|(name, _)| name.as_str() == interface))
+                    .map(|(_, properties)| properties);
+                if let Some(properties) = properties {
+                    properties.extend(changed);
+                    for property in invalidated {
+                        properties.remove(&property);
+                    }
+                }
+            },
+            _ => {}
+        }
+        true
+    }
+
+    /// the callers take the values apart, so they get their own copy
+    fn snapshot(&self) -> ManagedObjects {
+        self.objects.iter().map(|(path, interfaces)| {
+            let interfaces = interfaces.iter().map(|(name, properties)| {
+                let properties = properties.iter()
+                    .filter_map(|(property, value)| Some((property.clone(), value.try_clone().ok()?)))
+                    .collect();
+                (name.clone(), properties)
+            }).collect();
+            (path.clone(), interfaces)
+        }).collect()
+    }
+}
 
 // Map of service id to map of char id to proxy
 pub type DeviceServiceChars<'a> = HashMap<String, HashMap<String, GattCharacteristicProxy<'a>>>;
+// Map of service id to map of char id to object path
+pub type DeviceServicePaths = HashMap<String, HashMap<String, String>>;
 
 #[derive(Debug)]
 pub enum DiscoveredDeviceEvent {
@@ -167,22 +647,127 @@ pub struct DiscoveredDevice {
 pub struct BluezSession<'a> {
     connection: Connection,
     pub adapter: AdapterProxy<'a>,
-    object_manager: ObjectManagerProxy<'a>
+    object_manager: ObjectManagerProxy<'a>,
+    /// how many parts of the app currently want discovery running
+    discovery_refs: Arc<async_lock::Mutex<u32>>,
+    /// the notify sessions that are running, by characteristic path
+    notify_sessions: Arc<async_lock::Mutex<HashMap<String, Weak<SharedNotify>>>>,
+    object_cache: Arc<async_lock::Mutex<Option<ObjectCache>>>
 }
 
 impl<'a> BluezSession<'a> {
     pub async fn new() -> zbus::Result<Self> {
-        let conn = Connection::system().await?;
+        Self::with_connection(Connection::system().await?).await
+    }
+
+    /// a session on a connection that isn't the system bus
+    pub async fn with_connection(conn: Connection) -> zbus::Result<Self> {
         let adapter = AdapterProxy::new(&conn).await?;
         let object_manager = ObjectManagerProxy::builder(&conn).destination("org.bluez")?.path("/")?.build().await?;
 
         Ok(Self {
             connection: conn,
             adapter,
-            object_manager
+            object_manager,
+            discovery_refs: Default::default(),
+            notify_sessions: Default::default(),
+            object_cache: Default::default()
         })
     }
 
+    /// everything BlueZ is managing - from the cache unless it's too old or might have missed something
+    async fn managed_objects(&self) -> zbus::Result<ManagedObjects> {
+        let mut cache = self.object_cache.lock().await;
+        if let Some(cache) = cache.as_mut() {
+            if cache.taken.elapsed() < OBJECT_CACHE_MAX_AGE && cache.catch_up() {
+                return Ok(cache.snapshot());
+            }
+        }
+
+        // listen before taking the snapshot, so nothing is missed in between
+        let rule = MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .sender("org.bluez")?
+            .build();
+        let signals = MessageStream::for_match_rule(rule, &self.connection, Some(OBJECT_CACHE_BACKLOG)).await?;
+        let objects: ManagedObjects = self.object_manager.get_managed_objects().await?;
+        let new_cache = ObjectCache { objects, signals, taken: Instant::now() };
+        let snapshot = new_cache.snapshot();
+        *cache = Some(new_cache);
+        Ok(snapshot)
+    }
+
+    /// listen to a characteristic's notifications
+    ///
+    /// BlueZ only gives out one AcquireNotify fd per characteristic, so everything in the app
+    /// that wants the same notifications (device events, steps, etc.) shares a single session through here
+    pub async fn subscribe_notify(&self, characteristic: &impl GattTransport) -> zbus::Result<NotifySubscription> {
+        let mut sessions = self.notify_sessions.lock().await;
+        let path = characteristic.object_path();
+        let running = sessions.get(&path)
+            .and_then(Weak::upgrade)
+            .filter(|shared| !shared.closed.load(Ordering::Relaxed));
+        let shared = match running {
+            Some(shared) => shared,
+            None => {
+                let (stream, mtu) = characteristic.notify_stream().await?;
+                let shared = Arc::new(SharedNotify {
+                    uuid: characteristic.uuid().await.unwrap_or_default(),
+                    mtu: mtu as usize,
+                    stream: async_lock::Mutex::new(stream),
+                    subscribers: Mutex::new(vec![]),
+                    closed: AtomicBool::new(false)
+                });
+                // forget the sessions that have ended
+                sessions.retain(|_, shared| shared.strong_count() > 0);
+                sessions.insert(path, Arc::downgrade(&shared));
+                shared
+            }
+        };
+
+        let (tx, rx) = async_channel::bounded(NOTIFY_BACKLOG);
+        shared.subscribers.lock().expect("can lock subscribers").push(tx);
+        Ok(NotifySubscription { shared, rx })
+    }
+
+    /// start discovery with this filter, or just take another reference if it's already running
+    ///
+    /// BlueZ keeps a single discovery session per D-Bus client - starting it twice fails,
+    /// and the first StopDiscovery ends it for everyone in this app - so always go through here
+    pub async fn acquire_discovery(&self, filter: DiscoveryFilter) -> zbus::Result<()> {
+        let mut refs = self.discovery_refs.lock().await;
+        if *refs == 0 {
+            self.adapter.set_discovery_filter(filter).await?;
+            match self.adapter.start_discovery().await {
+                // BlueZ still had our session from before
+                Err(err) if bluez_error_name(&err) == Some("InProgress") => {},
+                result => result?
+            }
+        }
+        *refs += 1;
+        Ok(())
+    }
+
+    /// drop a reference taken with `acquire_discovery`, stopping discovery after the last one
+    pub async fn release_discovery(&self) -> zbus::Result<()> {
+        let mut refs = self.discovery_refs.lock().await;
+        match *refs {
+            0 => Ok(()),
+            1 => {
+                *refs = 0;
+                match self.adapter.stop_discovery().await {
+                    // it already ended (the adapter was powered off, etc)
+                    Err(err) if matches!(bluez_error_name(&err), Some("NotReady" | "Failed")) => Ok(()),
+                    result => result
+                }
+            },
+            _ => {
+                *refs -= 1;
+                Ok(())
+            }
+        }
+    }
+
     /// make sure `path` under our adapter and it is not a subpath of a device (which would contain a '/')
     fn is_device_path(&self, path: &ObjectPath) -> bool {
         let adapter_path = self.adapter.0.path().as_str();
@@ -195,7 +780,7 @@ impl<'a> BluezSession<'a> {
     /// get all known devices
     pub async fn get_devices(&self) -> zbus::Result<Vec<DiscoveredDevice>> {
         // get existing managed objects
-        let objects: HashMap<OwnedObjectPath, HashMap<OwnedInterfaceName, HashMap<String, OwnedValue>>> = self.object_manager.get_managed_objects().await?;
+        let objects = self.managed_objects().await?;
 
         // convert each item into a Device
         Ok(objects.into_iter()
@@ -265,7 +850,7 @@ impl<'a> BluezSession<'a> {
         let device_path = device_path.as_str();
 
         // iterate through all objects, finding the chars and services
-        let objects: HashMap<OwnedObjectPath, HashMap<OwnedInterfaceName, HashMap<String, OwnedValue>>> = self.object_manager.get_managed_objects().await?;
+        let objects = self.managed_objects().await?;
 
         for (path, mut interfaces) in objects {
             // make sure it's under this device
@@ -297,7 +882,87 @@ impl<'a> BluezSession<'a> {
         }).collect())
     }
 
+    /// make proxies for characteristics found in an earlier `get_device_characteristics`
+    /// fails if any of them are gone or have changed
+    pub async fn characteristics_from_paths<'c>(&self, paths: &DeviceServicePaths) -> zbus::Result<DeviceServiceChars<'c>> {
+        let mut services = DeviceServiceChars::new();
+        for (service_uuid, chars) in paths {
+            let char_map = services.entry(service_uuid.clone()).or_insert_with(|| HashMap::new());
+            for (uuid, path) in chars {
+                let char_proxy = GattCharacteristicProxy::builder(&self.connection).path(path.clone())?.build().await?;
+                // building the proxy doesn't check if the object exists
+                if char_proxy.uuid().await? != *uuid {
+                    return Err(zbus::Error::Failure(format!("Characteristic at {path} has changed")));
+                }
+                char_map.insert(uuid.clone(), char_proxy);
+            }
+        }
+        Ok(services)
+    }
+
+    /// handle the pairing requests for `device` until `unregister_agent` is called
+    /// it isn't the default agent, so BlueZ only asks it about pairing started from this app - the desktop's agent handles everything else
+    /// the receiver gets everything that needs the user's input, and closes once the agent is unregistered
+    pub async fn register_agent(&self, device: OwnedObjectPath) -> zbus::Result<async_channel::Receiver<AgentRequest>> {
+        let (tx, rx) = async_channel::unbounded();
+        let object_server = self.connection.object_server();
+        // an agent left over from an attempt that wasn't cleaned up would keep the old device (usually there isn't one)
+        let _ = object_server.remove::<PairingAgent, _>(AGENT_PATH).await;
+        object_server.at(AGENT_PATH, PairingAgent { tx, device }).await?;
+
+        let path = ObjectPath::try_from(AGENT_PATH)?;
+        let manager = AgentManagerProxy::new(&self.connection).await?;
+        if let Err(err) = manager.register_agent(&path, "KeyboardDisplay").await {
+            object_server.remove::<PairingAgent, _>(AGENT_PATH).await?;
+            return Err(err);
+        }
+        Ok(rx)
+    }
+
+    /// stop handling pairing requests
+    pub async fn unregister_agent(&self) -> zbus::Result<()> {
+        let path = ObjectPath::try_from(AGENT_PATH)?;
+        let result = AgentManagerProxy::new(&self.connection).await?.unregister_agent(&path).await;
+        // this drops the sender, which ends the receiver
+        self.connection.object_server().remove::<PairingAgent, _>(AGENT_PATH).await?;
+        result
+    }
+
     pub async fn proxy_from_discovered_device<'b, 'c>(&'b self, device_path: OwnedObjectPath) -> zbus::Result<DeviceProxy<'c>> {
         DeviceProxy::builder(&self.connection).path(device_path).expect("is a valid path").build().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use async_io::block_on;
+    use futures::AsyncWriteExt;
+
+    use super::*;
+
+    #[test]
+    fn notifications_arent_cut_short_by_a_small_mtu() {
+        block_on(async {
+            let (stream, mut band) = UnixStream::pair().expect("can make a socket pair");
+            let (tx, rx) = async_channel::bounded(NOTIFY_BACKLOG);
+            // BlueZ reporting something odd
+            let shared = Arc::new(SharedNotify {
+                uuid: String::new(),
+                mtu: 0,
+                stream: async_lock::Mutex::new(stream),
+                subscribers: Mutex::new(vec![tx]),
+                closed: AtomicBool::new(false)
+            });
+            let subscription = NotifySubscription { shared, rx };
+
+            let value: Vec<u8> = (0..20).collect();
+            band.write_all(&value).await.expect("can notify");
+            assert_eq!(subscription.recv().await.expect("gets the notification"), value);
+
+            // the band went away
+            drop(band);
+            assert!(subscription.recv().await.is_err());
+            assert!(subscription.shared.closed.load(Ordering::Relaxed));
+        });
+    }
+}
@@ -0,0 +1,58 @@
+use chrono::{DateTime, Local};
+
+use crate::{calendar::CalendarEvent, sleep::SleepSession, store::{TemperatureUnit, TimeFormat}, weather::{WeatherFormat, WeatherReport}};
+
+/// condition, current temperature, and today's high/low on one line
+pub fn compose_weather(weather: &WeatherReport, format: WeatherFormat) -> String {
+    let temperature = |celsius: f64| match format.unit {
+        TemperatureUnit::Celsius => format!("{:.0}°C", celsius),
+        TemperatureUnit::Fahrenheit => format!("{:.0}°F", celsius * 9.0 / 5.0 + 32.0)
+    };
+    format!(
+        "{} {} ({}/{})",
+        weather.condition.localized(format.language), temperature(weather.temperature), temperature(weather.high), temperature(weather.low)
+    )
+}
+
+/// how long and when they slept, for the morning sleep summary
+pub fn compose_sleep_summary(sleep: &SleepSession, time_format: TimeFormat) -> String {
+    let minutes = sleep.asleep.as_secs() / 60;
+    let time = |time: &DateTime<Local>| match time_format {
+        TimeFormat::H12 => time.format("%-I:%M %p").to_string(),
+        TimeFormat::H24 => time.format("%H:%M").to_string()
+    };
+    format!("Slept {}h {:02}m\n{} - {}", minutes / 60, minutes % 60, time(&sleep.start), time(&sleep.end))
+}
+
+/// the band only shows a few lines, so keep everything short
+pub fn compose_briefing(
+    weather: Option<&WeatherReport>,
+    event: Option<&CalendarEvent>,
+    sleep: Option<&SleepSession>,
+    time_format: TimeFormat,
+    weather_format: WeatherFormat
+) -> String {
+    let mut lines = vec![];
+
+    if let Some(weather) = weather {
+        lines.push(compose_weather(weather, weather_format));
+    }
+
+    match event {
+        Some(event) => {
+            let time = match time_format {
+                TimeFormat::H12 => event.start.format("%-I:%M %p"),
+                TimeFormat::H24 => event.start.format("%H:%M")
+            };
+            lines.push(format!("{time} {}", event.summary));
+        },
+        None => lines.push("No events today".into())
+    }
+
+    if let Some(sleep) = sleep {
+        let minutes = sleep.asleep.as_secs() / 60;
+        lines.push(format!("Slept {}h {:02}m", minutes / 60, minutes % 60));
+    }
+
+    lines.join("\n")
+}
@@ -0,0 +1,57 @@
+use std::{collections::HashMap, time::Duration};
+
+use async_io::Timer;
+use chrono::{DateTime, Local, TimeZone};
+use futures::{select, FutureExt, StreamExt};
+use zbus::{proxy, zvariant::OwnedValue, Connection};
+
+/// the calendar server sends events asynchronously, so give it this long to send them all
+const CALENDAR_WAIT: Duration = Duration::from_secs(3);
+
+// GNOME Shell's calendar server exposes the events from Evolution Data Server
+#[proxy(default_service = "org.gnome.Shell.CalendarServer", default_path = "/org/gnome/Shell/CalendarServer", interface = "org.gnome.Shell.CalendarServer", gen_blocking = false)]
+trait CalendarServer {
+    fn set_time_range(&self, since: i64, until: i64, force_reload: bool) -> zbus::Result<()>;
+
+    /// (id, summary, start, end, extras)
+    #[zbus(signal)]
+    fn events_added_or_updated(&self, events: Vec<(String, String, i64, i64, HashMap<String, OwnedValue>)>) -> zbus::Result<()>;
+}
+
+#[derive(Debug, Clone)]
+pub struct CalendarEvent {
+    pub summary: String,
+    pub start: DateTime<Local>
+}
+
+/// the next event in the desktop calendar between now and `until`
+pub async fn next_event(until: DateTime<Local>) -> zbus::Result<Option<CalendarEvent>> {
+    let conn = Connection::session().await?;
+    let proxy = CalendarServerProxy::new(&conn).await?;
+
+    // subscribe before asking for the range so we don't miss anything
+    let mut events = proxy.receive_events_added_or_updated().await?.fuse();
+    let now = Local::now();
+    proxy.set_time_range(now.timestamp(), until.timestamp(), false).await?;
+
+    let mut next: Option<CalendarEvent> = None;
+    let mut timeout = Timer::after(CALENDAR_WAIT).fuse();
+    loop {
+        select! {
+            _ = timeout => break,
+            signal = events.next() => {
+                let Some(signal) = signal else { break };
+                let args = signal.args()?;
+                for (_id, summary, start, _end, _extras) in args.events() {
+                    let Some(start) = Local.timestamp_opt(*start, 0).single() else { continue };
+                    // all day and in-progress events start before now
+                    if start < now { continue }
+                    if next.as_ref().map(|n| start < n.start).unwrap_or(true) {
+                        next = Some(CalendarEvent { summary: summary.clone(), start });
+                    }
+                }
+            }
+        }
+    }
+    Ok(next)
+}
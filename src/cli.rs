@@ -0,0 +1,220 @@
+use std::error::Error;
+
+use chrono::{Local, TimeDelta};
+use gtk::glib::ExitCode;
+
+use crate::{band::{Alert, AlertType, BandError, MiBand}, bluez::BluezSession, store::{Store, TimeFormat, ACTIVITY_HISTORY_DAYS}, utils::{decode_hex, format_date}};
+
+const USAGE: &'static str = "Usage: miband4-gtk <command> [--band <mac address or alias>]
+
+Commands:
+  sync                  fetch the activity recorded since the last sync
+  alert <title> <body>  show a message on the band
+  battery               print the battery level
+  set-time              set the band's time to this computer's
+
+--band can be left out if only one band has been saved";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Sync,
+    Alert { title: String, body: String },
+    Battery,
+    SetTime
+}
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+/// the command and the band it's for
+/// `None` if the arguments aren't a command (so the window should open)
+fn parse_args(args: &[String]) -> Option<Result<(Command, Option<String>), String>> {
+    let command = args.get(1)?;
+    if !matches!(command.as_str(), "sync" | "alert" | "battery" | "set-time") { return None }
+
+    let mut band = None;
+    let mut positional = vec![];
+    let mut rest = args[2..].iter();
+    while let Some(arg) = rest.next() {
+        if arg == "--band" {
+            match rest.next() {
+                Some(value) => band = Some(value.clone()),
+                None => return Some(Err("--band needs a mac address or alias".into()))
+            }
+        } else {
+            positional.push(arg.clone());
+        }
+    }
+
+    let command = match (command.as_str(), &positional[..]) {
+        ("sync", []) => Command::Sync,
+        ("alert", [title, body]) => Command::Alert { title: title.clone(), body: body.clone() },
+        ("battery", []) => Command::Battery,
+        ("set-time", []) => Command::SetTime,
+        _ => return Some(Err(USAGE.into()))
+    };
+    Some(Ok((command, band)))
+}
+
+/// the band `query` refers to, or the only saved band if there's no query
+/// `find` looks up a saved band by its mac address or alias
+fn select_band(saved: &[String], find: impl Fn(&str) -> Option<String>, query: Option<&str>) -> CliResult<String> {
+    if let Some(query) = query {
+        return find(query).ok_or_else(|| format!("No saved band is called {query}").into());
+    }
+    match saved {
+        [band] => Ok(band.clone()),
+        [] => Err("No bands have been saved yet - connect to one in the app first".into()),
+        bands => Err(format!("Choose a band with --band: {}", bands.join(", ")).into())
+    }
+}
+
+async fn run_on_band(band: &MiBand<'_>, store: &mut Store, command: Command) -> CliResult<String> {
+    match command {
+        Command::Sync => {
+            let since = store.last_synced_activity(&band.address)?
+                .map(|time| time + TimeDelta::minutes(1))
+                .unwrap_or_else(|| Local::now() - TimeDelta::days(ACTIVITY_HISTORY_DAYS));
+            let samples = band.fetch_activity(since, |_| {}, None).await?;
+            store.history(&band.address).insert_activity(&band.address, &samples)?;
+            let band_conf = store.get_band(band.address.clone());
+            band_conf.sync_marks.get_or_insert_with(Default::default).record_activity(&samples);
+            band_conf.last_synced = Some(Local::now().timestamp());
+            store.save().await?;
+            Ok(format!("Synced {} new samples", samples.len()))
+        },
+        Command::Alert { title, body } => {
+            band.send_alert(&Alert { alert_type: AlertType::Message, title: &title, message: &body }).await?;
+            Ok("Sent".to_string())
+        },
+        Command::Battery => {
+            let battery = band.get_battery().await?;
+            let time_format = store.stored_band(&band.address)
+                .and_then(|b| b.display_format.as_ref())
+                .map(|f| f.time_format)
+                .unwrap_or(TimeFormat::H12);
+            Ok(format!(
+                "{}%{}, last charged {}",
+                battery.battery_level,
+                if battery.charging { " (charging)" } else { "" },
+                format_date(&battery.last_charge, time_format)
+            ))
+        },
+        Command::SetTime => {
+            band.set_band_time(Local::now()).await?;
+            Ok("Time synced".to_string())
+        }
+    }
+}
+
+/// connect to the band, run the command, and put the connection back how it was
+async fn run_command(command: Command, query: Option<String>) -> CliResult<String> {
+    let mut store = Store::init().await?;
+    let address = select_band(&store.saved_bands(), |query| store.find_band(query), query.as_deref())?;
+
+    let session = BluezSession::new().await?;
+    if !session.adapter.powered().await? {
+        return Err("Bluetooth is off".into());
+    }
+    let device = MiBand::get_known_bands(&session).await?
+        .into_iter()
+        .find(|device| device.address == address)
+        .ok_or_else(|| format!("{address} isn't known to BlueZ - connect to it in the app first"))?;
+
+    let was_connected = device.connected;
+    let mut band = MiBand::from_discovered_device(session.clone(), device).await?;
+    let auth_key = {
+        let band_conf = store.stored_band(&address);
+        band.set_gatt_paths(band_conf.and_then(|b| b.gatt_paths.clone()));
+        band.set_io_policy(store.app.io_policy.unwrap_or_default());
+        band.set_simplify_alerts(store.app.simplify_alerts.unwrap_or(false));
+        band_conf.and_then(|b| b.auth_key.as_deref()).and_then(decode_hex)
+    };
+
+    let result = match (band.initialize().await, auth_key) {
+        (Err(err), _) => Err(err.into()),
+        (Ok(()), Some(auth_key)) => match band.authenticate(&auth_key).await {
+            Ok(()) => run_on_band(&band, &mut store, command).await,
+            Err(err) => Err(err.into())
+        },
+        (Ok(()), None) => Err(BandError::RequiresAuth.into())
+    };
+    // even if it failed partway through
+    if !was_connected {
+        let _ = band.disconnect().await;
+    }
+    result
+}
+
+/// run a command without opening the window
+/// `None` if the arguments aren't a command
+pub fn run(args: &[String]) -> Option<ExitCode> {
+    let (command, band) = match parse_args(args)? {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            eprintln!("{message}");
+            return Some(ExitCode::FAILURE);
+        }
+    };
+    Some(match async_io::block_on(run_command(command, band)) {
+        Ok(message) => {
+            println!("{message}");
+            ExitCode::SUCCESS
+        },
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        ["miband4-gtk"].iter().chain(args).map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(parse_args(&args(&["sync"])), Some(Ok((Command::Sync, None))));
+        assert_eq!(parse_args(&args(&["battery", "--band", "Wrist"])), Some(Ok((Command::Battery, Some("Wrist".into())))));
+        assert_eq!(parse_args(&args(&["set-time"])), Some(Ok((Command::SetTime, None))));
+        // --band can go anywhere after the command
+        assert_eq!(
+            parse_args(&args(&["alert", "--band", "AA:BB:CC:DD:EE:FF", "Title", "Body"])),
+            Some(Ok((Command::Alert { title: "Title".into(), body: "Body".into() }, Some("AA:BB:CC:DD:EE:FF".into()))))
+        );
+        assert_eq!(
+            parse_args(&args(&["alert", "Title", "Body", "--band", "Wrist"])),
+            Some(Ok((Command::Alert { title: "Title".into(), body: "Body".into() }, Some("Wrist".into()))))
+        );
+    }
+
+    #[test]
+    fn bad_commands() {
+        // the window opens for anything that isn't a command
+        assert_eq!(parse_args(&args(&[])), None);
+        assert_eq!(parse_args(&args(&["--gapplication-service"])), None);
+
+        assert!(matches!(parse_args(&args(&["sync", "--band"])), Some(Err(_))));
+        assert_eq!(parse_args(&args(&["alert", "Title"])), Some(Err(USAGE.into())));
+        assert_eq!(parse_args(&args(&["battery", "extra"])), Some(Err(USAGE.into())));
+    }
+
+    #[test]
+    fn band_selection() {
+        let one = ["AA:BB:CC:DD:EE:FF".to_string()];
+        let two = ["AA:BB:CC:DD:EE:FF".to_string(), "11:22:33:44:55:66".to_string()];
+        let find = |query: &str| (query == "Wrist").then(|| "11:22:33:44:55:66".to_string());
+
+        assert_eq!(select_band(&one, find, None).unwrap(), "AA:BB:CC:DD:EE:FF");
+        assert_eq!(select_band(&two, find, Some("Wrist")).unwrap(), "11:22:33:44:55:66");
+
+        let missing = select_band(&two, find, Some("Other")).unwrap_err().to_string();
+        assert!(missing.contains("Other"));
+        let ambiguous = select_band(&two, find, None).unwrap_err().to_string();
+        assert!(ambiguous.contains("--band") && ambiguous.contains("11:22:33:44:55:66"));
+        assert!(select_band(&[], find, None).is_err());
+    }
+}
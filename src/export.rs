@@ -0,0 +1,189 @@
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+
+use crate::band::{ActivitySample, WorkoutKind, WorkoutSummary};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ExportFormat {
+    Gpx,
+    Tcx,
+    Csv
+}
+
+impl ExportFormat {
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "gpx" => Some(Self::Gpx),
+            "tcx" => Some(Self::Tcx),
+            "csv" => Some(Self::Csv),
+            _ => None
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Gpx => "gpx",
+            Self::Tcx => "tcx",
+            Self::Csv => "csv"
+        }
+    }
+}
+
+/// timestamps in GPX/TCX files are always UTC
+fn xml_time(time: &DateTime<Local>) -> String {
+    time.with_timezone(&Utc).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// the activity types Strava and Runalyze recognize in GPX files
+fn gpx_type(kind: WorkoutKind) -> &'static str {
+    match kind {
+        WorkoutKind::OutdoorRun | WorkoutKind::Treadmill => "running",
+        WorkoutKind::Walking => "walking",
+        WorkoutKind::OutdoorCycling | WorkoutKind::IndoorCycling => "cycling",
+        WorkoutKind::PoolSwimming | WorkoutKind::OpenWaterSwimming => "swimming",
+        _ => "workout"
+    }
+}
+
+/// TCX only knows about these three
+fn tcx_sport(kind: WorkoutKind) -> &'static str {
+    match kind {
+        WorkoutKind::OutdoorRun | WorkoutKind::Treadmill => "Running",
+        WorkoutKind::OutdoorCycling | WorkoutKind::IndoorCycling => "Biking",
+        _ => "Other"
+    }
+}
+
+/// one track per workout
+/// the band has no GPS, so the tracks have no segments (an empty one trips up some importers) - they still pick up the type and start time
+pub fn workouts_to_gpx(workouts: &[WorkoutSummary]) -> String {
+    let tracks: String = workouts.iter().map(|workout| format!(
+        "  <trk>\n    <name>{} {}</name>\n    <type>{}</type>\n  </trk>\n",
+        workout.kind, workout.start.format("%Y-%m-%d %H:%M"), gpx_type(workout.kind)
+    )).collect();
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <gpx version=\"1.1\" creator=\"miband4-gtk\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n\
+        {tracks}</gpx>\n"
+    )
+}
+
+/// one activity with a single lap per workout
+pub fn workouts_to_tcx(workouts: &[WorkoutSummary]) -> String {
+    let activities: String = workouts.iter().map(|workout| {
+        let start = xml_time(&workout.start);
+        let avg_hr = workout.avg_hr
+            .map(|hr| format!("        <AverageHeartRateBpm><Value>{hr}</Value></AverageHeartRateBpm>\n"))
+            .unwrap_or_default();
+        format!(
+            "    <Activity Sport=\"{}\">\n      <Id>{start}</Id>\n      <Lap StartTime=\"{start}\">\n        \
+            <TotalTimeSeconds>{}</TotalTimeSeconds>\n        <DistanceMeters>{:.1}</DistanceMeters>\n        \
+            <Calories>{}</Calories>\n{avg_hr}        <Intensity>Active</Intensity>\n        \
+            <TriggerMethod>Manual</TriggerMethod>\n      </Lap>\n    </Activity>\n",
+            tcx_sport(workout.kind), workout.duration.as_secs(), workout.distance, workout.calories
+        )
+    }).collect();
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <TrainingCenterDatabase xmlns=\"http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2\">\n  \
+        <Activities>\n{activities}  </Activities>\n</TrainingCenterDatabase>\n"
+    )
+}
+
+pub fn workouts_to_csv(workouts: &[WorkoutSummary]) -> String {
+    let rows: String = workouts.iter().map(|workout| format!(
+        "{},{},{},{:.1},{},{}\n",
+        workout.start.to_rfc3339_opts(SecondsFormat::Secs, false),
+        workout.kind,
+        workout.duration.as_secs(),
+        workout.distance,
+        workout.calories,
+        workout.avg_hr.map(|hr| hr.to_string()).unwrap_or_default()
+    )).collect();
+    format!("start,kind,duration_seconds,distance_meters,calories,avg_heart_rate\n{rows}")
+}
+
+pub fn export_workouts(workouts: &[WorkoutSummary], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Gpx => workouts_to_gpx(workouts),
+        ExportFormat::Tcx => workouts_to_tcx(workouts),
+        ExportFormat::Csv => workouts_to_csv(workouts)
+    }
+}
+
+/// per-minute activity - only CSV makes sense here
+pub fn activity_to_csv(samples: &[ActivitySample]) -> String {
+    let rows: String = samples.iter().map(|sample| format!(
+        "{},{},{},{},{}\n",
+        sample.time.to_rfc3339_opts(SecondsFormat::Secs, false),
+        sample.kind,
+        sample.intensity,
+        sample.steps,
+        sample.heart_rate.map(|hr| hr.to_string()).unwrap_or_default()
+    )).collect();
+    format!("time,kind,intensity,steps,heart_rate\n{rows}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn workouts() -> Vec<WorkoutSummary> {
+        vec![
+            WorkoutSummary {
+                kind: WorkoutKind::OutdoorRun,
+                start: Local.with_ymd_and_hms(2024, 5, 10, 7, 30, 0).unwrap(),
+                duration: Duration::from_secs(1800),
+                distance: 5000.0,
+                calories: 320,
+                avg_hr: Some(150)
+            },
+            WorkoutSummary {
+                kind: WorkoutKind::Elliptical,
+                start: Local.with_ymd_and_hms(2024, 5, 11, 18, 5, 0).unwrap(),
+                duration: Duration::from_secs(900),
+                distance: 0.0,
+                calories: 110,
+                avg_hr: None
+            }
+        ]
+    }
+
+    /// the text of every `<tag>` in `xml`, in order
+    fn elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+        let (open, close) = (format!("<{tag}>"), format!("</{tag}>"));
+        xml.split(open.as_str()).skip(1)
+            .filter_map(|rest| rest.split_once(close.as_str()).map(|(text, _)| text))
+            .collect()
+    }
+
+    #[test]
+    fn gpx_round_trip() {
+        let workouts = workouts();
+        let gpx = workouts_to_gpx(&workouts);
+        assert!(gpx.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<gpx version=\"1.1\""));
+        assert!(gpx.ends_with("</gpx>\n"));
+        // the band has no GPS, so there's nothing to put in a segment
+        assert!(!gpx.contains("<trkseg"));
+
+        let tracks = elements(&gpx, "trk");
+        assert_eq!(tracks.len(), workouts.len());
+        for (track, workout) in tracks.iter().zip(&workouts) {
+            assert_eq!(elements(track, "name"), [format!("{} {}", workout.kind, workout.start.format("%Y-%m-%d %H:%M"))]);
+            assert_eq!(elements(track, "type"), [gpx_type(workout.kind)]);
+        }
+        assert_eq!(elements(&gpx, "type"), ["running", "workout"]);
+    }
+
+    #[test]
+    fn empty_gpx() {
+        let gpx = workouts_to_gpx(&[]);
+        assert!(elements(&gpx, "trk").is_empty());
+        assert!(gpx.ends_with("</gpx>\n"));
+    }
+}
@@ -0,0 +1,133 @@
+//! the C functions behind include/miband4.h
+//! everything runs on the thread-default main context, so the caller needs a running GLib main loop
+// the safety requirements are the usual GObject ones: valid, non-NULL objects and UTF-8 strings
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::c_char;
+
+use gtk::glib::{self, ffi::GType, translate::*, StaticType, StrV};
+
+use self::{device::Device, metrics::Metrics, session::Session};
+
+mod device;
+mod metrics;
+mod session;
+
+type SessionPtr = *mut <Session as glib::object::ObjectType>::GlibType;
+type DevicePtr = *mut <Device as glib::object::ObjectType>::GlibType;
+
+#[no_mangle]
+pub extern "C" fn miband4_session_get_type() -> GType {
+    Session::static_type().into_glib()
+}
+
+#[no_mangle]
+pub extern "C" fn miband4_device_get_type() -> GType {
+    Device::static_type().into_glib()
+}
+
+#[no_mangle]
+pub extern "C" fn miband4_metrics_get_type() -> GType {
+    Metrics::static_type().into_glib()
+}
+
+#[no_mangle]
+pub extern "C" fn miband4_session_new() -> SessionPtr {
+    Session::new().into_glib_ptr()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn miband4_session_load(session: SessionPtr) {
+    Session::from_glib_borrow(session).load();
+}
+
+/// NULL-terminated, empty until `ready`
+#[no_mangle]
+pub unsafe extern "C" fn miband4_session_get_saved_bands(session: SessionPtr) -> *mut *mut c_char {
+    StrV::from(Session::from_glib_borrow(session).saved_bands()).into_raw()
+}
+
+/// NULL if the session isn't ready or there's no saved band called `query`
+#[no_mangle]
+pub unsafe extern "C" fn miband4_session_get_device(session: SessionPtr, query: *const c_char) -> DevicePtr {
+    let query: glib::GString = from_glib_none(query);
+    Session::from_glib_borrow(session).device(&query).into_glib_ptr()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn miband4_device_connect_band(device: DevicePtr) {
+    Device::from_glib_borrow(device).connect_band();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn miband4_device_refresh(device: DevicePtr) {
+    Device::from_glib_borrow(device).refresh();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn miband4_device_send_alert(device: DevicePtr, title: *const c_char, message: *const c_char) {
+    Device::from_glib_borrow(device).send_alert(from_glib_none(title), from_glib_none(message));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn miband4_device_disconnect_band(device: DevicePtr) {
+    Device::from_glib_borrow(device).disconnect_band();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, ffi::CString, ptr};
+
+    use gtk::glib::{Object, Type};
+
+    use super::*;
+
+    /// the names followed by `(` - the functions a C file declares or defines
+    fn called_names(source: &str, prefix: &str) -> BTreeSet<String> {
+        source.match_indices(prefix).filter_map(|(start, _)| {
+            let name: String = source[start..].chars().take_while(|c| c.is_ascii_alphanumeric() || *c == '_').collect();
+            source[start + name.len()..].trim_start_matches(' ').starts_with('(').then_some(name)
+        }).collect()
+    }
+
+    #[test]
+    fn header_matches_the_exports() {
+        let declared = called_names(include_str!("../include/miband4.h"), "miband4_");
+        let exported: BTreeSet<String> = include_str!("gobject.rs").lines()
+            .filter_map(|line| line.split_once("extern \"C\" fn "))
+            .map(|(_, rest)| rest.chars().take_while(|c| c.is_ascii_alphanumeric() || *c == '_').collect())
+            .collect();
+        assert!(declared.contains("miband4_session_new"));
+        assert_eq!(declared, exported);
+    }
+
+    #[test]
+    fn exported_types() {
+        for (get_type, name) in [
+            (miband4_session_get_type as extern "C" fn() -> GType, "MiBand4Session"),
+            (miband4_device_get_type, "MiBand4Device"),
+            (miband4_metrics_get_type, "MiBand4Metrics")
+        ] {
+            let type_: Type = unsafe { from_glib(get_type()) };
+            assert_eq!(type_.name(), name);
+            assert!(type_.is_a(Object::static_type()));
+        }
+    }
+
+    #[test]
+    fn unloaded_session() {
+        unsafe {
+            let session = miband4_session_new();
+            assert!(!session.is_null());
+            assert_eq!(Session::from_glib_borrow(session).type_(), Session::static_type());
+
+            // nothing is there until it's loaded
+            let bands: StrV = from_glib_full(miband4_session_get_saved_bands(session));
+            assert!(bands.is_empty());
+            let query = CString::new("AA:BB:CC:DD:EE:FF").unwrap();
+            assert_eq!(miband4_session_get_device(session, query.as_ptr()), ptr::null_mut());
+
+            let _: Session = from_glib_full(session);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::error::Error;
+
+use gtk::{glib::{self, clone, MainContext, Object}, prelude::*, subclass::prelude::*};
+
+use crate::{band::{Alert, AlertType, BandError, MiBand}, bluez::{BluezSession, DeviceServicePaths}, store::IoPolicy};
+
+use super::metrics::Metrics;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+glib::wrapper! {
+    pub struct Device(ObjectSubclass<imp::Device>);
+}
+
+/// what's needed from the session and store to talk to the band
+pub struct DeviceConfig {
+    pub session: BluezSession<'static>,
+    pub auth_key: Option<Vec<u8>>,
+    pub gatt_paths: Option<DeviceServicePaths>,
+    pub io_policy: IoPolicy,
+    pub simplify_alerts: bool
+}
+
+impl Device {
+    pub fn new(address: &str, config: DeviceConfig) -> Self {
+        let device: Self = Object::builder()
+            .property("address", address)
+            .build();
+        let _ = device.imp().config.set(config);
+        device
+    }
+    /// connect and authenticate - `connected` and `authenticated` change as it goes
+    pub fn connect_band(&self) {
+        self.spawn(|device| async move { device.try_connect().await });
+    }
+    /// read the battery and the current activity into `metrics`
+    pub fn refresh(&self) {
+        self.spawn(|device| async move { device.try_refresh().await });
+    }
+    pub fn send_alert(&self, title: String, message: String) {
+        self.spawn(|device| async move {
+            let band = device.imp().band.read().await;
+            let band = band.as_ref().ok_or(BandError::NotInitialized)?;
+            band.send_alert(&Alert { alert_type: AlertType::Message, title: &title, message: &message }).await?;
+            Ok(())
+        });
+    }
+    pub fn disconnect_band(&self) {
+        self.spawn(|device| async move {
+            if let Some(mut band) = device.imp().band.write().await.take() {
+                band.disconnect().await?;
+            }
+            device.set_state(false, false);
+            Ok(())
+        });
+    }
+
+    /// run `f` on the thread-default main context, reporting any error with the `error` signal
+    fn spawn<F, Fut>(&self, f: F)
+    where F: FnOnce(Self) -> Fut + 'static, Fut: std::future::Future<Output = Result<()>> + 'static {
+        MainContext::ref_thread_default().spawn_local(clone!(@weak self as device => async move {
+            if let Err(err) = f(device.clone()).await {
+                device.emit_by_name::<()>("error", &[&err.to_string()]);
+            }
+        }));
+    }
+    fn set_state(&self, connected: bool, authenticated: bool) {
+        let imp = self.imp();
+        if imp.connected.replace(connected) != connected { self.notify_connected(); }
+        if imp.authenticated.replace(authenticated) != authenticated { self.notify_authenticated(); }
+    }
+    async fn try_connect(&self) -> Result<()> {
+        let config = self.imp().config.get().expect("device was created with a config");
+        let address = self.address();
+        let discovered = MiBand::get_known_bands(&config.session).await?
+            .into_iter()
+            .find(|device| device.address == address)
+            .ok_or_else(|| format!("{address} isn't known to BlueZ - connect to it in the app first"))?;
+
+        let mut band = MiBand::from_discovered_device(config.session.clone(), discovered).await?;
+        band.set_gatt_paths(config.gatt_paths.clone());
+        band.set_io_policy(config.io_policy);
+        band.set_simplify_alerts(config.simplify_alerts);
+        band.initialize().await?;
+        // kept even if authenticating fails, so it can still be disconnected
+        let mut slot = self.imp().band.write().await;
+        let band = slot.insert(band);
+        self.set_state(true, false);
+
+        let auth_key = config.auth_key.as_deref().ok_or(BandError::RequiresAuth)?;
+        band.authenticate(auth_key).await?;
+        self.set_state(true, true);
+        Ok(())
+    }
+    async fn try_refresh(&self) -> Result<()> {
+        let band = self.imp().band.read().await;
+        let band = band.as_ref().ok_or(BandError::NotInitialized)?;
+        let metrics = self.metrics();
+        metrics.apply_battery(&band.get_battery().await?);
+        metrics.apply_activity(&band.get_current_activity().await?);
+        Ok(())
+    }
+}
+
+mod imp {
+    use std::cell::{Cell, OnceCell, RefCell};
+    use std::sync::OnceLock;
+
+    use async_lock::RwLock;
+    use gtk::{glib::{self, subclass::Signal, Properties}, prelude::*, subclass::prelude::*};
+
+    use crate::{band::MiBand, gobject::metrics::Metrics};
+
+    use super::DeviceConfig;
+
+    #[derive(Properties, Default)]
+    #[properties(wrapper_type = super::Device)]
+    pub struct Device {
+        #[property(get, construct_only)]
+        pub(super) address: RefCell<String>,
+        #[property(get)]
+        pub(super) connected: Cell<bool>,
+        #[property(get)]
+        pub(super) authenticated: Cell<bool>,
+        #[property(get)]
+        pub(super) metrics: RefCell<Metrics>,
+        pub(super) config: OnceCell<DeviceConfig>,
+        /// set once it's connected
+        pub(super) band: RwLock<Option<MiBand<'static>>>
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for Device {
+        const NAME: &'static str = "MiBand4Device";
+        type Type = super::Device;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for Device {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                // param is the error message
+                vec![Signal::builder("error").param_types([String::static_type()]).build()]
+            })
+        }
+    }
+}
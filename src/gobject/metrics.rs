@@ -0,0 +1,71 @@
+use gtk::{glib::{self, Object}, prelude::*, subclass::prelude::*};
+
+use crate::band::{BatteryStatus, CurrentActivity};
+
+glib::wrapper! {
+    pub struct Metrics(ObjectSubclass<imp::Metrics>);
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Object::builder().build()
+    }
+    pub(super) fn apply_battery(&self, battery: &BatteryStatus) {
+        let imp = self.imp();
+        imp.battery_level.set(battery.battery_level as u32);
+        imp.charging.set(battery.charging);
+        imp.last_charge.set(battery.last_charge.timestamp());
+        self.notify_battery_level();
+        self.notify_charging();
+        self.notify_last_charge();
+    }
+    pub(super) fn apply_activity(&self, activity: &CurrentActivity) {
+        let imp = self.imp();
+        imp.steps.set(activity.steps as u32);
+        imp.calories.set(activity.calories as u32);
+        imp.meters.set(activity.meters as u32);
+        self.notify_steps();
+        self.notify_calories();
+        self.notify_meters();
+    }
+}
+
+mod imp {
+    use std::cell::Cell;
+
+    use gtk::{glib::{self, Properties}, prelude::*, subclass::prelude::*};
+
+    /// read-only from the bindings - they're filled in by `miband4_device_refresh`
+    #[derive(Properties, Default)]
+    #[properties(wrapper_type = super::Metrics)]
+    pub struct Metrics {
+        #[property(get)]
+        pub(super) battery_level: Cell<u32>,
+        #[property(get)]
+        pub(super) charging: Cell<bool>,
+        /// unix timestamp
+        #[property(get)]
+        pub(super) last_charge: Cell<i64>,
+        #[property(get)]
+        pub(super) steps: Cell<u32>,
+        #[property(get)]
+        pub(super) calories: Cell<u32>,
+        #[property(get)]
+        pub(super) meters: Cell<u32>
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for Metrics {
+        const NAME: &'static str = "MiBand4Metrics";
+        type Type = super::Metrics;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for Metrics {}
+}
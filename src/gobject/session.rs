@@ -0,0 +1,93 @@
+use std::error::Error;
+
+use gtk::{glib::{self, clone, MainContext, Object}, prelude::*, subclass::prelude::*};
+
+use crate::{bluez::BluezSession, store::Store, utils::decode_hex};
+
+use super::device::{Device, DeviceConfig};
+
+glib::wrapper! {
+    pub struct Session(ObjectSubclass<imp::Session>);
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Object::builder().build()
+    }
+    /// open the store and connect to BlueZ - `ready` is set when it's done
+    pub fn load(&self) {
+        MainContext::ref_thread_default().spawn_local(clone!(@weak self as session => async move {
+            match session.try_load().await {
+                Ok(()) => {
+                    session.imp().ready.set(true);
+                    session.notify_ready();
+                },
+                Err(err) => session.emit_by_name::<()>("error", &[&err.to_string()])
+            }
+        }));
+    }
+    /// the mac addresses of the bands saved in the app
+    pub fn saved_bands(&self) -> Vec<String> {
+        self.imp().store.borrow().as_ref().map(|store| store.saved_bands()).unwrap_or_default()
+    }
+    /// a saved band, by mac address or alias
+    /// `None` if it's not loaded yet or there's no band by that name
+    pub fn device(&self, query: &str) -> Option<Device> {
+        let imp = self.imp();
+        let store = imp.store.borrow();
+        let store = store.as_ref()?;
+        let address = store.find_band(query)?;
+        let band_conf = store.stored_band(&address);
+        Some(Device::new(&address, DeviceConfig {
+            session: imp.bluez.get()?.clone(),
+            auth_key: band_conf.and_then(|b| b.auth_key.as_deref()).and_then(decode_hex),
+            gatt_paths: band_conf.and_then(|b| b.gatt_paths.clone()),
+            io_policy: store.app.io_policy.unwrap_or_default(),
+            simplify_alerts: store.app.simplify_alerts.unwrap_or(false)
+        }))
+    }
+
+    async fn try_load(&self) -> Result<(), Box<dyn Error>> {
+        let store = Store::init().await?;
+        let bluez = BluezSession::new().await?;
+        let imp = self.imp();
+        imp.store.replace(Some(store));
+        let _ = imp.bluez.set(bluez);
+        Ok(())
+    }
+}
+
+mod imp {
+    use std::cell::{Cell, OnceCell, RefCell};
+    use std::sync::OnceLock;
+
+    use gtk::{glib::{self, subclass::Signal, Properties}, prelude::*, subclass::prelude::*};
+
+    use crate::{bluez::BluezSession, store::Store};
+
+    #[derive(Properties, Default)]
+    #[properties(wrapper_type = super::Session)]
+    pub struct Session {
+        #[property(get)]
+        pub(super) ready: Cell<bool>,
+        pub(super) store: RefCell<Option<Store>>,
+        pub(super) bluez: OnceCell<BluezSession<'static>>
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for Session {
+        const NAME: &'static str = "MiBand4Session";
+        type Type = super::Session;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for Session {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                // param is the error message
+                vec![Signal::builder("error").param_types([String::static_type()]).build()]
+            })
+        }
+    }
+}
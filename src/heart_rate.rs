@@ -0,0 +1,108 @@
+use std::{io, path::PathBuf};
+
+use async_fs::{create_dir_all, remove_file};
+use async_net::unix::{UnixListener, UnixStream};
+use futures::{channel::mpsc::Receiver, select, AsyncWriteExt, StreamExt};
+use gtk::glib;
+use log::warn;
+
+use chrono::{DateTime, Local};
+
+use crate::{band::HeartRate, utils::{encode_uint, ByteOrder, APP_ID}};
+
+/// anything outside of this isn't a real heart rate - usually the sensor lost contact
+const MIN_BPM: u16 = 30;
+const MAX_BPM: u16 = 220;
+/// the most a heart rate can plausibly change in a minute
+const MAX_CHANGE_PER_MINUTE: u16 = 40;
+/// after this many minutes without a valid sample, any change is plausible
+const MAX_CHANGE_WINDOW: i64 = 10;
+
+/// rejects heart rate samples that can't be real
+/// samples have to be given oldest first
+#[derive(Default)]
+pub struct HeartRateFilter {
+    /// the last valid sample
+    last: Option<(DateTime<Local>, u16)>
+}
+
+impl HeartRateFilter {
+    /// start from a sample that's already been accepted
+    pub fn new(last: Option<(DateTime<Local>, u16)>) -> Self {
+        Self { last }
+    }
+
+    /// whether this sample is valid
+    pub fn accept(&mut self, time: DateTime<Local>, bpm: u16) -> bool {
+        if !(MIN_BPM..=MAX_BPM).contains(&bpm) { return false }
+
+        if let Some((last_time, last_bpm)) = self.last {
+            let minutes = (time - last_time).num_minutes();
+            if minutes < MAX_CHANGE_WINDOW && bpm.abs_diff(last_bpm) > MAX_CHANGE_PER_MINUTE * minutes.max(1) as u16 {
+                return false;
+            }
+        }
+        self.last = Some((time, bpm));
+        true
+    }
+}
+
+/// the socket heart rate measurements are shared on
+pub fn socket_path() -> PathBuf {
+    let mut path = glib::user_runtime_dir();
+    path.push(APP_ID);
+    path.push("heart-rate.sock");
+    path
+}
+
+/// encode a measurement in the standard BLE Heart Rate Measurement (0x2a37) format
+/// the flags byte tells readers whether a u8 or a u16 follows, so the packets are self-delimiting
+fn encode_measurement(heart_rate: HeartRate) -> Vec<u8> {
+    match u8::try_from(heart_rate.bpm) {
+        Ok(bpm) => vec![0x00, bpm],
+        Err(_) => [&[0x01][..], &encode_uint(heart_rate.bpm as u64, 2, ByteOrder::Little).unwrap_or_default()].concat()
+    }
+}
+
+/// share heart rate measurements with every client connected to the socket
+/// runs until `rx` is closed
+pub async fn serve_heart_rate(mut rx: Receiver<HeartRate>) -> io::Result<()> {
+    let path = socket_path();
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir).await?;
+    }
+    // remove the socket from a previous run
+    let _ = remove_file(&path).await;
+
+    let listener = UnixListener::bind(&path)?;
+    let mut incoming = listener.incoming().fuse();
+    let mut clients: Vec<UnixStream> = vec![];
+
+    loop {
+        select! {
+            client = incoming.next() => {
+                match client {
+                    Some(Ok(client)) => clients.push(client),
+                    Some(Err(err)) => warn!("Error while accepting a heart rate client: {err}"),
+                    None => break
+                }
+            },
+            heart_rate = rx.next() => {
+                let Some(heart_rate) = heart_rate else { break };
+                let payload = encode_measurement(heart_rate);
+
+                let mut connected = Vec::with_capacity(clients.len());
+                for mut client in clients.drain(..) {
+                    // drop clients that have gone away
+                    if client.write_all(&payload).await.is_ok() {
+                        connected.push(client);
+                    }
+                }
+                clients = connected;
+            }
+        }
+    }
+
+    let _ = remove_file(&path).await;
+    Ok(())
+}
@@ -0,0 +1,204 @@
+use std::{error::Error as StdError, fmt::{self, Display, Formatter}};
+
+use gtk::gio;
+use log::warn;
+use serde_json::Value;
+
+const TOKENS_URL: &'static str = "https://api-user.huami.com/registrations";
+const LOGIN_URL: &'static str = "https://account.huami.com/v2/client/login";
+/// the accounts are kept on a different API host in each region (AWS region the account was registered in -> host)
+const REGION_HOSTS: &[(&'static str, &'static str)] = &[
+    ("us-", "api-mifit-us2.huami.com"),
+    ("eu-", "api-mifit-de2.huami.com"),
+    ("ap-", "api-mifit-sg2.huami.com"),
+    ("cn-", "api-mifit-cn2.huami.com")
+];
+/// for accounts that don't say where they were registered
+const DEFAULT_HOST: &'static str = "api-mifit-us2.huami.com";
+/// the sign in page sends us here with the access token in the query
+const REDIRECT_URL: &'static str = "https://s3-us-west-2.amazonaws.com/hm-registration/successsignin.html";
+
+#[derive(Debug)]
+pub enum Error {
+    HttpError(Box<ureq::Error>),
+    IoError(std::io::Error),
+    InvalidCredentials,
+    InvalidResponse,
+    /// the account doesn't have a band with this address
+    BandNotFound(String)
+}
+
+impl From<ureq::Error> for Error {
+    fn from(value: ureq::Error) -> Self {
+        Self::HttpError(Box::new(value))
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::IoError(value)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HttpError(err) => write!(f, "HTTP error: {}", err),
+            Self::IoError(err) => write!(f, "I/O error: {}", err),
+            Self::InvalidCredentials => write!(f, "The email address, password or token was not accepted"),
+            Self::InvalidResponse => write!(f, "The Zepp servers sent an invalid response"),
+            Self::BandNotFound(address) => write!(f, "There is no band with the address {} on this account", address)
+        }
+    }
+}
+impl StdError for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// how to sign in to the Huami/Zepp account
+pub enum Credentials {
+    Password { email: String, password: String },
+    /// the `access` value from the sign in redirect (e.g. after signing in with a third party account)
+    AccessToken(String)
+}
+
+/// percent-encode everything except unreserved characters
+fn encode_component(value: &str) -> String {
+    value.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+        b => format!("%{b:02X}")
+    }).collect()
+}
+
+fn decode_component(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(b)) => { decoded.push(b); i += 3; },
+            (b'+', _) => { decoded.push(b' '); i += 1; },
+            (b, _) => { decoded.push(b); i += 1; }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// a value from the query string of a url
+fn query_value(url: &str, key: &str) -> Option<String> {
+    let (_, query) = url.split_once('?')?;
+    query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| decode_component(v))
+}
+
+/// sign in with an email and password, returning the access token
+fn request_access_token(email: &str, password: &str) -> Result<String> {
+    // the token is in the redirect, so don't follow it
+    let agent = ureq::AgentBuilder::new().redirects(0).build();
+    let response = agent.post(&format!("{TOKENS_URL}/{}/tokens", encode_component(email)))
+        .send_form(&[
+            ("state", "REDIRECTION"),
+            ("client_id", "HuaMi"),
+            ("password", password),
+            ("redirect_uri", REDIRECT_URL),
+            ("region", "us-west-2"),
+            ("token", "access"),
+            ("country_code", "US")
+        ])?;
+    response.header("location")
+        .and_then(|location| query_value(location, "access"))
+        .ok_or(Error::InvalidCredentials)
+}
+
+/// the API host for an account registered in this region
+fn region_host(region: Option<&str>) -> &'static str {
+    let host = region.and_then(|region| REGION_HOSTS.iter().find(|(prefix, _)| region.starts_with(prefix)));
+    match host {
+        Some((_, host)) => host,
+        None => {
+            warn!("No Zepp API host known for the region {region:?}, using {DEFAULT_HOST}");
+            DEFAULT_HOST
+        }
+    }
+}
+
+/// exchange the access token for an app token, user id and the API host for the account
+fn login(access_token: &str) -> Result<(String, String, &'static str)> {
+    let response: Value = ureq::post(LOGIN_URL)
+        .send_form(&[
+            ("app_name", "com.huami.midong"),
+            ("app_version", "5.9.2-play_100355"),
+            ("code", access_token),
+            ("country_code", "US"),
+            ("device_id", "02:00:00:00:00:00"),
+            ("device_model", "android_phone"),
+            ("dn", "account.huami.com,api-user.huami.com,api-watch.huami.com,api-analytics.huami.com,app-analytics.huami.com,api-mifit.huami.com"),
+            ("grant_type", "access_token"),
+            ("allow_registration", "false"),
+            ("source", "com.huami.watch.hmwatchmanager"),
+            ("third_name", "huami"),
+            ("lang", "en")
+        ])?
+        .into_json()?;
+
+    let token_info = &response["token_info"];
+    let host = region_host(response["regist_info"]["region"].as_str());
+    match (token_info["app_token"].as_str(), token_info["user_id"].as_str()) {
+        (Some(app_token), Some(user_id)) => Ok((app_token.to_string(), user_id.to_string(), host)),
+        _ => Err(Error::InvalidCredentials)
+    }
+}
+
+/// the auth key of every band on the account (mac address -> key)
+fn request_auth_keys(host: &str, app_token: &str, user_id: &str) -> Result<Vec<(String, String)>> {
+    let response: Value = ureq::get(&format!("https://{host}/users/{}/devices", encode_component(user_id)))
+        .query("enableMultiDevice", "true")
+        .set("apptoken", app_token)
+        .call()?
+        .into_json()?;
+
+    let items = response["items"].as_array().ok_or(Error::InvalidResponse)?;
+    Ok(items.iter().filter_map(|item| {
+        let address = item["macAddress"].as_str()?;
+        // additionalInfo is JSON in a string
+        let info: Value = serde_json::from_str(item["additionalInfo"].as_str()?).ok()?;
+        let key = info["auth_key"].as_str()?;
+        let key = key.strip_prefix("0x").unwrap_or(key);
+        (!key.is_empty()).then(|| (address.to_string(), key.to_lowercase()))
+    }).collect())
+}
+
+/// sign in to the Huami/Zepp account and get the auth key for the band with this address
+/// the credentials are only sent to Huami - nothing is kept
+pub async fn fetch_auth_key(credentials: Credentials, address: String) -> Result<String> {
+    // ureq blocks, so keep it off the main thread
+    gio::spawn_blocking(move || -> Result<String> {
+        let access_token = match credentials {
+            Credentials::Password { email, password } => request_access_token(&email, &password)?,
+            Credentials::AccessToken(token) => token
+        };
+        let (app_token, user_id, host) = login(&access_token)?;
+        request_auth_keys(host, &app_token, &user_id)?
+            .into_iter()
+            .find(|(mac, _)| mac.eq_ignore_ascii_case(&address))
+            .map(|(_, key)| key)
+            .ok_or(Error::BandNotFound(address))
+    }).await.map_err(|_| Error::InvalidResponse)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_hosts() {
+        assert_eq!(region_host(Some("us-west-2")), "api-mifit-us2.huami.com");
+        assert_eq!(region_host(Some("eu-central-1")), "api-mifit-de2.huami.com");
+        assert_eq!(region_host(Some("ap-southeast-1")), "api-mifit-sg2.huami.com");
+        // unknown regions fall back to the US host
+        assert_eq!(region_host(Some("sa-east-1")), DEFAULT_HOST);
+        assert_eq!(region_host(None), DEFAULT_HOST);
+    }
+}
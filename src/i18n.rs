@@ -0,0 +1,70 @@
+use gettextrs::{bind_textdomain_codeset, bindtextdomain, ngettext, setlocale, textdomain, LocaleCategory};
+use log::warn;
+
+pub use gettextrs::gettext;
+
+/// the .mo files are named after this, and the .ui files use it as their domain
+pub const GETTEXT_PACKAGE: &'static str = "miband4-gtk";
+/// where the compiled translations are installed - set `MIBAND4_GTK_LOCALEDIR` when building to change it
+const LOCALE_DIR: &'static str = match option_env!("MIBAND4_GTK_LOCALEDIR") {
+    Some(dir) => dir,
+    None => "/usr/share/locale"
+};
+
+/// use the system locale, and the translations in `LOCALE_DIR`
+/// without them (or without a translation for the locale) everything is in English
+pub fn init() {
+    setlocale(LocaleCategory::LcAll, "");
+    if let Err(err) = bindtextdomain(GETTEXT_PACKAGE, LOCALE_DIR)
+        .and_then(|_| bind_textdomain_codeset(GETTEXT_PACKAGE, "UTF-8"))
+        .and_then(|_| textdomain(GETTEXT_PACKAGE).map(|_| ()))
+    {
+        warn!("Couldn't load the translations: {err}");
+    }
+}
+
+/// marks a string for translation where `gettext` can't be called (e.g. in a const) - translate it with `gettext` when it's shown
+pub const fn n_(message: &'static str) -> &'static str {
+    message
+}
+
+/// translate `format`, then fill in each `{}` with the next argument
+/// (translations can't be done on the result of `format!`)
+pub fn i18n_f(format: &str, args: &[&str]) -> String {
+    fill(gettext(format), args)
+}
+
+/// `i18n_f` with the singular or plural form for `n`
+pub fn i18n_nf(singular: &str, plural: &str, n: u32, args: &[&str]) -> String {
+    fill(ngettext(singular, plural, n), args)
+}
+
+/// one pass over `message`, so a `{}` inside an argument isn't filled in by the next one
+fn fill(message: String, args: &[&str]) -> String {
+    let mut filled = String::with_capacity(message.len());
+    let mut args = args.iter();
+    let mut parts = message.split("{}");
+    if let Some(first) = parts.next() {
+        filled.push_str(first);
+    }
+    for part in parts {
+        // a placeholder without an argument is left as it was
+        filled.push_str(args.next().copied().unwrap_or("{}"));
+        filled.push_str(part);
+    }
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_placeholders() {
+        assert_eq!(fill("{} of {}".into(), &["1", "2"]), "1 of 2");
+        // arguments that contain a placeholder are left alone
+        assert_eq!(fill("{} - {}".into(), &["{}", "b"]), "{} - b");
+        assert_eq!(fill("{}, {}".into(), &["a"]), "a, {}");
+        assert_eq!(fill("none".into(), &["a"]), "none");
+    }
+}
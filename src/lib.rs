@@ -0,0 +1,17 @@
+//! libmiband4 - the band protocol stack, shared by the app (main.rs) and exposed as GObject types
+//! (MiBand4Session, MiBand4Device, MiBand4Metrics), so GNOME Shell extensions and Python scripts can use it
+//! through GObject introspection.
+
+pub mod app_lock;
+pub mod band;
+pub mod bluez;
+pub mod heart_rate;
+pub mod metrics;
+pub mod mpris;
+pub mod protocol;
+pub mod secrets;
+pub mod sleep;
+pub mod store;
+pub mod trace;
+pub mod utils;
+mod gobject;
@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use async_io::Timer;
+use futures::{select, FutureExt, StreamExt};
+use zbus::{proxy, zvariant::{ObjectPath, OwnedObjectPath}, Connection};
+
+use crate::utils::APP_ID;
+
+/// give up if GeoClue hasn't found us by then
+const LOCATION_TIMEOUT: Duration = Duration::from_secs(15);
+/// GCLUE_ACCURACY_LEVEL_CITY - plenty for the weather
+const ACCURACY_CITY: u32 = 4;
+
+#[proxy(default_service = "org.freedesktop.GeoClue2", default_path = "/org/freedesktop/GeoClue2/Manager", interface = "org.freedesktop.GeoClue2.Manager", gen_blocking = false)]
+trait Manager {
+    fn get_client(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(default_service = "org.freedesktop.GeoClue2", interface = "org.freedesktop.GeoClue2.Client", gen_blocking = false)]
+trait Client {
+    fn start(&self) -> zbus::Result<()>;
+    fn stop(&self) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn desktop_id(&self) -> zbus::Result<String>;
+    #[zbus(property)]
+    fn set_desktop_id(&self, id: &str) -> zbus::Result<()>;
+    #[zbus(property)]
+    fn requested_accuracy_level(&self) -> zbus::Result<u32>;
+    #[zbus(property)]
+    fn set_requested_accuracy_level(&self, level: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn location_updated(&self, previous: ObjectPath<'_>, current: ObjectPath<'_>) -> zbus::Result<()>;
+}
+
+#[proxy(default_service = "org.freedesktop.GeoClue2", interface = "org.freedesktop.GeoClue2.Location", gen_blocking = false)]
+trait Location {
+    #[zbus(property)]
+    fn latitude(&self) -> zbus::Result<f64>;
+    #[zbus(property)]
+    fn longitude(&self) -> zbus::Result<f64>;
+}
+
+/// (latitude, longitude) of this computer, from GeoClue
+/// None if it couldn't find a location in time
+pub async fn current_location() -> zbus::Result<Option<(f64, f64)>> {
+    let conn = Connection::system().await?;
+    let manager = ManagerProxy::new(&conn).await?;
+    let client_path = manager.get_client().await?;
+    let client = ClientProxy::builder(&conn).path(client_path)?.build().await?;
+
+    // GeoClue asks the desktop's agent whether this app is allowed to know where it is
+    client.set_desktop_id(APP_ID).await?;
+    client.set_requested_accuracy_level(ACCURACY_CITY).await?;
+
+    // subscribe before starting so we don't miss the first update
+    let mut updates = client.receive_location_updated().await?.fuse();
+    client.start().await?;
+
+    let mut timeout = Timer::after(LOCATION_TIMEOUT).fuse();
+    let location_path = select! {
+        _ = timeout => None,
+        update = updates.next() => match update {
+            Some(update) => Some(update.args()?.current().to_owned()),
+            None => None
+        }
+    };
+    client.stop().await?;
+
+    let Some(location_path) = location_path else { return Ok(None) };
+    let location = LocationProxy::builder(&conn).path(location_path)?.build().await?;
+    Ok(Some((location.latitude().await?, location.longitude().await?)))
+}
@@ -1,32 +1,62 @@
-use gtk::{gdk::Display, gio::resources_register_include, glib::ExitCode, prelude::*, style_context_add_provider_for_display, Application, CssProvider, STYLE_PROVIDER_PRIORITY_USER};
+use adw::{prelude::*, Application};
+use gtk::{gio::resources_register_include, glib::ExitCode};
+use miband4::{app_lock, band, bluez, heart_rate, metrics, mpris, protocol, secrets, sleep, store, trace, utils};
 use ui::window::MiBandWindow;
 use utils::APP_ID;
 
-mod band;
-mod utils;
-mod bluez;
-mod store;
 mod notifications;
+mod alert_sources;
 mod ui;
-mod mpris;
+mod movement;
+mod metric_sinks;
+mod export;
+mod weather;
+mod calendar;
+mod briefing;
+mod location;
+mod report;
+mod replay;
+mod huami;
+mod auth_import;
+mod auth_providers;
+mod toggles;
+mod soak;
+mod cli;
+mod power;
+mod band_service;
+mod i18n;
 
 fn main() -> ExitCode {
     resources_register_include!("resources.gresource").expect("failed to register resources");
 
     env_logger::init();
+    i18n::init();
+    soak::init();
+
+    // miband4-gtk --replay capture.txt decodes a saved capture without opening a window
+    let args: Vec<String> = std::env::args().collect();
+    // miband4-gtk sync/alert/battery/set-time talk to a saved band without opening a window
+    if let Some(code) = cli::run(&args) {
+        return code;
+    }
+    if let [_, flag, path] = &args[..] {
+        if flag == "--replay" {
+            return match std::fs::read_to_string(path) {
+                Ok(capture) => {
+                    print!("{}", replay::replay(&trace::parse_capture(&capture)));
+                    ExitCode::SUCCESS
+                },
+                Err(err) => {
+                    eprintln!("Couldn't read {path}: {err}");
+                    ExitCode::FAILURE
+                }
+            };
+        }
+    }
     
+    // libadwaita loads style.css (and style-hc.css with high contrast) from the resources itself,
+    // and follows the system's dark/light preference
     let app = Application::builder().application_id(APP_ID).build();
-    app.connect_startup(|_app| {
-        let provider = CssProvider::new();
-        provider.load_from_resource("/me/grimsteel/miband4-gtk/style.css");
-        style_context_add_provider_for_display(
-            &Display::default().expect("Could not connect to display"),
-            &provider,
-            // for some reason my styles aren't working so I have to use user priority...
-            STYLE_PROVIDER_PRIORITY_USER,
-        );
-        
-    });
     // connect a handler to the activate signal
     app.connect_activate(|app| {
         MiBandWindow::new(app).present();
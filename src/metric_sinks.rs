@@ -0,0 +1,314 @@
+use std::{cell::RefCell, collections::BTreeMap, future::Future, io, path::PathBuf, time::Duration};
+
+use async_fs::{create_dir_all, rename, write};
+use async_io::Timer;
+use async_lock::Mutex;
+use async_net::TcpStream;
+use chrono::{DateTime, Local};
+use futures::{future::LocalBoxFuture, pin_mut, select, AsyncReadExt, AsyncWriteExt, FutureExt};
+use gtk::glib;
+use serde_json::json;
+
+use crate::{band::{ActivitySample, CurrentActivity, WorkoutSummary}, store::{self, Store}, utils::APP_ID};
+
+/// a typed measurement from the band, handed to every enabled `MetricSink`
+#[derive(Debug, Clone)]
+pub enum MetricSample {
+    HeartRate { time: DateTime<Local>, bpm: u16 },
+    /// heart rates the band recorded between syncs, oldest first
+    HeartRates(Vec<(DateTime<Local>, u16)>),
+    Battery { time: DateTime<Local>, level: u8, charging: bool },
+    /// the band's running totals for today
+    Totals { time: DateTime<Local>, totals: CurrentActivity },
+    Activity(Vec<ActivitySample>),
+    Workouts(Vec<WorkoutSummary>),
+    Sleep { time: DateTime<Local>, event: SleepEvent }
+}
+
+/// a change in sleep state, from the band's live events
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SleepEvent {
+    Started,
+    /// woke up during the night - this isn't the end of sleep unless they stay awake
+    Awake,
+    Ended
+}
+
+impl SleepEvent {
+    /// what Sleep as Android calls it, so automations written for it work with these too
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Started => "sleep_tracking_started",
+            Self::Awake => "awake",
+            Self::Ended => "sleep_tracking_stopped"
+        }
+    }
+}
+
+/// something that stores or exports samples from the band
+///
+/// all sinks receive the same samples, and each one can be enabled separately
+/// local sinks get them in order - remote ones are sent them in the background, so a slow one can see them out of order
+pub trait MetricSink {
+    fn name(&self) -> &'static str;
+
+    /// whether this sends samples over the network
+    /// remote sinks are handled in the background so they can't hold up the band
+    fn is_remote(&self) -> bool {
+        false
+    }
+
+    /// handle a sample from the band with this mac address
+    fn handle<'a>(&'a self, band_mac: &'a str, sample: &'a MetricSample) -> LocalBoxFuture<'a, Result<(), String>>;
+}
+
+/// the local SQLite history
+pub struct HistorySink {
+    pub store: &'static Mutex<Store>
+}
+
+impl MetricSink for HistorySink {
+    fn name(&self) -> &'static str {
+        "History"
+    }
+
+    fn handle<'a>(&'a self, band_mac: &'a str, sample: &'a MetricSample) -> LocalBoxFuture<'a, Result<(), String>> {
+        async move {
+            let mut store = self.store.lock().await;
+            let history = store.history(band_mac);
+            let result = match sample {
+                MetricSample::HeartRate { time, bpm } => history.insert_heart_rate(band_mac, *time, *bpm),
+                MetricSample::HeartRates(samples) => samples.iter()
+                    .try_for_each(|(time, bpm)| history.insert_heart_rate(band_mac, *time, *bpm)),
+                // the band's counters reset at midnight, which doesn't always line up with our clock
+                MetricSample::Totals { time, totals } => history.totals_date(band_mac, *time, totals)
+                    .and_then(|date| history.update_daily_totals(band_mac, date, totals)),
+                MetricSample::Activity(samples) => history.insert_activity(band_mac, samples),
+                MetricSample::Workouts(workouts) => history.insert_workouts(band_mac, workouts),
+                // the battery level isn't kept, and sleep is worked out from the activity
+                MetricSample::Battery { .. } | MetricSample::Sleep { .. } => Ok(())
+            };
+            result.map_err(|err| store::Error::from(err).to_string())
+        }.boxed_local()
+    }
+}
+
+/// where the Prometheus textfile is written
+/// point node_exporter's `--collector.textfile.directory` here
+pub fn prometheus_textfile_path() -> PathBuf {
+    let mut path = glib::user_runtime_dir();
+    path.push(APP_ID);
+    path.push("miband4.prom");
+    path
+}
+
+/// keeps the latest values in a node_exporter textfile
+#[derive(Default)]
+pub struct PrometheusSink {
+    /// metric line (name + labels) -> value
+    values: RefCell<BTreeMap<String, f64>>
+}
+
+impl MetricSink for PrometheusSink {
+    fn name(&self) -> &'static str {
+        "Prometheus"
+    }
+
+    fn handle<'a>(&'a self, band_mac: &'a str, sample: &'a MetricSample) -> LocalBoxFuture<'a, Result<(), String>> {
+        async move {
+            let labels = format!("{{band=\"{band_mac}\"}}");
+            let contents = {
+                let mut values = self.values.borrow_mut();
+                match sample {
+                    MetricSample::HeartRate { bpm, .. } => {
+                        values.insert(format!("miband_heart_rate_bpm{labels}"), *bpm as f64);
+                    },
+                    MetricSample::HeartRates(samples) => {
+                        let Some((_, bpm)) = samples.last() else { return Ok(()) };
+                        values.insert(format!("miband_heart_rate_bpm{labels}"), *bpm as f64);
+                    },
+                    MetricSample::Battery { level, charging, .. } => {
+                        values.insert(format!("miband_battery_percent{labels}"), *level as f64);
+                        values.insert(format!("miband_battery_charging{labels}"), if *charging { 1.0 } else { 0.0 });
+                    },
+                    MetricSample::Totals { totals, .. } => {
+                        values.insert(format!("miband_steps_today{labels}"), totals.steps as f64);
+                        values.insert(format!("miband_meters_today{labels}"), totals.meters as f64);
+                        values.insert(format!("miband_calories_today{labels}"), totals.calories as f64);
+                    },
+                    MetricSample::Activity(samples) => {
+                        let Some(last) = samples.last() else { return Ok(()) };
+                        values.insert(format!("miband_activity_last_sync_timestamp_seconds{labels}"), last.time.timestamp() as f64);
+                    },
+                    MetricSample::Workouts(workouts) => {
+                        let Some(last) = workouts.last() else { return Ok(()) };
+                        values.insert(format!("miband_workout_last_start_timestamp_seconds{labels}"), last.start.timestamp() as f64);
+                    },
+                    MetricSample::Sleep { event, .. } => {
+                        // waking up in the night doesn't count
+                        if *event != SleepEvent::Awake {
+                            values.insert(format!("miband_asleep{labels}"), if *event == SleepEvent::Started { 1.0 } else { 0.0 });
+                        }
+                    }
+                }
+                values.iter().map(|(metric, value)| format!("{metric} {value}\n")).collect::<String>()
+            };
+
+            write_textfile(contents).await.map_err(|err| err.to_string())
+        }.boxed_local()
+    }
+}
+
+async fn write_textfile(contents: String) -> io::Result<()> {
+    let path = prometheus_textfile_path();
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir).await?;
+    }
+    // node_exporter could read it while it's being written, so write it elsewhere first
+    let temp_path = path.with_extension("prom.tmp");
+    write(&temp_path, contents).await?;
+    rename(temp_path, path).await
+}
+
+/// how long a webhook has to connect and respond
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// (host, port, path)
+/// only plain `http://` is supported - the webhooks are meant for local services like Home Assistant or Node-RED
+fn parse_http_url(url: &str) -> Option<(&str, u16, &str)> {
+    let rest = url.strip_prefix("http://")?;
+    let (authority, path) = rest.find('/').map(|i| rest.split_at(i)).unwrap_or((rest, "/"));
+    match authority.rsplit_once(':') {
+        Some((host, port)) => Some((host, port.parse().ok()?, path)),
+        None => Some((authority, 80, path))
+    }
+}
+
+/// make sure a webhook URL can be posted to before it's saved
+pub fn check_webhook_url(url: &str) -> io::Result<()> {
+    if url.is_empty() || parse_http_url(url).is_some() {
+        Ok(())
+    } else if url.starts_with("https://") {
+        Err(io::Error::new(io::ErrorKind::InvalidInput, "HTTPS webhooks aren't supported - use an http:// URL"))
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Unsupported webhook URL: {url}")))
+    }
+}
+
+/// fail if `op` doesn't finish within `WEBHOOK_TIMEOUT`
+async fn with_timeout<T>(what: &str, op: impl Future<Output = io::Result<T>>) -> Result<T, String> {
+    let op = op.fuse();
+    let timer = Timer::after(WEBHOOK_TIMEOUT).fuse();
+    pin_mut!(op, timer);
+    select! {
+        result = op => result.map_err(|err| err.to_string()),
+        _ = timer => Err(format!("Webhook didn't {what} within {} seconds", WEBHOOK_TIMEOUT.as_secs()))
+    }
+}
+
+/// POST a JSON body, and make sure it was accepted
+async fn post_json(url: &str, body: serde_json::Value) -> Result<(), String> {
+    check_webhook_url(url).map_err(|err| err.to_string())?;
+    let (host, port, path) = parse_http_url(url).ok_or_else(|| format!("Unsupported webhook URL: {url}"))?;
+    let body = body.to_string();
+    let request = format!(
+        "POST {path} HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+
+    let mut stream = with_timeout("connect", TcpStream::connect((host, port))).await?;
+    with_timeout("accept the request", stream.write_all(request.as_bytes())).await?;
+
+    // HTTP/1.1 200 OK
+    let mut response = vec![0; 64];
+    let len = with_timeout("respond", stream.read(&mut response)).await?;
+    let status = String::from_utf8_lossy(&response[..len]);
+    match status.split(' ').nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => Err(format!("Webhook responded with {}", status.lines().next().unwrap_or_default()))
+    }
+}
+
+/// POSTs every sample as JSON to a URL
+pub struct WebhookSink {
+    pub url: String
+}
+
+fn sample_json(band_mac: &str, sample: &MetricSample) -> serde_json::Value {
+    match sample {
+        MetricSample::HeartRate { time, bpm } => json!({
+            "type": "heart_rate", "band": band_mac, "time": time.to_rfc3339(), "bpm": bpm
+        }),
+        MetricSample::HeartRates(samples) => json!({
+            "type": "heart_rates",
+            "band": band_mac,
+            "samples": samples.iter().map(|(time, bpm)| json!({ "time": time.to_rfc3339(), "bpm": bpm })).collect::<Vec<_>>()
+        }),
+        MetricSample::Battery { time, level, charging } => json!({
+            "type": "battery", "band": band_mac, "time": time.to_rfc3339(), "level": level, "charging": charging
+        }),
+        MetricSample::Totals { time, totals } => json!({
+            "type": "totals", "band": band_mac, "time": time.to_rfc3339(),
+            "steps": totals.steps, "meters": totals.meters, "calories": totals.calories
+        }),
+        MetricSample::Activity(samples) => json!({
+            "type": "activity",
+            "band": band_mac,
+            "samples": samples.iter().map(|s| json!({
+                "time": s.time.to_rfc3339(), "kind": s.kind, "intensity": s.intensity, "steps": s.steps, "heart_rate": s.heart_rate
+            })).collect::<Vec<_>>()
+        }),
+        MetricSample::Workouts(workouts) => json!({
+            "type": "workouts",
+            "band": band_mac,
+            "workouts": workouts.iter().map(|w| json!({
+                "kind": w.kind.to_string(),
+                "start": w.start.to_rfc3339(),
+                "duration_seconds": w.duration.as_secs(),
+                "distance_meters": w.distance,
+                "calories": w.calories,
+                "avg_heart_rate": w.avg_hr
+            })).collect::<Vec<_>>()
+        }),
+        MetricSample::Sleep { time, event } => json!({
+            "type": "sleep", "band": band_mac, "time": time.to_rfc3339(), "event": event.name()
+        })
+    }
+}
+
+impl MetricSink for WebhookSink {
+    fn name(&self) -> &'static str {
+        "Webhook"
+    }
+
+    fn is_remote(&self) -> bool {
+        true
+    }
+
+    fn handle<'a>(&'a self, band_mac: &'a str, sample: &'a MetricSample) -> LocalBoxFuture<'a, Result<(), String>> {
+        post_json(&self.url, sample_json(band_mac, sample)).boxed_local()
+    }
+}
+
+/// POSTs only the sleep events, in the same shape as Sleep as Android's webhooks
+/// (`{"event": "sleep_tracking_started"}`), so existing "lights off when I fall asleep" automations can be pointed here
+pub struct SleepWebhookSink {
+    pub url: String
+}
+
+impl MetricSink for SleepWebhookSink {
+    fn name(&self) -> &'static str {
+        "Sleep webhook"
+    }
+
+    fn is_remote(&self) -> bool {
+        true
+    }
+
+    fn handle<'a>(&'a self, _band_mac: &'a str, sample: &'a MetricSample) -> LocalBoxFuture<'a, Result<(), String>> {
+        async move {
+            let MetricSample::Sleep { time, event } = sample else { return Ok(()) };
+            post_json(&self.url, json!({ "event": event.name(), "value1": time.timestamp_millis().to_string() })).await
+        }.boxed_local()
+    }
+}
@@ -0,0 +1,105 @@
+use std::{collections::VecDeque, sync::{atomic::{AtomicU64, AtomicUsize, Ordering}, Mutex}, time::{Duration, Instant}};
+
+/// operations/sec is averaged over this window
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+static READS: AtomicU64 = AtomicU64::new(0);
+static WRITES: AtomicU64 = AtomicU64::new(0);
+static NOTIFICATIONS: AtomicU64 = AtomicU64::new(0);
+static FAILURES: AtomicU64 = AtomicU64::new(0);
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+static READ_LATENCY_MICROS: AtomicU64 = AtomicU64::new(0);
+static WRITE_LATENCY_MICROS: AtomicU64 = AtomicU64::new(0);
+static RECENT: Mutex<VecDeque<Instant>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Operation {
+    Read,
+    Write
+}
+
+/// tracks a GATT operation while it's waiting on the band
+/// call `finish` once it completes
+#[derive(Debug)]
+pub struct PendingOperation {
+    operation: Operation,
+    start: Instant
+}
+
+impl PendingOperation {
+    pub fn finish(self, success: bool) {
+        IN_FLIGHT.fetch_sub(1, Ordering::Relaxed);
+        let latency = self.start.elapsed().as_micros() as u64;
+        match self.operation {
+            Operation::Read => {
+                READS.fetch_add(1, Ordering::Relaxed);
+                READ_LATENCY_MICROS.fetch_add(latency, Ordering::Relaxed);
+            },
+            Operation::Write => {
+                WRITES.fetch_add(1, Ordering::Relaxed);
+                WRITE_LATENCY_MICROS.fetch_add(latency, Ordering::Relaxed);
+            }
+        }
+        if !success {
+            FAILURES.fetch_add(1, Ordering::Relaxed);
+        }
+        record_recent();
+    }
+}
+
+fn record_recent() {
+    let now = Instant::now();
+    let mut recent = RECENT.lock().expect("can lock metrics");
+    recent.push_back(now);
+    while recent.front().is_some_and(|t| now.duration_since(*t) > RATE_WINDOW) {
+        recent.pop_front();
+    }
+}
+
+pub fn start(operation: Operation) -> PendingOperation {
+    IN_FLIGHT.fetch_add(1, Ordering::Relaxed);
+    PendingOperation { operation, start: Instant::now() }
+}
+
+/// a notification came in on an acquired notify stream
+pub fn record_notification() {
+    NOTIFICATIONS.fetch_add(1, Ordering::Relaxed);
+    record_recent();
+}
+
+#[derive(Debug)]
+pub struct MetricsSnapshot {
+    pub reads: u64,
+    pub writes: u64,
+    pub notifications: u64,
+    pub failures: u64,
+    pub in_flight: usize,
+    pub ops_per_sec: f64,
+    pub avg_read_latency: Option<Duration>,
+    pub avg_write_latency: Option<Duration>
+}
+
+pub fn snapshot() -> MetricsSnapshot {
+    let reads = READS.load(Ordering::Relaxed);
+    let writes = WRITES.load(Ordering::Relaxed);
+    let average = |total: &AtomicU64, count: u64| {
+        if count == 0 { None } else { Some(Duration::from_micros(total.load(Ordering::Relaxed) / count)) }
+    };
+
+    let now = Instant::now();
+    let recent = RECENT.lock().expect("can lock metrics")
+        .iter()
+        .filter(|t| now.duration_since(**t) <= RATE_WINDOW)
+        .count();
+
+    MetricsSnapshot {
+        reads,
+        writes,
+        notifications: NOTIFICATIONS.load(Ordering::Relaxed),
+        failures: FAILURES.load(Ordering::Relaxed),
+        in_flight: IN_FLIGHT.load(Ordering::Relaxed),
+        ops_per_sec: recent as f64 / RATE_WINDOW.as_secs_f64(),
+        avg_read_latency: average(&READ_LATENCY_MICROS, reads),
+        avg_write_latency: average(&WRITE_LATENCY_MICROS, writes)
+    }
+}
@@ -0,0 +1,130 @@
+use std::{collections::VecDeque, time::Duration};
+
+use chrono::{DateTime, Datelike, Local, TimeDelta, Timelike, Weekday};
+use zbus::{proxy, Connection};
+
+use crate::store::MovementGate;
+
+/// how often the step count is checked
+pub const MOVEMENT_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// `IsInhibited` flag for "inhibit the session being marked as idle"
+const INHIBIT_IDLE: u32 = 8;
+
+#[proxy(default_service = "org.gnome.SessionManager", default_path = "/org/gnome/SessionManager", interface = "org.gnome.SessionManager", gen_blocking = false)]
+trait SessionManager {
+    fn is_inhibited(&self, flags: u32) -> zbus::Result<bool>;
+}
+
+/// whether something is keeping the session from going idle - usually a video call or a presentation
+pub async fn in_meeting() -> bool {
+    async fn is_idle_inhibited() -> zbus::Result<bool> {
+        let conn = Connection::session().await?;
+        SessionManagerProxy::new(&conn).await?.is_inhibited(INHIBIT_IDLE).await
+    }
+    is_idle_inhibited().await.unwrap_or(false)
+}
+
+/// whether `time` is inside the hours the movement gate is active
+pub fn in_schedule(gate: &MovementGate, time: &DateTime<Local>) -> bool {
+    if gate.weekdays_only && matches!(time.weekday(), Weekday::Sat | Weekday::Sun) { return false }
+
+    // minutes since midnight
+    let minute = time.hour() * 60 + time.minute();
+    let start = gate.start_hour as u32 * 60 + gate.start_minute as u32;
+    let end = gate.end_hour as u32 * 60 + gate.end_minute as u32;
+    if start <= end {
+        minute >= start && minute < end
+    } else {
+        // the schedule wraps around midnight
+        minute >= start || minute < end
+    }
+}
+
+/// keeps an hour of step count samples and decides when to remind the user to move
+#[derive(Default)]
+pub struct MovementTracker {
+    samples: VecDeque<(DateTime<Local>, u16)>,
+    level: u8,
+    /// when the last reminder was sent - there isn't another one until they've moved or an hour has passed
+    last_reminder: Option<DateTime<Local>>
+}
+
+impl MovementTracker {
+    /// record the current step count
+    /// returns the reminder level (starting at 1) if they haven't walked enough in the last hour
+    /// and there hasn't been a reminder for it yet
+    pub fn record(&mut self, gate: &MovementGate, time: DateTime<Local>, steps: u16) -> Option<u8> {
+        let window = TimeDelta::hours(1);
+        self.samples.push_back((time, steps));
+
+        // keep the newest sample that's at least an hour old as the baseline
+        while self.samples.len() > 1 && self.samples[1].0 <= time - window {
+            self.samples.pop_front();
+        }
+
+        let (baseline_time, baseline_steps) = self.samples[0];
+        // we need an entire hour of data
+        if time - baseline_time < window { return None }
+
+        // the band resets the step count at midnight
+        let walked = if steps >= baseline_steps { steps - baseline_steps } else { steps };
+        if walked >= gate.steps {
+            self.level = 0;
+            self.last_reminder = None;
+            return None;
+        }
+        // they were already reminded about this hour
+        if self.last_reminder.is_some_and(|last| time - last < window) { return None }
+
+        self.last_reminder = Some(time);
+        self.level = self.level.saturating_add(1);
+        Some(self.level)
+    }
+
+    /// go back to the first reminder level
+    /// the reminder that was just returned wasn't sent, so the next check can remind them again
+    pub fn reset_level(&mut self) {
+        self.level = 0;
+        self.last_reminder = None;
+    }
+
+    /// forget everything (e.g. outside of the schedule)
+    pub fn reset(&mut self) {
+        self.samples.clear();
+        self.level = 0;
+        self.last_reminder = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn reminds_once_per_hour() {
+        let gate = MovementGate { steps: 250, ..Default::default() };
+        let start = Local.with_ymd_and_hms(2024, 5, 10, 9, 0, 0).unwrap();
+        let check = |i: i64| start + TimeDelta::minutes(i * 5);
+        let mut tracker = MovementTracker::default();
+
+        // the first hour is needed as a baseline
+        for i in 0..12 {
+            assert_eq!(tracker.record(&gate, check(i), 100), None);
+        }
+        assert_eq!(tracker.record(&gate, check(12), 100), Some(1));
+        // still sitting - no reminder on every check until a full hour has passed
+        for i in 13..24 {
+            assert_eq!(tracker.record(&gate, check(i), 100), None);
+        }
+        assert_eq!(tracker.record(&gate, check(24), 100), Some(2));
+
+        // walking enough re-arms it, starting over at the first level once they've sat for another hour
+        for i in 25..37 {
+            assert_eq!(tracker.record(&gate, check(i), 400), None);
+        }
+        assert_eq!(tracker.record(&gate, check(37), 400), Some(1));
+    }
+}
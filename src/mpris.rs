@@ -8,7 +8,8 @@ use crate::band::MusicEvent;
 
 #[proxy(default_path = "/org/mpris/MediaPlayer2", interface = "org.mpris.MediaPlayer2.Player", gen_blocking = false, default_service = "org.mpris.MediaPlayer2.playerctld")]
 trait MediaPlayer {
-    fn play_pause(&self) -> zbus::Result<()>;
+    fn play(&self) -> zbus::Result<()>;
+    fn pause(&self) -> zbus::Result<()>;
     fn next(&self) -> zbus::Result<()>;
     fn previous(&self) -> zbus::Result<()>;
 
@@ -26,6 +27,9 @@ trait MediaPlayer {
 
 #[proxy(default_path = "/org/mpris/MediaPlayer2", interface = "com.github.altdesktop.playerctld", gen_blocking = false, default_service = "org.mpris.MediaPlayer2.playerctld")]
 trait PlayerCtlD {
+    /// make the next player the active one
+    fn shift(&self) -> zbus::Result<String>;
+
     #[zbus(property)]
     fn player_names(&self) -> zbus::Result<Vec<String>>;
 }
@@ -46,6 +50,8 @@ impl Default for MediaState {
 #[derive(Debug, Default, Clone)]
 pub struct MediaInfo {
     pub track: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
     pub volume: Option<u8>, // 0 to 100
     pub position: Option<u64>,
     pub duration: Option<u64>,
@@ -54,6 +60,13 @@ pub struct MediaInfo {
 
 const STREAM_THROTTLE: Duration = Duration::from_millis(100);
 
+/// xesam:artist is a list of names
+fn parse_artists(value: &Value) -> Option<String> {
+    let Value::Array(artists) = value else { return None };
+    let artists: Vec<&str> = artists.iter().filter_map(|artist| artist.downcast_ref().ok()).collect();
+    if artists.is_empty() { None } else { Some(artists.join(", ")) }
+}
+
 pub async fn watch_mpris(mut tx: Sender<Option<MediaInfo>>, mut controller_rx: Receiver<MusicEvent>) -> zbus::Result<()> {
     let conn = Connection::session().await?;
     let player_proxy = MediaPlayerProxy::new(&conn).await?;
@@ -111,10 +124,17 @@ pub async fn watch_mpris(mut tx: Sender<Option<MediaInfo>>, mut controller_rx: R
                             .and_then(|s| s.downcast_ref::<i64>().ok())
                             .and_then(|s| s.try_into().ok());
                         current_media_info.track = Some(title.into());
+                        current_media_info.artist = metadata.get("xesam:artist").and_then(parse_artists);
+                        current_media_info.album = metadata.get("xesam:album")
+                            .and_then(|s| s.downcast_ref::<&str>().ok())
+                            .filter(|album| !album.is_empty())
+                            .map(|album| album.to_string());
                         current_media_info.duration = duration_micros;
                     } else {
                         // set default values
                         current_media_info.track = None;
+                        current_media_info.artist = None;
+                        current_media_info.album = None;
                         current_media_info.duration = None;
                     }
                     need_send = true;
@@ -161,9 +181,12 @@ pub async fn watch_mpris(mut tx: Sender<Option<MediaInfo>>, mut controller_rx: R
                             need_send = false;
                         }
                     },
-                    Some(MusicEvent::PlayPause) => {
+                    Some(MusicEvent::Play) => {
                         // ignore errors
-                        let _ = player_proxy.play_pause().await;
+                        let _ = player_proxy.play().await;
+                    },
+                    Some(MusicEvent::Pause) => {
+                        let _ = player_proxy.pause().await;
                     },
                     Some(MusicEvent::Previous) => {
                         let _ = player_proxy.previous().await;
@@ -177,6 +200,10 @@ pub async fn watch_mpris(mut tx: Sender<Option<MediaInfo>>, mut controller_rx: R
                             let _ = player_proxy.set_volume(new_vol).await;
                         }
                     },
+                    Some(MusicEvent::SwitchPlayer) => {
+                        // the new player's track is sent when playerctld's properties change
+                        let _ = playerctl_proxy.shift().await;
+                    },
                     None | Some(MusicEvent::Close) => {}
                 }
             },
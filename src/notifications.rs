@@ -1,7 +1,7 @@
-use std::{error::Error, fmt::Display};
+use std::{collections::HashMap, error::Error, fmt::Display};
 
 use log::warn;
-use zbus::{fdo::MonitoringProxy, message, zvariant::Structure, Connection, MatchRule, Message, MessageStream};
+use zbus::{fdo::{self, MonitoringProxy}, message, zvariant::Value, Connection, MatchRule, Message, MessageStream};
 use futures::{Stream, StreamExt};
 
 // notification error type
@@ -33,9 +33,13 @@ impl Error for NotificationParseError {}
 
 #[derive(Debug, Clone)]
 pub struct Notification {
+    /// assigned by the notification server (it's in the reply to Notify)
+    pub id: u32,
     pub app: String,
     pub summary: String,
-    pub body: String
+    pub body: String,
+    /// the `category` hint (e.g. `call.incoming`)
+    pub category: Option<String>
 }
 
 impl TryFrom<Message> for Notification {
@@ -50,16 +54,20 @@ impl TryFrom<Message> for Notification {
             return Err(Self::Error::WrongSignature);
         }
 
-        let body: Structure = body.deserialize()?;
-        let fields = body.fields();
-        let app_name: &str = fields[0].downcast_ref().expect("first argument is string");
-        let summary: &str = fields[3].downcast_ref().expect("fourth argument is string");
-        let body: &str = fields[4].downcast_ref().expect("fifth argument is string");
+        let (app_name, _replaces_id, _icon, summary, body, _actions, hints, _timeout): (&str, u32, &str, &str, &str, Vec<&str>, HashMap<&str, Value<'_>>, i32) = body.deserialize()?;
+        // See https://specifications.freedesktop.org/notification-spec/notification-spec-latest.html#categories
+        let category = match hints.get("category") {
+            Some(Value::Str(category)) => Some(category.to_string()),
+            _ => None
+        };
         
         Ok(Notification {
+            // filled in from the reply
+            id: 0,
             app: app_name.to_string(),
             summary: summary.to_string(),
-            body: body.to_string()
+            body: body.to_string(),
+            category
         })
     }
     
@@ -67,35 +75,83 @@ impl TryFrom<Message> for Notification {
 
 // methods
 
+/// whether `err` means the bus refused to let us become a monitor (D-Bus policy, sandboxing, etc)
+pub fn is_monitor_denied(err: &zbus::Error) -> bool {
+    match err {
+        zbus::Error::FDO(err) => matches!(**err, fdo::Error::AccessDenied(_) | fdo::Error::AuthFailed(_) | fdo::Error::NotSupported(_)),
+        zbus::Error::MethodError(name, _, _) => matches!(
+            name.as_str(),
+            "org.freedesktop.DBus.Error.AccessDenied" | "org.freedesktop.DBus.Error.AuthFailed" | "org.freedesktop.DBus.Error.NotSupported"
+        ),
+        _ => false
+    }
+}
+
+/// Notify calls that haven't been replied to yet are dropped after this many more come in
+const PENDING_NOTIFICATIONS_MAX: usize = 32;
+
+/// (sender, serial) of a Notify call, which the reply is addressed to
+type CallKey = (String, u32);
+
 pub async fn stream_notifications() -> zbus::Result<impl Stream<Item = Notification>> {
     let conn = Connection::session().await?;
     let proxy = MonitoringProxy::new(&conn).await?;
     
     // match all calls to Notify on fdo.Notifications
-    let rule = MatchRule::builder()
+    let call_rule = MatchRule::builder()
         .path("/org/freedesktop/Notifications")?
         .interface("org.freedesktop.Notifications")?
         .member("Notify")?
         .msg_type(message::Type::MethodCall)
         .build();
-    proxy.become_monitor(&[rule.clone()], 0).await?;
+    // and the server's replies, which have the notification's id
+    let reply_rule = MatchRule::builder()
+        .sender("org.freedesktop.Notifications")?
+        .msg_type(message::Type::MethodReturn)
+        .build();
+    proxy.become_monitor(&[call_rule.clone(), reply_rule.clone()], 0).await?;
     
     // start streaming messages
     let stream: MessageStream = conn.into();
+    let mut pending: HashMap<CallKey, Notification> = HashMap::new();
     Ok(stream.filter_map(move |item| {
-        let rule = rule.clone();
-        async move {
-            let item = item.ok()?;
-            // make sure it matches
-            if !rule.matches(&item).unwrap_or(false) { return None }
-
-            match item.try_into() {
-                Ok(notif) => Some(notif),
-                Err(err) => {
-                    warn!("An error occurred while parsing a notification: {err}");
-                    None
+        let item = item.ok();
+        let notif = item.and_then(|item| {
+            let header = item.header();
+            if call_rule.matches(&item).unwrap_or(false) {
+                let key = (header.sender()?.to_string(), item.primary_header().serial_num().get());
+                match Notification::try_from(item) {
+                    Ok(notif) => {
+                        // the server never replied to these
+                        if pending.len() >= PENDING_NOTIFICATIONS_MAX {
+                            pending.clear();
+                        }
+                        pending.insert(key, notif);
+                    },
+                    Err(err) => warn!("An error occurred while parsing a notification: {err}")
                 }
-            }
-        }
+                None
+            } else if reply_rule.matches(&item).unwrap_or(false) {
+                let key = (header.destination()?.to_string(), header.reply_serial()?.get());
+                let mut notif = pending.remove(&key)?;
+                // other method returns from the server (GetCapabilities, etc) won't have been pending
+                notif.id = item.body().deserialize::<u32>().ok()?;
+                Some(notif)
+            } else { None }
+        });
+        async move { notif }
     }))
 }
+
+/// close a notification another app sent, by the id its Notify call got back
+pub async fn close_notification(id: u32) -> zbus::Result<()> {
+    let conn = Connection::session().await?;
+    conn.call_method(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        Some("org.freedesktop.Notifications"),
+        "CloseNotification",
+        &(id,)
+    ).await?;
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use futures::{select, StreamExt};
+use zbus::{proxy, Connection};
+
+#[proxy(default_service = "org.freedesktop.UPower", default_path = "/org/freedesktop/UPower", interface = "org.freedesktop.UPower", gen_blocking = false)]
+trait UPower {
+    #[zbus(property)]
+    fn on_battery(&self) -> zbus::Result<bool>;
+}
+
+/// the combined state of all of the computer's batteries
+#[proxy(default_service = "org.freedesktop.UPower", default_path = "/org/freedesktop/UPower/devices/DisplayDevice", interface = "org.freedesktop.UPower.Device", gen_blocking = false)]
+trait DisplayDevice {
+    #[zbus(property)]
+    fn percentage(&self) -> zbus::Result<f64>;
+}
+
+/// the computer's power supply (not the band's)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerState {
+    pub on_battery: bool,
+    pub percentage: f64
+}
+
+impl PowerState {
+    /// running on a battery that's below `threshold` percent
+    pub fn is_low(&self, threshold: u8) -> bool {
+        self.on_battery && self.percentage < threshold as f64
+    }
+}
+
+/// send the power state to start with, and then every time it changes
+/// returns once nobody is listening
+pub async fn watch_power(tx: async_channel::Sender<PowerState>) -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    let upower = UPowerProxy::new(&conn).await?;
+    let display_device = DisplayDeviceProxy::new(&conn).await?;
+
+    let mut on_battery_stream = upower.receive_on_battery_changed().await.fuse();
+    let mut percentage_stream = display_device.receive_percentage_changed().await.fuse();
+
+    let mut state = PowerState {
+        on_battery: upower.on_battery().await?,
+        // desktops don't have a battery
+        percentage: display_device.percentage().await.unwrap_or(100.0)
+    };
+    if tx.send(state).await.is_err() { return Ok(()) }
+
+    loop {
+        let mut new_state = state;
+        select! {
+            on_battery = on_battery_stream.next() => {
+                let Some(on_battery) = on_battery else { break };
+                if let Ok(on_battery) = on_battery.get().await {
+                    new_state.on_battery = on_battery;
+                }
+            },
+            percentage = percentage_stream.next() => {
+                let Some(percentage) = percentage else { break };
+                if let Ok(percentage) = percentage.get().await {
+                    new_state.percentage = percentage;
+                }
+            }
+        }
+        if new_state != state {
+            state = new_state;
+            if tx.send(state).await.is_err() { break }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,785 @@
+// byte-level encoding and decoding for the band's characteristics
+// nothing in here touches D-Bus, so it can be checked against captured payloads
+
+use std::{borrow::Cow, time::Duration};
+
+use chrono::{DateTime, Datelike, Local, TimeDelta, TimeZone, Timelike};
+
+use crate::{band::{ActivitySample, Alert, BatteryStatus, CurrentActivity, DeviceEvent, HeartRate, MusicEvent, WorkoutKind, WorkoutSummary}, mpris::{MediaInfo, MediaState}, store::{ActivityGoal, BandLock, DateDisplay, DistanceUnit, TimeFormat}, utils::{encode_uint, encrypt_value, transliterate, truncate_utf8, ByteOrder}};
+
+/// payloads on the chunked transfer characteristic are split into pieces this long
+const CHUNK_LENGTH: usize = 17;
+/// the chunked transfer message type for music info
+pub const CHUNKED_MUSIC: u8 = 0x03;
+/// the chunked transfer message type for alerts
+pub const CHUNKED_ALERT: u8 = 0x00;
+/// alerts longer than this don't fit in a single write to the alert characteristic (the default ATT MTU)
+pub const ALERT_WRITE_LIMIT: usize = 20;
+/// the band cuts anything after this off anyway
+const ALERT_TITLE_LIMIT: usize = 32;
+const ALERT_MESSAGE_LIMIT: usize = 230;
+
+/// reads a value from the start, failing instead of panicking when the value is too short
+struct Reader<'a> {
+    value: &'a [u8],
+    offset: usize
+}
+
+impl<'a> Reader<'a> {
+    fn new(value: &'a [u8]) -> Self {
+        Self { value, offset: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.value.get(self.offset..self.offset.checked_add(len)?)?;
+        self.offset += len;
+        Some(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.bytes(2)?.try_into().ok()?))
+    }
+
+    /// everything that hasn't been read yet
+    fn rest(&self) -> &'a [u8] {
+        &self.value[self.offset..]
+    }
+}
+
+fn u16_at(value: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(value.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn u32_at(value: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(value.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn f32_at(value: &[u8], offset: usize) -> Option<f32> {
+    Some(f32::from_le_bytes(value.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+// #region time
+
+/// times on the band are at least this long (anything after is ignored)
+pub const TIME_LENGTH: usize = 7;
+
+/// parse a time out of a 7 byte array
+pub fn parse_time(value: &[u8]) -> Option<DateTime<Local>> {
+    let mut reader = Reader::new(value);
+    let year = reader.u16()?;
+    let month = reader.u8()?;
+    let day = reader.u8()?;
+    let hour = reader.u8()?;
+    let minute = reader.u8()?;
+    let second = reader.u8()?;
+
+    let time = Local.with_ymd_and_hms(year.into(), month.into(), day.into(), hour.into(), minute.into(), second.into());
+    match time {
+        chrono::offset::LocalResult::Single(time) => Some(time),
+        _ => None
+    }
+}
+
+/// the band keeps the year in two bytes
+fn encode_year(year: i32) -> Vec<u8> {
+    encode_uint(year.clamp(0, u16::MAX as i32) as u64, 2, ByteOrder::Little).unwrap_or_default()
+}
+
+/// year (two bytes), month, day, hour, minute, second, day of week, fractions of a second, adjust reason, timezone (in 15 minute increments)
+pub fn encode_time(time: &DateTime<Local>) -> Vec<u8> {
+    let day_of_week = time.weekday().num_days_from_sunday() as u8;
+    let timezone = (time.offset().local_minus_utc() / (15 * 60)) as i8;
+    [
+        &encode_year(time.year())[..],
+        &[time.month() as u8, time.day() as u8, time.hour() as u8, time.minute() as u8, time.second() as u8, day_of_week, 0, 0, timezone as u8]
+    ].concat()
+}
+
+/// year (two bytes), month, day, hour, minute, timezone (in 15 minute increments)
+pub fn encode_fetch_time(time: &DateTime<Local>) -> Vec<u8> {
+    let timezone = (time.offset().local_minus_utc() / (15 * 60)) as i8;
+    [
+        &encode_year(time.year())[..],
+        &[time.month() as u8, time.day() as u8, time.hour() as u8, time.minute() as u8, timezone as u8]
+    ].concat()
+}
+
+// #endregion
+
+// #region auth
+
+/// written to the auth characteristic to ask the band for something to encrypt
+pub const AUTH_REQUEST_CHALLENGE: [u8; 2] = [0x02, 0x00];
+
+/// what the band sent on the auth characteristic
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AuthResponse {
+    /// 0x10 0x01 0x01 - ask for a challenge again
+    Restart,
+    /// 0x10 0x02 0x01 <16 bytes to encrypt>
+    Challenge([u8; 16]),
+    /// 0x10 0x03 0x01
+    Success,
+    /// 0x10 0x03 0x08 - the key is wrong
+    InvalidKey,
+    Unknown
+}
+
+pub fn parse_auth_response(value: &[u8]) -> AuthResponse {
+    match value {
+        [0x10, 0x01, 0x01, ..] => AuthResponse::Restart,
+        // a short challenge can't be answered
+        [0x10, 0x02, 0x01, challenge @ ..] => challenge.get(..16)
+            .and_then(|challenge| challenge.try_into().ok())
+            .map_or(AuthResponse::Unknown, AuthResponse::Challenge),
+        [0x10, 0x03, 0x01, ..] => AuthResponse::Success,
+        [0x10, 0x03, 0x08, ..] => AuthResponse::InvalidKey,
+        _ => AuthResponse::Unknown
+    }
+}
+
+/// 0x03 0x00 <the first 16 bytes of the challenge encrypted with the auth key>
+pub fn encode_auth_answer(auth_key: &[u8], challenge: &[u8; 16]) -> Option<Vec<u8>> {
+    let encrypted = encrypt_value(auth_key, challenge)?;
+    Some([&[0x03, 0x00], &encrypted[0..16]].concat())
+}
+
+// #endregion
+
+// #region readings
+
+/// <unknown> <level> <charging> <last off (7 bytes)> <unknown> <last charge (7 bytes)>
+pub fn parse_battery(value: &[u8]) -> Option<BatteryStatus> {
+    let mut reader = Reader::new(value);
+    reader.skip(1)?;
+    let battery_level = reader.u8()?;
+    let charging = reader.u8()? != 0;
+
+    //let last_off = parse_time(reader.bytes(TIME_LENGTH)?)?;
+    reader.skip(TIME_LENGTH + 1)?;
+    let last_charge = parse_time(reader.rest())?;
+
+    Some(BatteryStatus {
+        battery_level,
+        charging,
+        //last_off,
+        last_charge
+    })
+}
+
+/// <unknown> <steps (2 bytes)> <unknown (2 bytes)> <meters (2 bytes)> <unknown (2 bytes)> <calories (2 bytes)>
+pub fn parse_current_activity(value: &[u8]) -> Option<CurrentActivity> {
+    let mut reader = Reader::new(value);
+    reader.skip(1)?;
+    let steps = reader.u16()?;
+    reader.skip(2)?;
+    let meters = reader.u16()?;
+    reader.skip(2)?;
+    let calories = reader.u16()?;
+    Some(CurrentActivity {
+        steps,
+        meters,
+        calories
+    })
+}
+
+/// standard heart rate measurement: the first bit of the flags says if the value is a u8 or a u16
+pub fn parse_heart_rate(value: &[u8]) -> Option<HeartRate> {
+    let flags = *value.first()?;
+    if flags & 0x01 == 0 {
+        Some(HeartRate { bpm: *value.get(1)? as u16 })
+    } else {
+        Some(HeartRate { bpm: u16_at(value, 1)? })
+    }
+}
+
+/// something that happened on the band (sent on the music/events characteristic)
+pub fn parse_device_event(value: &[u8]) -> Option<DeviceEvent> {
+    match value.first()? {
+        0x01 => Some(DeviceEvent::FellAsleep),
+        0x02 => Some(DeviceEvent::WokeUp),
+        0x03 => Some(DeviceEvent::GoalReached),
+        0x04 => Some(DeviceEvent::ButtonPressed),
+        0x06 => Some(DeviceEvent::RemovedFromWrist),
+        0x09 => Some(DeviceEvent::AlertDismissed),
+        0x0b => Some(DeviceEvent::ButtonLongPressed),
+        0xfe => parse_music_event(value).map(DeviceEvent::Music),
+        _ => None
+    }
+}
+
+/// a button press on the band's music screen (0xfe <button>)
+pub fn parse_music_event(value: &[u8]) -> Option<MusicEvent> {
+    match value.get(1)? {
+        0xe0 => Some(MusicEvent::Open),
+        0xe1 => Some(MusicEvent::Close),
+        // the band keeps track of whether it's playing, so these aren't toggles
+        0x00 => Some(MusicEvent::Play),
+        0x01 => Some(MusicEvent::Pause),
+        0x03 => Some(MusicEvent::Next),
+        0x04 => Some(MusicEvent::Previous),
+        0x05 => Some(MusicEvent::VolumeUp),
+        0x06 => Some(MusicEvent::VolumeDown),
+        _ => None
+    }
+}
+
+// #endregion
+
+// #region history
+
+/// what the band sent on the fetch characteristic
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FetchResponse {
+    /// 0x10 0x01 0x01 <length (4 bytes)> <start time>
+    /// `start` is `None` if the time couldn't be parsed
+    Started { length: u32, start: Option<DateTime<Local>> },
+    /// the band refused the request or has nothing to send (with the status code)
+    Rejected(u8),
+    Finished,
+    Failed(u8),
+    Unknown
+}
+
+/// written to the firmware characteristic - the band restarts, keeping everything on it
+pub const COMMAND_REBOOT: [u8; 1] = [0x05];
+/// written to the config characteristic - erases everything, including the auth key
+pub const COMMAND_FACTORY_RESET: [u8; 4] = [0x06, 0x0b, 0x00, 0x01];
+
+/// status codes in 0x10 <command> <status> responses
+pub const STATUS_SUCCESS: u8 = 0x01;
+pub const STATUS_NOT_AUTHORIZED: u8 = 0x04;
+pub const STATUS_BUSY: u8 = 0x05;
+pub const STATUS_LOW_BATTERY: u8 = 0x06;
+
+/// the status byte of a 0x10 <command> <status> response
+pub fn response_status(value: &[u8]) -> Option<u8> {
+    match value {
+        [0x10, _, status, ..] => Some(*status),
+        _ => None
+    }
+}
+
+/// written to the fetch characteristic to start sending the data that was announced
+pub const COMMAND_FETCH_START: [u8; 1] = [0x02];
+/// written to the fetch characteristic to end a transfer (finished or not) - 0x09 keeps the data on the band
+pub const COMMAND_FETCH_STOP: [u8; 2] = [0x03, 0x09];
+
+/// 0x01 <type> <time>
+pub fn encode_fetch_request(data_type: u8, since: &DateTime<Local>) -> Vec<u8> {
+    [&[0x01, data_type], &encode_fetch_time(since)[..]].concat()
+}
+
+pub fn parse_fetch_response(value: &[u8]) -> FetchResponse {
+    match value {
+        [0x10, 0x01, 0x01, ..] if value.len() >= 14 => FetchResponse::Started {
+            length: u32_at(value, 3).unwrap_or_default(),
+            start: parse_time(&value[7..])
+        },
+        // the band accepted, but the length or start time got cut off
+        [0x10, 0x01, 0x01, ..] => FetchResponse::Unknown,
+        [0x10, 0x01, status, ..] => FetchResponse::Rejected(*status),
+        [0x10, 0x02, 0x01, ..] => FetchResponse::Finished,
+        [0x10, 0x02, status, ..] => FetchResponse::Failed(*status),
+        _ => FetchResponse::Unknown
+    }
+}
+
+/// split fetched activity data into samples
+/// kind, intensity, steps, heart rate - one sample per minute
+pub fn parse_activity(start: DateTime<Local>, data: &[u8]) -> Vec<ActivitySample> {
+    data.chunks_exact(4).enumerate().map(|(i, sample)| ActivitySample {
+        time: start + TimeDelta::minutes(i as i64),
+        kind: sample[0],
+        intensity: sample[1],
+        steps: sample[2],
+        // 0xff and 0x00 mean there was no measurement
+        heart_rate: Some(sample[3]).filter(|hr| *hr != 0xff && *hr != 0x00)
+    }).collect()
+}
+
+/// parse a sports summary
+/// the layout is the one Gadgetbridge uses (HuamiActivitySummaryParser) - version 2 moved most of the fields around
+pub fn parse_workout_summary(value: &[u8]) -> Option<WorkoutSummary> {
+    let version = u16_at(value, 0)?;
+    let kind = WorkoutKind::from(u16_at(value, 2)?);
+    let start = Local.timestamp_opt(u32_at(value, 4)?.into(), 0).single()?;
+
+    // (distance, active seconds, calories, avg heart rate)
+    let (distance, active_seconds, calories, avg_hr) = if version >= 0x0200 {
+        (f32_at(value, 52)?, u32_at(value, 28)?, f32_at(value, 48)?, u16_at(value, 116))
+    } else {
+        (f32_at(value, 24)?, u32_at(value, 64)?, f32_at(value, 68)?, None)
+    };
+
+    Some(WorkoutSummary {
+        kind,
+        start,
+        duration: Duration::from_secs(active_seconds.into()),
+        distance: distance.max(0.0),
+        calories: calories.max(0.0) as u16,
+        // 0 means the band didn't measure it
+        avg_hr: avg_hr.filter(|hr| *hr > 0)
+    })
+}
+
+// #endregion
+
+// #region settings
+
+/// config keys - the two bytes after the opcode in a config write or query
+pub const CONFIG_TIME_FORMAT: [u8; 2] = [0x02, 0x00];
+pub const CONFIG_DISTANCE_UNIT: [u8; 2] = [0x03, 0x00];
+pub const CONFIG_GOAL_NOTIFICATIONS: [u8; 2] = [0x06, 0x00];
+pub const CONFIG_DATE_DISPLAY: [u8; 2] = [0x0a, 0x00];
+pub const CONFIG_BAND_LOCK: [u8; 2] = [0x21, 0x00];
+
+/// 0x06 <key> <value>
+fn encode_config(key: [u8; 2], value: &[u8]) -> Vec<u8> {
+    [&[0x06][..], &key, value].concat()
+}
+
+/// 0x07 <key> - asks the band for the value it has for a config key
+pub fn encode_config_query(key: [u8; 2]) -> Vec<u8> {
+    vec![0x07, key[0], key[1]]
+}
+
+/// the value in a 0x10 0x07 <status> <key> <value> response
+/// `None` if the band refused the query or the response is for another key
+pub fn parse_config_value(key: [u8; 2], value: &[u8]) -> Option<&[u8]> {
+    match value {
+        [0x10, 0x07, STATUS_SUCCESS, k0, k1, rest @ ..] if [*k0, *k1] == key => Some(rest),
+        _ => None
+    }
+}
+
+/// whether this is the band's (possibly failed) answer to a config query
+pub fn is_config_response(value: &[u8]) -> bool {
+    matches!(value, [0x10, 0x07, ..])
+}
+
+/// (config payload that turns the goal notification on/off, settings payload with the step goal)
+pub fn encode_activity_goal(goal: &ActivityGoal) -> (Vec<u8>, Vec<u8>) {
+    let notifs_enabled_byte = if goal.notifications { 0x01 } else { 0x00 };
+    (
+        encode_config(CONFIG_GOAL_NOTIFICATIONS, &[notifs_enabled_byte]),
+        [&[0x10, 0x00, 0x00][..], &encode_uint(goal.steps as u64, 4, ByteOrder::Little).unwrap_or_default()].concat()
+    )
+}
+
+pub fn encode_distance_unit(unit: DistanceUnit) -> Vec<u8> {
+    let unit_byte = match unit {
+        DistanceUnit::Metric => 0x00,
+        DistanceUnit::Imperial => 0x01
+    };
+    encode_config(CONFIG_DISTANCE_UNIT, &[unit_byte])
+}
+
+pub fn encode_time_format(time_format: TimeFormat) -> Vec<u8> {
+    let format_byte = match time_format {
+        TimeFormat::H12 => 0x00,
+        TimeFormat::H24 => 0x01
+    };
+    encode_config(CONFIG_TIME_FORMAT, &[format_byte])
+}
+
+pub fn encode_date_display(date_display: DateDisplay) -> Vec<u8> {
+    let display_byte = match date_display {
+        DateDisplay::Time => 0x00,
+        DateDisplay::DateTime => 0x03
+    };
+    encode_config(CONFIG_DATE_DISPLAY, &[display_byte])
+}
+
+/// on is scheduled for the whole day, so it doesn't turn off until it's turned off here
+pub fn encode_do_not_disturb(enabled: bool) -> Vec<u8> {
+    if enabled {
+        vec![0x09, 0x81, 0x00, 0x00, 23, 59]
+    } else {
+        vec![0x09, 0x82]
+    }
+}
+
+/// the PIN has to be 4 digits between 1-4 - check it with `BandLock::is_valid` first
+pub fn encode_band_lock(lock: &BandLock) -> Vec<u8> {
+    encode_config(CONFIG_BAND_LOCK, &[
+        &[if lock.enabled { 0x01 } else { 0x00 }],
+        lock.pin.as_bytes(),
+        &[0x00]
+    ].concat())
+}
+
+/// the step goal read back from the settings characteristic - the same 0x10 0x00 0x00 <steps> layout that's written
+pub fn parse_step_goal(value: &[u8]) -> Option<u16> {
+    match value {
+        [0x10, 0x00, 0x00, ..] => u16::try_from(u32_at(value, 3)?).ok(),
+        _ => None
+    }
+}
+
+/// 0x00 or 0x01
+fn parse_flag(byte: u8) -> Option<bool> {
+    match byte {
+        0x00 => Some(false),
+        0x01 => Some(true),
+        _ => None
+    }
+}
+
+pub fn parse_goal_notifications(value: &[u8]) -> Option<bool> {
+    match value {
+        [byte] => parse_flag(*byte),
+        _ => None
+    }
+}
+
+pub fn parse_distance_unit(value: &[u8]) -> Option<DistanceUnit> {
+    match value {
+        [0x00] => Some(DistanceUnit::Metric),
+        [0x01] => Some(DistanceUnit::Imperial),
+        _ => None
+    }
+}
+
+pub fn parse_time_format(value: &[u8]) -> Option<TimeFormat> {
+    match value {
+        [0x00] => Some(TimeFormat::H12),
+        [0x01] => Some(TimeFormat::H24),
+        _ => None
+    }
+}
+
+pub fn parse_date_display(value: &[u8]) -> Option<DateDisplay> {
+    match value {
+        [0x00] => Some(DateDisplay::Time),
+        [0x03] => Some(DateDisplay::DateTime),
+        _ => None
+    }
+}
+
+/// <enabled> <pin> 0x00
+pub fn parse_band_lock(value: &[u8]) -> Option<BandLock> {
+    let (&enabled, rest) = value.split_first()?;
+    let pin = rest.strip_suffix(&[0x00]).unwrap_or(rest);
+    Some(BandLock {
+        enabled: parse_flag(enabled)?,
+        pin: String::from_utf8(pin.to_vec()).ok()?
+    })
+}
+
+/// <type> 0x01 <title> 0x00 <message> 0x00
+/// the title and message are cut down to what the band shows, without splitting any characters
+pub fn encode_alert(alert: &Alert<'_>, simplify_text: bool) -> Vec<u8> {
+    let (title, message) = if simplify_text {
+        (Cow::Owned(transliterate(alert.title)), Cow::Owned(transliterate(alert.message)))
+    } else {
+        (Cow::Borrowed(alert.title), Cow::Borrowed(alert.message))
+    };
+    [
+        &[alert.alert_type as u8, 0x01],
+        truncate_utf8(&title, ALERT_TITLE_LIMIT).as_bytes(),
+        &[0x00],
+        truncate_utf8(&message, ALERT_MESSAGE_LIMIT).as_bytes(),
+        &[0x00]
+    ].concat()
+}
+
+// #endregion
+
+// #region music
+
+/// the music info payload (before it's split into chunks)
+/// `None` means nothing is playing
+pub fn encode_media_info(media: &Option<MediaInfo>) -> Vec<u8> {
+    let Some(media) = media else { return vec![0x40 | 0x20, 0x00] };
+
+    // mpris uses microseconds, the band uses whole seconds (as a u16)
+    let seconds = |micros: u64| encode_uint((micros / 1_000_000).min(u16::MAX as u64), 2, ByteOrder::Little).unwrap_or_default();
+    let pos_bytes = media.position.map(seconds).unwrap_or_else(|| vec![0x00, 0x00]);
+    let all_fields = [
+        // always include the position (even if it's just [0x00, 0x00])
+        (0x00u8, Some(pos_bytes)),
+        // artist + null term
+        (0x02u8, media.artist.as_ref().map(|b| [b.as_bytes(), &[0x00]].concat())),
+        // album + null term
+        (0x04u8, media.album.as_ref().map(|b| [b.as_bytes(), &[0x00]].concat())),
+        // track + null term
+        (0x08u8, media.track.as_ref().map(|b| [b.as_bytes(), &[0x00]].concat())),
+        (0x10u8, media.duration.map(seconds)),
+        // single byte volume
+        (0x40u8, media.volume.map(|d| vec![d]))
+    ];
+    let (flags, bufs): (Vec<u8>, Vec<Vec<u8>>) = all_fields.into_iter()
+        .filter_map(|(flag, buf)| {
+            // basically filter out the `None`s
+            Some((flag, buf?))
+        })
+        .unzip();
+
+    // OR all of the flags together with 0x01
+    let flag = flags.into_iter().fold(0x01, |acc, f| acc | f);
+    let buf = bufs.concat();
+
+    [
+        &[flag, if media.state == MediaState::Playing { 1 } else { 0 }, 0x00],
+        &buf[..]
+    ].concat()
+}
+
+/// split a payload for the chunked transfer characteristic
+pub fn encode_chunks(message_type: u8, payload: &[u8]) -> Vec<Vec<u8>> {
+    let chunks = payload.chunks(CHUNK_LENGTH).enumerate();
+    let num_chunks = chunks.len();
+    chunks.map(|(i, chunk)| {
+        let flag = match (i == 0, i == num_chunks - 1) {
+            // first and last chunk
+            (true, true) => 0x40 | 0x80,
+            // first chunk
+            (true, false) => 0,
+            // last chunk
+            (false, true) => 0x80,
+            // middle chunk
+            (false, false) => 0x40
+        } | message_type;
+        // 0x00 <flag> <num chunks> <data...>
+        [&[0x00, flag, (i & 0xff) as u8], chunk].concat()
+    }).collect()
+}
+
+// #endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHALLENGE: [u8; 16] = [0x5a, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0xa5];
+
+    /// 2024-05-10 12:30:45
+    const TIME: [u8; TIME_LENGTH] = [0xe8, 0x07, 5, 10, 12, 30, 45];
+
+    fn challenge_response() -> Vec<u8> {
+        [&[0x10, 0x02, 0x01][..], &CHALLENGE].concat()
+    }
+
+    fn battery_response() -> Vec<u8> {
+        [&[0x0f, 55, 0x01][..], &TIME, &[0x00], &TIME].concat()
+    }
+
+    fn activity_response() -> Vec<u8> {
+        vec![0x0c, 0x10, 0x27, 0x00, 0x00, 0xe8, 0x03, 0x00, 0x00, 0x64, 0x00]
+    }
+
+    #[test]
+    fn auth_responses() {
+        assert_eq!(parse_auth_response(&[0x10, 0x01, 0x01]), AuthResponse::Restart);
+        assert_eq!(parse_auth_response(&challenge_response()), AuthResponse::Challenge(CHALLENGE));
+        assert_eq!(parse_auth_response(&[0x10, 0x03, 0x01]), AuthResponse::Success);
+        assert_eq!(parse_auth_response(&[0x10, 0x03, 0x08]), AuthResponse::InvalidKey);
+    }
+
+    #[test]
+    fn short_auth_responses() {
+        let response = challenge_response();
+        // every prefix, including the ones that cut the challenge short
+        for len in 0..response.len() {
+            assert_eq!(parse_auth_response(&response[..len]), AuthResponse::Unknown, "{len} bytes");
+        }
+        assert_eq!(parse_auth_response(&[0x10, 0x03]), AuthResponse::Unknown);
+    }
+
+    #[test]
+    fn oversized_auth_responses() {
+        // anything after the challenge is ignored
+        let response = [&challenge_response()[..], &[0xff; 8]].concat();
+        assert_eq!(parse_auth_response(&response), AuthResponse::Challenge(CHALLENGE));
+        assert_eq!(parse_auth_response(&[0x10, 0x03, 0x01, 0x00, 0x00]), AuthResponse::Success);
+    }
+
+    #[test]
+    fn readings() {
+        let time = parse_time(&TIME).expect("time parses");
+        assert_eq!((time.year(), time.month(), time.day(), time.hour(), time.minute(), time.second()), (2024, 5, 10, 12, 30, 45));
+
+        let battery = parse_battery(&battery_response()).expect("battery parses");
+        assert_eq!((battery.battery_level, battery.charging, battery.last_charge), (55, true, time));
+
+        let activity = parse_current_activity(&activity_response()).expect("activity parses");
+        assert_eq!((activity.steps, activity.meters, activity.calories), (10000, 1000, 100));
+    }
+
+    #[test]
+    fn truncated_readings() {
+        for len in 0..TIME.len() {
+            assert!(parse_time(&TIME[..len]).is_none(), "time with {len} bytes");
+        }
+        let battery = battery_response();
+        for len in 0..battery.len() {
+            assert!(parse_battery(&battery[..len]).is_none(), "battery with {len} bytes");
+        }
+        let activity = activity_response();
+        for len in 0..activity.len() {
+            assert!(parse_current_activity(&activity[..len]).is_none(), "activity with {len} bytes");
+        }
+    }
+
+    #[test]
+    fn time_round_trip() {
+        let time = parse_time(&TIME).expect("time parses");
+        let encoded = encode_time(&time);
+        assert_eq!(encoded.len(), 12);
+        assert_eq!(&encoded[..TIME_LENGTH], &TIME);
+        assert_eq!(parse_time(&encoded), Some(time));
+        // the fetch time stops at the minute
+        assert_eq!(&encode_fetch_time(&time)[..6], &TIME[..6]);
+    }
+
+    #[test]
+    fn impossible_times() {
+        // month 13, day 32 and hour 24
+        assert_eq!(parse_time(&[0xe8, 0x07, 13, 10, 12, 30, 45]), None);
+        assert_eq!(parse_time(&[0xe8, 0x07, 5, 32, 12, 30, 45]), None);
+        assert_eq!(parse_time(&[0xe8, 0x07, 5, 10, 24, 30, 45]), None);
+    }
+
+    #[test]
+    fn heart_rate_widths() {
+        assert_eq!(parse_heart_rate(&[0x00, 72]).map(|hr| hr.bpm), Some(72));
+        assert_eq!(parse_heart_rate(&[0x01, 0x2c, 0x01]).map(|hr| hr.bpm), Some(300));
+        assert!(parse_heart_rate(&[0x01, 0x2c]).is_none());
+        assert!(parse_heart_rate(&[]).is_none());
+    }
+
+    #[test]
+    fn fetch_responses() {
+        let started = [&[0x10, 0x01, 0x01, 0x20, 0x00, 0x00, 0x00][..], &TIME].concat();
+        assert_eq!(parse_fetch_response(&started), FetchResponse::Started { length: 32, start: parse_time(&TIME) });
+        // too short for the start time - it's not a rejection
+        assert_eq!(parse_fetch_response(&started[..10]), FetchResponse::Unknown);
+        assert_eq!(parse_fetch_response(&started[..3]), FetchResponse::Unknown);
+        assert_eq!(parse_fetch_response(&[0x10, 0x01, STATUS_BUSY]), FetchResponse::Rejected(STATUS_BUSY));
+        assert_eq!(parse_fetch_response(&[0x10, 0x02, 0x01]), FetchResponse::Finished);
+        assert_eq!(parse_fetch_response(&[0x10, 0x02, 0x04]), FetchResponse::Failed(0x04));
+        assert_eq!(parse_fetch_response(&[0x10]), FetchResponse::Unknown);
+    }
+
+    #[test]
+    fn activity_samples() {
+        let start = parse_time(&TIME).expect("time parses");
+        // the partial sample at the end is dropped
+        let samples = parse_activity(start, &[0x01, 0x10, 0x05, 0x48, 0x02, 0x00, 0x00, 0xff, 0x03]);
+        assert_eq!(samples.len(), 2);
+        assert_eq!((samples[0].kind, samples[0].intensity, samples[0].steps, samples[0].heart_rate), (0x01, 0x10, 0x05, Some(0x48)));
+        assert_eq!(samples[1].time, start + TimeDelta::minutes(1));
+        assert_eq!(samples[1].heart_rate, None);
+    }
+
+    #[test]
+    fn settings() {
+        let (config, settings) = encode_activity_goal(&ActivityGoal { notifications: true, steps: 10000 });
+        assert_eq!(config, [0x06, 0x06, 0x00, 0x01]);
+        assert_eq!(settings, [0x10, 0x00, 0x00, 0x10, 0x27, 0x00, 0x00]);
+        assert_eq!(encode_band_lock(&BandLock { enabled: true, pin: "1234".into() }), [0x06, 0x21, 0x00, 0x01, b'1', b'2', b'3', b'4', 0x00]);
+        assert_eq!(encode_time_format(TimeFormat::H24), [0x06, 0x02, 0x00, 0x01]);
+        assert_eq!(encode_distance_unit(DistanceUnit::Imperial), [0x06, 0x03, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn band_lock_pins() {
+        let lock = |pin: &str| BandLock { enabled: true, pin: pin.into() };
+        assert!(lock("1234").is_valid());
+        assert!(lock("4411").is_valid());
+        // a hand-edited bands.json
+        assert!(!lock("1250").is_valid());
+        assert!(!lock("123").is_valid());
+        assert!(!lock("12341").is_valid());
+        assert!(!lock("").is_valid());
+    }
+
+    #[test]
+    fn settings_read_back() {
+        assert_eq!(encode_config_query(CONFIG_BAND_LOCK), [0x07, 0x21, 0x00]);
+        // every setting parses back to what was written
+        let (config, settings) = encode_activity_goal(&ActivityGoal { notifications: false, steps: 8000 });
+        assert_eq!(parse_goal_notifications(&config[3..]), Some(false));
+        assert_eq!(parse_step_goal(&settings), Some(8000));
+        assert_eq!(parse_distance_unit(&encode_distance_unit(DistanceUnit::Imperial)[3..]), Some(DistanceUnit::Imperial));
+        assert_eq!(parse_time_format(&encode_time_format(TimeFormat::H24)[3..]), Some(TimeFormat::H24));
+        assert_eq!(parse_date_display(&encode_date_display(DateDisplay::Time)[3..]), Some(DateDisplay::Time));
+        let lock = parse_band_lock(&encode_band_lock(&BandLock { enabled: true, pin: "4321".into() })[3..]).unwrap();
+        assert!(lock.enabled);
+        assert_eq!(lock.pin, "4321");
+
+        let response = [0x10, 0x07, STATUS_SUCCESS, 0x02, 0x00, 0x01];
+        assert_eq!(parse_config_value(CONFIG_TIME_FORMAT, &response), Some(&[0x01][..]));
+        // an answer for a different key, or a refusal, isn't a value
+        assert_eq!(parse_config_value(CONFIG_DISTANCE_UNIT, &response), None);
+        assert_eq!(parse_config_value(CONFIG_TIME_FORMAT, &[0x10, 0x07, STATUS_NOT_AUTHORIZED]), None);
+        assert!(is_config_response(&[0x10, 0x07, STATUS_NOT_AUTHORIZED]));
+        assert_eq!(parse_step_goal(&[0x10, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00]), None);
+    }
+
+    #[test]
+    fn media_info() {
+        assert_eq!(encode_media_info(&None), [0x60, 0x00]);
+        let media = MediaInfo {
+            track: Some("Song".into()),
+            artist: None,
+            album: None,
+            volume: Some(50),
+            position: Some(61_500_000),
+            duration: None,
+            state: MediaState::Playing
+        };
+        assert_eq!(encode_media_info(&Some(media)), [0x01 | 0x08 | 0x40, 0x01, 0x00, 61, 0x00, b'S', b'o', b'n', b'g', 0x00, 50]);
+    }
+
+    #[test]
+    fn chunk_framing() {
+        let single = encode_chunks(CHUNKED_ALERT, &[0xaa; 5]);
+        assert_eq!(single, vec![[&[0x00, 0xc0, 0x00][..], &[0xaa; 5]].concat()]);
+
+        let payload: Vec<u8> = (0..40).collect();
+        let chunks = encode_chunks(CHUNKED_MUSIC, &payload);
+        let flags: Vec<u8> = chunks.iter().map(|chunk| chunk[1]).collect();
+        assert_eq!(flags, [CHUNKED_MUSIC, 0x40 | CHUNKED_MUSIC, 0x80 | CHUNKED_MUSIC]);
+        assert!(chunks.iter().enumerate().all(|(i, chunk)| chunk[0] == 0x00 && chunk[2] == i as u8));
+        // nothing is lost or added in between
+        assert_eq!(chunks.iter().flat_map(|chunk| chunk[3..].to_vec()).collect::<Vec<u8>>(), payload);
+
+        assert!(encode_chunks(CHUNKED_ALERT, &[]).is_empty());
+    }
+
+    #[test]
+    fn music_events() {
+        let buttons = [
+            (0xe0, MusicEvent::Open), (0xe1, MusicEvent::Close), (0x00, MusicEvent::Play), (0x01, MusicEvent::Pause),
+            (0x03, MusicEvent::Next), (0x04, MusicEvent::Previous), (0x05, MusicEvent::VolumeUp), (0x06, MusicEvent::VolumeDown)
+        ];
+        for (button, event) in buttons {
+            assert_eq!(parse_device_event(&[0xfe, button]), Some(DeviceEvent::Music(event)), "button {button:#04x}");
+        }
+        assert_eq!(parse_device_event(&[0xfe]), None);
+        assert_eq!(parse_device_event(&[0xfe, 0x02]), None);
+        assert_eq!(parse_device_event(&[]), None);
+    }
+
+    #[test]
+    fn wrong_opcode_auth_responses() {
+        // not a response
+        assert_eq!(parse_auth_response(&[0x11, 0x02, 0x01]), AuthResponse::Unknown);
+        // an unknown command
+        assert_eq!(parse_auth_response(&[0x10, 0x04, 0x01]), AuthResponse::Unknown);
+        // a failed challenge request
+        assert_eq!(parse_auth_response(&[&[0x10, 0x02, 0x04][..], &CHALLENGE].concat()), AuthResponse::Unknown);
+        // an unknown status
+        assert_eq!(parse_auth_response(&[0x10, 0x03, 0x02]), AuthResponse::Unknown);
+    }
+}
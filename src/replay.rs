@@ -0,0 +1,80 @@
+use chrono::{DateTime, Local};
+
+use crate::{band::{CHAR_ACTIVITY_DATA, CHAR_AUTH, CHAR_BATTERY, CHAR_FETCH, CHAR_TIME}, protocol::{parse_activity, parse_auth_response, parse_battery, parse_fetch_response, parse_time, AuthResponse, FetchResponse}, store::TimeFormat, trace::{TraceEntry, TraceKind}, utils::format_date};
+
+/// an activity fetch that's being put back together
+struct Fetch {
+    start: DateTime<Local>,
+    data: Vec<u8>
+}
+
+fn describe_time(value: &[u8]) -> String {
+    match parse_time(value) {
+        Some(time) => format!("time: {}", format_date(&time, TimeFormat::H24)),
+        None => "time: invalid".to_string()
+    }
+}
+
+/// run captured payloads back through the parsers
+/// returns one line for every payload that was decoded (or failed to decode)
+pub fn replay(entries: &[TraceEntry]) -> String {
+    let mut lines = vec![];
+    let mut fetch: Option<Fetch> = None;
+
+    for entry in entries {
+        let time = entry.time.format("%H:%M:%S%.3f");
+        match (entry.kind, entry.uuid.as_str()) {
+            (TraceKind::Read, CHAR_TIME) => lines.push(format!("{time} {}", describe_time(&entry.value))),
+            (TraceKind::Read, CHAR_BATTERY) => lines.push(match parse_battery(&entry.value) {
+                Some(battery) => format!(
+                    "{time} battery: {}%, charging: {}, last charge: {}",
+                    battery.battery_level, battery.charging, format_date(&battery.last_charge, TimeFormat::H24)
+                ),
+                None => format!("{time} battery: invalid")
+            }),
+            (TraceKind::Notification, CHAR_FETCH) => match parse_fetch_response(&entry.value) {
+                FetchResponse::Started { start: Some(start), .. } => {
+                    lines.push(format!("{time} fetch started at {}", format_date(&start, TimeFormat::H24)));
+                    fetch = Some(Fetch { start, data: vec![] });
+                },
+                FetchResponse::Started { start: None, .. } => lines.push(format!("{time} fetch start time: invalid")),
+                FetchResponse::Rejected(status) => lines.push(format!("{time} fetch: nothing to send (status 0x{status:02x})")),
+                FetchResponse::Finished => {
+                    let Some(Fetch { start, data }) = fetch.take() else { continue };
+                    let samples = parse_activity(start, &data);
+                    lines.push(format!("{time} fetch finished: {} bytes, {} samples", data.len(), samples.len()));
+                    if data.len() % 4 != 0 {
+                        lines.push(format!("{time}   {} trailing bytes were ignored", data.len() % 4));
+                    }
+                    lines.extend(samples.iter().map(|sample| format!(
+                        "{time}   {} kind {} intensity {} steps {} heart rate {}",
+                        sample.time.format("%Y-%m-%d %H:%M"), sample.kind, sample.intensity, sample.steps,
+                        sample.heart_rate.map(|hr| hr.to_string()).unwrap_or_else(|| "-".into())
+                    )));
+                },
+                FetchResponse::Failed(status) => {
+                    fetch = None;
+                    lines.push(format!("{time} fetch failed (status 0x{status:02x})"));
+                },
+                FetchResponse::Unknown => {}
+            },
+            // the key itself isn't in the trace, so only the band's side can be checked
+            (TraceKind::Notification, CHAR_AUTH) => lines.push(match parse_auth_response(&entry.value) {
+                AuthResponse::Restart => format!("{time} auth: restart"),
+                AuthResponse::Challenge(_) => format!("{time} auth: challenge"),
+                AuthResponse::Success => format!("{time} auth: success"),
+                AuthResponse::InvalidKey => format!("{time} auth: invalid key"),
+                AuthResponse::Unknown => format!("{time} auth: invalid")
+            }),
+            (TraceKind::Notification, CHAR_ACTIVITY_DATA) => {
+                // the first byte is a packet counter
+                if let (Some(fetch), [_, data @ ..]) = (&mut fetch, entry.value.as_slice()) {
+                    fetch.data.extend_from_slice(data);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    lines.into_iter().map(|line| line + "\n").collect()
+}
@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use chrono::{NaiveDate, TimeDelta};
+use gtk::{cairo::Context, prelude::*, TextDirection, Widget};
+
+use crate::{sleep::SleepSession, store::{DayStats, DistanceUnit}, utils::format_distance};
+
+/// how many days a report covers
+pub const REPORT_DAYS: i64 = 7;
+
+/// a week of activity, sleep, and heart rate, for sharing with a coach or a doctor
+pub struct WeeklyReport {
+    pub band_name: String,
+    /// the last day in the report
+    pub end: NaiveDate,
+    pub days: Vec<DayStats>,
+    pub sleep: Vec<SleepSession>,
+    pub step_goal: Option<u16>,
+    pub distance_unit: DistanceUnit
+}
+
+/// one line of the daily table
+struct ReportRow {
+    date: NaiveDate,
+    day: Option<DayStats>,
+    asleep: Option<Duration>
+}
+
+fn format_sleep(asleep: Duration) -> String {
+    let minutes = asleep.as_secs() / 60;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+impl WeeklyReport {
+    pub fn start(&self) -> NaiveDate {
+        self.end - TimeDelta::days(REPORT_DAYS - 1)
+    }
+
+    fn rows(&self) -> Vec<ReportRow> {
+        (0..REPORT_DAYS).map(|i| {
+            let date = self.start() + TimeDelta::days(i);
+            // a night of sleep counts for the day they woke up
+            let asleep = self.sleep.iter()
+                .filter(|session| session.end.date_naive() == date)
+                .map(|session| session.asleep)
+                .reduce(|a, b| a + b);
+            ReportRow { date, day: self.days.iter().find(|day| day.date == date).cloned(), asleep }
+        }).collect()
+    }
+
+    fn goal_met(&self, day: &DayStats) -> Option<bool> {
+        self.step_goal.map(|goal| day.steps >= goal as u32)
+    }
+
+    /// the totals and averages shown above the table
+    fn summary(&self) -> Vec<String> {
+        let recorded: Vec<&DayStats> = self.days.iter().collect();
+        let mut lines = vec![];
+        if recorded.is_empty() {
+            lines.push("Nothing was recorded this week.".to_string());
+            return lines;
+        }
+
+        let steps: u32 = recorded.iter().map(|day| day.steps).sum();
+        lines.push(format!("{steps} steps ({} per day)", steps / recorded.len() as u32));
+        let meters: u32 = recorded.iter().map(|day| day.meters).sum();
+        lines.push(format!("{} walked", format_distance(meters, self.distance_unit)));
+
+        if let Some(goal) = self.step_goal {
+            let met = recorded.iter().filter(|day| self.goal_met(day) == Some(true)).count();
+            lines.push(format!("Step goal ({goal}) met on {met} of {} days", recorded.len()));
+        }
+
+        let heart_rates: Vec<f64> = recorded.iter().filter_map(|day| day.avg_heart_rate).collect();
+        if !heart_rates.is_empty() {
+            let average = heart_rates.iter().sum::<f64>() / heart_rates.len() as f64;
+            let low = heart_rates.iter().cloned().fold(f64::MAX, f64::min);
+            let high = heart_rates.iter().cloned().fold(0.0, f64::max);
+            lines.push(format!("Average heart rate {average:.0} bpm (daily averages {low:.0}-{high:.0} bpm)"));
+        }
+
+        if !self.sleep.is_empty() {
+            let total: Duration = self.sleep.iter().map(|session| session.asleep).sum();
+            lines.push(format!("{} of sleep per night", format_sleep(total / self.sleep.len() as u32)));
+        }
+        lines
+    }
+
+    fn title(&self) -> String {
+        format!("Weekly Report: {} to {}", self.start().format("%b %-d"), self.end.format("%b %-d, %Y"))
+    }
+
+    /// (date, steps, distance, calories, heart rate, sleep, goal) - the same columns in both formats
+    fn table(&self) -> Vec<[String; 7]> {
+        self.rows().into_iter().map(|row| {
+            let date = row.date.format("%a %b %-d").to_string();
+            let sleep = row.asleep.map(format_sleep).unwrap_or_else(|| "-".into());
+            match row.day {
+                Some(day) => [
+                    date,
+                    day.steps.to_string(),
+                    format_distance(day.meters, self.distance_unit),
+                    format!("{} kcal", day.calories),
+                    day.avg_heart_rate.map(|hr| format!("{hr:.0} bpm")).unwrap_or_else(|| "-".into()),
+                    sleep,
+                    match self.goal_met(&day) { Some(true) => "Met".into(), Some(false) => "Missed".into(), None => "-".into() }
+                ],
+                None => [date, "-".into(), "-".into(), "-".into(), "-".into(), sleep, "-".into()]
+            }
+        }).collect()
+    }
+
+    pub fn to_html(&self) -> String {
+        let summary: String = self.summary().iter().map(|line| format!("<li>{}</li>\n", html_escape(line))).collect();
+        let rows: String = self.table().iter().map(|cells| {
+            let cells: String = cells.iter().map(|cell| format!("<td>{}</td>", html_escape(cell))).collect();
+            format!("<tr>{cells}</tr>\n")
+        }).collect();
+
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+            <style>body {{ font-family: sans-serif; margin: 2em; }} table {{ border-collapse: collapse; }} \
+            th, td {{ border: 1px solid #ccc; padding: 4px 10px; text-align: left; }}</style>\n</head>\n<body>\n\
+            <h1>{title}</h1>\n<p>{band}</p>\n<ul>\n{summary}</ul>\n\
+            <table>\n<tr><th>Day</th><th>Steps</th><th>Distance</th><th>Calories</th><th>Heart Rate</th><th>Sleep</th><th>Goal</th></tr>\n\
+            {rows}</table>\n</body>\n</html>\n",
+            title = html_escape(&self.title()),
+            band = html_escape(&self.band_name)
+        )
+    }
+
+    /// draw the report on a page `width` points wide (for printing)
+    pub fn draw(&self, cr: &Context, width: f64) {
+        // lay everything out from the right for right-to-left languages
+        let rtl = Widget::default_direction() == TextDirection::Rtl;
+        // `x` is where the text starts, measured from the reading side of the page
+        let show_text = |text: &str, x: f64, y: f64| {
+            let x = match cr.text_extents(text) {
+                Ok(extents) if rtl => width - x - extents.x_advance(),
+                _ => x
+            };
+            cr.move_to(x, y);
+            let _ = cr.show_text(text);
+        };
+
+        cr.set_source_rgb(0.0, 0.0, 0.0);
+        let mut y = 24.0;
+
+        cr.set_font_size(18.0);
+        show_text(&self.title(), 0.0, y);
+        y += 22.0;
+
+        cr.set_font_size(11.0);
+        show_text(&self.band_name, 0.0, y);
+        y += 24.0;
+
+        for line in self.summary() {
+            show_text(&format!("• {line}"), 0.0, y);
+            y += 16.0;
+        }
+        y += 16.0;
+
+        let column = width / 7.0;
+        let header = ["Day", "Steps", "Distance", "Calories", "Heart Rate", "Sleep", "Goal"].map(String::from);
+        for (i, cells) in std::iter::once(header).chain(self.table()).enumerate() {
+            for (j, cell) in cells.iter().enumerate() {
+                show_text(cell, column * j as f64, y);
+            }
+            // underline the header
+            if i == 0 {
+                cr.set_line_width(0.5);
+                cr.move_to(0.0, y + 4.0);
+                cr.line_to(width, y + 4.0);
+                let _ = cr.stroke();
+            }
+            y += 18.0;
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use zbus::{proxy, zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Type, Value}, Connection};
+
+use crate::utils::APP_ID;
+
+/// where new items go (usually the login keyring)
+const DEFAULT_COLLECTION: &'static str = "/org/freedesktop/secrets/aliases/default";
+/// returned instead of a prompt when none is needed
+const NO_PROMPT: &'static str = "/";
+
+/// org.freedesktop.Secret.Secret - (session, parameters, value, content type)
+#[derive(Debug, Type, Serialize, Deserialize)]
+struct Secret {
+    session: OwnedObjectPath,
+    parameters: Vec<u8>,
+    value: Vec<u8>,
+    content_type: String
+}
+
+#[proxy(default_service = "org.freedesktop.secrets", default_path = "/org/freedesktop/secrets", interface = "org.freedesktop.Secret.Service", gen_blocking = false)]
+trait Service {
+    fn open_session(&self, algorithm: &str, input: &Value<'_>) -> zbus::Result<(OwnedValue, OwnedObjectPath)>;
+    fn search_items(&self, attributes: HashMap<&str, &str>) -> zbus::Result<(Vec<OwnedObjectPath>, Vec<OwnedObjectPath>)>;
+    fn unlock(&self, objects: &[ObjectPath<'_>]) -> zbus::Result<(Vec<OwnedObjectPath>, OwnedObjectPath)>;
+    fn get_secrets(&self, items: &[ObjectPath<'_>], session: &ObjectPath<'_>) -> zbus::Result<HashMap<OwnedObjectPath, Secret>>;
+}
+
+#[proxy(default_service = "org.freedesktop.secrets", interface = "org.freedesktop.Secret.Collection", gen_blocking = false)]
+trait Collection {
+    fn create_item(&self, properties: HashMap<&str, Value<'_>>, secret: &Secret, replace: bool) -> zbus::Result<(OwnedObjectPath, OwnedObjectPath)>;
+}
+
+#[proxy(default_service = "org.freedesktop.secrets", interface = "org.freedesktop.Secret.Item", gen_blocking = false)]
+trait Item {
+    fn delete(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(default_service = "org.freedesktop.secrets", interface = "org.freedesktop.Secret.Prompt", gen_blocking = false)]
+trait Prompt {
+    fn prompt(&self, window_id: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn completed(&self, dismissed: bool, result: Value<'_>) -> zbus::Result<()>;
+}
+
+/// the Secret Service (GNOME Keyring, KWallet, etc.)
+/// items are looked up by the app id, what kind of secret it is, and what it's for (e.g. a band's mac address)
+#[derive(Clone)]
+pub struct SecretStore {
+    conn: Connection,
+    service: ServiceProxy<'static>,
+    /// a "plain" session - the secrets only travel over the session bus
+    session: OwnedObjectPath
+}
+
+impl SecretStore {
+    /// fails if there's no Secret Service running
+    pub async fn connect() -> zbus::Result<Self> {
+        let conn = Connection::session().await?;
+        let service = ServiceProxy::new(&conn).await?;
+        let (_, session) = service.open_session("plain", &Value::from("")).await?;
+        Ok(Self { conn, service, session })
+    }
+
+    fn attributes<'a>(kind: &'a str, id: &'a str) -> HashMap<&'a str, &'a str> {
+        HashMap::from([("application", APP_ID), ("kind", kind), ("id", id)])
+    }
+
+    /// let the user unlock the keyring if it needs to be
+    /// returns the prompt's result, or None if there wasn't a prompt
+    async fn run_prompt(&self, prompt: OwnedObjectPath) -> zbus::Result<Option<OwnedValue>> {
+        if prompt.as_str() == NO_PROMPT { return Ok(None) }
+        let prompt = PromptProxy::builder(&self.conn).path(prompt)?.build().await?;
+        let mut completed = prompt.receive_completed().await?;
+        prompt.prompt("").await?;
+        let completed = completed.next().await
+            .ok_or_else(|| zbus::Error::Failure("The keyring prompt went away".into()))?;
+        let args = completed.args()?;
+        if *args.dismissed() {
+            return Err(zbus::Error::Failure("The keyring prompt was dismissed".into()));
+        }
+        Ok(Some(args.result().try_to_owned()?))
+    }
+
+    async fn find(&self, kind: &str, id: &str) -> zbus::Result<Vec<OwnedObjectPath>> {
+        let (mut unlocked, locked) = self.service.search_items(Self::attributes(kind, id)).await?;
+        if !locked.is_empty() {
+            let locked: Vec<ObjectPath> = locked.iter().map(|p| p.as_ref()).collect();
+            let (newly_unlocked, prompt) = self.service.unlock(&locked).await?;
+            unlocked.extend(newly_unlocked);
+            // the prompt's result is the rest of the items it unlocked
+            if let Some(result) = self.run_prompt(prompt).await? {
+                unlocked.extend(Vec::<OwnedObjectPath>::try_from(Value::from(result))?);
+            }
+        }
+        Ok(unlocked)
+    }
+
+    pub async fn get(&self, kind: &str, id: &str) -> zbus::Result<Option<String>> {
+        let items = self.find(kind, id).await?;
+        let Some(item) = items.first() else { return Ok(None) };
+        let mut secrets = self.service.get_secrets(&[item.as_ref()], &self.session.as_ref()).await?;
+        Ok(secrets.remove(item).map(|secret| String::from_utf8_lossy(&secret.value).into_owned()))
+    }
+
+    /// add or replace a secret
+    pub async fn set(&self, kind: &str, id: &str, label: &str, value: &str) -> zbus::Result<()> {
+        let collection = CollectionProxy::builder(&self.conn).path(DEFAULT_COLLECTION)?.build().await?;
+        let attributes = Self::attributes(kind, id);
+        let properties = HashMap::from([
+            ("org.freedesktop.Secret.Item.Label", Value::from(label)),
+            ("org.freedesktop.Secret.Item.Attributes", Value::from(attributes))
+        ]);
+        let secret = Secret {
+            session: self.session.clone(),
+            parameters: vec![],
+            value: value.as_bytes().to_vec(),
+            content_type: "text/plain".into()
+        };
+        let (_, prompt) = collection.create_item(properties, &secret, true).await?;
+        self.run_prompt(prompt).await?;
+        Ok(())
+    }
+
+    pub async fn delete(&self, kind: &str, id: &str) -> zbus::Result<()> {
+        for item in self.find(kind, id).await? {
+            let item = ItemProxy::builder(&self.conn).path(item)?.build().await?;
+            let prompt = item.delete().await?;
+            self.run_prompt(prompt).await?;
+        }
+        Ok(())
+    }
+}
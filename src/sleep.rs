@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+
+use crate::band::ActivitySample;
+
+/// a wake up in the middle of the night shorter than this doesn't end the session
+const MAX_AWAKE_GAP: i64 = 60;
+
+/// a night of sleep, derived from the per-minute activity
+#[derive(Debug, Clone)]
+pub struct SleepSession {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    /// minutes actually spent asleep (excludes short wake ups)
+    pub asleep: Duration
+}
+
+/// the band marks sleeping minutes with these activity kinds (the same ones Gadgetbridge uses)
+pub fn is_sleep_kind(kind: u8) -> bool {
+    matches!(kind, 9 | 11) || kind & 0xf0 == 0x70
+}
+
+/// find the longest sleep session in `samples` (which must be sorted by time)
+pub fn detect_sleep(samples: &[ActivitySample]) -> Option<SleepSession> {
+    let mut sessions: Vec<SleepSession> = vec![];
+    for sample in samples.iter().filter(|s| is_sleep_kind(s.kind)) {
+        match sessions.last_mut() {
+            Some(session) if (sample.time - session.end).num_minutes() <= MAX_AWAKE_GAP => {
+                session.end = sample.time;
+                session.asleep += Duration::from_secs(60);
+            },
+            _ => sessions.push(SleepSession { start: sample.time, end: sample.time, asleep: Duration::from_secs(60) })
+        }
+    }
+    sessions.into_iter().max_by_key(|s| s.asleep)
+}
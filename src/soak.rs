@@ -0,0 +1,119 @@
+use std::{collections::BTreeMap, fs, io, path::PathBuf, sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Mutex, OnceLock}, time::{Duration, Instant}};
+
+use async_fs::OpenOptions;
+use chrono::Local;
+use futures::AsyncWriteExt;
+use gtk::glib;
+use serde_json::json;
+
+use crate::utils::APP_ID;
+
+/// how often a sample is written while soak mode is on
+pub const SOAK_INTERVAL: Duration = Duration::from_secs(300);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static STARTED: OnceLock<Instant> = OnceLock::new();
+/// background tasks that are running, by name
+static TASKS: Mutex<BTreeMap<&'static str, usize>> = Mutex::new(BTreeMap::new());
+static DISCONNECTS: AtomicU64 = AtomicU64::new(0);
+static RECONNECTS: AtomicU64 = AtomicU64::new(0);
+static RECONNECT_FAILURES: AtomicU64 = AtomicU64::new(0);
+
+/// counts a background task as running until it's dropped
+/// keep it alive for as long as the task's loop is
+#[derive(Debug)]
+pub struct TaskGuard(&'static str);
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        let mut tasks = TASKS.lock().expect("can lock tasks");
+        if let Some(count) = tasks.get_mut(self.0) {
+            *count = count.saturating_sub(1);
+        }
+    }
+}
+
+/// start the uptime clock
+pub fn init() {
+    STARTED.get_or_init(Instant::now);
+}
+
+/// tasks are always counted, so the numbers are right when soak mode is turned on later
+pub fn track_task(name: &'static str) -> TaskGuard {
+    *TASKS.lock().expect("can lock tasks").entry(name).or_default() += 1;
+    TaskGuard(name)
+}
+
+/// the connection to the band dropped on its own
+pub fn record_disconnect() {
+    DISCONNECTS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_reconnect(success: bool) {
+    if success {
+        RECONNECTS.fetch_add(1, Ordering::Relaxed);
+    } else {
+        RECONNECT_FAILURES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// where the samples go (one JSON object per line)
+pub fn log_path() -> PathBuf {
+    let mut path = glib::user_data_dir();
+    path.push(APP_ID);
+    path.push("soak.jsonl");
+    path
+}
+
+#[derive(Debug)]
+pub struct SoakState {
+    pub enabled: bool,
+    pub path: PathBuf
+}
+
+pub fn state() -> SoakState {
+    SoakState { enabled: is_enabled(), path: log_path() }
+}
+
+/// a number from /proc/self/status (VmRSS and VmSize are in kB)
+fn status_value(status: &str, key: &str) -> Option<u64> {
+    status.lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+        .and_then(|value| value.split_whitespace().next()?.parse().ok())
+}
+
+/// how the process is doing right now
+fn sample() -> serde_json::Value {
+    let status = fs::read_to_string("/proc/self/status").unwrap_or_default();
+    let open_files = fs::read_dir("/proc/self/fd").map(|fds| fds.count()).ok();
+    let tasks = TASKS.lock().expect("can lock tasks").clone();
+    json!({
+        "time": Local::now().to_rfc3339(),
+        "uptime_secs": STARTED.get_or_init(Instant::now).elapsed().as_secs(),
+        "rss_kb": status_value(&status, "VmRSS"),
+        "vm_size_kb": status_value(&status, "VmSize"),
+        "threads": status_value(&status, "Threads"),
+        "open_files": open_files,
+        "tasks": tasks,
+        "disconnects": DISCONNECTS.load(Ordering::Relaxed),
+        "reconnects": RECONNECTS.load(Ordering::Relaxed),
+        "reconnect_failures": RECONNECT_FAILURES.load(Ordering::Relaxed)
+    })
+}
+
+/// add a sample to the end of the soak log
+pub async fn write_sample() -> io::Result<()> {
+    let mut line = sample().to_string();
+    line.push('\n');
+    let mut file = OpenOptions::new().create(true).append(true).open(log_path()).await?;
+    file.write_all(line.as_bytes()).await?;
+    file.flush().await
+}
@@ -1,15 +1,53 @@
-use std::{collections::HashMap, fmt::{self, Formatter, Display}, io::{self, ErrorKind}, path::{Path, PathBuf}};
-use async_fs::{create_dir_all, read, write};
-use gtk::glib;
+use std::{collections::HashMap, fmt::{self, Formatter, Display}, io::{self, ErrorKind}, path::{Path, PathBuf}, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex}};
+use async_fs::{copy, create_dir_all, read, remove_file, rename, write, File};
+use async_lock::MutexGuard;
+use chrono::{DateTime, Local, TimeZone};
+use futures::{future::LocalBoxFuture, AsyncWriteExt};
+use gtk::{gio, glib};
+use log::warn;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
-use crate::utils::APP_ID;
+use crate::{app_lock::PinHash, band::{ActivitySample, BatteryStatus, CurrentActivity, WorkoutSummary}, secrets::SecretStore, sleep::is_sleep_kind, utils::APP_ID};
+
+/// the kind of Secret Service item auth keys are kept in
+pub const SECRET_AUTH_KEY: &'static str = "auth-key";
+
+/// how far back to fetch activity the first time, and how much is exported
+pub const ACTIVITY_HISTORY_DAYS: i64 = 7;
+
+/// the version of bands.json and app.json this writes
+/// bump it (and add a migration to both lists) whenever either one changes in a way older versions can't read
+const CONFIG_VERSION: u32 = 1;
+
+/// upgrades a config file from version i to i + 1
+type Migration = fn(Value) -> Value;
+
+const BAND_MIGRATIONS: [Migration; CONFIG_VERSION as usize] = [
+    // version 0 was just the map of bands
+    |bands| json!({ "bands": bands })
+];
+
+const APP_MIGRATIONS: [Migration; CONFIG_VERSION as usize] = [
+    // only the version was added
+    |app| app
+];
+
+mod history;
+mod settings;
+pub use history::{DayStats, History};
+pub use settings::{GSettings, JsonSettings, SettingsBackend};
 
 // custom error wrapper type
 #[derive(Debug)]
 pub enum Error {
     IoError(io::Error),
-    SerdeError(serde_json::Error)
+    SerdeError(serde_json::Error),
+    DatabaseError(rusqlite::Error),
+    SecretError(zbus::Error),
+    SettingsError(glib::BoolError),
+    /// a newer version of the app wrote this file (file name, version)
+    NewerConfig(String, u32)
 }
 
 impl From<io::Error> for Error {
@@ -24,11 +62,33 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+impl From<zbus::Error> for Error {
+    fn from(value: zbus::Error) -> Self {
+        Self::SecretError(value)
+    }
+}
+
+impl From<glib::BoolError> for Error {
+    fn from(value: glib::BoolError) -> Self {
+        Self::SettingsError(value)
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(value: rusqlite::Error) -> Self {
+        Self::DatabaseError(value)
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Self::IoError(err) => write!(f, "I/O error: {}", err),
             Self::SerdeError(err) => write!(f, "Serialization error: {}", err),
+            Self::DatabaseError(err) => write!(f, "Database error: {}", err),
+            Self::SecretError(err) => write!(f, "Secret Service error: {}", err),
+            Self::SettingsError(err) => write!(f, "Settings error: {}", err),
+            Self::NewerConfig(name, version) => write!(f, "{} was saved by a newer version of the app (version {}) and can't be read", name, version),
         }
     }
 }
@@ -36,7 +96,11 @@ impl std::error::Error for Error {}
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Serialize, Deserialize, Clone)]
+/// the band stores the goal as a u16, and anything much lower isn't much of a goal
+pub const STEP_GOAL_MIN: u16 = 1000;
+pub const STEP_GOAL_MAX: u16 = u16::MAX;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct ActivityGoal {
     pub notifications: bool,
     pub steps: u16
@@ -48,7 +112,50 @@ impl Default for ActivityGoal {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+/// how long a single characteristic read/write can take before giving up, and how many times it's tried
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct IoPolicy {
+    pub timeout_secs: u64,
+    /// only operations that are safe to repeat are retried after timing out
+    pub attempts: u32
+}
+
+impl Default for IoPolicy {
+    fn default() -> Self {
+        Self { timeout_secs: 5, attempts: 3 }
+    }
+}
+
+/// switches that can also be flipped from the desktop's quick settings
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct QuickToggles {
+    pub forwarding: bool,
+    pub do_not_disturb: bool,
+    /// no continuous heart rate, and disconnect from the band sooner
+    pub battery_saver: bool
+}
+
+impl Default for QuickToggles {
+    fn default() -> Self {
+        Self { forwarding: true, do_not_disturb: false, battery_saver: false }
+    }
+}
+
+/// pause background syncs and continuous heart rate while the computer is on battery and running low
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct PowerThrottle {
+    pub enabled: bool,
+    /// percent
+    pub threshold: u8
+}
+
+impl Default for PowerThrottle {
+    fn default() -> Self {
+        Self { enabled: false, threshold: 30 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct BandLock {
     pub pin: String,
     pub enabled: bool
@@ -60,18 +167,482 @@ impl Default for BandLock {
     }
 }
 
+/// the band only accepts PINs made of these
+pub const BAND_LOCK_PIN_DIGITS: [char; 4] = ['1', '2', '3', '4'];
+pub const BAND_LOCK_PIN_LENGTH: usize = 4;
+
+impl BandLock {
+    /// whether the band would accept the PIN - bands.json can be edited (or imported) by hand
+    pub fn is_valid(&self) -> bool {
+        self.pin.chars().count() == BAND_LOCK_PIN_LENGTH && self.pin.chars().all(|digit| BAND_LOCK_PIN_DIGITS.contains(&digit))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug)]
+pub enum DistanceUnit {
+    Metric,
+    Imperial
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug)]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit
+}
+
+/// the language weather conditions are sent to the band in
+/// the band shows whatever text it's given, so this doesn't have to match the app's
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum WeatherLanguage {
+    #[default]
+    English,
+    German,
+    Spanish,
+    French,
+    Italian,
+    Portuguese,
+    Russian,
+    Chinese
+}
+
+impl WeatherLanguage {
+    pub const ALL: [Self; 8] = [
+        Self::English, Self::German, Self::Spanish, Self::French, Self::Italian, Self::Portuguese, Self::Russian, Self::Chinese
+    ];
+
+    /// ISO 639-1
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::English => "en",
+            Self::German => "de",
+            Self::Spanish => "es",
+            Self::French => "fr",
+            Self::Italian => "it",
+            Self::Portuguese => "pt",
+            Self::Russian => "ru",
+            Self::Chinese => "zh"
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug)]
+pub enum TimeFormat {
+    H12,
+    H24
+}
+
+/// what the band shows on the watchface
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug)]
+pub enum DateDisplay {
+    Time,
+    DateTime
+}
+
+impl Default for DateDisplay {
+    fn default() -> Self {
+        Self::DateTime
+    }
+}
+
+/// units used both on the band and in the app
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct DisplayFormat {
+    pub distance_unit: DistanceUnit,
+    pub time_format: TimeFormat,
+    // not present in older configs
+    #[serde(default)]
+    pub date_display: DateDisplay
+}
+
+impl Default for DisplayFormat {
+    fn default() -> Self {
+        Self { distance_unit: DistanceUnit::Imperial, time_format: TimeFormat::H12, date_display: DateDisplay::default() }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct HeartRateSharing {
+    pub enabled: bool
+}
+
+/// what to do when the band is taken off
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct WearDetection {
+    /// show a desktop notification
+    pub notify: bool,
+    /// stop continuous heart rate measurement until the band is worn again
+    pub pause_heart_rate: bool
+}
+
+/// desktop notifications about the band's battery, checked whenever it's read
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BatteryAlert {
+    /// when it drops below `threshold` percent
+    pub notify_low: bool,
+    pub threshold: u8,
+    /// when it's finished charging
+    pub notify_charged: bool
+}
+
+impl Default for BatteryAlert {
+    fn default() -> Self {
+        Self { notify_low: true, threshold: 20, notify_charged: true }
+    }
+}
+
+/// reminds the user to move if they haven't walked enough in the last hour
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MovementGate {
+    pub enabled: bool,
+    /// minimum steps per hour
+    pub steps: u16,
+    pub start_hour: u8,
+    pub end_hour: u8,
+    // not present in older configs
+    #[serde(default)]
+    pub start_minute: u8,
+    #[serde(default)]
+    pub end_minute: u8,
+    pub weekdays_only: bool,
+    pub skip_meetings: bool
+}
+
+impl Default for MovementGate {
+    fn default() -> Self {
+        Self { enabled: false, steps: 250, start_hour: 9, end_hour: 17, start_minute: 0, end_minute: 0, weekdays_only: true, skip_meetings: true }
+    }
+}
+
+/// disconnect after a while without anything to do, to save power
+/// the band is reconnected when it's needed again
+#[derive(Serialize, Deserialize, Clone)]
+pub struct IdleDisconnect {
+    pub enabled: bool,
+    pub minutes: u16
+}
+
+impl Default for IdleDisconnect {
+    fn default() -> Self {
+        Self { enabled: false, minutes: 15 }
+    }
+}
+
+/// periodically fetch new activity from the band while it's connected
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SyncSchedule {
+    pub enabled: bool,
+    pub interval_minutes: u16,
+    /// scheduled syncs wait while the band's battery is below this percentage (0 never waits)
+    // not present in older configs
+    #[serde(default)]
+    pub min_battery: u8
+}
+
+impl Default for SyncSchedule {
+    /// syncing in the background is opt-in
+    fn default() -> Self {
+        Self { enabled: false, interval_minutes: 30, min_battery: 20 }
+    }
+}
+
+/// a summary of the day sent to the band every morning
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MorningBriefing {
+    pub enabled: bool,
+    pub hour: u8,
+    pub minute: u8,
+    /// (latitude, longitude) - the weather is left out if this isn't set
+    pub weather_location: Option<(f64, f64)>,
+    /// `None` follows the band's distance unit
+    pub temperature_unit: Option<TemperatureUnit>,
+    // not present in older configs
+    #[serde(default)]
+    pub weather_language: WeatherLanguage
+}
+
+impl Default for MorningBriefing {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hour: 7,
+            minute: 30,
+            weather_location: None,
+            temperature_unit: None,
+            weather_language: WeatherLanguage::default()
+        }
+    }
+}
+
+/// a summary of last night's sleep, sent after the first sync past `hour:minute`
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SleepSummary {
+    pub enabled: bool,
+    pub hour: u8,
+    pub minute: u8,
+    pub to_band: bool,
+    pub to_desktop: bool,
+    /// unix timestamp of when it was last sent, so it's only sent once a day
+    pub last_sent: Option<i64>
+}
+
+impl Default for SleepSummary {
+    fn default() -> Self {
+        Self { enabled: false, hour: 6, minute: 0, to_band: true, to_desktop: false, last_sent: None }
+    }
+}
+
+/// what a band gets from this computer while it's the connected one
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct Forwarding {
+    pub notifications: bool,
+    /// incoming and missed call notifications
+    pub calls: bool,
+    /// the current media, and the band's music controls
+    pub media: bool
+}
+
+impl Default for Forwarding {
+    fn default() -> Self {
+        Self { notifications: true, calls: true, media: true }
+    }
+}
+
+/// where band data is exported to (the local history is always kept)
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct MetricSinks {
+    pub prometheus: bool,
+    /// disabled if empty
+    pub webhook_url: String,
+    /// only gets sleep events, in Sleep as Android's format (disabled if empty)
+    // not present in older configs
+    #[serde(default)]
+    pub sleep_webhook_url: String
+}
+
+/// for when the user is away from home - see `MiBandWindow::process_new_travel_mode`
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct TravelMode {
+    pub enabled: bool
+}
+
+/// how far each kind of data has been synced from the band (unix timestamps)
+/// fetches only ask the band for anything newer than these
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct SyncMarks {
+    /// the newest activity sample
+    pub activity: Option<i64>,
+    /// the newest minute the band recorded as sleep
+    pub sleep: Option<i64>,
+    /// the newest minute with a heart rate measurement
+    pub heart_rate: Option<i64>,
+    /// the start of the newest workout
+    pub workouts: Option<i64>
+}
+
+impl SyncMarks {
+    /// sleep and heart rate come in the per-minute activity samples, so they move along with it
+    pub fn record_activity(&mut self, samples: &[ActivitySample]) {
+        let newest = |mark: &mut Option<i64>, time: Option<i64>| {
+            if let Some(time) = time {
+                *mark = Some(mark.map_or(time, |mark| mark.max(time)));
+            }
+        };
+        newest(&mut self.activity, samples.last().map(|s| s.time.timestamp()));
+        newest(&mut self.sleep, samples.iter().rev().find(|s| is_sleep_kind(s.kind)).map(|s| s.time.timestamp()));
+        newest(&mut self.heart_rate, samples.iter().rev().find(|s| s.heart_rate.is_some()).map(|s| s.time.timestamp()));
+    }
+
+    pub fn record_workouts(&mut self, workouts: &[WorkoutSummary]) {
+        if let Some(last) = workouts.iter().map(|w| w.start.timestamp()).max() {
+            self.workouts = Some(self.workouts.map_or(last, |mark| mark.max(last)));
+        }
+    }
+}
+
+/// a value read from the band, and when (unix timestamp)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Cached<T> {
+    pub value: T,
+    pub read_at: i64
+}
+
+impl<T> Cached<T> {
+    /// just read
+    pub fn now(value: T) -> Self {
+        Self { value, read_at: Local::now().timestamp() }
+    }
+
+    pub fn read_at(&self) -> Option<DateTime<Local>> {
+        Local.timestamp_opt(self.read_at, 0).single()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CachedBattery {
+    pub level: u8,
+    pub charging: bool,
+    /// unix timestamp
+    pub last_charge: i64
+}
+
+impl From<&BatteryStatus> for CachedBattery {
+    fn from(battery: &BatteryStatus) -> Self {
+        Self { level: battery.battery_level, charging: battery.charging, last_charge: battery.last_charge.timestamp() }
+    }
+}
+
+impl From<&CachedBattery> for BatteryStatus {
+    fn from(battery: &CachedBattery) -> Self {
+        Self {
+            battery_level: battery.level,
+            last_charge: Local.timestamp_opt(battery.last_charge, 0).single().unwrap_or_default(),
+            charging: battery.charging
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CachedActivity {
+    pub steps: u16,
+    pub calories: u16,
+    pub meters: u16
+}
+
+impl From<&CurrentActivity> for CachedActivity {
+    fn from(activity: &CurrentActivity) -> Self {
+        Self { steps: activity.steps, calories: activity.calories, meters: activity.meters }
+    }
+}
+
+impl From<&CachedActivity> for CurrentActivity {
+    fn from(activity: &CachedActivity) -> Self {
+        Self { steps: activity.steps, calories: activity.calories, meters: activity.meters }
+    }
+}
+
+/// the last values read from the band, to show while it can't be reached
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct CachedValues {
+    pub battery: Option<Cached<CachedBattery>>,
+    pub activity: Option<Cached<CachedActivity>>,
+    /// the band's clock (unix timestamp)
+    pub time: Option<Cached<i64>>
+}
+
+/// require a PIN (or system authentication) before showing anything
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AppLock {
+    pub enabled: bool,
+    pub pin: Option<PinHash>,
+    /// allow unlocking with polkit instead of the PIN
+    pub system_auth: bool,
+    /// lock again once the window has been in the background this long
+    pub timeout_minutes: u16
+}
+
+impl Default for AppLock {
+    fn default() -> Self {
+        Self { enabled: false, pin: None, system_auth: false, timeout_minutes: 5 }
+    }
+}
+
+/// settings for the app itself rather than a band
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct AppConf {
+    pub app_lock: Option<AppLock>,
+    /// keep discovery running so nearby bands show up without scanning
+    pub passive_discovery: Option<bool>,
+    pub io_policy: Option<IoPolicy>,
+    pub quick_toggles: Option<QuickToggles>,
+    /// write memory usage, task counts and reconnects to soak.jsonl
+    pub soak_diagnostics: Option<bool>,
+    /// disconnect from the band when the app exits, instead of leaving BlueZ connected to it
+    pub disconnect_on_exit: Option<bool>,
+    pub power_throttle: Option<PowerThrottle>,
+    /// replace punctuation and emoji parts the band can't show in alerts
+    pub simplify_alerts: Option<bool>,
+    /// stop sending music updates this many seconds after the last music event (0 keeps sending them)
+    pub music_idle_timeout_secs: Option<u32>,
+    /// less padding around the cards and rows
+    pub compact_style: Option<bool>,
+    /// desktop notification when the day's step goal is reached
+    pub goal_notifications: Option<bool>,
+    /// desktop notification with the day's totals in the evening
+    pub day_summary: Option<bool>,
+    /// at startup, offer to forget saved bands that haven't been connected to for this many days (0 never does)
+    pub stale_band_days: Option<u32>
+}
+
 #[derive(Serialize, Deserialize, Default)]
 pub struct BandConf {
     pub auth_key: Option<String>,
 
     pub activity_goal: Option<ActivityGoal>,
     pub band_lock: Option<BandLock>,
-    pub alias: Option<String>
+    pub display_format: Option<DisplayFormat>,
+    pub heart_rate_sharing: Option<HeartRateSharing>,
+    pub wear_detection: Option<WearDetection>,
+    pub battery_alert: Option<BatteryAlert>,
+    pub movement_gate: Option<MovementGate>,
+    pub metric_sinks: Option<MetricSinks>,
+    pub sync_schedule: Option<SyncSchedule>,
+    pub morning_briefing: Option<MorningBriefing>,
+    pub sleep_summary: Option<SleepSummary>,
+    pub travel_mode: Option<TravelMode>,
+    pub forwarding: Option<Forwarding>,
+    /// connect as soon as the band shows up
+    pub auto_connect: Option<bool>,
+    pub idle_disconnect: Option<IdleDisconnect>,
+    /// the GATT characteristic object paths from the last connection (service -> characteristic -> path)
+    pub gatt_paths: Option<HashMap<String, HashMap<String, String>>>,
+    /// unix timestamp of the last successful activity sync
+    pub last_synced: Option<i64>,
+    /// unix timestamp of the last connection
+    pub last_connected: Option<i64>,
+    pub sync_marks: Option<SyncMarks>,
+    /// the last battery, activity and time read from the band
+    pub cached: Option<CachedValues>,
+    pub alias: Option<String>,
+    /// the band's serial number, to recognize it if its mac address changes
+    pub serial: Option<String>
+}
+
+/// what the auth key is called in the keyring
+fn auth_key_label(band_mac: &str) -> String {
+    format!("Mi Band 4 auth key ({band_mac})")
+}
+
+/// take the auth keys out of a config file (at any depth, so it works for every version)
+fn remove_auth_keys(config: &mut Value) {
+    match config {
+        Value::Object(map) => {
+            map.remove("auth_key");
+            map.values_mut().for_each(remove_auth_keys);
+        },
+        Value::Array(values) => values.iter_mut().for_each(remove_auth_keys),
+        _ => {}
+    }
 }
 
 pub struct Store {
     data_dir: PathBuf,
-    bands: HashMap<String, BandConf>
+    bands: HashMap<String, BandConf>,
+    pub app: AppConf,
+    /// settings stay in bands.json, but synced data goes in here
+    history: History,
+    /// bands connected in guest mode - nothing about them is written to disk
+    guests: HashMap<String, BandConf>,
+    guest_history: History,
+    /// auth keys are kept here instead of bands.json when it's available
+    secrets: Option<SecretStore>,
+    /// the auth keys as they are in the Secret Service, so only changed ones are written
+    saved_auth_keys: Arc<Mutex<HashMap<String, Option<String>>>>,
+    /// where `app` is kept
+    settings: Arc<dyn SettingsBackend + Send + Sync>,
+    /// saves are written one at a time, and one that was overtaken by a newer save isn't written at all
+    /// (the number of the last save started, and the number of the last one written)
+    save_generation: AtomicU64,
+    saved_generation: Arc<async_lock::Mutex<u64>>
 }
 
 impl Store {
@@ -83,40 +654,284 @@ impl Store {
 
         // load existing config
         let bands = Store::load_band_conf(&data_dir).await?;
+        // use dconf if the schema is installed
+        let settings: Arc<dyn SettingsBackend + Send + Sync> = match GSettings::new(data_dir.clone()) {
+            Some(settings) => Arc::new(settings),
+            None => Arc::new(JsonSettings::new(data_dir.clone()))
+        };
+        let app = settings.load().await?;
+        let history = History::open(&data_dir.join("history.sqlite3"))?;
+        let guest_history = History::open_in_memory()?;
+        let secrets = match SecretStore::connect().await {
+            Ok(secrets) => Some(secrets),
+            Err(err) => {
+                warn!("The Secret Service isn't available, so auth keys are kept in bands.json: {err}");
+                None
+            }
+        };
         
-        Ok(Self {
+        let mut store = Self {
             data_dir,
-            bands
-        })
+            bands,
+            app,
+            history,
+            guests: HashMap::new(),
+            guest_history,
+            secrets,
+            saved_auth_keys: Arc::new(Mutex::new(HashMap::new())),
+            settings,
+            save_generation: AtomicU64::new(0),
+            saved_generation: Arc::new(async_lock::Mutex::new(0))
+        };
+        if let Err(err) = store.load_secrets().await {
+            warn!("Couldn't read the auth keys from the Secret Service, so they're kept in bands.json: {err}");
+            store.secrets = None;
+        }
+        Ok(store)
     }
-    async fn load_band_conf(data_dir: &Path) -> Result<HashMap<String, BandConf>> {
-        // read the band conf
-        match read(data_dir.join("bands.json")).await {
-            Ok(data) => {
-                Ok(serde_json::from_slice(&data)?)
-            },
-            Err(err) => {
-                // if we couldn't fine the band conf file, just return an empty map
-                if err.kind() == ErrorKind::NotFound {
-                    Ok(HashMap::new())
-                } else {
-                    // otherwise propagate the error
-                    Err(err.into())
-                }
+    /// read the auth keys from the Secret Service,
+    /// moving any that are still in bands.json (from before it was used) there
+    async fn load_secrets(&mut self) -> Result<()> {
+        let Some(secrets) = &self.secrets else { return Ok(()) };
+        let mut migrated = false;
+        let mut saved_auth_keys = HashMap::new();
+        for (band_mac, band) in self.bands.iter_mut() {
+            match &band.auth_key {
+                Some(auth_key) => {
+                    secrets.set(SECRET_AUTH_KEY, band_mac, &auth_key_label(band_mac), auth_key).await?;
+                    migrated = true;
+                },
+                None => band.auth_key = secrets.get(SECRET_AUTH_KEY, band_mac).await?
+            }
+            saved_auth_keys.insert(band_mac.clone(), band.auth_key.clone());
+        }
+        *self.saved_auth_keys.lock().expect("can lock saved auth keys") = saved_auth_keys;
+        // take them out of bands.json
+        if migrated {
+            self.save().await?;
+            // the backup is the file from before, with the keys still in it
+            match remove_file(self.data_dir.join("bands.json.bak")).await {
+                Ok(()) => {},
+                Err(err) if err.kind() == ErrorKind::NotFound => {},
+                Err(err) => return Err(err.into())
+            }
+        }
+        Ok(())
+    }
+    /// the auth keys that changed since they were last saved
+    fn changed_auth_keys(&self) -> Vec<(String, Option<String>)> {
+        let saved_auth_keys = self.saved_auth_keys.lock().expect("can lock saved auth keys");
+        self.bands.iter()
+            .filter(|(band_mac, band)| saved_auth_keys.get(*band_mac) != Some(&band.auth_key))
+            .map(|(band_mac, band)| (band_mac.clone(), band.auth_key.clone()))
+            // bands that were moved to another address
+            .chain(saved_auth_keys.iter()
+                .filter(|(band_mac, auth_key)| auth_key.is_some() && !self.bands.contains_key(*band_mac))
+                .map(|(band_mac, _)| (band_mac.clone(), None)))
+            .collect()
+    }
+    /// write the auth keys that changed
+    async fn save_secrets(secrets: &SecretStore, saved_auth_keys: &Mutex<HashMap<String, Option<String>>>, changed: Vec<(String, Option<String>)>) -> Result<()> {
+        for (band_mac, auth_key) in changed {
+            match &auth_key {
+                Some(auth_key) => secrets.set(SECRET_AUTH_KEY, &band_mac, &auth_key_label(&band_mac), auth_key).await?,
+                None => secrets.delete(SECRET_AUTH_KEY, &band_mac).await?
+            }
+            saved_auth_keys.lock().expect("can lock saved auth keys").insert(band_mac, auth_key);
+        }
+        Ok(())
+    }
+    /// read a config file and upgrade it to the current version
+    /// if it had to be upgraded, the original (minus any auth keys) is kept next to it as <name>.v<version>.bak
+    async fn load_config(data_dir: &Path, name: &str, migrations: &[Migration]) -> Result<Option<Value>> {
+        let data = match read(data_dir.join(name)).await {
+            Ok(data) => data,
+            // it hasn't been saved yet
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into())
+        };
+        let mut config: Value = serde_json::from_slice(&data)?;
+        // the auth keys are moved to the Secret Service, and shouldn't be left behind in the backup
+        let mut backup = config.clone();
+        remove_auth_keys(&mut backup);
+        let version = config.as_object_mut()
+            .and_then(|config| config.remove("version"))
+            .and_then(|version| version.as_u64())
+            .unwrap_or(0) as u32;
+        // don't touch it - saving would lose whatever the newer version added
+        if version > CONFIG_VERSION {
+            return Err(Error::NewerConfig(name.to_string(), version));
+        }
+        if version < CONFIG_VERSION {
+            write(data_dir.join(format!("{name}.v{version}.bak")), serde_json::to_vec(&backup)?).await?;
+            for migrate in &migrations[version as usize..] {
+                config = migrate(config);
             }
         }
+        Ok(Some(config))
+    }
+    /// write a config file with the current version
+    /// the previous file is kept as <name>.bak, and a crash while writing can't leave half a file
+    async fn save_config(data_dir: &Path, name: &str, mut config: Value) -> Result<()> {
+        if let Some(config) = config.as_object_mut() {
+            config.insert("version".into(), CONFIG_VERSION.into());
+        }
+        let path = data_dir.join(name);
+        match copy(&path, data_dir.join(format!("{name}.bak"))).await {
+            Ok(_) => {},
+            Err(err) if err.kind() == ErrorKind::NotFound => {},
+            Err(err) => return Err(err.into())
+        }
+        let temp_path = data_dir.join(format!("{name}.tmp"));
+        let mut file = File::create(&temp_path).await?;
+        file.write_all(&serde_json::to_vec(&config)?).await?;
+        // it has to be on disk before it replaces the old one
+        file.sync_all().await?;
+        Ok(rename(temp_path, path).await?)
+    }
+    async fn load_band_conf(data_dir: &Path) -> Result<HashMap<String, BandConf>> {
+        match Store::load_config(data_dir, "bands.json", &BAND_MIGRATIONS).await? {
+            Some(mut config) => Ok(serde_json::from_value(config["bands"].take())?),
+            // if there isn't a band conf file, just return an empty map
+            None => Ok(HashMap::new())
+        }
+    }
+    /// the preferences can be changed from outside the app if this is `Some` - reload them when it changes
+    pub fn settings_monitor(&self) -> Option<gio::Settings> {
+        self.settings.monitor()
+    }
+    pub async fn reload_settings(&mut self) -> Result<()> {
+        self.app = self.settings.load().await?;
+        Ok(())
     }
     pub fn get_band(&mut self, band_mac: String) -> &mut BandConf {
-        self.bands.entry(band_mac).or_default()
+        if self.guests.contains_key(&band_mac) {
+            self.guests.entry(band_mac).or_default()
+        } else {
+            self.bands.entry(band_mac).or_default()
+        }
+    }
+    /// the history for this band - guests get one that's only kept in memory
+    pub fn history(&mut self, band_mac: &str) -> &mut History {
+        if self.guests.contains_key(band_mac) { &mut self.guest_history } else { &mut self.history }
+    }
+    /// start or stop treating a band as a guest
+    /// a guest starts out with no settings, and everything set while it's a guest is forgotten when it stops being one
+    pub fn set_guest(&mut self, band_mac: String, guest: bool) {
+        if guest {
+            self.guests.entry(band_mac).or_default();
+        } else {
+            self.guests.remove(&band_mac);
+        }
+    }
+    /// whether this band should be connected to without asking
+    pub fn should_auto_connect(&self, band_mac: &str) -> bool {
+        !self.is_guest(band_mac) && self.bands.get(band_mac).and_then(|b| b.auto_connect).unwrap_or(false)
+    }
+    /// the time of the newest activity sample synced from this band
+    pub fn last_synced_activity(&mut self, band_mac: &str) -> Result<Option<DateTime<Local>>> {
+        let mark = self.get_band(band_mac.to_string()).sync_marks.as_ref()
+            .and_then(|marks| marks.activity)
+            .and_then(|t| Local.timestamp_opt(t, 0).single());
+        match mark {
+            Some(mark) => Ok(Some(mark)),
+            // synced before the marks were kept
+            None => Ok(self.history(band_mac).last_activity_time(band_mac)?)
+        }
+    }
+    /// a saved band with this mac address or alias (ignoring case)
+    pub fn find_band(&self, query: &str) -> Option<String> {
+        self.bands.iter()
+            .find(|(band_mac, band)| band_mac.eq_ignore_ascii_case(query) || band.alias.as_ref().is_some_and(|alias| alias.eq_ignore_ascii_case(query)))
+            .map(|(band_mac, _)| band_mac.clone())
+    }
+    /// a saved band other than `band_mac` with this serial number
+    pub fn band_with_serial(&self, serial: &str, band_mac: &str) -> Option<String> {
+        self.bands.iter()
+            .find(|(mac, band)| *mac != band_mac && band.serial.as_deref() == Some(serial))
+            .map(|(mac, _)| mac.clone())
+    }
+    /// move a saved band's settings (alias, auth key, ...) and history to a new mac address,
+    /// replacing whatever the new address had
+    pub fn migrate_band(&mut self, old_mac: &str, new_mac: String) -> Result<()> {
+        let Some(mut band_conf) = self.bands.remove(old_mac) else { return Ok(()) };
+        // the characteristic paths belong to the new address's BlueZ device
+        band_conf.gatt_paths = self.bands.get(&new_mac).and_then(|band| band.gatt_paths.clone());
+        self.history.move_band(old_mac, &new_mac)?;
+        self.bands.insert(new_mac, band_conf);
+        Ok(())
+    }
+    /// saved bands that haven't been connected to (or synced, before connections were kept) since `before`
+    /// bands with neither start counting from now
+    pub fn stale_bands(&mut self, before: DateTime<Local>) -> Vec<String> {
+        let now = Local::now().timestamp();
+        let mut stale: Vec<String> = self.bands.iter_mut()
+            .filter_map(|(band_mac, band)| {
+                let seen = *band.last_connected.get_or_insert(band.last_synced.unwrap_or(now));
+                (seen < before.timestamp()).then(|| band_mac.clone())
+            })
+            .collect();
+        stale.sort();
+        stale
+    }
+    /// forget everything about a saved band - its settings, auth key and history
+    pub fn remove_band(&mut self, band_mac: &str) -> Result<()> {
+        self.history.delete_band(band_mac)?;
+        self.bands.remove(band_mac);
+        Ok(())
+    }
+    /// the mac addresses of every saved band
+    pub fn saved_bands(&self) -> Vec<String> {
+        self.bands.keys().cloned().collect()
+    }
+    /// the saved settings for a band, without adding it if there aren't any
+    pub fn stored_band(&self, band_mac: &str) -> Option<&BandConf> {
+        self.bands.get(band_mac)
+    }
+    pub fn is_guest(&self, band_mac: &str) -> bool {
+        self.guests.contains_key(band_mac)
     }
     /// returns the band alias, or the mac address if there was no alias
     pub fn get_band_alias<'a>(&'a self, band_mac: &'a str) -> &'a str {
-        self.bands.get(band_mac).and_then(|b| b.alias.as_ref()).map(|s| s.as_str()).unwrap_or(band_mac)
+        let bands = if self.is_guest(band_mac) { &self.guests } else { &self.bands };
+        bands.get(band_mac).and_then(|b| b.alias.as_ref()).map(|s| s.as_str()).unwrap_or(band_mac)
     }
 
-    pub async fn save(&self) -> Result<()> {
-        let band_config = serde_json::to_vec(&self.bands)?;
-        // write it to the bands file
-        Ok(write(self.data_dir.join("bands.json"), band_config).await?)
+    /// everything is copied out before this returns, so the store doesn't have to stay locked while it's written
+    /// (the Secret Service might be showing an unlock prompt) - see `save_and_unlock`
+    pub fn save(&self) -> LocalBoxFuture<'static, Result<()>> {
+        let band_config = serde_json::to_value(&self.bands);
+        let secrets = self.secrets.clone().map(|secrets| (secrets, self.changed_auth_keys()));
+        let saved_auth_keys = self.saved_auth_keys.clone();
+        let data_dir = self.data_dir.clone();
+        let settings = self.settings.clone();
+        let app = self.app.clone();
+        let generation = self.save_generation.fetch_add(1, Ordering::Relaxed) + 1;
+        let saved_generation = self.saved_generation.clone();
+        Box::pin(async move {
+            let mut band_config = band_config?;
+            let mut saved_generation = saved_generation.lock().await;
+            // a newer save already wrote all of this
+            if *saved_generation > generation { return Ok(()) }
+            if let Some((secrets, changed)) = secrets {
+                Store::save_secrets(&secrets, &saved_auth_keys, changed).await?;
+                // the auth keys are in the Secret Service instead
+                for band in band_config.as_object_mut().into_iter().flat_map(|bands| bands.values_mut()) {
+                    if let Some(band) = band.as_object_mut() {
+                        band.remove("auth_key");
+                    }
+                }
+            }
+            Store::save_config(&data_dir, "bands.json", json!({ "bands": band_config })).await?;
+            settings.save(&app).await?;
+            *saved_generation = generation;
+            Ok(())
+        })
+    }
+    /// save, releasing the lock on the store before anything is written
+    pub async fn save_and_unlock(store: MutexGuard<'_, Store>) -> Result<()> {
+        let save = store.save();
+        drop(store);
+        save.await
     }
 }
@@ -0,0 +1,408 @@
+use std::{path::Path, time::Duration};
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Timelike};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{band::{ActivitySample, CurrentActivity, WorkoutKind, WorkoutSummary}, heart_rate::HeartRateFilter, sleep::SleepSession};
+
+/// everything recorded on a single day
+#[derive(Debug, Clone)]
+pub struct DayStats {
+    pub date: NaiveDate,
+    pub steps: u32,
+    pub meters: u32,
+    pub calories: u32,
+    pub avg_heart_rate: Option<f64>,
+    /// including the samples the filter rejected
+    pub avg_heart_rate_raw: Option<f64>
+}
+
+/// how close to midnight (in minutes) a reading of the totals has to be to possibly belong to the other day
+/// the band's clock - and so its reset - can be a little off from ours
+const ROLLOVER_GRACE_MINUTES: u32 = 15;
+
+/// each entry upgrades the schema by one version (stored in `user_version`)
+/// never edit an existing migration - add a new one
+const MIGRATIONS: [&str; 4] = [
+    "CREATE TABLE activity (
+        band TEXT NOT NULL,
+        time INTEGER NOT NULL,
+        kind INTEGER NOT NULL,
+        intensity INTEGER NOT NULL,
+        steps INTEGER NOT NULL,
+        heart_rate INTEGER,
+        PRIMARY KEY (band, time)
+    );
+    CREATE TABLE heart_rate (
+        band TEXT NOT NULL,
+        time INTEGER NOT NULL,
+        bpm INTEGER NOT NULL,
+        PRIMARY KEY (band, time)
+    );
+    CREATE TABLE sleep_sessions (
+        band TEXT NOT NULL,
+        start INTEGER NOT NULL,
+        end INTEGER NOT NULL,
+        PRIMARY KEY (band, start)
+    );
+    CREATE TABLE workouts (
+        band TEXT NOT NULL,
+        start INTEGER NOT NULL,
+        kind INTEGER NOT NULL,
+        duration INTEGER NOT NULL,
+        distance REAL NOT NULL,
+        calories INTEGER NOT NULL,
+        avg_hr INTEGER,
+        PRIMARY KEY (band, start)
+    );",
+    "CREATE TABLE daily_totals (
+        band TEXT NOT NULL,
+        date TEXT NOT NULL,
+        steps INTEGER NOT NULL,
+        meters INTEGER NOT NULL,
+        calories INTEGER NOT NULL,
+        PRIMARY KEY (band, date)
+    );",
+    // raw heart rates are kept, but only the valid ones are used
+    "ALTER TABLE activity ADD COLUMN heart_rate_valid INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE heart_rate ADD COLUMN valid INTEGER NOT NULL DEFAULT 1;",
+    // when the band said the step goal was reached
+    "CREATE TABLE goal_achievements (
+        band TEXT NOT NULL,
+        time INTEGER NOT NULL,
+        PRIMARY KEY (band, time)
+    );"
+];
+
+/// synced activity data, kept in SQLite since it grows without bound
+pub struct History {
+    conn: Connection
+}
+
+fn from_timestamp(timestamp: i64) -> DateTime<Local> {
+    Local.timestamp_opt(timestamp, 0).single().unwrap_or_default()
+}
+
+impl History {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let mut history = Self { conn: Connection::open(path)? };
+        history.migrate()?;
+        Ok(history)
+    }
+
+    /// a history that's gone once the app closes
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        let mut history = Self { conn: Connection::open_in_memory()? };
+        history.migrate()?;
+        Ok(history)
+    }
+
+    /// bring the schema up to date
+    fn migrate(&mut self) -> rusqlite::Result<()> {
+        let version: usize = self.conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = self.conn.transaction()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", (i + 1) as i64)?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    /// the newest valid heart rate from the activity before `before`
+    fn last_valid_heart_rate(&self, band_mac: &str, before: DateTime<Local>) -> rusqlite::Result<Option<(DateTime<Local>, u16)>> {
+        self.conn
+            .query_row(
+                "SELECT time, heart_rate FROM activity
+                WHERE band = ?1 AND time < ?2 AND heart_rate IS NOT NULL AND heart_rate_valid
+                ORDER BY time DESC LIMIT 1",
+                params![band_mac, before.timestamp()],
+                |row| Ok((from_timestamp(row.get(0)?), row.get(1)?))
+            )
+            .optional()
+    }
+
+    pub fn insert_activity(&mut self, band_mac: &str, samples: &[ActivitySample]) -> rusqlite::Result<()> {
+        let mut filter = match samples.first() {
+            Some(first) => HeartRateFilter::new(self.last_valid_heart_rate(band_mac, first.time)?),
+            None => return Ok(())
+        };
+        let tx = self.conn.transaction()?;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT OR REPLACE INTO activity (band, time, kind, intensity, steps, heart_rate, heart_rate_valid) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+            )?;
+            for sample in samples {
+                let valid = sample.heart_rate.map_or(true, |hr| filter.accept(sample.time, hr as u16));
+                insert.execute(params![band_mac, sample.time.timestamp(), sample.kind, sample.intensity, sample.steps, sample.heart_rate, valid])?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// per-minute activity recorded after `since`, oldest first
+    /// heart rates the filter rejected are left out
+    pub fn activity(&self, band_mac: &str, since: DateTime<Local>) -> rusqlite::Result<Vec<ActivitySample>> {
+        let mut query = self.conn.prepare_cached(
+            "SELECT time, kind, intensity, steps, CASE WHEN heart_rate_valid THEN heart_rate END
+            FROM activity WHERE band = ?1 AND time >= ?2 ORDER BY time"
+        )?;
+        let rows = query.query_map(params![band_mac, since.timestamp()], |row| Ok(ActivitySample {
+            time: from_timestamp(row.get(0)?),
+            kind: row.get(1)?,
+            intensity: row.get(2)?,
+            steps: row.get(3)?,
+            heart_rate: row.get(4)?
+        }))?;
+        rows.collect()
+    }
+
+    /// the time of the newest activity sample
+    pub fn last_activity_time(&self, band_mac: &str) -> rusqlite::Result<Option<DateTime<Local>>> {
+        let time: Option<i64> = self.conn
+            .query_row("SELECT MAX(time) FROM activity WHERE band = ?1", params![band_mac], |row| row.get(0))
+            .optional()?
+            .flatten();
+        Ok(time.map(from_timestamp))
+    }
+
+    /// the band's totals reset at midnight, so the last ones recorded each day are the day's totals
+    pub fn update_daily_totals(&mut self, band_mac: &str, date: NaiveDate, totals: &CurrentActivity) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO daily_totals (band, date, steps, meters, calories) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![band_mac, date.format("%Y-%m-%d").to_string(), totals.steps, totals.meters, totals.calories]
+        )?;
+        Ok(())
+    }
+
+    fn daily_steps(&self, band_mac: &str, date: NaiveDate) -> rusqlite::Result<Option<u32>> {
+        self.conn
+            .query_row(
+                "SELECT steps FROM daily_totals WHERE band = ?1 AND date = ?2",
+                params![band_mac, date.format("%Y-%m-%d").to_string()],
+                |row| row.get(0)
+            )
+            .optional()
+    }
+
+    /// which day a reading of the band's totals at `time` belongs to
+    /// right around midnight, the band may not have reset its counters yet (or may have already)
+    pub fn totals_date(&self, band_mac: &str, time: DateTime<Local>, totals: &CurrentActivity) -> rusqlite::Result<NaiveDate> {
+        let today = time.date_naive();
+        let minutes = time.num_seconds_from_midnight() / 60;
+        let steps = totals.steps as u32;
+
+        if minutes < ROLLOVER_GRACE_MINUTES {
+            // still counting up from yesterday's total - it hasn't reset yet
+            if let (None, Some(yesterday)) = (self.daily_steps(band_mac, today)?, today.pred_opt()) {
+                if let Some(yesterday_steps) = self.daily_steps(band_mac, yesterday)? {
+                    if yesterday_steps > 0 && steps >= yesterday_steps { return Ok(yesterday) }
+                }
+            }
+        } else if minutes >= 24 * 60 - ROLLOVER_GRACE_MINUTES {
+            // dropped below today's total - it's already reset
+            if let (Some(today_steps), Some(tomorrow)) = (self.daily_steps(band_mac, today)?, today.succ_opt()) {
+                if steps < today_steps { return Ok(tomorrow) }
+            }
+        }
+        Ok(today)
+    }
+
+    /// per-day totals and average heart rate since `since`, oldest first
+    ///
+    /// the steps are summed from the synced activity, since `daily_totals` is only written while the band is connected
+    /// the live totals are used when they're higher (today's activity usually hasn't all been synced yet)
+    /// the distance and calories only come from `daily_totals`, so they're 0 for days that were only synced
+    pub fn daily_stats(&self, band_mac: &str, since: NaiveDate) -> rusqlite::Result<Vec<DayStats>> {
+        let mut query = self.conn.prepare_cached(
+            "WITH synced AS (
+                SELECT date(time, 'unixepoch', 'localtime') AS date,
+                    SUM(steps) AS steps,
+                    AVG(CASE WHEN heart_rate_valid THEN heart_rate END) AS avg_hr,
+                    AVG(heart_rate) AS avg_hr_raw
+                FROM activity
+                WHERE band = ?1
+                GROUP BY date
+            ), dates AS (
+                SELECT date FROM synced
+                UNION
+                SELECT date FROM daily_totals WHERE band = ?1
+            )
+            SELECT dates.date,
+                MAX(COALESCE(synced.steps, 0), COALESCE(totals.steps, 0)),
+                COALESCE(totals.meters, 0),
+                COALESCE(totals.calories, 0),
+                synced.avg_hr,
+                synced.avg_hr_raw
+            FROM dates
+            LEFT JOIN synced ON synced.date = dates.date
+            LEFT JOIN daily_totals totals ON totals.band = ?1 AND totals.date = dates.date
+            WHERE dates.date >= ?2
+            ORDER BY dates.date"
+        )?;
+        let rows = query.query_map(params![band_mac, since.format("%Y-%m-%d").to_string()], |row| {
+            let date: String = row.get(0)?;
+            Ok(DayStats {
+                date: NaiveDate::parse_from_str(&date, "%Y-%m-%d").unwrap_or_default(),
+                steps: row.get(1)?,
+                meters: row.get(2)?,
+                calories: row.get(3)?,
+                avg_heart_rate: row.get(4)?,
+                avg_heart_rate_raw: row.get(5)?
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// relabel everything synced from one mac address as another's
+    /// if both have something for the same time, the new address's is kept
+    pub fn move_band(&mut self, from_mac: &str, to_mac: &str) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        for table in ["activity", "heart_rate", "sleep_sessions", "workouts", "daily_totals", "goal_achievements"] {
+            tx.execute(&format!("UPDATE OR IGNORE {table} SET band = ?2 WHERE band = ?1"), params![from_mac, to_mac])?;
+            tx.execute(&format!("DELETE FROM {table} WHERE band = ?1"), params![from_mac])?;
+        }
+        tx.commit()
+    }
+
+    /// delete everything synced from this band
+    pub fn delete_band(&mut self, band_mac: &str) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        for table in ["activity", "heart_rate", "sleep_sessions", "workouts", "daily_totals", "goal_achievements"] {
+            tx.execute(&format!("DELETE FROM {table} WHERE band = ?1"), params![band_mac])?;
+        }
+        tx.commit()
+    }
+
+    pub fn insert_goal_achievement(&mut self, band_mac: &str, time: DateTime<Local>) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO goal_achievements (band, time) VALUES (?1, ?2)",
+            params![band_mac, time.timestamp()]
+        )?;
+        Ok(())
+    }
+
+    /// when the step goal was first reached on `date`, if it was
+    pub fn goal_achievement(&self, band_mac: &str, date: NaiveDate) -> rusqlite::Result<Option<DateTime<Local>>> {
+        let start = date.and_hms_opt(0, 0, 0).and_then(|start| start.and_local_timezone(Local).earliest());
+        let end = date.succ_opt().and_then(|end| end.and_hms_opt(0, 0, 0)).and_then(|end| end.and_local_timezone(Local).earliest());
+        let (Some(start), Some(end)) = (start, end) else { return Ok(None) };
+        let time: Option<i64> = self.conn.query_row(
+            "SELECT MIN(time) FROM goal_achievements WHERE band = ?1 AND time >= ?2 AND time < ?3",
+            params![band_mac, start.timestamp(), end.timestamp()],
+            |row| row.get(0)
+        )?;
+        Ok(time.map(from_timestamp))
+    }
+
+    pub fn insert_heart_rate(&mut self, band_mac: &str, time: DateTime<Local>, bpm: u16) -> rusqlite::Result<()> {
+        let last = self.conn
+            .query_row(
+                "SELECT time, bpm FROM heart_rate WHERE band = ?1 AND time < ?2 AND valid ORDER BY time DESC LIMIT 1",
+                params![band_mac, time.timestamp()],
+                |row| Ok((from_timestamp(row.get(0)?), row.get(1)?))
+            )
+            .optional()?;
+        let valid = HeartRateFilter::new(last).accept(time, bpm);
+        self.conn.execute(
+            "INSERT OR REPLACE INTO heart_rate (band, time, bpm, valid) VALUES (?1, ?2, ?3, ?4)",
+            params![band_mac, time.timestamp(), bpm, valid]
+        )?;
+        Ok(())
+    }
+
+    pub fn insert_sleep(&mut self, band_mac: &str, session: &SleepSession) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO sleep_sessions (band, start, end) VALUES (?1, ?2, ?3)",
+            params![band_mac, session.start.timestamp(), session.end.timestamp()]
+        )?;
+        Ok(())
+    }
+
+    /// sleep sessions that ended after `since`, oldest first
+    pub fn sleep_sessions(&self, band_mac: &str, since: DateTime<Local>) -> rusqlite::Result<Vec<SleepSession>> {
+        let mut query = self.conn.prepare_cached(
+            "SELECT start, end FROM sleep_sessions WHERE band = ?1 AND end >= ?2 ORDER BY start"
+        )?;
+        // the minutes asleep aren't stored - count the whole session
+        let rows = query.query_map(params![band_mac, since.timestamp()], |row| {
+            let (start, end) = (from_timestamp(row.get(0)?), from_timestamp(row.get(1)?));
+            Ok(SleepSession { start, end, asleep: (end - start).to_std().unwrap_or_default() })
+        })?;
+        rows.collect()
+    }
+
+    pub fn insert_workouts(&mut self, band_mac: &str, workouts: &[WorkoutSummary]) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT OR IGNORE INTO workouts (band, start, kind, duration, distance, calories, avg_hr) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+            )?;
+            for workout in workouts {
+                insert.execute(params![
+                    band_mac,
+                    workout.start.timestamp(),
+                    u16::from(workout.kind),
+                    workout.duration.as_secs() as i64,
+                    workout.distance,
+                    workout.calories,
+                    workout.avg_hr
+                ])?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// every stored workout, oldest first
+    pub fn workouts(&self, band_mac: &str) -> rusqlite::Result<Vec<WorkoutSummary>> {
+        let mut query = self.conn.prepare_cached(
+            "SELECT start, kind, duration, distance, calories, avg_hr FROM workouts WHERE band = ?1 ORDER BY start"
+        )?;
+        let rows = query.query_map(params![band_mac], |row| Ok(WorkoutSummary {
+            start: from_timestamp(row.get(0)?),
+            kind: WorkoutKind::from(row.get::<_, u16>(1)?),
+            duration: Duration::from_secs(row.get::<_, i64>(2)?.max(0) as u64),
+            distance: row.get(3)?,
+            calories: row.get(4)?,
+            avg_hr: row.get(5)?
+        }))?;
+        rows.collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    fn sample(time: DateTime<Local>, steps: u8) -> ActivitySample {
+        ActivitySample { time, kind: 1, intensity: 20, steps, heart_rate: Some(70) }
+    }
+
+    #[test]
+    fn daily_stats_include_synced_days() {
+        let mut history = History::open_in_memory().unwrap();
+        let band = "AA:BB:CC:DD:EE:FF";
+        let day = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        let noon = day.and_hms_opt(12, 0, 0).unwrap().and_local_timezone(Local).unwrap();
+        history.insert_activity(band, &[sample(noon, 30), sample(noon + TimeDelta::minutes(1), 40)]).unwrap();
+
+        // only synced through activity fetch - there are no live totals
+        let stats = history.daily_stats(band, day).unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].date, day);
+        assert_eq!(stats[0].steps, 70);
+        assert_eq!(stats[0].meters, 0);
+
+        // the live totals are used once they're ahead of the synced activity
+        let next_day = day.succ_opt().unwrap();
+        history.update_daily_totals(band, day, &CurrentActivity { steps: 500, calories: 20, meters: 350 }).unwrap();
+        history.update_daily_totals(band, next_day, &CurrentActivity { steps: 100, calories: 5, meters: 70 }).unwrap();
+        let stats = history.daily_stats(band, day).unwrap();
+        assert_eq!(stats.iter().map(|day| (day.steps, day.meters)).collect::<Vec<_>>(), [(500, 350), (100, 70)]);
+        assert_eq!(stats[1].avg_heart_rate, None);
+
+        assert!(history.daily_stats(band, next_day.succ_opt().unwrap()).unwrap().is_empty());
+    }
+}
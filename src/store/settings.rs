@@ -0,0 +1,183 @@
+use std::path::PathBuf;
+
+use futures::future::LocalBoxFuture;
+use gtk::{gio::{self, prelude::*, SettingsSchemaSource}, glib};
+use log::warn;
+
+use crate::utils::APP_ID;
+
+use super::{AppConf, IoPolicy, PowerThrottle, QuickToggles, Result, Store, APP_MIGRATIONS};
+
+/// where the app's preferences (`AppConf`) are kept
+pub trait SettingsBackend {
+    fn load(&self) -> LocalBoxFuture<'_, Result<AppConf>>;
+    fn save<'a>(&'a self, app: &'a AppConf) -> LocalBoxFuture<'a, Result<()>>;
+    /// the settings to watch if the preferences can be changed while the app is running
+    fn monitor(&self) -> Option<gio::Settings> { None }
+}
+
+/// everything in app.json, next to bands.json
+pub struct JsonSettings {
+    data_dir: PathBuf
+}
+
+impl JsonSettings {
+    pub fn new(data_dir: PathBuf) -> Self {
+        Self { data_dir }
+    }
+}
+
+impl SettingsBackend for JsonSettings {
+    fn load(&self) -> LocalBoxFuture<'_, Result<AppConf>> {
+        Box::pin(async move {
+            match Store::load_config(&self.data_dir, "app.json", &APP_MIGRATIONS).await? {
+                Some(config) => Ok(serde_json::from_value(config)?),
+                None => Ok(AppConf::default())
+            }
+        })
+    }
+
+    fn save<'a>(&'a self, app: &'a AppConf) -> LocalBoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            Store::save_config(&self.data_dir, "app.json", serde_json::to_value(app)?).await
+        })
+    }
+}
+
+/// the preferences in dconf (through the me.grimsteel.miband4-gtk schema), so they can be changed with
+/// `gsettings` or dconf-editor and reset like any other GNOME app's
+///
+/// the app lock stays in app.json - anything could change it in dconf
+pub struct GSettings {
+    file: JsonSettings
+}
+
+impl GSettings {
+    /// `None` if the schema isn't installed
+    pub fn new(data_dir: PathBuf) -> Option<Self> {
+        SettingsSchemaSource::default()?.lookup(APP_ID, true)?;
+        Some(Self { file: JsonSettings::new(data_dir) })
+    }
+
+    /// GLib shares the backend between these, so making one is cheap
+    fn settings(&self) -> gio::Settings {
+        gio::Settings::new(APP_ID)
+    }
+}
+
+impl SettingsBackend for GSettings {
+    fn load(&self) -> LocalBoxFuture<'_, Result<AppConf>> {
+        Box::pin(async move {
+            let file = self.file.load().await?;
+            // move anything saved before the schema was installed into dconf (this also takes it out of app.json)
+            let from_file = file.passive_discovery.is_some() || file.io_policy.is_some() || file.quick_toggles.is_some()
+                || file.soak_diagnostics.is_some() || file.disconnect_on_exit.is_some() || file.power_throttle.is_some()
+                || file.simplify_alerts.is_some() || file.music_idle_timeout_secs.is_some()
+                || file.compact_style.is_some() || file.goal_notifications.is_some() || file.day_summary.is_some()
+                || file.stale_band_days.is_some();
+            if from_file {
+                self.save(&file).await?;
+            }
+
+            let settings = self.settings();
+            let set = |key: &str| settings.user_value(key).is_some();
+            let boolean = |key: &str| set(key).then(|| settings.boolean(key));
+            let uint = |key: &str| set(key).then(|| settings.uint(key));
+            // keys that haven't been set are left as `None`, so saving doesn't pin them to the current default
+            Ok(AppConf {
+                app_lock: file.app_lock,
+                passive_discovery: boolean("passive-discovery"),
+                io_policy: (set("io-timeout-secs") || set("io-attempts")).then(|| IoPolicy {
+                    timeout_secs: settings.uint("io-timeout-secs") as u64,
+                    attempts: settings.uint("io-attempts")
+                }),
+                quick_toggles: (set("forwarding") || set("do-not-disturb") || set("battery-saver")).then(|| QuickToggles {
+                    forwarding: settings.boolean("forwarding"),
+                    do_not_disturb: settings.boolean("do-not-disturb"),
+                    battery_saver: settings.boolean("battery-saver")
+                }),
+                soak_diagnostics: boolean("soak-diagnostics"),
+                disconnect_on_exit: boolean("disconnect-on-exit"),
+                power_throttle: (set("power-throttle") || set("power-throttle-threshold")).then(|| PowerThrottle {
+                    enabled: settings.boolean("power-throttle"),
+                    threshold: settings.uint("power-throttle-threshold") as u8
+                }),
+                simplify_alerts: boolean("simplify-alerts"),
+                music_idle_timeout_secs: uint("music-idle-timeout"),
+                compact_style: boolean("compact-style"),
+                goal_notifications: boolean("goal-notifications"),
+                day_summary: boolean("day-summary"),
+                stale_band_days: uint("stale-band-days")
+            })
+        })
+    }
+
+    fn save<'a>(&'a self, app: &'a AppConf) -> LocalBoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let settings = self.settings();
+            // only write what's been set, so everything else follows the schema's defaults
+            if let Some(passive_discovery) = app.passive_discovery {
+                set_boolean(&settings, "passive-discovery", passive_discovery)?;
+            }
+            if let Some(io_policy) = app.io_policy {
+                set_uint(&settings, "io-timeout-secs", io_policy.timeout_secs.try_into().unwrap_or(u32::MAX))?;
+                set_uint(&settings, "io-attempts", io_policy.attempts)?;
+            }
+            if let Some(toggles) = app.quick_toggles {
+                set_boolean(&settings, "forwarding", toggles.forwarding)?;
+                set_boolean(&settings, "do-not-disturb", toggles.do_not_disturb)?;
+                set_boolean(&settings, "battery-saver", toggles.battery_saver)?;
+            }
+            if let Some(soak_diagnostics) = app.soak_diagnostics {
+                set_boolean(&settings, "soak-diagnostics", soak_diagnostics)?;
+            }
+            if let Some(disconnect_on_exit) = app.disconnect_on_exit {
+                set_boolean(&settings, "disconnect-on-exit", disconnect_on_exit)?;
+            }
+            if let Some(power_throttle) = app.power_throttle {
+                set_boolean(&settings, "power-throttle", power_throttle.enabled)?;
+                set_uint(&settings, "power-throttle-threshold", power_throttle.threshold as u32)?;
+            }
+            if let Some(simplify_alerts) = app.simplify_alerts {
+                set_boolean(&settings, "simplify-alerts", simplify_alerts)?;
+            }
+            if let Some(music_idle_timeout_secs) = app.music_idle_timeout_secs {
+                set_uint(&settings, "music-idle-timeout", music_idle_timeout_secs)?;
+            }
+            if let Some(compact_style) = app.compact_style {
+                set_boolean(&settings, "compact-style", compact_style)?;
+            }
+            if let Some(goal_notifications) = app.goal_notifications {
+                set_boolean(&settings, "goal-notifications", goal_notifications)?;
+            }
+            if let Some(day_summary) = app.day_summary {
+                set_boolean(&settings, "day-summary", day_summary)?;
+            }
+            if let Some(stale_band_days) = app.stale_band_days {
+                set_uint(&settings, "stale-band-days", stale_band_days)?;
+            }
+            self.file.save(&AppConf { app_lock: app.app_lock.clone(), ..Default::default() }).await
+        })
+    }
+
+    fn monitor(&self) -> Option<gio::Settings> {
+        Some(self.settings())
+    }
+}
+
+/// write `key` only if it's changing - a key that was reset keeps following the schema's default
+fn set_boolean(settings: &gio::Settings, key: &str, value: bool) -> std::result::Result<(), glib::BoolError> {
+    if settings.boolean(key) == value { return Ok(()) }
+    settings.set_boolean(key, value)
+}
+
+/// like `set_boolean`, but values outside the schema's range are skipped (app.json didn't limit them)
+fn set_uint(settings: &gio::Settings, key: &str, value: u32) -> std::result::Result<(), glib::BoolError> {
+    if settings.uint(key) == value { return Ok(()) }
+    let in_range = settings.settings_schema().map_or(true, |schema| schema.key(key).range_check(&value.to_variant()));
+    if !in_range {
+        warn!("Not saving {value} for {key}: it's outside the allowed range");
+        return Ok(());
+    }
+    settings.set_uint(key, value)
+}
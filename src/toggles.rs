@@ -0,0 +1,82 @@
+use zbus::{connection, interface, Connection};
+
+use crate::store::QuickToggles;
+
+/// a different name from the GApplication's, which gio owns
+const BUS_NAME: &'static str = "me.grimsteel.miband4-gtk.Toggles";
+const TOGGLES_PATH: &'static str = "/me/grimsteel/miband4gtk/Toggles";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Toggle {
+    Forwarding,
+    DoNotDisturb,
+    BatterySaver
+}
+
+/// the quick settings a desktop extension can flip
+/// it only keeps track of the state - the window does the actual work
+struct Toggles {
+    state: QuickToggles,
+    tx: async_channel::Sender<(Toggle, bool)>
+}
+
+impl Toggles {
+    async fn request(&self, toggle: Toggle, enabled: bool) -> zbus::fdo::Result<()> {
+        self.tx.send((toggle, enabled)).await
+            .map_err(|_| zbus::fdo::Error::Failed("The app isn't listening".into()))
+    }
+}
+
+#[interface(name = "me.grimsteel.miband4gtk.Toggles")]
+impl Toggles {
+    /// forward desktop notifications to the band
+    #[zbus(property)]
+    fn forwarding(&self) -> bool {
+        self.state.forwarding
+    }
+
+    #[zbus(property)]
+    async fn set_forwarding(&mut self, enabled: bool) -> zbus::fdo::Result<()> {
+        self.request(Toggle::Forwarding, enabled).await?;
+        self.state.forwarding = enabled;
+        Ok(())
+    }
+
+    /// keep the band's do not disturb mode on
+    #[zbus(property)]
+    fn do_not_disturb(&self) -> bool {
+        self.state.do_not_disturb
+    }
+
+    #[zbus(property)]
+    async fn set_do_not_disturb(&mut self, enabled: bool) -> zbus::fdo::Result<()> {
+        self.request(Toggle::DoNotDisturb, enabled).await?;
+        self.state.do_not_disturb = enabled;
+        Ok(())
+    }
+
+    /// stop continuous heart rate and disconnect from the band sooner
+    #[zbus(property)]
+    fn battery_saver(&self) -> bool {
+        self.state.battery_saver
+    }
+
+    #[zbus(property)]
+    async fn set_battery_saver(&mut self, enabled: bool) -> zbus::fdo::Result<()> {
+        self.request(Toggle::BatterySaver, enabled).await?;
+        self.state.battery_saver = enabled;
+        Ok(())
+    }
+}
+
+/// serve the toggles on the session bus, starting out with `state`
+/// the receiver gets every change - keep the connection around for as long as it should be served
+pub async fn serve_toggles(state: QuickToggles) -> zbus::Result<(Connection, async_channel::Receiver<(Toggle, bool)>)> {
+    let (tx, rx) = async_channel::unbounded();
+    let connection = connection::Builder::session()?
+        .name(BUS_NAME)?
+        .serve_at(TOGGLES_PATH, Toggles { state, tx })?
+        .build()
+        .await?;
+    Ok((connection, rx))
+}
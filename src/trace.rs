@@ -0,0 +1,126 @@
+use std::{collections::VecDeque, sync::{atomic::{AtomicBool, Ordering}, Mutex}};
+
+use chrono::{DateTime, Local};
+
+use crate::utils::decode_hex;
+
+/// the oldest entries are dropped after this many
+const MAX_ENTRIES: usize = 10000;
+/// how many of the latest entries a capture keeps
+pub const CAPTURE_ENTRIES: usize = 500;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static ENTRIES: Mutex<VecDeque<TraceEntry>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TraceKind {
+    Read,
+    Write,
+    Notification
+}
+
+impl TraceKind {
+    fn id(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Notification => "notify"
+        }
+    }
+
+    fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "read" => Some(Self::Read),
+            "write" => Some(Self::Write),
+            "notify" => Some(Self::Notification),
+            _ => None
+        }
+    }
+}
+
+/// a single characteristic operation
+#[derive(Debug, Clone)]
+pub struct TraceEntry {
+    pub time: DateTime<Local>,
+    pub kind: TraceKind,
+    pub uuid: String,
+    pub value: Vec<u8>
+}
+
+#[derive(Debug)]
+pub struct TraceState {
+    pub enabled: bool,
+    pub entries: usize
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn state() -> TraceState {
+    TraceState {
+        enabled: is_enabled(),
+        entries: ENTRIES.lock().expect("can lock trace").len()
+    }
+}
+
+/// record an operation on the characteristic with this `uuid`
+/// does nothing unless tracing is enabled
+pub fn record(kind: TraceKind, uuid: &str, value: &[u8]) {
+    if !is_enabled() { return }
+
+    let mut entries = ENTRIES.lock().expect("can lock trace");
+    if entries.len() >= MAX_ENTRIES {
+        entries.pop_front();
+    }
+    entries.push_back(TraceEntry { time: Local::now(), kind, uuid: uuid.into(), value: value.to_vec() });
+}
+
+pub fn clear() {
+    ENTRIES.lock().expect("can lock trace").clear();
+}
+
+/// 0x01 0x02 0x03
+fn hex_dump(value: &[u8]) -> String {
+    value.iter().map(|b| format!("0x{b:02x}")).collect::<Vec<_>>().join(" ")
+}
+
+/// format the trace the way Gadgetbridge logs GATT traffic, so the two can be compared directly
+pub fn export_gadgetbridge() -> String {
+    let entries = ENTRIES.lock().expect("can lock trace");
+    entries.iter().map(|entry| {
+        let message = match entry.kind {
+            TraceKind::Read => format!("Characteristic read: {} value: {}", entry.uuid, hex_dump(&entry.value)),
+            TraceKind::Write => format!("writing to characteristic: {}: {}", entry.uuid, hex_dump(&entry.value)),
+            TraceKind::Notification => format!("Characteristic changed: {} value: {}", entry.uuid, hex_dump(&entry.value))
+        };
+        format!("{} [bluez] DEBUG me.grimsteel.miband4_gtk.band - {}\n", entry.time.format("%H:%M:%S%.3f"), message)
+    }).collect()
+}
+
+/// save the latest `CAPTURE_ENTRIES` payloads so they can be replayed later
+/// one entry per line: time, kind, characteristic, hex value (tab separated)
+pub fn export_capture() -> String {
+    let entries = ENTRIES.lock().expect("can lock trace");
+    entries.iter().skip(entries.len().saturating_sub(CAPTURE_ENTRIES)).map(|entry| {
+        let value: String = entry.value.iter().map(|b| format!("{b:02x}")).collect();
+        format!("{}\t{}\t{}\t{value}\n", entry.time.to_rfc3339(), entry.kind.id(), entry.uuid)
+    }).collect()
+}
+
+/// read a capture saved with `export_capture`
+/// lines that can't be parsed (comments, etc) are skipped
+pub fn parse_capture(capture: &str) -> Vec<TraceEntry> {
+    capture.lines().filter_map(|line| {
+        let mut fields = line.trim().split('\t');
+        let time = DateTime::parse_from_rfc3339(fields.next()?).ok()?.with_timezone(&Local);
+        let kind = TraceKind::from_id(fields.next()?)?;
+        let uuid = fields.next()?.to_string();
+        let value = decode_hex(fields.next().unwrap_or_default())?;
+        Some(TraceEntry { time, kind, uuid, value })
+    }).collect()
+}
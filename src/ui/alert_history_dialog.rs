@@ -0,0 +1,139 @@
+use chrono::{DateTime, Local};
+use gtk::{glib::{self, clone, Object}, pango::WrapMode, prelude::*, subclass::prelude::*, Accessible, Align, Buildable, Button, ConstraintTarget, Grid, Label, ListBoxRow, Native, Root, ShortcutManager, Widget, Window};
+
+use crate::{band::{Alert, AlertType}, i18n::gettext, store::TimeFormat, utils::format_date};
+
+/// an alert the band was sent
+#[derive(Debug, Clone)]
+pub struct SentAlert {
+    /// unique while the app is open
+    pub id: u64,
+    pub time: DateTime<Local>,
+    pub alert_type: AlertType,
+    pub title: String,
+    pub message: String
+}
+
+impl SentAlert {
+    pub fn as_alert(&self) -> Alert<'_> {
+        Alert { alert_type: self.alert_type, title: &self.title, message: &self.message }
+    }
+}
+
+glib::wrapper! {
+    pub struct AlertHistoryDialog(ObjectSubclass<imp::AlertHistoryDialog>)
+        // https://docs.gtk.org/gtk4/class.Window.html#hierarchy
+        @extends Window, Widget,
+        @implements Accessible, Buildable, ConstraintTarget, Native, Root, ShortcutManager;
+}
+
+impl AlertHistoryDialog {
+    pub fn new() -> Self {
+        Object::builder().build()
+    }
+
+    /// replace the list with these alerts (oldest first, they're shown newest first)
+    pub fn set_alerts<'a>(&self, alerts: impl DoubleEndedIterator<Item = &'a SentAlert>, time_format: TimeFormat) {
+        let imp = self.imp();
+        let list = &imp.list_alerts;
+        while let Some(child) = list.first_child() {
+            list.remove(&child);
+        }
+        let mut empty = true;
+        for alert in alerts.rev() {
+            list.append(&self.build_row(alert, time_format));
+            empty = false;
+        }
+        imp.label_empty.set_visible(empty);
+    }
+
+    fn build_row(&self, alert: &SentAlert, time_format: TimeFormat) -> ListBoxRow {
+        let grid = Grid::new();
+        grid.set_row_spacing(4);
+        grid.set_column_spacing(12);
+
+        let title = Label::new(Some(&alert.title));
+        title.set_halign(Align::Start);
+        title.set_hexpand(true);
+        title.add_css_class("heading");
+        grid.attach(&title, 0, 0, 1, 1);
+
+        let time = Label::new(Some(&format_date(&alert.time, time_format)));
+        time.set_halign(Align::End);
+        time.add_css_class("dim-label");
+        grid.attach(&time, 1, 0, 1, 1);
+
+        let message = Label::new(Some(&alert.message));
+        message.set_halign(Align::Start);
+        message.set_xalign(0.0);
+        message.set_wrap(true);
+        message.set_wrap_mode(WrapMode::WordChar);
+        grid.attach(&message, 0, 1, 1, 1);
+
+        let resend = Button::with_label(&gettext("Resend"));
+        resend.set_valign(Align::Center);
+        let id = alert.id;
+        resend.connect_clicked(clone!(@weak self as dialog => move |_| {
+            dialog.emit_by_name::<()>("resend", &[&id]);
+        }));
+        grid.attach(&resend, 1, 1, 1, 1);
+
+        let row = ListBoxRow::new();
+        row.set_activatable(false);
+        row.set_child(Some(&grid));
+        row
+    }
+}
+
+mod imp {
+    use std::sync::OnceLock;
+
+    use gtk::{glib::{self, subclass::{InitializingObject, Signal}}, prelude::*, subclass::prelude::*, template_callbacks, CompositeTemplate, Label, ListBox, TemplateChild, Window};
+
+    #[derive(CompositeTemplate, Default)]
+    #[template(resource = "/me/grimsteel/miband4-gtk/alert_history_dialog.ui")]
+    pub struct AlertHistoryDialog {
+        #[template_child]
+        pub list_alerts: TemplateChild<ListBox>,
+        #[template_child]
+        pub label_empty: TemplateChild<Label>
+    }
+
+    #[template_callbacks]
+    impl AlertHistoryDialog {
+        #[template_callback]
+        fn handle_close_clicked(&self) {
+            self.obj().close();
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for AlertHistoryDialog {
+        const NAME: &'static str = "MiBand4AlertHistoryDialog";
+        type Type = super::AlertHistoryDialog;
+        type ParentType = Window;
+
+        fn class_init(class: &mut Self::Class) {
+            class.bind_template();
+            class.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for AlertHistoryDialog {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![
+                    // param is the id of the alert to send again
+                    Signal::builder("resend").param_types([u64::static_type()]).build()
+                ]
+            })
+        }
+    }
+    impl WidgetImpl for AlertHistoryDialog {}
+    impl WindowImpl for AlertHistoryDialog {}
+}
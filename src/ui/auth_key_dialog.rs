@@ -1,4 +1,6 @@
-use gtk::{glib::{self, Object}, Accessible, Buildable, ConstraintTarget, Native, Root, ShortcutManager, Widget, Window};
+use gtk::{glib::{self, Object}, subclass::prelude::*, Accessible, Buildable, ConstraintTarget, Native, Root, ShortcutManager, Widget, Window};
+
+use crate::auth_providers::AuthKeys;
 
 glib::wrapper! {
     pub struct AuthKeyDialog(ObjectSubclass<imp::AuthKeyDialog>)
@@ -11,23 +13,56 @@ impl AuthKeyDialog {
     pub fn new() -> Self {
         Object::builder().build()
     }
+
+    /// the keys the provider found, after `auth-keys-found`
+    pub fn take_keys(&self) -> AuthKeys {
+        self.imp().found_keys.take()
+    }
 }
 
 mod imp {
-    use std::{cell::RefCell, sync::OnceLock};
+    use std::{cell::RefCell, rc::Rc, sync::OnceLock};
 
-    use gtk::{glib::{self, subclass::{InitializingObject, Signal}, Properties}, prelude::*, subclass::prelude::*, template_callbacks, Button, CompositeTemplate, Entry, TemplateChild, Window};
+    use gtk::{glib::{self, clone, spawn_future_local, subclass::{InitializingObject, Signal}, Properties}, prelude::*, subclass::prelude::*, template_callbacks, Button, CompositeTemplate, DropDown, Entry, FileDialog, Label, PasswordEntry, Spinner, Stack, StringList, TemplateChild, Window};
 
-    use crate::utils::is_hex_string;
+    use crate::{auth_providers::{auth_providers, AuthInput, AuthInputKind, AuthKeys, AuthProvider, AuthProviderError}, huami::Credentials, i18n::{gettext, i18n_f}};
 
     #[derive(CompositeTemplate, Default, Properties)]
     #[template(resource = "/me/grimsteel/miband4-gtk/auth_key_dialog.ui")]
     #[properties(wrapper_type = super::AuthKeyDialog)]
     pub struct AuthKeyDialog {
+        #[template_child]
+        dropdown_provider: TemplateChild<DropDown>,
+        #[template_child]
+        label_provider_description: TemplateChild<Label>,
+        #[template_child]
+        stack_input: TemplateChild<Stack>,
         #[template_child]
         entry_auth_key: TemplateChild<Entry>,
+        #[template_child]
+        entry_huami_email: TemplateChild<Entry>,
+        #[template_child]
+        entry_huami_password: TemplateChild<PasswordEntry>,
+        #[template_child]
+        entry_huami_token: TemplateChild<Entry>,
+        #[template_child]
+        label_file: TemplateChild<Label>,
+        #[template_child]
+        label_status: TemplateChild<Label>,
+        #[template_child]
+        spinner_fetch: TemplateChild<Spinner>,
+        #[template_child]
+        btn_save: TemplateChild<Button>,
+        #[property(get, set)]
+        pub auth_key: RefCell<String>,
+        /// mac address of the band the key is for
         #[property(get, set)]
-        pub auth_key: RefCell<String>
+        pub address: RefCell<String>,
+        /// in the same order as the dropdown
+        providers: RefCell<Vec<Rc<dyn AuthProvider>>>,
+        /// (name, contents) of the file that was chosen
+        chosen_file: RefCell<Option<(String, String)>>,
+        pub found_keys: RefCell<AuthKeys>
     }
 
     #[template_callbacks]
@@ -39,19 +74,99 @@ mod imp {
         #[template_callback]
         fn handle_auth_key_save(&self, _button: &Button) {
             self.entry_auth_key.remove_css_class("error");
-            
-            // validate the value of the input
-            let value = self.get_entered_key();
-            if value.len() == 32 && is_hex_string(&value) {
-                self.obj().emit_by_name::<()>("new-auth-key", &[&value]);
-                self.obj().close();
-            } else {
-                // reflect that in the state of the entry
-                self.entry_auth_key.add_css_class("error");
+            let Some(provider) = self.selected_provider() else { return };
+            let Some(input) = self.get_input(provider.input_kind()) else { return };
+
+            self.set_fetching(true);
+            self.label_status.set_text("");
+            let address = self.address.borrow().clone();
+            spawn_future_local(clone!(@weak self as dialog => async move {
+                let result = provider.fetch(address, input).await;
+                dialog.set_fetching(false);
+                match result {
+                    Ok(keys) => {
+                        dialog.entry_huami_password.set_text("");
+                        dialog.entry_huami_token.set_text("");
+                        dialog.found_keys.replace(keys);
+                        // the window saves them, and closes the dialog if there's one for this band
+                        dialog.obj().emit_by_name::<()>("auth-keys-found", &[]);
+                    },
+                    Err(err) => {
+                        if matches!(err, AuthProviderError::Invalid(_)) && provider.input_kind() == AuthInputKind::Key {
+                            dialog.entry_auth_key.add_css_class("error");
+                        }
+                        dialog.label_status.set_text(&err.to_string());
+                    }
+                }
+            }));
+        }
+        #[template_callback]
+        fn handle_choose_file(&self, _button: &Button) {
+            let file_dialog = FileDialog::builder()
+                .title(gettext("Choose a File with Auth Keys"))
+                .modal(true)
+                .build();
+            spawn_future_local(clone!(@weak self as dialog => async move {
+                // an error here means they cancelled
+                let Some(path) = file_dialog.open_future(Some(&*dialog.obj())).await.ok().and_then(|f| f.path()) else { return };
+                let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                match async_fs::read_to_string(&path).await {
+                    Ok(contents) => {
+                        dialog.label_file.set_text(&name);
+                        dialog.chosen_file.replace(Some((name, contents)));
+                        dialog.label_status.set_text("");
+                    },
+                    Err(err) => dialog.label_status.set_text(&i18n_f("Couldn't read the file: {}", &[&err.to_string()]))
+                }
+            }));
+        }
+        fn selected_provider(&self) -> Option<Rc<dyn AuthProvider>> {
+            self.providers.borrow().get(self.dropdown_provider.selected() as usize).cloned()
+        }
+        /// show the description and the inputs for the chosen provider
+        fn show_provider(&self) {
+            let Some(provider) = self.selected_provider() else { return };
+            self.label_provider_description.set_text(&gettext(provider.description()));
+            self.stack_input.set_visible_child_name(match provider.input_kind() {
+                AuthInputKind::Key => "key",
+                AuthInputKind::Account => "account",
+                AuthInputKind::File => "file",
+                AuthInputKind::Nothing => "nothing"
+            });
+            self.label_status.set_text("");
+        }
+        /// what was filled in for the provider - `None` (with a message) if something's missing
+        fn get_input(&self, kind: AuthInputKind) -> Option<AuthInput> {
+            match kind {
+                AuthInputKind::Key => Some(AuthInput::Key(self.entry_auth_key.buffer().text().as_str().to_string())),
+                AuthInputKind::Account => {
+                    let token = self.entry_huami_token.text().trim().to_string();
+                    if !token.is_empty() {
+                        return Some(AuthInput::Account(Credentials::AccessToken(token)));
+                    }
+                    let email = self.entry_huami_email.text().trim().to_string();
+                    let password = self.entry_huami_password.text().to_string();
+                    if email.is_empty() || password.is_empty() {
+                        self.label_status.set_text(&gettext("Enter your email and password, or an access token"));
+                        return None;
+                    }
+                    Some(AuthInput::Account(Credentials::Password { email, password }))
+                },
+                AuthInputKind::File => match self.chosen_file.borrow().clone() {
+                    Some((name, contents)) => Some(AuthInput::File { name, contents }),
+                    None => {
+                        self.label_status.set_text(&gettext("Choose a file first"));
+                        None
+                    }
+                },
+                AuthInputKind::Nothing => Some(AuthInput::Nothing)
             }
         }
-        fn get_entered_key(&self) -> String {
-            self.entry_auth_key.buffer().text().as_str().to_string()
+        fn set_fetching(&self, fetching: bool) {
+            self.btn_save.set_sensitive(!fetching);
+            self.dropdown_provider.set_sensitive(!fetching);
+            self.spinner_fetch.set_visible(fetching);
+            self.spinner_fetch.set_spinning(fetching);
         }
     }
 
@@ -76,13 +191,27 @@ mod imp {
         fn constructed(&self) {
             self.parent_constructed();
 
+            let providers = auth_providers();
+            let names: Vec<String> = providers.iter().map(|provider| gettext(provider.name())).collect();
+            self.dropdown_provider.set_model(Some(&StringList::new(&names.iter().map(String::as_str).collect::<Vec<_>>())));
+            self.providers.replace(providers);
+            self.show_provider();
+            self.dropdown_provider.connect_selected_notify(clone!(@weak self as dialog => move |_| {
+                dialog.show_provider();
+            }));
+
             // update the entry's contents when auth_key is changed
             self.obj().connect_auth_key_notify(|win| {
                 win.imp().entry_auth_key.buffer().set_text(win.auth_key());
             });
 
             self.obj().connect_show(|win| {
-                win.imp().entry_auth_key.remove_css_class("error");
+                let imp = win.imp();
+                imp.entry_auth_key.remove_css_class("error");
+                imp.label_status.set_text("");
+                // the file was for the last band
+                imp.chosen_file.replace(None);
+                imp.label_file.set_text(&gettext("No file chosen"));
             });
         }
 
@@ -90,7 +219,8 @@ mod imp {
             static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
             SIGNALS.get_or_init(|| {
                 vec![
-                    Signal::builder("new-auth-key").param_types([String::static_type()]).build()
+                    // the keys are read with `take_keys`
+                    Signal::builder("auth-keys-found").build()
                 ]
             })
         }
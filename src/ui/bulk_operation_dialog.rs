@@ -0,0 +1,110 @@
+use gtk::{glib::{self, Object}, prelude::*, subclass::prelude::*, Accessible, Buildable, ConstraintTarget, Label, Native, Root, ShortcutManager, Widget, Window};
+
+use crate::i18n::i18n_f;
+
+glib::wrapper! {
+    pub struct BulkOperationDialog(ObjectSubclass<imp::BulkOperationDialog>)
+        // https://docs.gtk.org/gtk4/class.Window.html#hierarchy
+        @extends Window, Widget,
+        @implements Accessible, Buildable, ConstraintTarget, Native, Root, ShortcutManager;
+}
+
+impl BulkOperationDialog {
+    pub fn new() -> Self {
+        Object::builder().build()
+    }
+
+    /// list the bands (address, alias) the operation is about to run on
+    pub fn start(&self, title: &str, bands: &[(String, String)]) {
+        let imp = self.imp();
+        imp.label_title.set_text(title);
+        imp.spinner.set_spinning(true);
+        imp.spinner.set_visible(true);
+        imp.btn_close.set_sensitive(false);
+        imp.label_status.set_text(&i18n_f("Running on {} bands", &[&bands.len().to_string()]));
+
+        // clear the last run
+        while let Some(child) = imp.grid_bands.first_child() {
+            imp.grid_bands.remove(&child);
+        }
+        let mut statuses = imp.statuses.borrow_mut();
+        statuses.clear();
+        for (row, (address, alias)) in bands.iter().enumerate() {
+            let name = Label::builder().label(alias).xalign(0.0).build();
+            let status = Label::builder().label("Waiting").xalign(1.0).hexpand(true).wrap(true).build();
+            status.add_css_class("dim-label");
+            imp.grid_bands.attach(&name, 0, row as i32, 1, 1);
+            imp.grid_bands.attach(&status, 1, row as i32, 1, 1);
+            statuses.insert(address.clone(), status);
+        }
+    }
+
+    pub fn set_band_status(&self, address: &str, status: &str) {
+        if let Some(label) = self.imp().statuses.borrow().get(address) {
+            label.set_text(status);
+        }
+    }
+
+    pub fn set_finished(&self, failed: usize) {
+        let imp = self.imp();
+        imp.spinner.set_spinning(false);
+        imp.spinner.set_visible(false);
+        imp.btn_close.set_sensitive(true);
+        let total = imp.statuses.borrow().len();
+        imp.label_status.set_text(&match failed {
+            0 => i18n_f("Done - all {} bands succeeded", &[&total.to_string()]),
+            failed => i18n_f("Done - {} of {} bands failed", &[&failed.to_string(), &total.to_string()])
+        });
+    }
+}
+
+mod imp {
+    use std::{cell::RefCell, collections::HashMap};
+
+    use gtk::{glib::{self, subclass::InitializingObject}, prelude::*, subclass::prelude::*, template_callbacks, Button, CompositeTemplate, Grid, Label, Spinner, TemplateChild, Window};
+
+    #[derive(CompositeTemplate, Default)]
+    #[template(resource = "/me/grimsteel/miband4-gtk/bulk_operation_dialog.ui")]
+    pub struct BulkOperationDialog {
+        #[template_child]
+        pub label_title: TemplateChild<Label>,
+        #[template_child]
+        pub spinner: TemplateChild<Spinner>,
+        #[template_child]
+        pub label_status: TemplateChild<Label>,
+        #[template_child]
+        pub grid_bands: TemplateChild<Grid>,
+        #[template_child]
+        pub btn_close: TemplateChild<Button>,
+        /// band address -> its status label
+        pub statuses: RefCell<HashMap<String, Label>>
+    }
+
+    #[template_callbacks]
+    impl BulkOperationDialog {
+        #[template_callback]
+        fn handle_close_clicked(&self) {
+            self.obj().close();
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for BulkOperationDialog {
+        const NAME: &'static str = "MiBand4BulkOperationDialog";
+        type Type = super::BulkOperationDialog;
+        type ParentType = Window;
+
+        fn class_init(class: &mut Self::Class) {
+            class.bind_template();
+            class.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for BulkOperationDialog {}
+    impl WidgetImpl for BulkOperationDialog {}
+    impl WindowImpl for BulkOperationDialog {}
+}
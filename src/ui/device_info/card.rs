@@ -1,10 +1,16 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use gtk::{glib::{self, clone, Object}, pango::EllipsizeMode, prelude::*, subclass::prelude::*, Accessible, Align, Box as GtkBox, Buildable, Button, ConstraintTarget, Entry, Label, Orientable, Orientation, Separator, Switch, Widget};
+use chrono::{NaiveTime, Timelike};
+
+use gtk::{accessible::{Relation, State}, glib::{self, clone, Object}, pango::WrapMode, prelude::*, subclass::prelude::*, Accessible, AccessibleInvalidState, Adjustment, Align, Box as GtkBox, Buildable, Button, ConstraintTarget, DropDown, Entry, Label, Orientable, Orientation, Separator, SpinButton, StateFlags, Switch, Widget};
+#[cfg(feature = "announcements")]
+use gtk::AccessibleAnnouncementPriority;
 
 use log::warn;
 
-use super::card_implementations::IntoInfoItemValues;
+use crate::i18n::{gettext, i18n_f};
+
+use super::{card_implementations::IntoInfoItemValues, pin_keypad::PinKeypad};
 
 glib::wrapper! {
     pub struct DeviceInfoCard(ObjectSubclass<imp::DeviceInfoCard>)
@@ -32,146 +38,499 @@ impl DeviceInfoCard {
             }
 
             let id: String = (*id).into();
-            
+            // the labels are only marked for translation in the item specs
+            let label = gettext(*label);
+            // for custom CSS: #item-<id> is the item's widget, and its label (if it has one) is .item-label
+            let widget_name = format!("item-{}", id.replace('_', "-"));
+
             match item_type {
                 InfoItemType::Field => {
                     // a label for this field
-                    let field_label = Label::new(Some(label));
+                    let field_label = Label::new(Some(&label));
                     field_label.set_halign(Align::Start);
                     field_label.add_css_class("dim-label");
+                    field_label.add_css_class("item-label");
                     self.append(&field_label);
 
                     // the actual field value
                     let value_label = Label::new(None);
                     value_label.set_halign(Align::Start);
-                    value_label.set_ellipsize(EllipsizeMode::End);
+                    value_label.set_xalign(0.0);
+                    // wrap rather than cut off the value with large text
+                    value_label.set_wrap(true);
+                    value_label.set_wrap_mode(WrapMode::WordChar);
                     value_label.add_css_class("title-4");
+                    value_label.add_css_class("item-field");
+                    value_label.set_widget_name(&widget_name);
                     for class in classes.iter() { value_label.add_css_class(class); }
+                    // so it can be reached with the keyboard (and copied)
+                    value_label.set_selectable(true);
+                    value_label.update_relation(&[Relation::LabelledBy(&[field_label.upcast_ref()])]);
                     
                     self.append(&value_label);
-                    widget_map.push((id, InfoItemWidget::Field(value_label)));
+                    widget_map.push((id, InfoItemWidget::Field(value_label, label)));
                 },
                 InfoItemType::Button => {
                     let button = Button::new();
-                    button.set_label(label);
+                    button.set_label(&label);
+                    button.add_css_class("item-button");
+                    button.set_widget_name(&widget_name);
                     for class in classes.iter() { button.add_css_class(class); }
                     button.set_halign(Align::Start);
 
                     // connect the event listener
                     button.connect_clicked(clone!(@weak self as win, @strong id => move |_button| {
+                        // the next `apply_values` is the saved values
+                        if is_save_button(&id) { win.imp().saving.set(true); }
                         win.emit_by_name::<()>("button-clicked", &[&id]);
                     }));
-                    
-                    self.append(&button);
+
+                    if is_save_button(&id) {
+                        // with a button to undo the changes instead
+                        let revert = Button::with_label(&gettext("Revert"));
+                        revert.add_css_class("item-button");
+                        revert.add_css_class("item-revert");
+                        revert.set_sensitive(false);
+                        revert.connect_clicked(clone!(@weak self as card => move |_button| card.revert()));
+
+                        let buttons = GtkBox::new(Orientation::Horizontal, 6);
+                        buttons.append(&button);
+                        buttons.append(&revert);
+                        self.append(&buttons);
+                        self.imp().revert_buttons.borrow_mut().push(revert);
+                    } else {
+                        self.append(&button);
+                    }
                     widget_map.push((id, InfoItemWidget::Button(button)));
                 },
                 InfoItemType::Indicator => {
-                    let indicator = Label::new(Some(label));
+                    let indicator = Label::new(None);
                     indicator.set_halign(Align::Start);
                     indicator.add_css_class("title-4");
-                    for class in classes.iter() { indicator.add_css_class(class); }
-                    
+                    indicator.add_css_class("item-indicator");
+                    indicator.set_widget_name(&widget_name);
+                    indicator.set_selectable(true);
+
                     self.append(&indicator);
-                    widget_map.push((id, InfoItemWidget::Indicator(indicator)));
+                    // the classes (usually a color) are only added while it's on
+                    widget_map.push((id, InfoItemWidget::Indicator(indicator, label, classes.iter().map(|c| c.to_string()).collect())));
                 },
                 InfoItemType::Switch => {
                     // a label for this switch
-                    let field_label = Label::new(Some(label));
+                    let field_label = Label::new(Some(&label));
                     field_label.set_halign(Align::Start);
                     field_label.add_css_class("dim-label");
+                    field_label.add_css_class("item-label");
                     self.append(&field_label);
 
                     // the toggle siwtch
                     let switch = Switch::new();
                     switch.set_halign(Align::Start);
+                    switch.add_css_class("item-switch");
+                    switch.set_widget_name(&widget_name);
+                    switch.update_relation(&[Relation::LabelledBy(&[field_label.upcast_ref()])]);
+                    switch.connect_active_notify(clone!(@weak self as card, @strong id => move |_switch| card.mark_dirty(&id)));
 
                     self.append(&switch);
                     widget_map.push((id, InfoItemWidget::Switch(switch)));
                 },
                 InfoItemType::Entry => {
                     // a label for this entry
-                    let field_label = Label::new(Some(label));
+                    let field_label = Label::new(Some(&label));
                     field_label.set_halign(Align::Start);
                     field_label.add_css_class("dim-label");
+                    field_label.add_css_class("item-label");
                     self.append(&field_label);
 
                     // the text entry
                     let entry = Entry::new();
+                    entry.add_css_class("item-entry");
+                    entry.set_widget_name(&widget_name);
+                    entry.update_relation(&[Relation::LabelledBy(&[field_label.upcast_ref()])]);
 
                     self.append(&entry);
+                    self.append_error_label(&id, entry.upcast_ref());
+                    entry.connect_changed(clone!(@weak self as card, @strong id => move |_entry| {
+                        card.clear_error(&id);
+                        card.mark_dirty(&id);
+                    }));
                     widget_map.push((id, InfoItemWidget::Entry(entry)));
+                },
+                InfoItemType::Number { min, max, step } => {
+                    // a label for this number
+                    let field_label = Label::new(Some(&label));
+                    field_label.set_halign(Align::Start);
+                    field_label.add_css_class("dim-label");
+                    field_label.add_css_class("item-label");
+                    self.append(&field_label);
+
+                    let adjustment = Adjustment::new(*min as f64, *min as f64, *max as f64, *step as f64, (*step * 10) as f64, 0.0);
+                    let spin = SpinButton::new(Some(&adjustment), 1.0, 0);
+                    // only digits can be typed
+                    spin.set_numeric(true);
+                    spin.set_halign(Align::Start);
+                    spin.add_css_class("item-number");
+                    spin.set_widget_name(&widget_name);
+                    spin.update_relation(&[Relation::LabelledBy(&[field_label.upcast_ref()])]);
+
+                    self.append(&spin);
+                    self.append_error_label(&id, spin.upcast_ref());
+                    spin.connect_changed(clone!(@weak self as card, @strong id => move |_spin| {
+                        card.clear_error(&id);
+                        card.mark_dirty(&id);
+                    }));
+                    widget_map.push((id, InfoItemWidget::Number(spin)));
+                },
+                InfoItemType::Dropdown { options } => {
+                    // a label for this dropdown
+                    let field_label = Label::new(Some(&label));
+                    field_label.set_halign(Align::Start);
+                    field_label.add_css_class("dim-label");
+                    field_label.add_css_class("item-label");
+                    self.append(&field_label);
+
+                    // the options are only marked for translation too
+                    let options: Vec<String> = options.iter().map(|option| gettext(*option)).collect();
+                    let dropdown = DropDown::from_strings(&options.iter().map(String::as_str).collect::<Vec<_>>());
+                    dropdown.set_halign(Align::Start);
+                    dropdown.add_css_class("item-dropdown");
+                    dropdown.set_widget_name(&widget_name);
+                    dropdown.update_relation(&[Relation::LabelledBy(&[field_label.upcast_ref()])]);
+                    dropdown.connect_selected_notify(clone!(@weak self as card, @strong id => move |_dropdown| card.mark_dirty(&id)));
+
+                    self.append(&dropdown);
+                    widget_map.push((id, InfoItemWidget::Dropdown(dropdown)));
+                },
+                InfoItemType::TimeRange => {
+                    // a label for this time range
+                    let field_label = Label::new(Some(&label));
+                    field_label.set_halign(Align::Start);
+                    field_label.add_css_class("dim-label");
+                    field_label.add_css_class("item-label");
+                    self.append(&field_label);
+
+                    // HH:MM – HH:MM
+                    let range = GtkBox::new(Orientation::Horizontal, 6);
+                    range.set_halign(Align::Start);
+                    range.add_css_class("item-time-range");
+                    range.set_widget_name(&widget_name);
+                    range.update_relation(&[Relation::LabelledBy(&[field_label.upcast_ref()])]);
+                    let start = Self::time_spin_buttons(&range, &gettext("Start Hour"), &gettext("Start Minute"));
+                    range.append(&Label::new(Some("–")));
+                    let end = Self::time_spin_buttons(&range, &gettext("End Hour"), &gettext("End Minute"));
+                    for spin in [&start.0, &start.1, &end.0, &end.1] {
+                        spin.connect_value_changed(clone!(@weak self as card, @strong id => move |_spin| card.mark_dirty(&id)));
+                    }
+
+                    self.append(&range);
+                    widget_map.push((id, InfoItemWidget::TimeRange(start, end)));
+                },
+                InfoItemType::Pin => {
+                    // a label for this PIN
+                    let field_label = Label::new(Some(&label));
+                    field_label.set_halign(Align::Start);
+                    field_label.add_css_class("dim-label");
+                    field_label.add_css_class("item-label");
+                    self.append(&field_label);
+
+                    let keypad = PinKeypad::new();
+                    keypad.add_css_class("item-pin");
+                    keypad.set_widget_name(&widget_name);
+                    keypad.update_relation(&[Relation::LabelledBy(&[field_label.upcast_ref()])]);
+                    // the save buttons can't be used until the PIN is complete
+                    keypad.connect_changed(clone!(@weak self as card, @strong id => move |_keypad| {
+                        card.mark_dirty(&id);
+                    }));
+
+                    self.append(&keypad);
+                    widget_map.push((id, InfoItemWidget::Pin(keypad)));
                 }
             }
         }
         self.imp().items.set(widget_map).expect("cell was not already filled");
     }
     /// set all widgets to loading
+    /// the values are covered by skeletons until `apply_values` fills them in, and nothing can be used
     pub fn set_loading(&self) {
-        if let Some(items) = self.imp().items.get() {
-            for (_id, widget) in items {
-                match widget {
-                    InfoItemWidget::Field(label) => {
-                        // set the value of the field to "loading..."
-                        label.set_label("Loading...");
-                    },
-                    InfoItemWidget::Indicator(label) => {
-                        label.set_visible(false);
-                    },
-                    InfoItemWidget::Button(button) => {
-                        button.set_sensitive(false);
-                    },
-                    InfoItemWidget::Switch(switch) => {
-                        switch.set_sensitive(false);
-                    },
-                    InfoItemWidget::Entry(entry) => {
-                        entry.set_sensitive(false);
-                    }
-                }
+        let imp = self.imp();
+        imp.loading.set(true);
+        self.update_state(&[State::Busy(true)]);
+        self.set_sensitive(false);
+        // it gets another try
+        if imp.unavailable.replace(false) {
+            imp.notice.set_visible(false);
+        }
+        for (_id, widget) in imp.items.get().into_iter().flatten() {
+            widget.set_skeleton(true);
+            widget.set_sensitive(true);
+        }
+    }
+    /// the card can't be loaded (e.g. it needs authentication): say why instead of loading forever
+    /// values from `apply_values` are still shown, but nothing can be used until the next `set_loading`
+    pub fn set_unavailable(&self, reason: &str) {
+        self.show_notice(reason, false);
+    }
+    /// like `set_unavailable`, but the values already applied (e.g. cached ones) are left showing, with `note` saying how old they are
+    pub fn set_stale(&self, note: &str) {
+        self.show_notice(note, true);
+    }
+    fn show_notice(&self, reason: &str, keep_values: bool) {
+        let imp = self.imp();
+        imp.unavailable.set(true);
+        imp.notice.set_label(reason);
+        imp.notice.set_visible(true);
+        for (_id, widget) in imp.items.get().into_iter().flatten() {
+            widget.set_skeleton(false);
+            widget.set_sensitive(false);
+            match widget {
+                // no value rather than a stale one
+                InfoItemWidget::Field(label, _) | InfoItemWidget::Indicator(label, _, _) if !keep_values => label.set_label("–"),
+                _ => {}
             }
         }
+        self.set_sensitive(true);
+        if imp.loading.replace(false) {
+            self.update_state(&[State::Busy(false)]);
+        }
+        self.update_property(&[gtk::accessible::Property::Description(reason)]);
     }
     /// set the values of the widget to the values provided
+    /// inputs the user has changed (but not saved) are left alone, unless this is the result of saving them
     pub fn apply_values<T: IntoInfoItemValues>(&self, values: T) {
         let values = values.into_info_item_values();
-        if let Some(items) = self.imp().items.get() {
+        let imp = self.imp();
+        if imp.saving.replace(false) {
+            imp.dirty.borrow_mut().clear();
+        }
+        imp.applying.set(true);
+        if let Some(items) = imp.items.get() {
             for (id, widget) in items {
                 // get the corresponding value
                 if let Some(value) = values.get(id) {
-                    // apply it
-                    match (value, widget) {
-                        (InfoItemValue::Field(value), InfoItemWidget::Field(label)) => {
-                            label.set_label(value);
-                        },
-                        (InfoItemValue::Indicator(visible), InfoItemWidget::Indicator(label)) => {
-                            label.set_visible(*visible);
-                        },
-                        (InfoItemValue::Button(enabled), InfoItemWidget::Button(button)) => {
-                            button.set_sensitive(*enabled);
-                        },
-                        (InfoItemValue::Switch(checked), InfoItemWidget::Switch(switch)) => {
-                            switch.set_sensitive(true);
-                            switch.set_active(*checked);
-                        },
-                        (InfoItemValue::Entry(contents), InfoItemWidget::Entry(entry)) => {
-                            entry.set_sensitive(true);
-                            entry.buffer().set_text(contents);
-                        },
-                        _ => {
-                            // they provided the wrong value type for this widget
-                            warn!("value {value:?} has wrong type for widget {widget:?}");
-                        }
-                    }
+                    // remembered for reverting
+                    imp.applied.borrow_mut().insert(id.clone(), value.clone());
+                    widget.set_skeleton(false);
+                    // keep the user's edit
+                    if imp.dirty.borrow().contains(id) { continue }
+                    self.apply_value(value, widget);
+                }
+            }
+        }
+        imp.applying.set(false);
+        self.clear_errors();
+        if imp.loading.replace(false) {
+            self.set_sensitive(true);
+            self.update_state(&[State::Busy(false)]);
+            self.announce_values();
+        }
+        // everything stays disabled until the next `set_loading`
+        if imp.unavailable.get() { return }
+        self.update_button_sensitivity();
+    }
+    fn apply_value(&self, value: &InfoItemValue, widget: &InfoItemWidget) {
+        match (value, widget) {
+            (InfoItemValue::Field(value), InfoItemWidget::Field(label, _)) => {
+                label.set_label(value);
+            },
+            (InfoItemValue::Indicator(on), InfoItemWidget::Indicator(label, text, classes)) => {
+                // say it in words too, so it doesn't depend on color or something being missing
+                if *on {
+                    label.set_label(text);
+                    for class in classes { label.add_css_class(class); }
+                } else {
+                    label.set_label(&i18n_f("Not {}", &[text]));
+                    for class in classes { label.remove_css_class(class); }
                 }
+            },
+            (InfoItemValue::Button(_), InfoItemWidget::Button(_)) => {
+                // set by `update_button_sensitivity`, since it depends on the other inputs too
+            },
+            (InfoItemValue::Switch(checked), InfoItemWidget::Switch(switch)) => {
+                switch.set_active(*checked);
+            },
+            (InfoItemValue::Entry(contents), InfoItemWidget::Entry(entry)) => {
+                entry.buffer().set_text(contents);
+            },
+            (InfoItemValue::Pin(pin), InfoItemWidget::Pin(keypad)) => {
+                keypad.set_pin(pin);
+            },
+            (InfoItemValue::Number(number), InfoItemWidget::Number(spin)) => {
+                if let Some(number) = number {
+                    spin.set_value(*number as f64);
+                }
+            },
+            (InfoItemValue::Dropdown(selected), InfoItemWidget::Dropdown(dropdown)) => {
+                dropdown.set_selected(*selected);
+            },
+            (InfoItemValue::TimeRange(start_time, end_time), InfoItemWidget::TimeRange(start, end)) => {
+                for ((hour, minute), time) in [(start, start_time), (end, end_time)] {
+                    hour.set_value(time.hour() as f64);
+                    minute.set_value(time.minute() as f64);
+                }
+            },
+            _ => {
+                // they provided the wrong value type for this widget
+                warn!("value {value:?} has wrong type for widget {widget:?}");
+            }
+        }
+    }
+    /// the user changed an input
+    fn mark_dirty(&self, id: &str) {
+        let imp = self.imp();
+        // not when it's being set by `apply_values`
+        if imp.applying.get() { return }
+        imp.dirty.borrow_mut().insert(id.to_string());
+        // even if it was already changed, since a PIN might be complete now
+        self.update_button_sensitivity();
+    }
+    /// put the changed inputs back to the last applied values
+    fn revert(&self) {
+        let imp = self.imp();
+        let dirty = imp.dirty.take();
+        imp.applying.set(true);
+        for (id, widget) in imp.items.get().into_iter().flatten() {
+            if !dirty.contains(id) { continue }
+            if let Some(value) = imp.applied.borrow().get(id) {
+                self.apply_value(value, widget);
+            }
+        }
+        imp.applying.set(false);
+        self.clear_errors();
+        self.update_button_sensitivity();
+    }
+    /// hour and minute spin buttons, added to `parent`
+    fn time_spin_buttons(parent: &GtkBox, hour_label: &str, minute_label: &str) -> (SpinButton, SpinButton) {
+        let hour = SpinButton::with_range(0.0, 23.0, 1.0);
+        let minute = SpinButton::with_range(0.0, 59.0, 1.0);
+        for (spin, label) in [(&hour, hour_label), (&minute, minute_label)] {
+            spin.set_numeric(true);
+            // 23:59 goes to 00:00
+            spin.set_wrap(true);
+            spin.set_orientation(Orientation::Vertical);
+            spin.update_property(&[gtk::accessible::Property::Label(label)]);
+            // always two digits
+            spin.connect_output(|spin| {
+                spin.set_text(&format!("{:02}", spin.value() as u32));
+                glib::Propagation::Stop
+            });
+        }
+        parent.append(&hour);
+        parent.append(&Label::new(Some(":")));
+        parent.append(&minute);
+        (hour, minute)
+    }
+    /// a hidden label under an input for `show_errors`
+    fn append_error_label(&self, id: &str, input: &Widget) {
+        let error_label = Label::new(None);
+        error_label.set_halign(Align::Start);
+        error_label.set_xalign(0.0);
+        error_label.set_wrap(true);
+        error_label.add_css_class("error");
+        error_label.add_css_class("caption");
+        error_label.add_css_class("item-error");
+        error_label.set_visible(false);
+        input.update_relation(&[Relation::ErrorMessage(&[error_label.upcast_ref()])]);
+
+        self.append(&error_label);
+        self.imp().errors.borrow_mut().insert(id.to_string(), (input.clone(), error_label));
+    }
+    /// show what's wrong with the values under their inputs
+    pub fn show_errors(&self, errors: &InfoItemErrors) {
+        // nothing was saved, so the changes are still unsaved
+        self.imp().saving.set(false);
+        self.clear_errors();
+        for (id, message) in errors {
+            if let Some((input, error_label)) = self.imp().errors.borrow().get(id) {
+                error_label.set_label(message);
+                error_label.set_visible(true);
+                input.add_css_class("error");
+                input.update_state(&[State::Invalid(AccessibleInvalidState::True)]);
+            } else {
+                warn!("no input {id} to show error {message:?} under");
+            }
+        }
+        // start with the first problem
+        let first = self.imp().items.get().into_iter().flatten()
+            .find(|(id, _widget)| errors.contains_key(id))
+            .and_then(|(id, _widget)| self.imp().errors.borrow().get(id).map(|(input, _)| input.clone()));
+        if let Some(input) = first {
+            input.grab_focus();
+        }
+    }
+    fn clear_error(&self, id: &str) {
+        if let Some((input, error_label)) = self.imp().errors.borrow().get(id) {
+            if !error_label.is_visible() { return }
+            error_label.set_visible(false);
+            input.remove_css_class("error");
+            input.update_state(&[State::Invalid(AccessibleInvalidState::False)]);
+        }
+    }
+    fn clear_errors(&self) {
+        let ids: Vec<_> = self.imp().errors.borrow().keys().cloned().collect();
+        for id in ids { self.clear_error(&id); }
+    }
+    /// whether every PIN on this card has all of its digits
+    fn pins_complete(&self) -> bool {
+        self.imp().items.get().into_iter().flatten()
+            .all(|(_id, widget)| !matches!(widget, InfoItemWidget::Pin(keypad) if !keypad.is_complete()))
+    }
+    /// buttons are enabled if their value says so
+    /// save buttons also need something to have changed, and any PINs to be complete
+    fn update_button_sensitivity(&self) {
+        let imp = self.imp();
+        // the buttons are re-enabled by `apply_values` once it's done loading
+        if imp.loading.get() { return }
+        let changed = !imp.dirty.borrow().is_empty();
+        let complete = self.pins_complete();
+        for (id, widget) in imp.items.get().into_iter().flatten() {
+            if let InfoItemWidget::Button(button) = widget {
+                let enabled = !matches!(imp.applied.borrow().get(id), Some(InfoItemValue::Button(false)));
+                let save_ready = !is_save_button(id) || (changed && complete);
+                button.set_sensitive(enabled && save_ready);
             }
         }
+        for button in imp.revert_buttons.borrow().iter() {
+            button.set_sensitive(changed);
+        }
+    }
+    /// the loaded values, read out as "<label>: <value>, ..."
+    fn values_summary(&self) -> String {
+        self.imp().items.get().into_iter().flatten()
+            .filter_map(|(_id, widget)| match widget {
+                InfoItemWidget::Field(value, label) => Some(i18n_f("{}: {}", &[label, &value.label()])),
+                InfoItemWidget::Indicator(value, _, _) => Some(value.label().to_string()),
+                _ => None
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+    /// tell screen readers about the new values - only if they're looking at this card,
+    /// since every card reloads at once
+    fn announce_values(&self) {
+        if !self.state_flags().contains(StateFlags::FOCUS_WITHIN) { return }
+        let summary = self.values_summary();
+        if summary.is_empty() { return }
+        // `announce` needs GTK 4.14 - otherwise the card's description is updated, which screen readers read out on focus
+        #[cfg(feature = "announcements")]
+        self.announce(&summary, AccessibleAnnouncementPriority::Medium);
+        #[cfg(not(feature = "announcements"))]
+        self.update_property(&[gtk::accessible::Property::Description(&summary)]);
     }
-    /// get the value of the switches and entries
+    /// get the value of the inputs
     pub fn get_values(&self) -> InfoItemValues {
         if let Some(items) = self.imp().items.get() {
             items.iter().filter_map(|item| {
                 match item {
                     (id, InfoItemWidget::Switch(switch)) => Some((id.clone(), InfoItemValue::Switch(switch.is_active()))),
                     (id, InfoItemWidget::Entry(entry)) => Some((id.clone(), InfoItemValue::Entry(entry.buffer().text().as_str().to_string()))),
+                    (id, InfoItemWidget::Pin(keypad)) => Some((id.clone(), InfoItemValue::Pin(keypad.pin()))),
+                    // what was typed, even if it hasn't been committed to the spin button yet
+                    (id, InfoItemWidget::Number(spin)) => Some((id.clone(), InfoItemValue::Number(spin.text().trim().parse().ok()))),
+                    (id, InfoItemWidget::Dropdown(dropdown)) => Some((id.clone(), InfoItemValue::Dropdown(dropdown.selected()))),
+                    (id, InfoItemWidget::TimeRange(start, end)) => {
+                        let time = |(hour, minute): &(SpinButton, SpinButton)| NaiveTime::from_hms_opt(hour.value_as_int() as u32, minute.value_as_int() as u32, 0).unwrap_or_default();
+                        Some((id.clone(), InfoItemValue::TimeRange(time(start), time(end))))
+                    },
                     _ => None
                 }
             }).collect()
@@ -189,41 +548,123 @@ pub struct InfoItem<'a> {
     pub classes: &'a[&'a str]
 }
 
+/// buttons with these IDs save the card's inputs
+fn is_save_button(id: &str) -> bool {
+    id.starts_with("save_")
+}
+
 #[derive(Debug)]
 enum InfoItemWidget {
-    Field(Label),
-    Indicator(Label),
+    /// the value, and its label
+    Field(Label, String),
+    /// the label, the text when it's on, and the classes to add when it's on
+    Indicator(Label, String, Vec<String>),
     Button(Button),
     Switch(Switch),
-    Entry(Entry)
+    Entry(Entry),
+    Pin(PinKeypad),
+    Number(SpinButton),
+    Dropdown(DropDown),
+    /// the start and end hour and minute
+    TimeRange((SpinButton, SpinButton), (SpinButton, SpinButton))
+}
+
+impl InfoItemWidget {
+    /// the item's main widget
+    fn widget(&self) -> Option<Widget> {
+        match self {
+            Self::Field(label, _) | Self::Indicator(label, _, _) => Some(label.clone().upcast()),
+            Self::Button(button) => Some(button.clone().upcast()),
+            Self::Switch(switch) => Some(switch.clone().upcast()),
+            Self::Entry(entry) => Some(entry.clone().upcast()),
+            Self::Pin(keypad) => Some(keypad.clone().upcast()),
+            Self::Number(spin) => Some(spin.clone().upcast()),
+            Self::Dropdown(dropdown) => Some(dropdown.clone().upcast()),
+            // the box the spin buttons are in
+            Self::TimeRange(start, _) => start.0.parent()
+        }
+    }
+    /// the widget that's covered while loading - buttons don't show a value, so they're just insensitive
+    fn skeleton_target(&self) -> Option<Widget> {
+        if let Self::Button(_) = self { return None }
+        self.widget()
+    }
+    fn set_sensitive(&self, sensitive: bool) {
+        if let Some(widget) = self.widget() {
+            widget.set_sensitive(sensitive);
+        }
+    }
+    /// a placeholder block instead of the (stale) value - see .skeleton in style.css
+    fn set_skeleton(&self, loading: bool) {
+        let Some(target) = self.skeleton_target() else { return };
+        if loading {
+            target.add_css_class("skeleton");
+        } else {
+            target.remove_css_class("skeleton");
+        }
+    }
 }
 
 /// a single value representing the state
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum InfoItemValue {
     Field(String),
     Indicator(bool),
     Button(bool),
     Switch(bool),
-    Entry(String)
+    Entry(String),
+    /// only ever has the digits the band accepts
+    Pin(String),
+    /// `None` if what was typed isn't a number
+    Number(Option<u32>),
+    /// the index of the selected option
+    Dropdown(u32),
+    /// start and end - the end can be before the start if it goes past midnight
+    TimeRange(NaiveTime, NaiveTime)
 }
 
 #[derive(Eq, PartialEq, Debug)]
-pub enum InfoItemType { Field, Indicator, Button, Switch, Entry }
+pub enum InfoItemType {
+    Field, Indicator, Button, Switch, Entry, Pin,
+    /// a spin button, limited to `min..=max`
+    Number { min: u32, max: u32, step: u32 },
+    /// one of `options` (marked with `n_`)
+    Dropdown { options: &'static [&'static str] },
+    TimeRange
+}
 
 pub type InfoItemValues = HashMap<String, InfoItemValue>;
+/// item ID + what's wrong with its value
+pub type InfoItemErrors = HashMap<String, String>;
 
 mod imp {
-    use std::{cell::OnceCell, sync::OnceLock};
+    use std::{cell::{Cell, OnceCell, RefCell}, collections::{HashMap, HashSet}, sync::OnceLock};
 
-    use gtk::{glib::{self, subclass::Signal}, prelude::*, subclass::prelude::*, Box as GtkBox, Orientation};
+    use gtk::{glib::{self, subclass::Signal}, prelude::*, subclass::prelude::*, AccessibleRole, Box as GtkBox, Button, Label, Orientation, Widget};
 
-    use super::InfoItemWidget;
+    use super::{InfoItemValues, InfoItemWidget};
 
     #[derive(Default)]
     pub struct DeviceInfoCard {
         // item ID + enum-widget
-        pub(super) items: OnceCell<Vec<(String, InfoItemWidget)>>
+        pub(super) items: OnceCell<Vec<(String, InfoItemWidget)>>,
+        /// between `set_loading` and `apply_values`
+        pub(super) loading: Cell<bool>,
+        /// item ID -> the input and the label under it for errors
+        pub(super) errors: RefCell<HashMap<String, (Widget, Label)>>,
+        /// IDs of the inputs the user has changed since they were applied
+        pub(super) dirty: RefCell<HashSet<String>>,
+        /// the last value `apply_values` had for each item, to revert to
+        pub(super) applied: RefCell<InfoItemValues>,
+        /// while `apply_values` is setting the inputs, so it doesn't count as a change
+        pub(super) applying: Cell<bool>,
+        /// between a save button being clicked and `apply_values`
+        pub(super) saving: Cell<bool>,
+        /// between `set_unavailable` and `set_loading`
+        pub(super) unavailable: Cell<bool>,
+        /// why the card is unavailable
+        pub(super) notice: Label,
+        pub(super) revert_buttons: RefCell<Vec<Button>>
     }
 
     #[glib::object_subclass]
@@ -231,6 +672,11 @@ mod imp {
         const NAME: &'static str = "MiBand4DeviceInfoCard";
         type Type = super::DeviceInfoCard;
         type ParentType = GtkBox;
+
+        fn class_init(klass: &mut Self::Class) {
+            // read out as a group of related items
+            klass.set_accessible_role(AccessibleRole::Group);
+        }
     }
 
     impl ObjectImpl for DeviceInfoCard {
@@ -253,6 +699,14 @@ mod imp {
             obj.set_orientation(Orientation::Vertical);
             obj.add_css_class("card");
             obj.add_css_class("device-info-card");
+
+            // above the items, which are added later
+            self.notice.set_halign(gtk::Align::Start);
+            self.notice.set_wrap(true);
+            self.notice.add_css_class("heading");
+            self.notice.add_css_class("item-notice");
+            self.notice.set_visible(false);
+            obj.append(&self.notice);
         }
     }
 
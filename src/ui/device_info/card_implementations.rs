@@ -1,98 +1,330 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, NaiveTime, Timelike};
 
-use crate::{band::{BatteryStatus, CurrentActivity, MiBand}, store::{ActivityGoal, BandLock}, utils::{format_date, meters_to_imperial}};
+use crate::{band::{Alert, AlertType, BatteryStatus, BondState, CurrentActivity, HeartRate, MiBand, WorkoutSummary}, heart_rate::socket_path, metric_sinks::prometheus_textfile_path, metrics::MetricsSnapshot, soak::SoakState, trace::TraceState, store::{ActivityGoal, STEP_GOAL_MAX, STEP_GOAL_MIN, AppLock, BatteryAlert, BandLock, DateDisplay, DisplayFormat, DistanceUnit, Forwarding, HeartRateSharing, IdleDisconnect, MetricSinks, MorningBriefing, MovementGate, SleepSummary, SyncSchedule, TemperatureUnit, TimeFormat, TravelMode, WearDetection, WeatherLanguage}, utils::{format_date, format_distance}, i18n::{gettext, i18n_f, n_}};
 
-use super::card::{InfoItem, InfoItemType, InfoItemValue, InfoItemValues};
+use super::card::{InfoItem, InfoItemErrors, InfoItemType, InfoItemValue, InfoItemValues};
 
-pub const BATTERY_ITEMS: [InfoItem<'static>; 3] = [
-    InfoItem { item_type: InfoItemType::Field, id: "level", label: "Battery Level", classes: &[] },
-    InfoItem { item_type: InfoItemType::Field, id: "last_charge", label: "Last Charge", classes: &[] },
-    InfoItem { item_type: InfoItemType::Indicator, id: "charging", label: "Charging", classes: &["success"] },
+pub const BATTERY_ITEMS: [InfoItem<'static>; 7] = [
+    InfoItem { item_type: InfoItemType::Field, id: "level", label: n_("Battery Level"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Field, id: "last_charge", label: n_("Last Charge"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Indicator, id: "charging", label: n_("Charging"), classes: &["success"] },
+    InfoItem { item_type: InfoItemType::Switch, id: "battery_notify_low", label: n_("Notify When Low"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Entry, id: "battery_threshold", label: n_("Low Battery Level (%)"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Switch, id: "battery_notify_charged", label: n_("Notify When Charged"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "save_battery_alert", label: n_("Save"), classes: &[] }
 ];
 
 pub const TIME_ITEMS: [InfoItem<'static>; 2] = [
-    InfoItem { item_type: InfoItemType::Field, id: "current_time", label: "Current Band Time", classes: &[] },
-    InfoItem { item_type: InfoItemType::Button, id: "sync_time", label: "Sync Time", classes: &[] }
+    InfoItem { item_type: InfoItemType::Field, id: "current_time", label: n_("Current Band Time"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "sync_time", label: n_("Sync Time"), classes: &[] }
 ];
 
-pub const DEVICE_INFO_ITEMS: [InfoItem<'static>; 4] = [
-    InfoItem { item_type: InfoItemType::Field, id: "mac", label: "MAC Address", classes: &[] },
-    InfoItem { item_type: InfoItemType::Field, id: "firmware_version", label: "Firmware Version", classes: &[] },
-    InfoItem { item_type: InfoItemType::Field, id: "dbus_path", label: "D-Bus Path", classes: &[] },
-    InfoItem { item_type: InfoItemType::Button, id: "disconnect", label: "Disconnect", classes: &[] }
+pub const DEVICE_INFO_ITEMS: [InfoItem<'static>; 12] = [
+    InfoItem { item_type: InfoItemType::Field, id: "mac", label: n_("MAC Address"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Field, id: "firmware_version", label: n_("Firmware Version"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Field, id: "dbus_path", label: n_("D-Bus Path"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Field, id: "bond_state", label: n_("BlueZ Bond"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "bond", label: n_("Bond"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "remove_bond", label: n_("Remove Bond"), classes: &["destructive-action"] },
+    InfoItem { item_type: InfoItemType::Switch, id: "auto_connect", label: n_("Connect Automatically"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Switch, id: "idle_disconnect", label: n_("Disconnect When Idle"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Entry, id: "idle_minutes", label: n_("Idle Minutes"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "save_connection", label: n_("Save"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "verify_settings", label: n_("Verify Settings"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "disconnect", label: n_("Disconnect"), classes: &[] }
 ];
 
-pub const ACTIVITY_ITEMS: [InfoItem<'static>; 3] = [
-    InfoItem { item_type: InfoItemType::Field, id: "steps", label: "Steps", classes: &[] },
-    InfoItem { item_type: InfoItemType::Field, id: "distance", label: "Distance", classes: &[] },
-    InfoItem { item_type: InfoItemType::Field, id: "calories", label: "Calories Burned", classes: &[] }
+pub const ACTIVITY_ITEMS: [InfoItem<'static>; 13] = [
+    InfoItem { item_type: InfoItemType::Field, id: "steps", label: n_("Steps"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Field, id: "distance", label: n_("Distance"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Field, id: "calories", label: n_("Calories Burned"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Field, id: "last_synced", label: n_("Last Synced"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Switch, id: "auto_sync", label: n_("Sync Automatically"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Entry, id: "sync_interval", label: n_("Sync Interval (minutes)"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Entry, id: "sync_min_battery", label: n_("Postpone Below Band Battery (%)"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "save_sync", label: n_("Save"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "sync_now", label: n_("Sync Now"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "export_activity", label: n_("Export Minute Data"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "view_statistics", label: n_("Statistics"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "export_report", label: n_("Export Weekly Report"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "print_report", label: n_("Print Weekly Report"), classes: &[] }
 ];
 
 pub const ACTIVITY_GOAL_ITEMS: [InfoItem<'static>; 3] = [
-    InfoItem { item_type: InfoItemType::Entry, id: "steps", label: "Step Goal", classes: &[] },
-    InfoItem { item_type: InfoItemType::Switch, id: "notifications", label: "Goal Notifications", classes: &[] },
-    InfoItem { item_type: InfoItemType::Button, id: "save_goal", label: "Save", classes: &[] }
+    InfoItem { item_type: InfoItemType::Number { min: STEP_GOAL_MIN as u32, max: STEP_GOAL_MAX as u32, step: 500 }, id: "steps", label: n_("Step Goal"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Switch, id: "notifications", label: n_("Goal Notifications"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "save_goal", label: n_("Save"), classes: &[] }
 ];
 
 pub const BAND_LOCK_ITEMS: [InfoItem<'static>; 3] = [
-    InfoItem { item_type: InfoItemType::Switch, id: "lock_enabled", label: "Enable Band Lock", classes: &[] },
-    InfoItem { item_type: InfoItemType::Entry, id: "lock_pin", label: "Band PIN", classes: &[] },
-    InfoItem { item_type: InfoItemType::Button, id: "save_band_lock", label: "Save", classes: &[] }
+    InfoItem { item_type: InfoItemType::Switch, id: "lock_enabled", label: n_("Enable Band Lock"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Pin, id: "lock_pin", label: n_("Band PIN"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "save_band_lock", label: n_("Save"), classes: &[] }
+];
+
+pub const DISPLAY_FORMAT_ITEMS: [InfoItem<'static>; 4] = [
+    InfoItem { item_type: InfoItemType::Switch, id: "metric", label: n_("Metric Units"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Switch, id: "24_hour", label: n_("24-Hour Time"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Switch, id: "show_date", label: n_("Show Date on Band"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "save_display_format", label: n_("Save"), classes: &[] }
+];
+
+pub const HEART_RATE_ITEMS: [InfoItem<'static>; 6] = [
+    InfoItem { item_type: InfoItemType::Field, id: "heart_rate", label: n_("Heart Rate"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Switch, id: "share", label: n_("Share With Local Apps"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Field, id: "socket_path", label: n_("Socket Path"), classes: &["monospace"] },
+    InfoItem { item_type: InfoItemType::Switch, id: "wear_notify", label: n_("Notify When Taken Off"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Switch, id: "wear_pause", label: n_("Pause Measuring When Taken Off"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "save_heart_rate", label: n_("Save"), classes: &[] }
+];
+
+pub const MOVEMENT_ITEMS: [InfoItem<'static>; 6] = [
+    InfoItem { item_type: InfoItemType::Switch, id: "movement_enabled", label: n_("Movement Reminders"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Entry, id: "movement_steps", label: n_("Steps Per Hour"), classes: &[] },
+    InfoItem { item_type: InfoItemType::TimeRange, id: "movement_hours", label: n_("Active Hours"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Switch, id: "weekdays_only", label: n_("Weekdays Only"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Switch, id: "skip_meetings", label: n_("Pause During Meetings"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "save_movement", label: n_("Save"), classes: &[] }
+];
+
+pub const WORKOUT_ITEMS: [InfoItem<'static>; 2] = [
+    InfoItem { item_type: InfoItemType::Field, id: "last_workout", label: n_("Last Workout"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "view_workouts", label: n_("Workout History"), classes: &[] }
+];
+
+/// `None`, then each `TemperatureUnit`
+const TEMPERATURE_UNIT_OPTIONS: [&str; 3] = [n_("Match Distance Unit"), n_("Celsius"), n_("Fahrenheit")];
+/// in the order of `WeatherLanguage::ALL`
+const WEATHER_LANGUAGE_OPTIONS: [&str; 8] = [
+    n_("English"), n_("German"), n_("Spanish"), n_("French"), n_("Italian"), n_("Portuguese"), n_("Russian"), n_("Chinese")
+];
+
+pub const BRIEFING_ITEMS: [InfoItem<'static>; 7] = [
+    InfoItem { item_type: InfoItemType::Switch, id: "briefing_enabled", label: n_("Send Every Morning"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Entry, id: "briefing_time", label: n_("Time (HH:MM)"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Entry, id: "weather_location", label: n_("Weather Location (lat, lon)"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Dropdown { options: &TEMPERATURE_UNIT_OPTIONS }, id: "temperature_unit", label: n_("Temperature Unit"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Dropdown { options: &WEATHER_LANGUAGE_OPTIONS }, id: "weather_language", label: n_("Weather Language"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "save_briefing", label: n_("Save"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "send_briefing", label: n_("Send Now"), classes: &[] }
+];
+
+pub const SLEEP_SUMMARY_ITEMS: [InfoItem<'static>; 5] = [
+    InfoItem { item_type: InfoItemType::Switch, id: "sleep_summary_enabled", label: n_("Sleep Summary Every Morning"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Entry, id: "sleep_summary_time", label: n_("Not Before (HH:MM)"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Switch, id: "sleep_summary_band", label: n_("Show on Band"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Switch, id: "sleep_summary_desktop", label: n_("Desktop Notification"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "save_sleep_summary", label: n_("Save"), classes: &[] }
+];
+
+pub const TRAVEL_ITEMS: [InfoItem<'static>; 3] = [
+    InfoItem { item_type: InfoItemType::Switch, id: "travel_enabled", label: n_("Travel Mode"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Field, id: "timezone", label: n_("Current Timezone"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "save_travel", label: n_("Apply"), classes: &[] }
+];
+
+pub const FORWARDING_ITEMS: [InfoItem<'static>; 4] = [
+    InfoItem { item_type: InfoItemType::Switch, id: "forward_notifications", label: n_("Forward Notifications"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Switch, id: "forward_calls", label: n_("Forward Calls"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Switch, id: "forward_media", label: n_("Music Controls"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "save_forwarding", label: n_("Save"), classes: &[] }
+];
+
+/// in the order of `TEST_ALERT_TYPES`
+const TEST_ALERT_TYPE_OPTIONS: [&str; 4] = [n_("Message"), n_("Mail"), n_("Call"), n_("Missed Call")];
+const TEST_ALERT_TYPES: [AlertType; 4] = [AlertType::Message, AlertType::Mail, AlertType::Call, AlertType::MissedCall];
+
+pub const TEST_ALERT_ITEMS: [InfoItem<'static>; 4] = [
+    InfoItem { item_type: InfoItemType::Entry, id: "test_alert_title", label: n_("Test Alert Title"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Entry, id: "test_alert_message", label: n_("Test Alert Message"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Dropdown { options: &TEST_ALERT_TYPE_OPTIONS }, id: "test_alert_type", label: n_("Alert Type"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "send_test_alert", label: n_("Send Test Alert"), classes: &[] }
+];
+
+pub const APP_LOCK_ITEMS: [InfoItem<'static>; 6] = [
+    InfoItem { item_type: InfoItemType::Switch, id: "app_lock_enabled", label: n_("Lock the App"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Entry, id: "app_pin", label: n_("New PIN (blank keeps the current one)"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Switch, id: "system_auth", label: n_("Allow System Authentication"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Entry, id: "lock_timeout", label: n_("Lock After (minutes in background)"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "save_app_lock", label: n_("Save"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "lock_now", label: n_("Lock Now"), classes: &[] }
+];
+
+pub const METRIC_SINK_ITEMS: [InfoItem<'static>; 5] = [
+    InfoItem { item_type: InfoItemType::Switch, id: "prometheus", label: n_("Prometheus Textfile"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Field, id: "prometheus_path", label: n_("Textfile Path"), classes: &["monospace"] },
+    InfoItem { item_type: InfoItemType::Entry, id: "webhook_url", label: n_("Webhook URL (http://)"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Entry, id: "sleep_webhook_url", label: n_("Sleep Webhook URL (http://)"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "save_metric_sinks", label: n_("Save"), classes: &[] }
+];
+
+pub const DEVELOPER_ITEMS: [InfoItem<'static>; 14] = [
+    InfoItem { item_type: InfoItemType::Field, id: "gatt_ops", label: n_("GATT Reads / Writes / Notifications"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Field, id: "ops_per_sec", label: n_("Operations / sec"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Field, id: "in_flight", label: n_("Pending Operations"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Field, id: "latency", label: n_("Average Read / Write Latency"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Field, id: "failures", label: n_("Failed Operations"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Switch, id: "trace_enabled", label: n_("Record Protocol Trace"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Field, id: "trace_entries", label: n_("Recorded Operations"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Switch, id: "soak_enabled", label: n_("Record Soak Diagnostics"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Field, id: "soak_file", label: n_("Soak Log"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "save_developer", label: n_("Save"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "export_trace", label: n_("Export Trace"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "export_capture", label: n_("Save Capture"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "replay_capture", label: n_("Replay Capture"), classes: &[] },
+    InfoItem { item_type: InfoItemType::Button, id: "clear_trace", label: n_("Clear Trace"), classes: &["destructive-action"] }
 ];
 
 pub trait IntoInfoItemValues {
     fn into_info_item_values(self) -> InfoItemValues;
 }
 
-impl IntoInfoItemValues for BatteryStatus {
+impl IntoInfoItemValues for InfoItemValues {
+    fn into_info_item_values(self) -> InfoItemValues {
+        self
+    }
+}
+
+// (battery, time format)
+impl IntoInfoItemValues for (BatteryStatus, TimeFormat) {
+    fn into_info_item_values(self) -> InfoItemValues {
+        HashMap::from([
+            ("level".into(), InfoItemValue::Field(format!("{}%", self.0.battery_level))),
+            ("last_charge".into(), InfoItemValue::Field(format_date(&self.0.last_charge, self.1))),
+            ("charging".into(), InfoItemValue::Indicator(self.0.charging))
+        ])
+    }
+}
+
+impl IntoInfoItemValues for &BatteryAlert {
     fn into_info_item_values(self) -> InfoItemValues {
         HashMap::from([
-            ("level".into(), InfoItemValue::Field(format!("{}%", self.battery_level))),
-            ("last_charge".into(), InfoItemValue::Field(format_date(&self.last_charge))),
-            ("charging".into(), InfoItemValue::Indicator(self.charging))
+            ("battery_notify_low".into(), InfoItemValue::Switch(self.notify_low)),
+            ("battery_threshold".into(), InfoItemValue::Entry(self.threshold.to_string())),
+            ("battery_notify_charged".into(), InfoItemValue::Switch(self.notify_charged)),
+            ("save_battery_alert".into(), InfoItemValue::Button(true))
         ])
     }
 }
 
-// (current_time, authenticated)
-impl IntoInfoItemValues for (DateTime<Local>, bool) {
+impl From<InfoItemValues> for BatteryAlert {
+    fn from(values: InfoItemValues) -> Self {
+        let default = Self::default();
+        let switch = |id: &str| values.get(id)
+            .and_then(|v| if let InfoItemValue::Switch(val) = v { Some(*val) } else { None });
+        Self {
+            notify_low: switch("battery_notify_low").unwrap_or(default.notify_low),
+            threshold: values.get("battery_threshold")
+                .and_then(|v| if let InfoItemValue::Entry(val) = v { val.trim().parse().ok() } else { None })
+                .filter(|t| *t <= 100)
+                .unwrap_or(default.threshold),
+            notify_charged: switch("battery_notify_charged").unwrap_or(default.notify_charged)
+        }
+    }
+}
+
+// (current_time, authenticated, time format)
+impl IntoInfoItemValues for (DateTime<Local>, bool, TimeFormat) {
     fn into_info_item_values(self) -> InfoItemValues {
         HashMap::from([
-            ("current_time".into(), InfoItemValue::Field(format_date(&self.0))),
+            ("current_time".into(), InfoItemValue::Field(format_date(&self.0, self.2))),
             // enable the button if we're authenticated
             ("sync_time".into(), InfoItemValue::Button(self.1))
         ])
     }
 }
 
-impl IntoInfoItemValues for CurrentActivity {
+// (activity, distance unit)
+impl IntoInfoItemValues for (CurrentActivity, DistanceUnit) {
+    fn into_info_item_values(self) -> InfoItemValues {
+        HashMap::from([
+            ("steps".into(), InfoItemValue::Field(self.0.steps.to_string())),
+            ("distance".into(), InfoItemValue::Field(format_distance(self.0.meters.into(), self.1))),
+            ("calories".into(), InfoItemValue::Field(self.0.calories.to_string())),
+            ("sync_now".into(), InfoItemValue::Button(true)),
+            ("export_activity".into(), InfoItemValue::Button(true))
+        ])
+    }
+}
+
+// (last synced, time format)
+impl IntoInfoItemValues for (Option<DateTime<Local>>, TimeFormat) {
     fn into_info_item_values(self) -> InfoItemValues {
         HashMap::from([
-            ("steps".into(), InfoItemValue::Field(self.steps.to_string())),
-            ("distance".into(), InfoItemValue::Field(meters_to_imperial(self.meters))),
-            ("calories".into(), InfoItemValue::Field(self.calories.to_string()))
+            ("last_synced".into(), InfoItemValue::Field(self.0.map(|t| format_date(&t, self.1)).unwrap_or_else(|| gettext("Never"))))
         ])
     }
 }
 
+impl IntoInfoItemValues for &SyncSchedule {
+    fn into_info_item_values(self) -> InfoItemValues {
+        HashMap::from([
+            ("auto_sync".into(), InfoItemValue::Switch(self.enabled)),
+            ("sync_interval".into(), InfoItemValue::Entry(self.interval_minutes.to_string())),
+            ("sync_min_battery".into(), InfoItemValue::Entry(self.min_battery.to_string())),
+            ("save_sync".into(), InfoItemValue::Button(true))
+        ])
+    }
+}
+
+impl From<InfoItemValues> for SyncSchedule {
+    fn from(values: InfoItemValues) -> Self {
+        let default = Self::default();
+        Self {
+            enabled: values.get("auto_sync")
+                .and_then(|v| if let InfoItemValue::Switch(val) = v { Some(*val) } else { None })
+                .unwrap_or(default.enabled),
+            // syncing more than once a minute would just keep the band busy
+            interval_minutes: values.get("sync_interval")
+                .and_then(|v| if let InfoItemValue::Entry(val) = v { val.trim().parse().ok() } else { None })
+                .filter(|i| *i > 0)
+                .unwrap_or(default.interval_minutes),
+            min_battery: values.get("sync_min_battery")
+                .and_then(|v| if let InfoItemValue::Entry(val) = v { val.trim().parse().ok() } else { None })
+                .filter(|i| *i <= 100)
+                .unwrap_or(default.min_battery)
+        }
+    }
+}
+
 // (device, firmware_revision)
-impl<'a> IntoInfoItemValues for (&MiBand<'a>, String) {
+// (band, firmware revision, auto-connect)
+impl<'a> IntoInfoItemValues for (&MiBand<'a>, String, bool) {
     fn into_info_item_values(self) -> InfoItemValues {
         HashMap::from([
             ("mac".into(), InfoItemValue::Field(self.0.address.clone())),
             ("firmware_version".into(), InfoItemValue::Field(self.1)),
             ("dbus_path".into(), InfoItemValue::Field(self.0.path().as_str().to_string())),
+            ("auto_connect".into(), InfoItemValue::Switch(self.2)),
+            ("save_connection".into(), InfoItemValue::Button(true)),
+            ("verify_settings".into(), InfoItemValue::Button(true)),
             ("disconnect".into(), InfoItemValue::Button(true))
         ])
     }
 }
 
+impl IntoInfoItemValues for BondState {
+    fn into_info_item_values(self) -> InfoItemValues {
+        let state = match self {
+            BondState { paired: false, .. } => gettext("Not paired"),
+            BondState { bonded: false, .. } => gettext("Paired, not bonded"),
+            BondState { trusted: true, .. } => gettext("Bonded, trusted"),
+            BondState { trusted: false, .. } => gettext("Bonded, not trusted")
+        };
+        HashMap::from([
+            ("bond_state".into(), InfoItemValue::Field(state)),
+            ("bond".into(), InfoItemValue::Button(!(self.bonded && self.trusted))),
+            ("remove_bond".into(), InfoItemValue::Button(self.paired))
+        ])
+    }
+}
+
 impl IntoInfoItemValues for &ActivityGoal {
     fn into_info_item_values(self) -> InfoItemValues {
         HashMap::from([
-            ("steps".into(), InfoItemValue::Entry(self.steps.to_string())),
+            ("steps".into(), InfoItemValue::Number(Some(self.steps as u32))),
             ("notifications".into(), InfoItemValue::Switch(self.notifications)),
             // always enabled
             ("save_goal".into(), InfoItemValue::Button(true))
@@ -100,18 +332,61 @@ impl IntoInfoItemValues for &ActivityGoal {
     }
 }
 
-impl From<InfoItemValues> for ActivityGoal {
-    fn from(values: InfoItemValues) -> Self {
-        Self {
-            steps: values.get("steps")
-            // get the steps value and parse it as a u16
-                .and_then(|v| if let InfoItemValue::Entry(val) = v { val.trim().parse().ok() } else { None })
-                .unwrap_or_default(),
+/// fails if the step goal isn't something the band can store
+impl TryFrom<InfoItemValues> for ActivityGoal {
+    type Error = InfoItemErrors;
+
+    fn try_from(values: InfoItemValues) -> Result<Self, Self::Error> {
+        let steps = values.get("steps")
+            // get the number out of the spin button, and make sure it's in range
+            .and_then(|v| if let InfoItemValue::Number(val) = v { *val } else { None })
+            .filter(|steps| (STEP_GOAL_MIN as u32..=STEP_GOAL_MAX as u32).contains(steps));
+        let Some(steps) = steps else {
+            return Err(HashMap::from([
+                ("steps".into(), i18n_f("The step goal must be a number from {} to {}", &[&STEP_GOAL_MIN.to_string(), &STEP_GOAL_MAX.to_string()]))
+            ]));
+        };
+        Ok(Self {
+            steps: steps as u16,
             notifications: values.get("notifications")
             // get the bool out of the switch
                 .and_then(|v| if let InfoItemValue::Switch(val) = v { Some(*val) } else { None })
                 .unwrap_or_default(),
+        })
+    }
+}
+
+/// an alert typed in by hand, to check alerts get to the band and how each type vibrates
+pub struct TestAlert {
+    pub alert_type: AlertType,
+    pub title: String,
+    pub message: String
+}
+
+impl TestAlert {
+    pub fn alert(&self) -> Alert<'_> {
+        Alert { alert_type: self.alert_type, title: &self.title, message: &self.message }
+    }
+}
+
+impl TryFrom<InfoItemValues> for TestAlert {
+    type Error = InfoItemErrors;
+
+    fn try_from(values: InfoItemValues) -> Result<Self, Self::Error> {
+        let entry = |id: &str| values.get(id)
+            .and_then(|v| if let InfoItemValue::Entry(val) = v { Some(val.trim().to_string()) } else { None })
+            .unwrap_or_default();
+        let title = entry("test_alert_title");
+        // the band shows an empty alert, which doesn't test much
+        if title.is_empty() {
+            return Err(HashMap::from([
+                ("test_alert_title".into(), gettext("Enter a title"))
+            ]));
         }
+        let alert_type = values.get("test_alert_type")
+            .and_then(|v| if let InfoItemValue::Dropdown(selected) = v { TEST_ALERT_TYPES.get(*selected as usize).copied() } else { None })
+            .unwrap_or(AlertType::Message);
+        Ok(Self { alert_type, title, message: entry("test_alert_message") })
     }
 }
 
@@ -119,7 +394,7 @@ impl IntoInfoItemValues for &BandLock {
     fn into_info_item_values(self) -> InfoItemValues {
         HashMap::from([
             ("lock_enabled".into(), InfoItemValue::Switch(self.enabled)),
-            ("lock_pin".into(), InfoItemValue::Entry(self.pin.clone())),
+            ("lock_pin".into(), InfoItemValue::Pin(self.pin.clone())),
             ("save_band_lock".into(), InfoItemValue::Button(true))
         ])
     }
@@ -133,9 +408,408 @@ impl From<InfoItemValues> for BandLock {
                 .and_then(|v| if let InfoItemValue::Switch(val) = v { Some(*val) } else { None })
                 .unwrap_or_default(),
             pin: values.get("lock_pin")
-            // get the string out of the trny
-                .and_then(|v| if let InfoItemValue::Entry(val) = v { Some(val.clone()) } else { None })
+            // get the string out of the keypad
+                .and_then(|v| if let InfoItemValue::Pin(val) = v { Some(val.clone()) } else { None })
+                .unwrap_or_default()
+        }
+    }
+}
+
+impl IntoInfoItemValues for &DisplayFormat {
+    fn into_info_item_values(self) -> InfoItemValues {
+        HashMap::from([
+            ("metric".into(), InfoItemValue::Switch(self.distance_unit == DistanceUnit::Metric)),
+            ("24_hour".into(), InfoItemValue::Switch(self.time_format == TimeFormat::H24)),
+            ("show_date".into(), InfoItemValue::Switch(self.date_display == DateDisplay::DateTime)),
+            ("save_display_format".into(), InfoItemValue::Button(true))
+        ])
+    }
+}
+
+impl From<InfoItemValues> for DisplayFormat {
+    fn from(values: InfoItemValues) -> Self {
+        let metric = values.get("metric")
+            .and_then(|v| if let InfoItemValue::Switch(val) = v { Some(*val) } else { None })
+            .unwrap_or_default();
+        let h24 = values.get("24_hour")
+            .and_then(|v| if let InfoItemValue::Switch(val) = v { Some(*val) } else { None })
+            .unwrap_or_default();
+        let show_date = values.get("show_date")
+            .and_then(|v| if let InfoItemValue::Switch(val) = v { Some(*val) } else { None })
+            .unwrap_or_default();
+        Self {
+            distance_unit: if metric { DistanceUnit::Metric } else { DistanceUnit::Imperial },
+            time_format: if h24 { TimeFormat::H24 } else { TimeFormat::H12 },
+            date_display: if show_date { DateDisplay::DateTime } else { DateDisplay::Time }
+        }
+    }
+}
+
+impl IntoInfoItemValues for HeartRate {
+    fn into_info_item_values(self) -> InfoItemValues {
+        HashMap::from([
+            ("heart_rate".into(), InfoItemValue::Field(format!("{} bpm", self.bpm)))
+        ])
+    }
+}
+
+impl IntoInfoItemValues for &HeartRateSharing {
+    fn into_info_item_values(self) -> InfoItemValues {
+        HashMap::from([
+            ("heart_rate".into(), InfoItemValue::Field(gettext(if self.enabled { "Waiting for measurement..." } else { "Not measuring" }))),
+            ("share".into(), InfoItemValue::Switch(self.enabled)),
+            ("socket_path".into(), InfoItemValue::Field(socket_path().display().to_string())),
+            ("save_heart_rate".into(), InfoItemValue::Button(true))
+        ])
+    }
+}
+
+impl From<InfoItemValues> for HeartRateSharing {
+    fn from(values: InfoItemValues) -> Self {
+        Self {
+            enabled: values.get("share")
+                .and_then(|v| if let InfoItemValue::Switch(val) = v { Some(*val) } else { None })
+                .unwrap_or_default()
+        }
+    }
+}
+
+impl IntoInfoItemValues for &WearDetection {
+    fn into_info_item_values(self) -> InfoItemValues {
+        HashMap::from([
+            ("wear_notify".into(), InfoItemValue::Switch(self.notify)),
+            ("wear_pause".into(), InfoItemValue::Switch(self.pause_heart_rate))
+        ])
+    }
+}
+
+impl From<InfoItemValues> for WearDetection {
+    fn from(values: InfoItemValues) -> Self {
+        let switch = |id: &str| values.get(id)
+            .and_then(|v| if let InfoItemValue::Switch(val) = v { Some(*val) } else { None })
+            .unwrap_or_default();
+        Self {
+            notify: switch("wear_notify"),
+            pause_heart_rate: switch("wear_pause")
+        }
+    }
+}
+
+impl IntoInfoItemValues for &MorningBriefing {
+    fn into_info_item_values(self) -> InfoItemValues {
+        HashMap::from([
+            ("briefing_enabled".into(), InfoItemValue::Switch(self.enabled)),
+            ("briefing_time".into(), InfoItemValue::Entry(format!("{:02}:{:02}", self.hour, self.minute))),
+            ("weather_location".into(), InfoItemValue::Entry(
+                self.weather_location.map(|(lat, lon)| format!("{lat}, {lon}")).unwrap_or_default()
+            )),
+            ("temperature_unit".into(), InfoItemValue::Dropdown(match self.temperature_unit {
+                None => 0,
+                Some(TemperatureUnit::Celsius) => 1,
+                Some(TemperatureUnit::Fahrenheit) => 2
+            })),
+            ("weather_language".into(), InfoItemValue::Dropdown(
+                WeatherLanguage::ALL.iter().position(|language| *language == self.weather_language).unwrap_or_default() as u32
+            )),
+            ("save_briefing".into(), InfoItemValue::Button(true)),
+            ("send_briefing".into(), InfoItemValue::Button(true))
+        ])
+    }
+}
+
+/// HH:MM
+fn parse_time_of_day(time: &str) -> Option<(u8, u8)> {
+    time.split_once(':')
+        .and_then(|(h, m)| Some((h.parse().ok().filter(|h| *h < 24)?, m.parse().ok().filter(|m| *m < 60)?)))
+}
+
+impl From<InfoItemValues> for MorningBriefing {
+    fn from(values: InfoItemValues) -> Self {
+        let default = Self::default();
+        let entry = |id: &str| values.get(id)
+            .and_then(|v| if let InfoItemValue::Entry(val) = v { Some(val.trim().to_string()) } else { None })
+            .unwrap_or_default();
+
+        let (hour, minute) = parse_time_of_day(&entry("briefing_time")).unwrap_or((default.hour, default.minute));
+
+        // lat, lon
+        let location = entry("weather_location");
+        let weather_location = location.split_once(',')
+            .and_then(|(lat, lon)| Some((lat.trim().parse().ok()?, lon.trim().parse().ok()?)));
+
+        let dropdown = |id: &str| values.get(id)
+            .and_then(|v| if let InfoItemValue::Dropdown(selected) = v { Some(*selected as usize) } else { None })
+            .unwrap_or_default();
+
+        let temperature_unit = match dropdown("temperature_unit") {
+            1 => Some(TemperatureUnit::Celsius),
+            2 => Some(TemperatureUnit::Fahrenheit),
+            _ => None
+        };
+
+        Self {
+            enabled: values.get("briefing_enabled")
+                .and_then(|v| if let InfoItemValue::Switch(val) = v { Some(*val) } else { None })
+                .unwrap_or_default(),
+            hour,
+            minute,
+            weather_location,
+            temperature_unit,
+            weather_language: WeatherLanguage::ALL.get(dropdown("weather_language")).copied().unwrap_or_default()
+        }
+    }
+}
+
+impl IntoInfoItemValues for &Forwarding {
+    fn into_info_item_values(self) -> InfoItemValues {
+        HashMap::from([
+            ("forward_notifications".into(), InfoItemValue::Switch(self.notifications)),
+            ("forward_calls".into(), InfoItemValue::Switch(self.calls)),
+            ("forward_media".into(), InfoItemValue::Switch(self.media)),
+            ("save_forwarding".into(), InfoItemValue::Button(true))
+        ])
+    }
+}
+
+impl From<InfoItemValues> for Forwarding {
+    fn from(values: InfoItemValues) -> Self {
+        let switch = |id: &str| values.get(id)
+            .and_then(|v| if let InfoItemValue::Switch(val) = v { Some(*val) } else { None })
+            .unwrap_or_default();
+        Self {
+            notifications: switch("forward_notifications"),
+            calls: switch("forward_calls"),
+            media: switch("forward_media")
+        }
+    }
+}
+
+impl IntoInfoItemValues for &SleepSummary {
+    fn into_info_item_values(self) -> InfoItemValues {
+        HashMap::from([
+            ("sleep_summary_enabled".into(), InfoItemValue::Switch(self.enabled)),
+            ("sleep_summary_time".into(), InfoItemValue::Entry(format!("{:02}:{:02}", self.hour, self.minute))),
+            ("sleep_summary_band".into(), InfoItemValue::Switch(self.to_band)),
+            ("sleep_summary_desktop".into(), InfoItemValue::Switch(self.to_desktop)),
+            ("save_sleep_summary".into(), InfoItemValue::Button(true))
+        ])
+    }
+}
+
+/// `last_sent` isn't included - it's kept from the stored summary
+impl From<InfoItemValues> for SleepSummary {
+    fn from(values: InfoItemValues) -> Self {
+        let default = Self::default();
+        let switch = |id: &str| values.get(id)
+            .and_then(|v| if let InfoItemValue::Switch(val) = v { Some(*val) } else { None })
+            .unwrap_or_default();
+        let time = values.get("sleep_summary_time")
+            .and_then(|v| if let InfoItemValue::Entry(val) = v { parse_time_of_day(val.trim()) } else { None });
+        let (hour, minute) = time.unwrap_or((default.hour, default.minute));
+
+        Self {
+            enabled: switch("sleep_summary_enabled"),
+            hour,
+            minute,
+            to_band: switch("sleep_summary_band"),
+            to_desktop: switch("sleep_summary_desktop"),
+            last_sent: None
+        }
+    }
+}
+
+impl IntoInfoItemValues for &MetricSinks {
+    fn into_info_item_values(self) -> InfoItemValues {
+        HashMap::from([
+            ("prometheus".into(), InfoItemValue::Switch(self.prometheus)),
+            ("prometheus_path".into(), InfoItemValue::Field(prometheus_textfile_path().display().to_string())),
+            ("webhook_url".into(), InfoItemValue::Entry(self.webhook_url.clone())),
+            ("sleep_webhook_url".into(), InfoItemValue::Entry(self.sleep_webhook_url.clone())),
+            ("save_metric_sinks".into(), InfoItemValue::Button(true))
+        ])
+    }
+}
+
+impl From<InfoItemValues> for MetricSinks {
+    fn from(values: InfoItemValues) -> Self {
+        Self {
+            prometheus: values.get("prometheus")
+                .and_then(|v| if let InfoItemValue::Switch(val) = v { Some(*val) } else { None })
+                .unwrap_or_default(),
+            webhook_url: values.get("webhook_url")
+                .and_then(|v| if let InfoItemValue::Entry(val) = v { Some(val.trim().to_string()) } else { None })
+                .unwrap_or_default(),
+            sleep_webhook_url: values.get("sleep_webhook_url")
+                .and_then(|v| if let InfoItemValue::Entry(val) = v { Some(val.trim().to_string()) } else { None })
+                .unwrap_or_default()
+        }
+    }
+}
+
+impl IntoInfoItemValues for &TravelMode {
+    fn into_info_item_values(self) -> InfoItemValues {
+        HashMap::from([
+            ("travel_enabled".into(), InfoItemValue::Switch(self.enabled)),
+            ("timezone".into(), InfoItemValue::Field(Local::now().format("UTC%:z").to_string())),
+            ("save_travel".into(), InfoItemValue::Button(true))
+        ])
+    }
+}
+
+impl From<InfoItemValues> for TravelMode {
+    fn from(values: InfoItemValues) -> Self {
+        Self {
+            enabled: values.get("travel_enabled")
+                .and_then(|v| if let InfoItemValue::Switch(val) = v { Some(*val) } else { None })
                 .unwrap_or_default()
         }
     }
 }
+
+impl IntoInfoItemValues for &AppLock {
+    fn into_info_item_values(self) -> InfoItemValues {
+        HashMap::from([
+            ("app_lock_enabled".into(), InfoItemValue::Switch(self.enabled)),
+            // never show the PIN
+            ("app_pin".into(), InfoItemValue::Entry(String::new())),
+            ("system_auth".into(), InfoItemValue::Switch(self.system_auth)),
+            ("lock_timeout".into(), InfoItemValue::Entry(self.timeout_minutes.to_string())),
+            ("save_app_lock".into(), InfoItemValue::Button(true)),
+            ("lock_now".into(), InfoItemValue::Button(self.enabled))
+        ])
+    }
+}
+
+/// the PIN isn't included - it has to be hashed first
+impl From<InfoItemValues> for AppLock {
+    fn from(values: InfoItemValues) -> Self {
+        let switch = |id: &str| values.get(id)
+            .and_then(|v| if let InfoItemValue::Switch(val) = v { Some(*val) } else { None })
+            .unwrap_or_default();
+        Self {
+            enabled: switch("app_lock_enabled"),
+            pin: None,
+            system_auth: switch("system_auth"),
+            timeout_minutes: values.get("lock_timeout")
+                .and_then(|v| if let InfoItemValue::Entry(val) = v { val.trim().parse().ok() } else { None })
+                .unwrap_or(Self::default().timeout_minutes)
+        }
+    }
+}
+
+impl IntoInfoItemValues for &IdleDisconnect {
+    fn into_info_item_values(self) -> InfoItemValues {
+        HashMap::from([
+            ("idle_disconnect".into(), InfoItemValue::Switch(self.enabled)),
+            ("idle_minutes".into(), InfoItemValue::Entry(self.minutes.to_string()))
+        ])
+    }
+}
+
+impl From<InfoItemValues> for IdleDisconnect {
+    fn from(values: InfoItemValues) -> Self {
+        let default = Self::default();
+        let enabled = matches!(values.get("idle_disconnect"), Some(InfoItemValue::Switch(true)));
+        let minutes = values.get("idle_minutes")
+            .and_then(|v| if let InfoItemValue::Entry(val) = v { val.trim().parse().ok() } else { None })
+            .filter(|m| *m > 0)
+            .unwrap_or(default.minutes);
+        Self { enabled, minutes }
+    }
+}
+
+impl IntoInfoItemValues for &MovementGate {
+    fn into_info_item_values(self) -> InfoItemValues {
+        HashMap::from([
+            ("movement_enabled".into(), InfoItemValue::Switch(self.enabled)),
+            ("movement_steps".into(), InfoItemValue::Entry(self.steps.to_string())),
+            ("movement_hours".into(), InfoItemValue::TimeRange(
+                NaiveTime::from_hms_opt(self.start_hour as u32, self.start_minute as u32, 0).unwrap_or_default(),
+                NaiveTime::from_hms_opt(self.end_hour as u32, self.end_minute as u32, 0).unwrap_or_default()
+            )),
+            ("weekdays_only".into(), InfoItemValue::Switch(self.weekdays_only)),
+            ("skip_meetings".into(), InfoItemValue::Switch(self.skip_meetings)),
+            ("save_movement".into(), InfoItemValue::Button(true))
+        ])
+    }
+}
+
+impl From<InfoItemValues> for MovementGate {
+    fn from(values: InfoItemValues) -> Self {
+        let default = Self::default();
+        let switch = |id: &str| values.get(id)
+            .and_then(|v| if let InfoItemValue::Switch(val) = v { Some(*val) } else { None })
+            .unwrap_or_default();
+        let entry = |id: &str| values.get(id)
+            .and_then(|v| if let InfoItemValue::Entry(val) = v { Some(val.trim().to_string()) } else { None })
+            .unwrap_or_default();
+        let (start, end) = values.get("movement_hours")
+            .and_then(|v| if let InfoItemValue::TimeRange(start, end) = v { Some((*start, *end)) } else { None })
+            .unwrap_or_else(|| (
+                NaiveTime::from_hms_opt(default.start_hour as u32, default.start_minute as u32, 0).unwrap_or_default(),
+                NaiveTime::from_hms_opt(default.end_hour as u32, default.end_minute as u32, 0).unwrap_or_default()
+            ));
+        Self {
+            enabled: switch("movement_enabled"),
+            steps: entry("movement_steps").parse().unwrap_or(default.steps),
+            start_hour: start.hour() as u8,
+            end_hour: end.hour() as u8,
+            start_minute: start.minute() as u8,
+            end_minute: end.minute() as u8,
+            weekdays_only: switch("weekdays_only"),
+            skip_meetings: switch("skip_meetings")
+        }
+    }
+}
+
+// (latest workout, authenticated, time format)
+impl IntoInfoItemValues for (Option<&WorkoutSummary>, bool, TimeFormat) {
+    fn into_info_item_values(self) -> InfoItemValues {
+        let last_workout = self.0
+            .map(|workout| i18n_f("{} on {}", &[&workout.kind.to_string(), &format_date(&workout.start, self.2)]))
+            .unwrap_or_else(|| gettext("None"));
+        HashMap::from([
+            ("last_workout".into(), InfoItemValue::Field(last_workout)),
+            // fetching requires authentication
+            ("view_workouts".into(), InfoItemValue::Button(self.1))
+        ])
+    }
+}
+
+impl IntoInfoItemValues for TraceState {
+    fn into_info_item_values(self) -> InfoItemValues {
+        HashMap::from([
+            ("trace_enabled".into(), InfoItemValue::Switch(self.enabled)),
+            ("trace_entries".into(), InfoItemValue::Field(self.entries.to_string())),
+            ("save_developer".into(), InfoItemValue::Button(true)),
+            ("export_trace".into(), InfoItemValue::Button(self.entries > 0)),
+            ("export_capture".into(), InfoItemValue::Button(self.entries > 0)),
+            ("replay_capture".into(), InfoItemValue::Button(true)),
+            ("clear_trace".into(), InfoItemValue::Button(self.entries > 0))
+        ])
+    }
+}
+
+impl IntoInfoItemValues for SoakState {
+    fn into_info_item_values(self) -> InfoItemValues {
+        HashMap::from([
+            ("soak_enabled".into(), InfoItemValue::Switch(self.enabled)),
+            ("soak_file".into(), InfoItemValue::Field(self.path.display().to_string()))
+        ])
+    }
+}
+
+impl IntoInfoItemValues for MetricsSnapshot {
+    fn into_info_item_values(self) -> InfoItemValues {
+        let latency = |latency: Option<Duration>| latency
+            .map(|l| format!("{:.1} ms", l.as_secs_f64() * 1000.0))
+            .unwrap_or_else(|| "-".into());
+        HashMap::from([
+            ("gatt_ops".into(), InfoItemValue::Field(format!("{} / {} / {}", self.reads, self.writes, self.notifications))),
+            ("ops_per_sec".into(), InfoItemValue::Field(format!("{:.1}", self.ops_per_sec))),
+            ("in_flight".into(), InfoItemValue::Field(self.in_flight.to_string())),
+            ("latency".into(), InfoItemValue::Field(format!("{} / {}", latency(self.avg_read_latency), latency(self.avg_write_latency)))),
+            ("failures".into(), InfoItemValue::Field(self.failures.to_string()))
+        ])
+    }
+}
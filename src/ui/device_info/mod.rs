@@ -1,2 +1,3 @@
 pub mod card;
 pub mod card_implementations;
+pub mod pin_keypad;
@@ -0,0 +1,151 @@
+use gtk::{accessible::Property, gdk::Key, glib::{self, clone, Object, Propagation}, prelude::*, subclass::prelude::*, Accessible, Align, Box as GtkBox, Buildable, Button, ConstraintTarget, EventControllerKey, Label, Orientable, Orientation, Widget};
+
+use crate::{i18n::{gettext, i18n_f}, store::{BAND_LOCK_PIN_DIGITS as PIN_DIGITS, BAND_LOCK_PIN_LENGTH as PIN_LENGTH}};
+
+glib::wrapper! {
+    pub struct PinKeypad(ObjectSubclass<imp::PinKeypad>)
+        @extends GtkBox, Widget,
+        @implements Accessible, Buildable, ConstraintTarget, Orientable;
+}
+
+impl PinKeypad {
+    pub fn new() -> Self {
+        Object::builder().build()
+    }
+    pub fn pin(&self) -> String {
+        self.imp().pin.borrow().clone()
+    }
+    /// anything the band wouldn't accept is dropped
+    pub fn set_pin(&self, pin: &str) {
+        let pin = pin.chars().filter(|c| PIN_DIGITS.contains(c)).take(PIN_LENGTH).collect();
+        self.imp().pin.replace(pin);
+        self.pin_changed();
+    }
+    /// whether the PIN can be sent to the band
+    pub fn is_complete(&self) -> bool {
+        self.imp().pin.borrow().len() == PIN_LENGTH
+    }
+    pub fn connect_changed<F: Fn(&Self) + 'static>(&self, f: F) -> glib::SignalHandlerId {
+        self.connect_local("changed", false, move |values| {
+            let keypad = values[0].get::<Self>().expect("first value is the keypad");
+            f(&keypad);
+            None
+        })
+    }
+    fn push_digit(&self, digit: char) {
+        {
+            let mut pin = self.imp().pin.borrow_mut();
+            if pin.len() >= PIN_LENGTH || !PIN_DIGITS.contains(&digit) { return }
+            pin.push(digit);
+        }
+        self.pin_changed();
+    }
+    fn pop_digit(&self) {
+        if self.imp().pin.borrow_mut().pop().is_none() { return }
+        self.pin_changed();
+    }
+    fn pin_changed(&self) {
+        let imp = self.imp();
+        let len = imp.pin.borrow().len();
+        // one dot per digit entered, and a dash for the ones left
+        let masked: String = "●".repeat(len) + &"–".repeat(PIN_LENGTH - len);
+        imp.display.set_label(&masked);
+        imp.display.update_property(&[Property::Description(&i18n_f("{} of {} digits entered", &[&len.to_string(), &PIN_LENGTH.to_string()]))]);
+        // nothing to take back, or no room for another digit
+        imp.button_backspace.set_sensitive(len > 0);
+        for button in imp.digit_buttons.borrow().iter() {
+            button.set_sensitive(len < PIN_LENGTH);
+        }
+        self.emit_by_name::<()>("changed", &[]);
+    }
+}
+
+mod imp {
+    use std::{cell::RefCell, sync::OnceLock};
+
+    use gtk::{glib::{self, subclass::Signal}, prelude::*, subclass::prelude::*, AccessibleRole, Box as GtkBox, Button, Label};
+
+    #[derive(Default)]
+    pub struct PinKeypad {
+        pub(super) pin: RefCell<String>,
+        pub(super) display: Label,
+        pub(super) digit_buttons: RefCell<Vec<Button>>,
+        pub(super) button_backspace: Button
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PinKeypad {
+        const NAME: &'static str = "MiBand4PinKeypad";
+        type Type = super::PinKeypad;
+        type ParentType = GtkBox;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.set_accessible_role(AccessibleRole::Group);
+        }
+    }
+
+    impl ObjectImpl for PinKeypad {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| vec![Signal::builder("changed").build()])
+        }
+
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().build();
+        }
+    }
+
+    impl WidgetImpl for PinKeypad {}
+    impl BoxImpl for PinKeypad {}
+}
+
+impl PinKeypad {
+    fn build(&self) {
+        let imp = self.imp();
+        self.set_orientation(Orientation::Vertical);
+        self.set_spacing(6);
+        self.set_halign(Align::Start);
+        self.add_css_class("pin-keypad");
+
+        imp.display.add_css_class("title-3");
+        imp.display.add_css_class("pin-display");
+        imp.display.set_halign(Align::Start);
+        self.append(&imp.display);
+
+        let row = GtkBox::new(Orientation::Horizontal, 6);
+        let mut digit_buttons = Vec::with_capacity(PIN_DIGITS.len());
+        for digit in PIN_DIGITS {
+            let button = Button::with_label(&digit.to_string());
+            button.add_css_class("circular");
+            button.connect_clicked(clone!(@weak self as keypad => move |_| keypad.push_digit(digit)));
+            row.append(&button);
+            digit_buttons.push(button);
+        }
+        imp.digit_buttons.replace(digit_buttons);
+
+        imp.button_backspace.set_icon_name("edit-clear-symbolic");
+        imp.button_backspace.add_css_class("circular");
+        imp.button_backspace.set_tooltip_text(Some(&gettext("Delete Last Digit")));
+        imp.button_backspace.update_property(&[Property::Label(&gettext("Delete Last Digit"))]);
+        imp.button_backspace.connect_clicked(clone!(@weak self as keypad => move |_| keypad.pop_digit()));
+        row.append(&imp.button_backspace);
+        self.append(&row);
+
+        // typing works too, as long as focus is somewhere in the keypad
+        let keys = EventControllerKey::new();
+        keys.connect_key_pressed(clone!(@weak self as keypad => @default-return Propagation::Proceed, move |_, key, _, _| {
+            match key {
+                Key::BackSpace => keypad.pop_digit(),
+                key => match key.to_unicode() {
+                    Some(digit) if PIN_DIGITS.contains(&digit) => keypad.push_digit(digit),
+                    _ => return Propagation::Proceed
+                }
+            }
+            Propagation::Stop
+        }));
+        self.add_controller(keys);
+
+        self.pin_changed();
+    }
+}
@@ -1,5 +1,6 @@
 use gtk::{glib::{self, Object}, Accessible, Grid, Buildable, ConstraintTarget, Orientable, Widget};
 
+use crate::{i18n::{gettext, i18n_f}, utils::signal_icon_name};
 
 glib::wrapper! {
     pub struct DeviceRow(ObjectSubclass<imp::DeviceRow>)
@@ -13,10 +14,19 @@ impl DeviceRow {
     }
 }
 
+/// `DeviceRowObject` uses 0 for an unknown RSSI
+fn rssi_icon_name(rssi: i32) -> &'static str {
+    signal_icon_name(if rssi == 0 { None } else { Some(rssi as i16) })
+}
+
+fn rssi_description(rssi: i32) -> String {
+    if rssi == 0 { gettext("Not in range") } else { i18n_f("Signal: {} dBm", &[&rssi.to_string()]) }
+}
+
 mod imp {
     use std::cell::RefCell;
 
-    use gtk::{glib::{self, closure, subclass::InitializingObject, Properties, Object}, prelude::*, subclass::prelude::*, Grid, CompositeTemplate, Label, Widget};
+    use gtk::{glib::{self, closure, subclass::InitializingObject, Properties, Object}, prelude::*, subclass::prelude::*, Grid, CompositeTemplate, Image, Label, Widget};
 
     use crate::ui::device_row_object::DeviceRowObject;
 
@@ -29,7 +39,7 @@ mod imp {
         #[template_child]
         pub address_label: TemplateChild<Label>,
         #[template_child]
-        pub rssi_label: TemplateChild<Label>,
+        pub rssi_image: TemplateChild<Image>,
         #[template_child]
         pub connected_label: TemplateChild<Label>
     }
@@ -60,15 +70,30 @@ mod imp {
             device.chain_property::<DeviceRowObject>("alias") // it should be called "alias_label" but I don't feel like changing everything
                 .bind(&self.address_label.get(), "label", Widget::NONE);
 
-            device.chain_property::<DeviceRowObject>("rssi")
-                .chain_closure::<String>(closure!(|_: Option<Object>, rssi: i32| {
-                    if rssi == 0 { "RSSI: ?".into() } else { format!("RSSI: {rssi}") }
+            let rssi = device.chain_property::<DeviceRowObject>("rssi");
+            rssi.chain_closure::<String>(closure!(|_: Option<Object>, rssi: i32| {
+                    super::rssi_icon_name(rssi).to_string()
+                }))
+                .bind(&self.rssi_image.get(), "icon-name", Widget::NONE);
+            // the exact value is still there on hover
+            rssi.chain_closure::<String>(closure!(|_: Option<Object>, rssi: i32| {
+                    super::rssi_description(rssi)
                 }))
-                .bind(&self.rssi_label.get(), "label", Widget::NONE);
+                .bind(&self.rssi_image.get(), "tooltip-text", Widget::NONE);
             
-            device.chain_property::<DeviceRowObject>("connected")
-                .bind(&self.connected_label.get(), "visible", Widget::NONE);
-                
+            let connected = device.chain_property::<DeviceRowObject>("connected");
+            connected.bind(&self.connected_label.get(), "visible", Widget::NONE);
+            // .device-row.connected, for custom CSS
+            let row = obj.downgrade();
+            let expression = connected.clone();
+            connected.watch(Some(&*obj), move || {
+                let Some(row) = row.upgrade() else { return };
+                if expression.evaluate_as::<bool, _>(Some(&row)).unwrap_or(false) {
+                    row.add_css_class("connected");
+                } else {
+                    row.remove_css_class("connected");
+                }
+            });
         }
     }
 
@@ -0,0 +1,94 @@
+use gtk::{glib::{self, Object}, prelude::*, subclass::prelude::*, Accessible, Buildable, ConstraintTarget, Native, Root, ShortcutManager, Widget, Window};
+
+use crate::i18n::i18n_f;
+
+glib::wrapper! {
+    /// a small window with the current band's status, for keeping in a corner of the screen
+    pub struct MiniWindow(ObjectSubclass<imp::MiniWindow>)
+        // https://docs.gtk.org/gtk4/class.Window.html#hierarchy
+        @extends Window, Widget,
+        @implements Accessible, Buildable, ConstraintTarget, Native, Root, ShortcutManager;
+}
+
+impl MiniWindow {
+    pub fn new() -> Self {
+        Object::builder().build()
+    }
+
+    /// the band that's open in the main window (`None` if there isn't one)
+    pub fn set_band(&self, alias: Option<&str>) {
+        let imp = self.imp();
+        imp.label_band.set_text(alias.unwrap_or("No band"));
+        if alias.is_none() {
+            imp.label_battery.set_text("-");
+            imp.label_steps.set_text("-");
+            imp.btn_find.set_sensitive(false);
+        }
+    }
+
+    /// the band can only be found while it's connected
+    pub fn set_connected(&self, connected: bool) {
+        self.imp().btn_find.set_sensitive(connected);
+    }
+
+    pub fn set_battery(&self, level: u8, charging: bool) {
+        let level = format!("{level}%");
+        self.imp().label_battery.set_text(&if charging { i18n_f("{} (charging)", &[&level]) } else { level });
+    }
+
+    pub fn set_steps(&self, steps: u16) {
+        self.imp().label_steps.set_text(&steps.to_string());
+    }
+}
+
+mod imp {
+    use std::sync::OnceLock;
+
+    use gtk::{glib::{self, subclass::{InitializingObject, Signal}}, prelude::*, subclass::prelude::*, template_callbacks, Button, CompositeTemplate, Label, TemplateChild, Window};
+
+    #[derive(CompositeTemplate, Default)]
+    #[template(resource = "/me/grimsteel/miband4-gtk/mini_window.ui")]
+    pub struct MiniWindow {
+        #[template_child]
+        pub label_band: TemplateChild<Label>,
+        #[template_child]
+        pub label_battery: TemplateChild<Label>,
+        #[template_child]
+        pub label_steps: TemplateChild<Label>,
+        #[template_child]
+        pub btn_find: TemplateChild<Button>
+    }
+
+    #[template_callbacks]
+    impl MiniWindow {
+        #[template_callback]
+        fn handle_find_clicked(&self) {
+            self.obj().emit_by_name::<()>("find-band", &[]);
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for MiniWindow {
+        const NAME: &'static str = "MiBand4MiniWindow";
+        type Type = super::MiniWindow;
+        type ParentType = Window;
+
+        fn class_init(class: &mut Self::Class) {
+            class.bind_template();
+            class.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for MiniWindow {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| vec![Signal::builder("find-band").build()])
+        }
+    }
+    impl WidgetImpl for MiniWindow {}
+    impl WindowImpl for MiniWindow {}
+}
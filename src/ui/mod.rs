@@ -2,4 +2,13 @@ pub mod window;
 mod device_row;
 mod device_row_object;
 mod auth_key_dialog;
+mod verify_settings_dialog;
+mod workout_history_dialog;
+mod alert_history_dialog;
+mod statistics_dialog;
+mod sync_progress_dialog;
+mod bulk_operation_dialog;
+mod pairing_dialog;
+mod onboarding_dialog;
+mod mini_window;
 mod device_info;
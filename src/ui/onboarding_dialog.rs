@@ -0,0 +1,289 @@
+use gtk::{gio::ListStore, glib::{self, Object}, prelude::*, subclass::prelude::*, Accessible, Align, Box as GtkBox, Buildable, ConstraintTarget, Label, ListBoxRow, Native, Orientation, Root, ShortcutManager, Widget, Window};
+
+use crate::{i18n::{gettext, i18n_f}, store::ActivityGoal, utils::decode_hex};
+
+use super::device_row_object::DeviceRowObject;
+
+glib::wrapper! {
+    pub struct OnboardingDialog(ObjectSubclass<imp::OnboardingDialog>)
+        // https://docs.gtk.org/gtk4/class.Window.html#hierarchy
+        @extends Window, Widget,
+        @implements Accessible, Buildable, ConstraintTarget, Native, Root, ShortcutManager;
+}
+
+/// auth keys are 16 bytes
+const AUTH_KEY_LEN: usize = 16;
+
+fn build_row(device: &DeviceRowObject) -> ListBoxRow {
+    let content = GtkBox::new(Orientation::Vertical, 2);
+    content.set_margin_top(8);
+    content.set_margin_bottom(8);
+    content.set_margin_start(8);
+    content.set_margin_end(8);
+
+    let alias = Label::new(Some(&device.alias()));
+    alias.set_halign(Align::Start);
+    alias.add_css_class("heading");
+    content.append(&alias);
+    // the alias is the address until it's been named
+    if device.alias() != device.address() {
+        let address = Label::new(Some(&device.address()));
+        address.set_halign(Align::Start);
+        address.add_css_class("dim-label");
+        content.append(&address);
+    }
+
+    let row = ListBoxRow::new();
+    row.set_child(Some(&content));
+    row
+}
+
+impl OnboardingDialog {
+    pub fn new() -> Self {
+        Object::builder().build()
+    }
+
+    /// start from the beginning, listing the bands in `devices`
+    pub fn start(&self, devices: &ListStore) {
+        let imp = self.imp();
+        imp.list_bands.bind_model(Some(devices), |item| {
+            build_row(item.downcast_ref::<DeviceRowObject>().expect("the device list has DeviceRowObjects")).upcast()
+        });
+        imp.devices.replace(Some(devices.clone()));
+        imp.label_scan_status.set_text("");
+        imp.entry_auth_key.set_text("");
+        self.set_scanning(false);
+        self.show_page("scan");
+        self.present();
+    }
+
+    pub fn set_scanning(&self, scanning: bool) {
+        let imp = self.imp();
+        imp.btn_scan.set_sensitive(!scanning);
+        imp.spinner_scan.set_visible(scanning);
+        imp.spinner_scan.set_spinning(scanning);
+    }
+
+    /// connecting to the chosen band
+    pub fn show_connecting(&self) {
+        self.show_working(&gettext("Connecting to the band…"));
+    }
+
+    /// connecting failed - back to picking a band
+    pub fn connect_failed(&self, error: &str) {
+        self.imp().label_scan_status.set_text(&i18n_f("Couldn't connect to the band: {}", &[error]));
+        self.show_page("scan");
+    }
+
+    /// connected - ask for the auth key, filled in with the one saved for the band (if any)
+    pub fn show_auth(&self, address: &str, auth_key: Option<&str>) {
+        let imp = self.imp();
+        imp.label_auth_band.set_text(&i18n_f("Connected to {}. Enter its auth key to finish setting it up.", &[address]));
+        if let Some(auth_key) = auth_key {
+            imp.entry_auth_key.set_text(auth_key);
+        }
+        imp.entry_auth_key.remove_css_class("error");
+        imp.label_auth_status.set_text("");
+        self.show_page("auth");
+        self.present();
+    }
+
+    /// fill in a key that came from the auth key dialog, and try it straight away
+    pub fn test_auth_key(&self, auth_key: &str) {
+        self.imp().entry_auth_key.set_text(auth_key);
+        self.imp().try_auth_key();
+    }
+
+    /// the band rejected the key (or it couldn't be tried) - say why instead of just going back
+    pub fn auth_failed(&self, reason: &str) {
+        let imp = self.imp();
+        imp.entry_auth_key.add_css_class("error");
+        imp.label_auth_status.set_text(reason);
+        self.show_page("auth");
+    }
+
+    /// authenticated - offer to set the time and goal
+    pub fn show_setup(&self, goal: &ActivityGoal) {
+        let imp = self.imp();
+        imp.spin_goal.set_value(goal.steps as f64);
+        imp.label_setup_status.set_text("");
+        self.show_page("setup");
+    }
+
+    pub fn setup_failed(&self, error: &str) {
+        self.imp().label_setup_status.set_text(&i18n_f("Couldn't finish setting up the band: {}", &[error]));
+        self.show_page("setup");
+    }
+
+    pub fn show_done(&self) {
+        self.show_page("done");
+    }
+
+    fn show_working(&self, message: &str) {
+        self.imp().label_working.set_text(message);
+        self.show_page("working");
+    }
+
+    fn show_page(&self, page: &str) {
+        let imp = self.imp();
+        imp.stack_pages.set_visible_child_name(page);
+        // nothing to go back to from the first and last pages, or while waiting on the band
+        imp.btn_back.set_visible(matches!(page, "auth" | "setup"));
+    }
+}
+
+mod imp {
+    use std::{cell::RefCell, sync::OnceLock};
+
+    use gtk::{gio::ListStore, glib::{self, subclass::{InitializingObject, Signal}}, prelude::*, subclass::prelude::*, template_callbacks, Button, CompositeTemplate, Entry, Label, ListBox, ListBoxRow, SpinButton, Spinner, Stack, Switch, TemplateChild, Window};
+
+    use crate::{i18n::gettext, store::{STEP_GOAL_MAX, STEP_GOAL_MIN}};
+
+    use super::{decode_hex, DeviceRowObject, AUTH_KEY_LEN};
+
+    #[derive(CompositeTemplate, Default)]
+    #[template(resource = "/me/grimsteel/miband4-gtk/onboarding_dialog.ui")]
+    pub struct OnboardingDialog {
+        #[template_child]
+        pub stack_pages: TemplateChild<Stack>,
+        #[template_child]
+        pub btn_back: TemplateChild<Button>,
+        #[template_child]
+        pub btn_scan: TemplateChild<Button>,
+        #[template_child]
+        pub spinner_scan: TemplateChild<Spinner>,
+        #[template_child]
+        pub list_bands: TemplateChild<ListBox>,
+        #[template_child]
+        pub label_scan_status: TemplateChild<Label>,
+        #[template_child]
+        pub label_working: TemplateChild<Label>,
+        #[template_child]
+        pub label_auth_band: TemplateChild<Label>,
+        #[template_child]
+        pub entry_auth_key: TemplateChild<Entry>,
+        #[template_child]
+        pub label_auth_status: TemplateChild<Label>,
+        #[template_child]
+        pub switch_set_time: TemplateChild<Switch>,
+        #[template_child]
+        pub switch_set_goal: TemplateChild<Switch>,
+        #[template_child]
+        pub spin_goal: TemplateChild<SpinButton>,
+        #[template_child]
+        pub label_setup_status: TemplateChild<Label>,
+        /// the window's device list, which `list_bands` shows
+        pub devices: RefCell<Option<ListStore>>
+    }
+
+    #[template_callbacks]
+    impl OnboardingDialog {
+        #[template_callback]
+        fn handle_scan_clicked(&self) {
+            self.label_scan_status.set_text("");
+            self.obj().emit_by_name::<()>("scan", &[]);
+        }
+
+        #[template_callback]
+        fn handle_band_activated(&self, row: &ListBoxRow) {
+            let Some(device) = self.devices.borrow().as_ref()
+                .and_then(|devices| devices.item(row.index() as u32))
+                .and_downcast::<DeviceRowObject>() else { return };
+            self.obj().show_connecting();
+            self.obj().emit_by_name::<()>("band-chosen", &[&device.address()]);
+        }
+
+        #[template_callback]
+        fn handle_other_ways_clicked(&self) {
+            self.obj().emit_by_name::<()>("find-auth-key", &[]);
+        }
+
+        #[template_callback]
+        fn handle_test_clicked(&self) {
+            self.try_auth_key();
+        }
+
+        #[template_callback]
+        fn handle_skip_auth_clicked(&self) {
+            // it can be set later from the band's page
+            self.obj().close();
+        }
+
+        #[template_callback]
+        fn handle_finish_clicked(&self) {
+            let goal = if self.switch_set_goal.is_active() { self.spin_goal.value_as_int() as u32 } else { 0 };
+            self.obj().show_working(&gettext("Setting up the band…"));
+            self.obj().emit_by_name::<()>("finish", &[&self.switch_set_time.is_active(), &goal]);
+        }
+
+        #[template_callback]
+        fn handle_back_clicked(&self) {
+            let page = match self.stack_pages.visible_child_name().as_deref() {
+                Some("setup") => "auth",
+                _ => "scan"
+            };
+            self.obj().show_page(page);
+        }
+
+        #[template_callback]
+        fn handle_close_clicked(&self) {
+            self.obj().close();
+        }
+
+        /// check it looks like a key before bothering the band with it
+        pub(super) fn try_auth_key(&self) {
+            let auth_key = self.entry_auth_key.text().trim().to_string();
+            if !decode_hex(&auth_key).is_some_and(|key| key.len() == AUTH_KEY_LEN) {
+                self.obj().auth_failed(&gettext("An auth key is 32 hexadecimal characters (0-9 and a-f)"));
+                return;
+            }
+            self.obj().show_working(&gettext("Checking the auth key with the band…"));
+            self.obj().emit_by_name::<()>("test-auth-key", &[&auth_key]);
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for OnboardingDialog {
+        const NAME: &'static str = "MiBand4OnboardingDialog";
+        type Type = super::OnboardingDialog;
+        type ParentType = Window;
+
+        fn class_init(class: &mut Self::Class) {
+            class.bind_template();
+            class.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for OnboardingDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.spin_goal.set_range(STEP_GOAL_MIN as f64, STEP_GOAL_MAX as f64);
+            self.spin_goal.set_increments(500.0, 1000.0);
+            self.switch_set_goal.bind_property("active", &*self.spin_goal, "sensitive").sync_create().build();
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![
+                    // look for bands nearby
+                    Signal::builder("scan").build(),
+                    // param is the mac address of the band to connect to
+                    Signal::builder("band-chosen").param_types([String::static_type()]).build(),
+                    // open the auth key dialog, for the other ways of getting the key
+                    Signal::builder("find-auth-key").build(),
+                    // param is the auth key (already checked to be 32 hex chars)
+                    Signal::builder("test-auth-key").param_types([String::static_type()]).build(),
+                    // params are whether to set the time, and the step goal (0 to leave it)
+                    Signal::builder("finish").param_types([bool::static_type(), u32::static_type()]).build()
+                ]
+            })
+        }
+    }
+    impl WidgetImpl for OnboardingDialog {}
+    impl WindowImpl for OnboardingDialog {}
+}
@@ -0,0 +1,160 @@
+use futures::channel::oneshot;
+use gtk::{glib::{self, Object}, prelude::*, subclass::prelude::*, Accessible, Buildable, ConstraintTarget, Native, Root, ShortcutManager, Widget, Window};
+
+use crate::i18n::gettext;
+
+glib::wrapper! {
+    pub struct PairingDialog(ObjectSubclass<imp::PairingDialog>)
+        // https://docs.gtk.org/gtk4/class.Window.html#hierarchy
+        @extends Window, Widget,
+        @implements Accessible, Buildable, ConstraintTarget, Native, Root, ShortcutManager;
+}
+
+/// what the user has to type in, if anything
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PairingInput {
+    None,
+    /// 6 digits
+    Passkey,
+    PinCode
+}
+
+impl PairingDialog {
+    pub fn new() -> Self {
+        Object::builder().build()
+    }
+
+    /// show `message` and wait for them to answer
+    /// returns what they typed in (empty for `PairingInput::None`), or None if they rejected it
+    pub async fn ask(&self, message: &str, input: PairingInput, accept_label: &str) -> Option<String> {
+        let imp = self.imp();
+        // a new request replaces the old one
+        self.respond(None);
+
+        imp.label_message.set_text(message);
+        imp.entry_code.set_text("");
+        imp.entry_code.remove_css_class("error");
+        imp.entry_code.set_visible(input != PairingInput::None);
+        imp.entry_code.set_placeholder_text(Some(if input == PairingInput::Passkey { "6 digit passkey" } else { "PIN code" }));
+        imp.input.set(input);
+        imp.btn_accept.set_label(accept_label);
+        imp.btn_accept.set_visible(true);
+        imp.btn_reject.set_label(&gettext("Reject"));
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        imp.reply.replace(Some(reply_tx));
+        self.present();
+        reply_rx.await.ok().flatten()
+    }
+
+    /// show a code that has to be typed in on the other device
+    pub fn show_code(&self, message: &str) {
+        let imp = self.imp();
+        self.respond(None);
+        imp.label_message.set_text(message);
+        imp.entry_code.set_visible(false);
+        imp.btn_accept.set_visible(false);
+        imp.btn_reject.set_label(&gettext("Close"));
+        self.present();
+    }
+
+    /// BlueZ cancelled the request
+    pub fn cancel(&self) {
+        self.respond(None);
+        self.set_visible(false);
+    }
+
+    fn respond(&self, response: Option<String>) {
+        if let Some(reply) = self.imp().reply.take() {
+            let _ = reply.send(response);
+        }
+    }
+}
+
+mod imp {
+    use std::cell::{Cell, RefCell};
+
+    use futures::channel::oneshot;
+    use gtk::{glib::{self, subclass::InitializingObject, Propagation}, prelude::*, subclass::prelude::*, template_callbacks, Button, CompositeTemplate, Entry, Label, TemplateChild, Window};
+
+    use super::PairingInput;
+
+    #[derive(CompositeTemplate)]
+    #[template(resource = "/me/grimsteel/miband4-gtk/pairing_dialog.ui")]
+    pub struct PairingDialog {
+        #[template_child]
+        pub label_message: TemplateChild<Label>,
+        #[template_child]
+        pub entry_code: TemplateChild<Entry>,
+        #[template_child]
+        pub btn_reject: TemplateChild<Button>,
+        #[template_child]
+        pub btn_accept: TemplateChild<Button>,
+
+        pub input: Cell<PairingInput>,
+        pub reply: RefCell<Option<oneshot::Sender<Option<String>>>>
+    }
+
+    impl Default for PairingDialog {
+        fn default() -> Self {
+            Self {
+                label_message: Default::default(),
+                entry_code: Default::default(),
+                btn_reject: Default::default(),
+                btn_accept: Default::default(),
+                input: Cell::new(PairingInput::None),
+                reply: Default::default()
+            }
+        }
+    }
+
+    #[template_callbacks]
+    impl PairingDialog {
+        #[template_callback]
+        fn handle_reject_clicked(&self) {
+            self.obj().close();
+        }
+
+        #[template_callback]
+        fn handle_accept_clicked(&self) {
+            let code = self.entry_code.text().trim().to_string();
+            let valid = match self.input.get() {
+                PairingInput::None => true,
+                PairingInput::Passkey => code.len() <= 6 && code.parse::<u32>().is_ok(),
+                PairingInput::PinCode => !code.is_empty()
+            };
+            if !valid {
+                self.entry_code.add_css_class("error");
+                return;
+            }
+            self.obj().respond(Some(code));
+            self.obj().set_visible(false);
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PairingDialog {
+        const NAME: &'static str = "MiBand4PairingDialog";
+        type Type = super::PairingDialog;
+        type ParentType = Window;
+
+        fn class_init(class: &mut Self::Class) {
+            class.bind_template();
+            class.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for PairingDialog {}
+    impl WidgetImpl for PairingDialog {}
+    impl WindowImpl for PairingDialog {
+        // closing it rejects the request
+        fn close_request(&self) -> Propagation {
+            self.obj().respond(None);
+            self.parent_close_request()
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use chrono::{Datelike, Local, Months, NaiveDate, TimeDelta};
+use gtk::{cairo::Context, glib::{self, Object}, prelude::*, subclass::prelude::*, Accessible, Buildable, ConstraintTarget, Native, Root, ShortcutManager, TextDirection, Widget, Window};
+
+use crate::{i18n::i18n_f, store::{ActivityGoal, DayStats, DistanceUnit}};
+
+/// how many bars each period shows
+const BUCKETS: u32 = 12;
+/// the daily view shows two weeks instead
+const DAILY_BUCKETS: i64 = 14;
+const BAR_COLOR: (f64, f64, f64) = (0.21, 0.52, 0.89);
+const GOAL_COLOR: (f64, f64, f64) = (0.88, 0.11, 0.14);
+/// room for the axis labels (on the right for right-to-left languages)
+const MARGIN_AXIS: f64 = 52.0;
+const MARGIN_BOTTOM: f64 = 22.0;
+const MARGIN_TOP: f64 = 12.0;
+
+glib::wrapper! {
+    pub struct StatisticsDialog(ObjectSubclass<imp::StatisticsDialog>)
+        // https://docs.gtk.org/gtk4/class.Window.html#hierarchy
+        @extends Window, Widget,
+        @implements Accessible, Buildable, ConstraintTarget, Native, Root, ShortcutManager;
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Metric {
+    Steps,
+    Calories,
+    Distance,
+    HeartRate
+}
+
+impl Metric {
+    /// same order as the metric dropdown
+    fn from_index(index: u32) -> Self {
+        match index {
+            1 => Self::Calories,
+            2 => Self::Distance,
+            3 => Self::HeartRate,
+            _ => Self::Steps
+        }
+    }
+
+    fn value(&self, day: &DayStats, unit: DistanceUnit, raw: bool) -> Option<f64> {
+        match self {
+            Self::Steps => Some(day.steps as f64),
+            Self::Calories => Some(day.calories as f64),
+            Self::Distance => Some(match unit {
+                DistanceUnit::Metric => day.meters as f64 / 1000.0,
+                DistanceUnit::Imperial => day.meters as f64 / 1609.344
+            }),
+            Self::HeartRate if raw => day.avg_heart_rate_raw,
+            Self::HeartRate => day.avg_heart_rate
+        }
+    }
+
+    fn unit_label(&self, unit: DistanceUnit) -> &'static str {
+        match (self, unit) {
+            (Self::Steps, _) => "steps",
+            (Self::Calories, _) => "kcal",
+            (Self::Distance, DistanceUnit::Metric) => "km",
+            (Self::Distance, DistanceUnit::Imperial) => "mi",
+            (Self::HeartRate, _) => "bpm"
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Period {
+    Day,
+    Week,
+    Month
+}
+
+impl Period {
+    /// same order as the period dropdown
+    fn from_index(index: u32) -> Self {
+        match index {
+            1 => Self::Week,
+            2 => Self::Month,
+            _ => Self::Day
+        }
+    }
+
+    /// (first day, day after the last day, label), oldest first
+    fn buckets(&self, today: NaiveDate) -> Vec<(NaiveDate, NaiveDate, String)> {
+        match self {
+            Self::Day => (0..DAILY_BUCKETS).rev()
+                .map(|i| today - TimeDelta::days(i))
+                .map(|day| (day, day + TimeDelta::days(1), day.format("%d").to_string()))
+                .collect(),
+            Self::Week => {
+                let this_week = today - TimeDelta::days(today.weekday().num_days_from_monday() as i64);
+                (0..BUCKETS as i64).rev()
+                    .map(|i| this_week - TimeDelta::weeks(i))
+                    .map(|week| (week, week + TimeDelta::weeks(1), week.format("%m/%d").to_string()))
+                    .collect()
+            },
+            Self::Month => {
+                let this_month = today.with_day(1).unwrap_or(today);
+                (0..BUCKETS).rev()
+                    .filter_map(|i| this_month.checked_sub_months(Months::new(i)))
+                    .map(|month| (month, month + Months::new(1), month.format("%b").to_string()))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// the average daily value of each bucket (None if nothing was recorded)
+/// `raw` includes the heart rates the filter rejected
+fn bucket_values(days: &[DayStats], metric: Metric, period: Period, unit: DistanceUnit, raw: bool) -> Vec<(String, Option<f64>)> {
+    period.buckets(Local::now().date_naive()).into_iter().map(|(start, end, label)| {
+        let values: Vec<f64> = days.iter()
+            .filter(|day| day.date >= start && day.date < end)
+            .filter_map(|day| metric.value(day, unit, raw))
+            .collect();
+        let average = if values.is_empty() { None } else { Some(values.iter().sum::<f64>() / values.len() as f64) };
+        (label, average)
+    }).collect()
+}
+
+fn format_value(value: f64, metric: Metric) -> String {
+    match metric {
+        Metric::Distance => format!("{value:.1}"),
+        _ => format!("{value:.0}")
+    }
+}
+
+impl StatisticsDialog {
+    pub fn new() -> Self {
+        Object::builder().build()
+    }
+
+    /// show this history (oldest first)
+    /// the step goal is drawn as a line on the step charts
+    pub fn set_history(&self, days: Vec<DayStats>, goal: Option<&ActivityGoal>, unit: DistanceUnit) {
+        let imp = self.imp();
+        imp.days.replace(days);
+        imp.step_goal.set(goal.map(|goal| goal.steps));
+        imp.distance_unit.set(unit);
+        self.refresh();
+    }
+
+    fn metric(&self) -> Metric {
+        Metric::from_index(self.imp().dropdown_metric.selected())
+    }
+
+    fn period(&self) -> Period {
+        Period::from_index(self.imp().dropdown_period.selected())
+    }
+
+    fn refresh(&self) {
+        let imp = self.imp();
+        let metric = self.metric();
+        let unit = imp.distance_unit.get();
+        // only heart rates are filtered
+        imp.check_raw.set_visible(metric == Metric::HeartRate);
+        let values = bucket_values(&imp.days.borrow(), metric, self.period(), unit, imp.check_raw.is_active());
+
+        let recorded: Vec<f64> = values.iter().filter_map(|(_, value)| *value).collect();
+        imp.label_empty.set_visible(recorded.is_empty());
+        if recorded.is_empty() {
+            imp.label_summary.set_text("");
+        } else {
+            let average = recorded.iter().sum::<f64>() / recorded.len() as f64;
+            imp.label_summary.set_text(&i18n_f("Average: {} {} per day", &[&format_value(average, metric), &metric.unit_label(unit)]));
+        }
+        imp.chart.queue_draw();
+    }
+
+    fn draw(&self, cr: &Context, width: i32, height: i32) {
+        let imp = self.imp();
+        let metric = self.metric();
+        let values = bucket_values(&imp.days.borrow(), metric, self.period(), imp.distance_unit.get(), imp.check_raw.is_active());
+        let goal = imp.step_goal.get().filter(|_| metric == Metric::Steps).map(|steps| steps as f64);
+
+        let max = values.iter()
+            .filter_map(|(_, value)| *value)
+            .chain(goal)
+            .fold(0.0, f64::max);
+        if max <= 0.0 { return }
+        // leave some headroom above the tallest bar
+        let max = max * 1.1;
+
+        let plot_width = width as f64 - MARGIN_AXIS;
+        let plot_height = height as f64 - MARGIN_BOTTOM - MARGIN_TOP;
+        let y_for = |value: f64| MARGIN_TOP + plot_height * (1.0 - value / max);
+
+        // right-to-left languages read the chart from the right - the axis goes there, and the oldest bar is rightmost
+        let rtl = imp.chart.direction() == TextDirection::Rtl;
+        let (plot_start, axis_x) = if rtl { (0.0, plot_width + 4.0) } else { (MARGIN_AXIS, 0.0) };
+        let plot_end = plot_start + plot_width;
+
+        // match the theme's text color
+        let text = imp.chart.color();
+        cr.set_font_size(11.0);
+
+        // grid lines + y axis labels
+        for i in 0..=4 {
+            let value = max * i as f64 / 4.0;
+            let y = y_for(value);
+            cr.set_source_rgba(text.red() as f64, text.green() as f64, text.blue() as f64, 0.15);
+            cr.set_line_width(1.0);
+            cr.move_to(plot_start, y);
+            cr.line_to(plot_end, y);
+            let _ = cr.stroke();
+
+            cr.set_source_rgba(text.red() as f64, text.green() as f64, text.blue() as f64, 0.7);
+            cr.move_to(axis_x, y + 4.0);
+            let _ = cr.show_text(&format_value(value, metric));
+        }
+
+        // bars + x axis labels
+        let slot = plot_width / values.len() as f64;
+        for (i, (label, value)) in values.iter().enumerate() {
+            let x = if rtl { plot_end - slot * (i + 1) as f64 } else { plot_start + slot * i as f64 };
+            if let Some(value) = value {
+                let (r, g, b) = BAR_COLOR;
+                cr.set_source_rgb(r, g, b);
+                let y = y_for(*value);
+                cr.rectangle(x + slot * 0.15, y, slot * 0.7, MARGIN_TOP + plot_height - y);
+                let _ = cr.fill();
+            }
+
+            cr.set_source_rgba(text.red() as f64, text.green() as f64, text.blue() as f64, 0.7);
+            if let Ok(extents) = cr.text_extents(label) {
+                cr.move_to(x + (slot - extents.width()) / 2.0, height as f64 - 6.0);
+                let _ = cr.show_text(label);
+            }
+        }
+
+        if let Some(goal) = goal {
+            let (r, g, b) = GOAL_COLOR;
+            cr.set_source_rgb(r, g, b);
+            cr.set_line_width(2.0);
+            cr.set_dash(&[6.0, 4.0], 0.0);
+            let y = y_for(goal);
+            cr.move_to(plot_start, y);
+            cr.line_to(plot_end, y);
+            let _ = cr.stroke();
+        }
+    }
+}
+
+mod imp {
+    use std::cell::{Cell, RefCell};
+
+    use gtk::{glib::{self, clone, subclass::InitializingObject}, prelude::*, subclass::prelude::*, template_callbacks, CheckButton, CompositeTemplate, DrawingArea, DropDown, Label, TemplateChild, Window};
+
+    use crate::store::{DayStats, DistanceUnit};
+
+    #[derive(CompositeTemplate)]
+    #[template(resource = "/me/grimsteel/miband4-gtk/statistics_dialog.ui")]
+    pub struct StatisticsDialog {
+        #[template_child]
+        pub dropdown_metric: TemplateChild<DropDown>,
+        #[template_child]
+        pub dropdown_period: TemplateChild<DropDown>,
+        #[template_child]
+        pub check_raw: TemplateChild<CheckButton>,
+        #[template_child]
+        pub chart: TemplateChild<DrawingArea>,
+        #[template_child]
+        pub label_summary: TemplateChild<Label>,
+        #[template_child]
+        pub label_empty: TemplateChild<Label>,
+
+        pub days: RefCell<Vec<DayStats>>,
+        pub step_goal: Cell<Option<u16>>,
+        pub distance_unit: Cell<DistanceUnit>
+    }
+
+    impl Default for StatisticsDialog {
+        fn default() -> Self {
+            Self {
+                dropdown_metric: Default::default(),
+                dropdown_period: Default::default(),
+                check_raw: Default::default(),
+                chart: Default::default(),
+                label_summary: Default::default(),
+                label_empty: Default::default(),
+                days: Default::default(),
+                step_goal: Default::default(),
+                distance_unit: Cell::new(DistanceUnit::Metric)
+            }
+        }
+    }
+
+    #[template_callbacks]
+    impl StatisticsDialog {
+        #[template_callback]
+        fn handle_selection_changed(&self) {
+            self.obj().refresh();
+        }
+
+        #[template_callback]
+        fn handle_close_clicked(&self) {
+            self.obj().close();
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for StatisticsDialog {
+        const NAME: &'static str = "MiBand4StatisticsDialog";
+        type Type = super::StatisticsDialog;
+        type ParentType = Window;
+
+        fn class_init(class: &mut Self::Class) {
+            class.bind_template();
+            class.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for StatisticsDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            self.chart.set_draw_func(clone!(@weak obj => move |_, cr, width, height| {
+                obj.draw(cr, width, height);
+            }));
+        }
+    }
+    impl WidgetImpl for StatisticsDialog {}
+    impl WindowImpl for StatisticsDialog {}
+}
@@ -0,0 +1,147 @@
+use chrono::{DateTime, Local};
+use gtk::{glib::{self, Object}, prelude::*, subclass::prelude::*, Accessible, Buildable, ConstraintTarget, Native, Root, ShortcutManager, Widget, Window};
+
+use crate::{band::FetchProgress, i18n::{gettext, i18n_f}, store::TimeFormat, utils::format_date};
+
+glib::wrapper! {
+    pub struct SyncProgressDialog(ObjectSubclass<imp::SyncProgressDialog>)
+        // https://docs.gtk.org/gtk4/class.Window.html#hierarchy
+        @extends Window, Widget,
+        @implements Accessible, Buildable, ConstraintTarget, Native, Root, ShortcutManager;
+}
+
+impl SyncProgressDialog {
+    pub fn new() -> Self {
+        Object::builder().build()
+    }
+
+    /// show which buttons make sense right now
+    fn set_running(&self, running: bool) {
+        let imp = self.imp();
+        imp.spinner.set_spinning(running);
+        imp.spinner.set_visible(running);
+        imp.btn_cancel.set_visible(running);
+        imp.btn_close.set_visible(!running);
+    }
+
+    /// a new sync is starting with the data recorded after `since`
+    pub fn start(&self, since: DateTime<Local>, time_format: TimeFormat) {
+        let imp = self.imp();
+        self.set_running(true);
+        imp.btn_resume.set_visible(false);
+        imp.label_status.set_text(&i18n_f("Requesting activity since {}", &[&format_date(&since, time_format)]));
+        imp.label_samples.set_text("0");
+        imp.label_current.set_text("-");
+        imp.label_rate.set_text("-");
+    }
+
+    /// `rate` is in samples per second
+    pub fn set_progress(&self, progress: &FetchProgress, rate: f64, time_format: TimeFormat) {
+        let imp = self.imp();
+        imp.label_status.set_text(&gettext("Downloading activity"));
+        imp.label_samples.set_text(&progress.activity_samples().to_string());
+        imp.label_current.set_text(&format_date(&progress.activity_time(), time_format));
+        imp.label_rate.set_text(&format!("{rate:.0} samples/s"));
+    }
+
+    pub fn set_finished(&self, samples: usize) {
+        self.set_running(false);
+        self.imp().label_status.set_text(&i18n_f("Done - {} new samples", &[&samples.to_string()]));
+    }
+
+    /// the samples received before cancelling are kept, so the next sync continues from `resume_from`
+    pub fn set_cancelled(&self, resume_from: Option<DateTime<Local>>, time_format: TimeFormat) {
+        let imp = self.imp();
+        self.set_running(false);
+        imp.btn_resume.set_visible(true);
+        let status = match resume_from {
+            Some(time) => i18n_f("Cancelled - the next sync continues from {}", &[&format_date(&time, time_format)]),
+            None => "Cancelled".to_string()
+        };
+        imp.label_status.set_text(&status);
+    }
+
+    pub fn set_failed(&self, error: &str) {
+        let imp = self.imp();
+        self.set_running(false);
+        imp.btn_resume.set_visible(true);
+        imp.label_status.set_text(&i18n_f("Failed: {}", &[&error.to_string()]));
+    }
+}
+
+mod imp {
+    use std::sync::OnceLock;
+
+    use gtk::{glib::{self, subclass::{InitializingObject, Signal}}, prelude::*, subclass::prelude::*, template_callbacks, Button, CompositeTemplate, Label, Spinner, TemplateChild, Window};
+
+    #[derive(CompositeTemplate, Default)]
+    #[template(resource = "/me/grimsteel/miband4-gtk/sync_progress_dialog.ui")]
+    pub struct SyncProgressDialog {
+        #[template_child]
+        pub spinner: TemplateChild<Spinner>,
+        #[template_child]
+        pub label_status: TemplateChild<Label>,
+        #[template_child]
+        pub label_samples: TemplateChild<Label>,
+        #[template_child]
+        pub label_current: TemplateChild<Label>,
+        #[template_child]
+        pub label_rate: TemplateChild<Label>,
+        #[template_child]
+        pub btn_cancel: TemplateChild<Button>,
+        #[template_child]
+        pub btn_resume: TemplateChild<Button>,
+        #[template_child]
+        pub btn_close: TemplateChild<Button>
+    }
+
+    #[template_callbacks]
+    impl SyncProgressDialog {
+        #[template_callback]
+        fn handle_cancel_clicked(&self) {
+            self.obj().emit_by_name::<()>("cancel", &[]);
+        }
+
+        #[template_callback]
+        fn handle_resume_clicked(&self) {
+            self.obj().emit_by_name::<()>("resume", &[]);
+        }
+
+        #[template_callback]
+        fn handle_close_clicked(&self) {
+            self.obj().close();
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SyncProgressDialog {
+        const NAME: &'static str = "MiBand4SyncProgressDialog";
+        type Type = super::SyncProgressDialog;
+        type ParentType = Window;
+
+        fn class_init(class: &mut Self::Class) {
+            class.bind_template();
+            class.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for SyncProgressDialog {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![
+                    // stop the current sync, keeping what's been received
+                    Signal::builder("cancel").build(),
+                    // start syncing again from where it stopped
+                    Signal::builder("resume").build()
+                ]
+            })
+        }
+    }
+    impl WidgetImpl for SyncProgressDialog {}
+    impl WindowImpl for SyncProgressDialog {}
+}
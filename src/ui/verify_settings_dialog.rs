@@ -0,0 +1,179 @@
+use gtk::{glib::{self, clone, Object}, prelude::*, subclass::prelude::*, Accessible, Align, Box as GtkBox, Buildable, Button, ConstraintTarget, Grid, Label, ListBoxRow, Native, Orientation, Root, ShortcutManager, Widget, Window};
+
+use crate::i18n::{gettext, i18n_f};
+
+glib::wrapper! {
+    pub struct VerifySettingsDialog(ObjectSubclass<imp::VerifySettingsDialog>)
+        // https://docs.gtk.org/gtk4/class.Window.html#hierarchy
+        @extends Window, Widget,
+        @implements Accessible, Buildable, ConstraintTarget, Native, Root, ShortcutManager;
+}
+
+/// a single setting compared between the store and the band
+#[derive(Debug)]
+pub struct SettingDiff {
+    pub id: &'static str,
+    pub label: &'static str,
+    /// the value stored on this computer
+    pub stored: String,
+    /// the value on the band, if it can be read back
+    pub band: Option<String>,
+    /// `None` if we can't tell
+    pub in_sync: Option<bool>,
+    /// whether the band's value can be saved to the store
+    pub adoptable: bool
+}
+
+impl SettingDiff {
+    /// compare a stored setting with the value read back from the band
+    /// `band` is `None` if the band couldn't report it
+    pub fn compare<T: PartialEq>(id: &'static str, label: &'static str, stored: Option<T>, band: Option<T>, describe: impl Fn(&T) -> String) -> Self {
+        Self {
+            id,
+            label,
+            stored: stored.as_ref().map(&describe).unwrap_or_else(|| gettext("not set")),
+            in_sync: band.as_ref().map(|band| stored.as_ref() == Some(band)),
+            adoptable: band.is_some(),
+            band: band.as_ref().map(&describe)
+        }
+    }
+}
+
+impl VerifySettingsDialog {
+    pub fn new() -> Self {
+        Object::builder().build()
+    }
+
+    /// replace the reconciliation list with these settings
+    pub fn set_diffs(&self, diffs: Vec<SettingDiff>) {
+        let list = &self.imp().list_settings;
+        while let Some(child) = list.first_child() {
+            list.remove(&child);
+        }
+        for diff in diffs {
+            list.append(&self.build_row(diff));
+        }
+        self.update_all_resolved();
+    }
+
+    fn build_row(&self, diff: SettingDiff) -> ListBoxRow {
+        let grid = Grid::new();
+        grid.set_row_spacing(4);
+        grid.set_column_spacing(12);
+
+        let label = Label::new(Some(diff.label));
+        label.set_halign(Align::Start);
+        label.set_hexpand(true);
+        label.add_css_class("heading");
+        grid.attach(&label, 0, 0, 1, 1);
+
+        let (status, status_class) = match diff.in_sync {
+            Some(true) => ("In sync", "success"),
+            Some(false) => ("Differs", "warning"),
+            None => ("Unknown", "dim-label")
+        };
+        let status_label = Label::new(Some(status));
+        status_label.set_halign(Align::End);
+        status_label.add_css_class(status_class);
+        grid.attach(&status_label, 1, 0, 1, 1);
+
+        let stored = Label::new(Some(&i18n_f("On this computer: {}", &[&diff.stored])));
+        stored.set_halign(Align::Start);
+        stored.set_wrap(true);
+        grid.attach(&stored, 0, 1, 2, 1);
+
+        let band = Label::new(Some(&i18n_f("On the band: {}", &[&diff.band.clone().unwrap_or_else(|| gettext("can't be read from the band"))])));
+        band.set_halign(Align::Start);
+        band.set_wrap(true);
+        band.add_css_class("dim-label");
+        grid.attach(&band, 0, 2, 2, 1);
+
+        let buttons = GtkBox::new(Orientation::Horizontal, 8);
+        buttons.set_halign(Align::End);
+        let push = Button::with_label(&gettext("Push mine"));
+        let adopt = Button::with_label(&gettext("Adopt band's"));
+        adopt.set_sensitive(diff.adoptable && diff.band.is_some());
+        let ignore = Button::with_label(&gettext("Ignore"));
+        ignore.add_css_class("flat");
+        buttons.append(&ignore);
+        buttons.append(&adopt);
+        buttons.append(&push);
+        grid.attach(&buttons, 0, 3, 2, 1);
+
+        let row = ListBoxRow::new();
+        row.set_activatable(false);
+        row.set_child(Some(&grid));
+
+        let id = diff.id.to_string();
+        for (button, action) in [(push, "push"), (adopt, "adopt"), (ignore, "ignore")] {
+            button.connect_clicked(clone!(@weak self as dialog, @weak row, @strong id => move |_button| {
+                if action != "ignore" {
+                    dialog.emit_by_name::<()>("resolve-setting", &[&id, &action.to_string()]);
+                }
+                // this setting is resolved
+                dialog.imp().list_settings.remove(&row);
+                dialog.update_all_resolved();
+            }));
+        }
+
+        row
+    }
+
+    fn update_all_resolved(&self) {
+        let imp = self.imp();
+        imp.label_all_resolved.set_visible(imp.list_settings.first_child().is_none());
+    }
+}
+
+mod imp {
+    use std::sync::OnceLock;
+
+    use gtk::{glib::{self, subclass::{InitializingObject, Signal}}, prelude::*, subclass::prelude::*, template_callbacks, CompositeTemplate, Label, ListBox, TemplateChild, Window};
+
+    #[derive(CompositeTemplate, Default)]
+    #[template(resource = "/me/grimsteel/miband4-gtk/verify_settings_dialog.ui")]
+    pub struct VerifySettingsDialog {
+        #[template_child]
+        pub list_settings: TemplateChild<ListBox>,
+        #[template_child]
+        pub label_all_resolved: TemplateChild<Label>
+    }
+
+    #[template_callbacks]
+    impl VerifySettingsDialog {
+        #[template_callback]
+        fn handle_close_clicked(&self) {
+            self.obj().close();
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VerifySettingsDialog {
+        const NAME: &'static str = "MiBand4VerifySettingsDialog";
+        type Type = super::VerifySettingsDialog;
+        type ParentType = Window;
+
+        fn class_init(class: &mut Self::Class) {
+            class.bind_template();
+            class.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for VerifySettingsDialog {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![
+                    // params are the setting id and the action ("push" or "adopt")
+                    Signal::builder("resolve-setting").param_types([String::static_type(), String::static_type()]).build()
+                ]
+            })
+        }
+    }
+    impl WidgetImpl for VerifySettingsDialog {}
+    impl WindowImpl for VerifySettingsDialog {}
+}
@@ -1,23 +1,131 @@
-use std::{cell::RefCell, collections::{HashMap, HashSet}, sync::{Mutex, Once}, time::Duration};
+use std::{cell::{Cell, RefCell}, cmp::Ordering, collections::{HashMap, HashSet, VecDeque}, io, rc::Rc, time::{Duration, Instant}};
 
 use async_io::Timer;
-use async_lock::{OnceCell, RwLock};
-use chrono::Local;
-use futures::{channel::mpsc::{self, Sender}, pin_mut, select, stream::SelectAll, SinkExt, StreamExt};
+use async_lock::{Mutex, OnceCell, RwLock};
+use chrono::{DateTime, Local, TimeDelta, TimeZone, Timelike};
+use futures::{channel::mpsc::{self, Sender}, future::join_all, pin_mut, select, stream::{self, SelectAll}, SinkExt, StreamExt};
 use gtk::{
-    gio::{ActionGroup, ActionMap, ListStore}, glib::{self, clone, object_subclass, spawn_future_local, subclass::InitializingObject, Object}, prelude::*, subclass::prelude::*, template_callbacks, Accessible, AlertDialog, Application, ApplicationWindow, Buildable, Button, CompositeTemplate, ConstraintTarget, EditableLabel, Label, ListItem, ListView, Native, NoSelection, Root, ShortcutManager, SignalListItemFactory, Stack, Widget, Window
+    gio::{self, ActionGroup, ActionMap, ListStore, Notification}, glib::{self, clone, object_subclass, spawn_future_local, subclass::InitializingObject, unix_signal_add_local_once, Object, Propagation, VariantTy}, prelude::*, subclass::prelude::*, template_callbacks, Accessible, AlertDialog, Box as GtkBox, Buildable, Button, CompositeTemplate, CssProvider, ConstraintTarget, DirectionType, EditableLabel, FileDialog, Image, Label, ListItem, ListView, MenuButton, Native, NoSelection, Root, ShortcutManager, PasswordEntry, SpinButton, Spinner, PrintOperation, PrintOperationAction, PrintOperationResult, SignalListItemFactory, SortListModel, SorterChange, CustomSorter, Stack, Switch, Widget, Window, style_context_add_provider_for_display, style_context_remove_provider_for_display, STYLE_PROVIDER_PRIORITY_APPLICATION
 };
-use log::error;
+use adw::{subclass::prelude::*, Application, ApplicationWindow, PreferencesWindow, Toast, ToastOverlay, ToastPriority};
+use log::{debug, error, warn};
 use zbus::zvariant::OwnedObjectPath;
 
-use crate::{band::{self, Alert, AlertType, BandChangeEvent, BandError, MiBand, MusicEvent}, bluez::{BluezSession, DiscoveredDevice, DiscoveredDeviceEvent}, mpris::watch_mpris, notifications::stream_notifications, store::{self, ActivityGoal, BandLock, Store}, utils::decode_hex};
+use crate::{app_lock::{authenticate_system, PinHash}, alert_sources::{AlertSource, AlertSourceError, CalendarSource, NotificationSource}, band_service::{serve_band_service, BandServiceHandle, SearchResult}, band::{self, ActivitySample, Alert, AlertType, BandChangeEvent, BandState, BatteryStatus, BandError, CurrentActivity, DeviceEvent, MiBand, MusicEvent, WorkoutSummary, HEART_RATE_PING_INTERVAL}, bluez::{AgentRequest, BluezSession, DiscoveredDevice, DiscoveredDeviceEvent}, briefing::{compose_briefing, compose_sleep_summary, compose_weather}, calendar::next_event, export::{activity_to_csv, export_workouts, ExportFormat}, heart_rate::serve_heart_rate, location::current_location, metric_sinks::{check_webhook_url, HistorySink, MetricSample, MetricSink, PrometheusSink, SleepEvent, SleepWebhookSink, WebhookSink}, movement::{in_meeting, in_schedule, MovementTracker, MOVEMENT_CHECK_INTERVAL}, mpris::watch_mpris, metrics, notifications::close_notification, power::{watch_power, PowerState}, replay::replay, report::{WeeklyReport, REPORT_DAYS}, sleep::{detect_sleep, SleepSession}, soak::{self, SOAK_INTERVAL}, store::{self, ActivityGoal, ACTIVITY_HISTORY_DAYS, AppLock, BandLock, BatteryAlert, DateDisplay, DisplayFormat, DistanceUnit, Forwarding, HeartRateSharing, IdleDisconnect, MetricSinks, MorningBriefing, MovementGate, PowerThrottle, QuickToggles, SleepSummary, Cached, CachedValues, Store, SyncSchedule, TemperatureUnit, TimeFormat, TravelMode, WearDetection}, trace, utils::{decode_hex, format_date, format_distance, format_recent_date, signal_icon_name}, i18n::{gettext, i18n_f, i18n_nf}, toggles::{serve_toggles, Toggle}, weather::{fetch_weather, WeatherFormat}};
 
-use super::{auth_key_dialog::AuthKeyDialog, device_info::{card::DeviceInfoCard, card_implementations::{ACTIVITY_GOAL_ITEMS, ACTIVITY_ITEMS, BAND_LOCK_ITEMS, BATTERY_ITEMS, DEVICE_INFO_ITEMS, TIME_ITEMS}}, device_row::DeviceRow, device_row_object::DeviceRowObject};
+use super::{auth_key_dialog::AuthKeyDialog, verify_settings_dialog::{SettingDiff, VerifySettingsDialog}, workout_history_dialog::WorkoutHistoryDialog, alert_history_dialog::{AlertHistoryDialog, SentAlert}, statistics_dialog::StatisticsDialog, sync_progress_dialog::SyncProgressDialog, bulk_operation_dialog::BulkOperationDialog, mini_window::MiniWindow, onboarding_dialog::OnboardingDialog, pairing_dialog::{PairingDialog, PairingInput}, device_info::{card::{DeviceInfoCard, InfoItemValue, InfoItemValues}, card_implementations::{TestAlert, ACTIVITY_GOAL_ITEMS, ACTIVITY_ITEMS, APP_LOCK_ITEMS, BAND_LOCK_ITEMS, BATTERY_ITEMS, BRIEFING_ITEMS, DEVELOPER_ITEMS, DEVICE_INFO_ITEMS, DISPLAY_FORMAT_ITEMS, FORWARDING_ITEMS, HEART_RATE_ITEMS, METRIC_SINK_ITEMS, MOVEMENT_ITEMS, SLEEP_SUMMARY_ITEMS, TEST_ALERT_ITEMS, TIME_ITEMS, TRAVEL_ITEMS, WORKOUT_ITEMS}}, device_row::DeviceRow, device_row_object::DeviceRowObject};
+
+/// loaded on first use by `MiBandWindow::store`
+static STORE: OnceCell<Mutex<Store>> = OnceCell::new();
+
+/// delays between attempts to start an alert source
+const MONITOR_RETRY_INITIAL: Duration = Duration::from_secs(5);
+const MONITOR_RETRY_MAX: Duration = Duration::from_secs(300);
+
+/// how long the lock page waits after a wrong PIN (doubling with each one in a row)
+const UNLOCK_DELAY_INITIAL: Duration = Duration::from_secs(1);
+const UNLOCK_DELAY_MAX: Duration = Duration::from_secs(30);
+
+/// how far back to look for workouts the first time they're fetched
+const WORKOUT_HISTORY_DAYS: i64 = 90;
+
+/// the statistics go back about a year, for the monthly charts
+const STATISTICS_HISTORY_DAYS: i64 = 366;
+
+/// how often to check if it's time for the morning briefing
+const BRIEFING_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// the end of day summary is shown at 9 PM
+const DAY_SUMMARY_HOUR: u32 = 21;
+
+/// delays between attempts to reconnect to a band that dropped its connection
+const RECONNECT_RETRY_INITIAL: Duration = Duration::from_secs(2);
+const RECONNECT_RETRY_MAX: Duration = Duration::from_secs(120);
+
+/// how long toasts stay up
+const TOAST_TIMEOUT: Duration = Duration::from_secs(4);
+/// problems stay up longer so there's time to read them
+const ERROR_TOAST_TIMEOUT: Duration = Duration::from_secs(10);
+/// how many problems "Details" shows
+const ERROR_LOG_LENGTH: usize = 50;
+/// how many sent alerts the alert history keeps
+const ALERT_HISTORY_LENGTH: usize = 50;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Severity {
+    Info,
+    Warning,
+    Error
+}
+
+impl Severity {
+    fn timeout(&self) -> Duration {
+        match self {
+            Self::Info => TOAST_TIMEOUT,
+            Self::Warning | Self::Error => ERROR_TOAST_TIMEOUT
+        }
+    }
+}
+
+/// how many bands a bulk operation works on at once
+const BULK_CONCURRENCY: usize = 2;
+
+/// how long the band can go unused with battery saver on, unless its own idle timeout is shorter
+const BATTERY_SAVER_IDLE_MINUTES: u16 = 5;
+
+/// something that can be done to every saved band that's in range
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum BulkOperation {
+    SyncTime,
+    Weather,
+    History
+}
+
+impl BulkOperation {
+    fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "time" => Some(Self::SyncTime),
+            "weather" => Some(Self::Weather),
+            "history" => Some(Self::History),
+            _ => None
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            Self::SyncTime => "Sync Time on All Bands",
+            Self::Weather => "Send Weather to All Bands",
+            Self::History => "Fetch History from All Bands"
+        }
+    }
+}
+
+/// matches the schema's default
+const DEFAULT_MUSIC_IDLE_TIMEOUT_SECS: u32 = 120;
+/// matches the schema's default
+const DEFAULT_STALE_BAND_DAYS: u32 = 90;
+
+/// waking up in the night only counts as getting up after staying awake this long
+const SLEEP_END_DELAY: Duration = Duration::from_secs(20 * 60);
+
+/// how long to wait before trying a scheduled sync again when the band is busy
+const BUSY_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// how often the GATT metrics on the developer card are refreshed
+const METRICS_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// these open something with band data in it, so they're turned off while the app is locked
+const LOCKED_ACTIONS: [&'static str; 4] = ["win.alert-history", "win.mini-window", "win.bulk-operation", "win.preferences"];
+
+/// how long the band gets to respond while the app is exiting
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+/// close the window (and shut down cleanly) when the app is asked to exit
+const SIGINT: i32 = 2;
+const SIGTERM: i32 = 15;
 
 glib::wrapper! {
     pub struct MiBandWindow(ObjectSubclass<MiBandWindowImpl>)
-        // refer to https://docs.gtk.org/gtk4/class.ApplicationWindow.html#hierarchy
-        @extends ApplicationWindow, Window, Widget,
+        // refer to https://gnome.pages.gitlab.gnome.org/libadwaita/doc/main/class.ApplicationWindow.html#hierarchy
+        @extends ApplicationWindow, gtk::ApplicationWindow, Window, Widget,
         @implements ActionGroup, ActionMap, Accessible, Buildable, ConstraintTarget, Native, Root, ShortcutManager;
 }
 
@@ -28,6 +136,31 @@ impl MiBandWindow {
         Object::builder().property("application", app).build()
     }
 
+    /// add or remove a state class on the window (`band-connected`, `band-charging` or `band-error`), for custom CSS
+    fn set_state_class(&self, class: &str, on: bool) {
+        if on {
+            self.add_css_class(class);
+        } else {
+            self.remove_css_class(class);
+        }
+    }
+
+    /// load the compact style on top of the normal one (or take it back off)
+    fn apply_compact_style(&self, enabled: bool) {
+        let provider = self.imp().compact_provider.get_or_init(|| {
+            let provider = CssProvider::new();
+            provider.load_from_resource("/me/grimsteel/miband4-gtk/compact.css");
+            provider
+        });
+        let display = WidgetExt::display(self);
+        if enabled {
+            // above style.css and style-hc.css, since it only changes the spacing
+            style_context_add_provider_for_display(&display, provider, STYLE_PROVIDER_PRIORITY_APPLICATION + 1);
+        } else {
+            style_context_remove_provider_for_display(&display, provider);
+        }
+    }
+
     fn set_page(&self, page: &str) {
         self.imp().main_stack.set_visible_child_name(page);
     }
@@ -48,23 +181,54 @@ impl MiBandWindow {
         }).await?)
     }
 
-    async fn store(&self) -> store::Result<&Mutex<Store>> {
-        static STORE: OnceCell<Mutex<Store>> = OnceCell::new();
+    async fn store(&self) -> store::Result<&'static Mutex<Store>> {
         Ok(STORE.get_or_try_init(|| async {
             Store::init().await.map(|s| Mutex::new(s))
         }).await?)
     }
 
-    fn show_error(&self, message: &str)  {
-        let dialog = AlertDialog::builder()
-            .message("An error occurred")
-            .detail(message)
-            .modal(true)
-            .build();
+    /// show a problem without interrupting anything
+    /// it's also kept in the log "Details" shows
+    fn show_error(&self, message: &str) {
+        error!("{}", message);
+        self.show_toast(message, Severity::Error);
+    }
 
-        error!("{}",message);
+    /// briefly show a message over the current page
+    fn show_toast(&self, message: &str, severity: Severity) {
+        let imp = self.imp();
+        if severity != Severity::Info {
+            let mut log = imp.error_log.borrow_mut();
+            if log.len() >= ERROR_LOG_LENGTH {
+                log.pop_front();
+            }
+            log.push_back((Local::now(), severity, message.to_string()));
+        }
 
-        dialog.show(Some(self));
+        let toast = Toast::builder()
+            .title(glib::markup_escape_text(message))
+            .timeout(severity.timeout().as_secs() as u32)
+            .priority(if severity == Severity::Info { ToastPriority::Normal } else { ToastPriority::High })
+            .build();
+        if severity != Severity::Info {
+            toast.set_button_label(Some(&gettext("Details")));
+            toast.connect_button_clicked(clone!(@weak self as win => move |_toast| {
+                win.show_error_log();
+            }));
+        }
+        toast.connect_dismissed(clone!(@weak self as win => move |toast| {
+            // a newer toast replaced it
+            if win.imp().current_toast.borrow().as_ref() != Some(toast) { return }
+            win.imp().current_toast.replace(None);
+            win.set_state_class("band-error", false);
+        }));
+        self.set_state_class("band-error", severity == Severity::Error);
+
+        // the latest toast replaces the last one, instead of waiting for it
+        if let Some(last_toast) = imp.current_toast.replace(Some(toast.clone())) {
+            last_toast.dismiss();
+        }
+        imp.toast_overlay.add_toast(toast);
     }
 
     fn show_home(&self) {
@@ -73,26 +237,120 @@ impl MiBandWindow {
         // hide the header buttons
         self.imp().btn_back.set_visible(false);
         self.imp().btn_reload.set_visible(false);
-        self.set_all_titles("Mi Smart Band 4");
+        self.imp().box_band_state.set_visible(false);
+        self.imp().mini_window.set_band(None);
+        self.set_all_titles(&gettext("Mi Smart Band 4"));
+    }
+
+    /// make the band vibrate (from the mini window)
+    #[template_callback]
+    fn handle_find_band(&self) {
+        spawn_future_local(clone!(@weak self as win => async move {
+            win.mark_active();
+            win.wake_from_idle().await;
+            if let Some(band) = win.imp().current_device.read().await.as_ref() {
+                // a call keeps vibrating until it's dismissed on the band
+                let alert = Alert { alert_type: AlertType::Call, title: "Find band", message: "" };
+                if let Err(err) = win.send_alert_to(band, &alert).await {
+                    win.show_error(&i18n_f("An error occurred while finding the band: {}", &[&err.to_string()]));
+                }
+            }
+        }));
+    }
+
+    /// list the recent problems, newest first (from a toast's "Details" button)
+    fn show_error_log(&self) {
+        let log = self.imp().error_log.borrow().iter().rev().map(|(time, severity, message)| {
+            let severity = if *severity == Severity::Warning { gettext("Warning") } else { gettext("Error") };
+            format!("{} {severity}: {message}", time.format("%H:%M:%S"))
+        }).collect::<Vec<_>>().join("\n\n");
+
+        let dialog = AlertDialog::builder()
+            .message(gettext("Recent problems"))
+            .detail(log)
+            .modal(true)
+            .build();
+        dialog.show(Some(self));
     }
 
     #[template_callback]
     fn handle_start_scan_clicked(&self, _button: &Button) {
         spawn_future_local(clone!(@weak self as win => async move {
             if let Err(err) = win.run_scan().await {
-                win.show_error(&format!("An error occurred while running the scan: {err}"));
+                win.show_error(&i18n_f("An error occurred while running the scan: {}", &[&err.to_string()]));
+            }
+        }));
+    }
+    #[template_callback]
+    fn handle_unlock_clicked(&self) {
+        let imp = self.imp();
+        // still checking the last one, or waiting after a wrong one
+        if !imp.btn_unlock.is_sensitive() { return }
+        imp.btn_unlock.set_sensitive(false);
+        imp.entry_app_pin.set_sensitive(false);
+
+        spawn_future_local(clone!(@weak self as win => async move {
+            let imp = win.imp();
+            let pin = imp.entry_app_pin.text().to_string();
+            let correct = match win.verify_app_pin(pin).await {
+                Ok(correct) => correct,
+                Err(err) => {
+                    win.show_error(&i18n_f("An error occurred while retrieving the store: {}", &[&err.to_string()]));
+                    false
+                }
+            };
+            imp.entry_app_pin.set_text("");
+            if correct {
+                imp.failed_unlocks.set(0);
+                win.unlock_app();
+            } else {
+                // each wrong PIN in a row doubles the wait before the next one
+                let failed = imp.failed_unlocks.get() + 1;
+                imp.failed_unlocks.set(failed);
+                let delay = (UNLOCK_DELAY_INITIAL * 2u32.saturating_pow(failed - 1)).min(UNLOCK_DELAY_MAX);
+                imp.label_unlock_error.set_label(&i18n_f("Incorrect PIN - try again in {} seconds", &[&delay.as_secs().to_string()]));
+                imp.label_unlock_error.set_visible(true);
+                Timer::after(delay).await;
+                imp.label_unlock_error.set_label(&gettext("Incorrect PIN"));
+            }
+            imp.btn_unlock.set_sensitive(true);
+            imp.entry_app_pin.set_sensitive(true);
+            imp.entry_app_pin.grab_focus();
+        }));
+    }
+    #[template_callback]
+    fn handle_system_unlock_clicked(&self) {
+        spawn_future_local(clone!(@weak self as win => async move {
+            match authenticate_system().await {
+                Ok(true) => win.unlock_app(),
+                Ok(false) => {
+                    let label = &win.imp().label_unlock_error;
+                    label.set_label(&gettext("Authentication failed"));
+                    label.set_visible(true);
+                },
+                Err(err) => win.show_error(&i18n_f("An error occurred while authenticating: {}", &[&err.to_string()]))
             }
         }));
     }
     #[template_callback]
     fn handle_back_clicked(&self) {
         self.show_home();
+        // the band stays connected, but nothing that was started from its page should keep running
+        let imp = self.imp();
+        if let Some(cancel_tx) = imp.sync_cancel.take() {
+            let _ = cancel_tx.try_send(());
+        }
+        spawn_future_local(clone!(@weak self as win => async move {
+            if let Some(band) = win.imp().current_device.read().await.as_ref() {
+                band.cancel_pending();
+            }
+        }));
     }
     #[template_callback]
     fn handle_reload_clicked(&self) {
         spawn_future_local(clone!(@weak self as win => async move {
             if let Err(err) = win.reload_current_device().await {
-                win.show_error(&format!("An error occurred while reloading the band: {err}"));
+                win.show_error(&i18n_f("An error occurred while reloading the band: {}", &[&err.to_string()]));
             }
         }));
     }
@@ -102,16 +360,222 @@ impl MiBandWindow {
         self.imp().auth_key_dialog.present();
     }
     #[template_callback]
-    fn handle_auth_key_submit(&self, value: String) {
+    /// saves the keys from whichever auth provider was used, and authenticates with the current band's
+    fn handle_auth_keys_found(&self) {
+        let dialog = self.imp().auth_key_dialog.get();
+        let keys = dialog.take_keys();
+        spawn_future_local(clone!(@weak self as win => async move {
+            match win.import_auth_keys(keys).await {
+                // it was opened from the onboarding dialog, which checks the key itself
+                Ok(Some(key)) if win.imp().onboarding_dialog.is_visible() => {
+                    dialog.close();
+                    win.imp().onboarding_dialog.test_auth_key(&key);
+                },
+                Ok(Some(key)) => {
+                    dialog.close();
+                    if let Err(err) = win.process_new_auth_key(key).await {
+                        win.show_error(&i18n_f("An error occurred while using the auth key: {}", &[&err.to_string()]));
+                    }
+                },
+                Ok(None) => win.show_toast(&gettext("There isn't a key for this band"), Severity::Warning),
+                Err(err) => win.show_error(&i18n_f("An error occurred while saving the auth keys: {}", &[&err.to_string()]))
+            }
+        }));
+    }
+    #[template_callback]
+    fn handle_reboot_clicked(&self) {
+        spawn_future_local(clone!(@weak self as win => async move {
+            let confirm = AlertDialog::builder()
+                .message(gettext("Restart the band?"))
+                .detail(gettext("It disconnects while it restarts, and reconnects by itself afterwards."))
+                .buttons([gettext("Cancel"), gettext("Restart")])
+                .cancel_button(0)
+                .default_button(0)
+                .build();
+            if confirm.choose_future(Some(&win)).await != Ok(1) { return }
+
+            if let Some(band) = win.imp().current_device.read().await.as_ref() {
+                match band.reboot().await {
+                    Ok(()) => win.show_toast(&gettext("The band is restarting"), Severity::Info),
+                    Err(err) => win.show_error(&i18n_f("An error occurred while restarting the band: {}", &[&err.to_string()]))
+                }
+            }
+        }));
+    }
+    #[template_callback]
+    fn handle_factory_reset_clicked(&self) {
+        spawn_future_local(clone!(@weak self as win => async move {
+            let confirm = AlertDialog::builder()
+                .message(gettext("Reset the band to factory settings?"))
+                .detail(gettext("Everything on the band is erased: its activity that hasn't been synced, its settings, and its auth key."))
+                .buttons([gettext("Cancel"), gettext("Continue")])
+                .cancel_button(0)
+                .default_button(0)
+                .build();
+            if confirm.choose_future(Some(&win)).await != Ok(1) { return }
+            // asked twice, since there's no undoing it
+            let confirm = AlertDialog::builder()
+                .message(gettext("Are you sure?"))
+                .detail(gettext("This can't be undone. Afterwards, the band has to be paired with the official app again to get a new auth key."))
+                .buttons([gettext("Cancel"), gettext("Erase Band")])
+                .cancel_button(0)
+                .default_button(0)
+                .build();
+            if confirm.choose_future(Some(&win)).await != Ok(1) { return }
+
+            if let Err(err) = win.factory_reset_current_band().await {
+                win.show_error(&i18n_f("An error occurred while resetting the band: {}", &[&err.to_string()]));
+            }
+        }));
+    }
+    #[template_callback]
+    fn handle_onboarding_clicked(&self) {
+        self.imp().onboarding_dialog.start(&self.devices());
+    }
+    #[template_callback]
+    fn handle_onboarding_scan(&self) {
+        spawn_future_local(clone!(@weak self as win => async move {
+            let dialog = win.imp().onboarding_dialog.get();
+            dialog.set_scanning(true);
+            if let Err(err) = win.run_scan().await {
+                win.show_error(&i18n_f("An error occurred while running the scan: {}", &[&err.to_string()]));
+            }
+            dialog.set_scanning(false);
+        }));
+    }
+    #[template_callback]
+    fn handle_onboarding_band_chosen(&self, address: String) {
+        spawn_future_local(clone!(@weak self as win => async move {
+            let dialog = win.imp().onboarding_dialog.get();
+            let device = win.devices().iter::<DeviceRowObject>()
+                .filter_map(Result::ok)
+                .find(|row| row.address() == address);
+            let Some(device) = device else {
+                dialog.connect_failed(&gettext("The band isn't in range anymore"));
+                return;
+            };
+            win.imp().auto_connect_skip.borrow_mut().remove(&address);
+            if let Err(err) = win.set_new_band(device.into()).await {
+                dialog.connect_failed(&err.to_string());
+                return;
+            }
+            if let Err(err) = win.continue_onboarding().await {
+                dialog.connect_failed(&err.to_string());
+            }
+        }));
+    }
+    #[template_callback]
+    fn handle_onboarding_test_auth_key(&self, auth_key: String) {
+        spawn_future_local(clone!(@weak self as win => async move {
+            let dialog = win.imp().onboarding_dialog.get();
+            match win.test_auth_key(auth_key).await {
+                Ok(()) => if let Err(err) = win.continue_onboarding().await {
+                    dialog.auth_failed(&err.to_string());
+                },
+                Err(BandError::InvalidAuthKey) => dialog.auth_failed(&gettext("The band didn't accept this key. Each band has its own key, and it changes when the band is reset or paired with another app.")),
+                Err(err) => dialog.auth_failed(&i18n_f("Couldn't check the key with the band: {}", &[&err.to_string()]))
+            }
+        }));
+    }
+    #[template_callback]
+    fn handle_onboarding_finish(&self, set_time: bool, goal: u32) {
+        spawn_future_local(clone!(@weak self as win => async move {
+            let dialog = win.imp().onboarding_dialog.get();
+            match win.finish_onboarding(set_time, goal).await {
+                Ok(()) => dialog.show_done(),
+                Err(err) => dialog.setup_failed(&err.to_string())
+            }
+        }));
+    }
+    #[template_callback]
+    fn handle_source_retry_clicked(&self) {
+        self.imp().label_source_retry.set_label(&gettext("Retrying..."));
+        for tx in self.imp().alert_sources_retry.borrow().iter() {
+            // if there's already a retry pending, that's fine
+            let _ = tx.try_send(());
+        }
+    }
+    #[template_callback]
+    fn handle_source_dismiss_clicked(&self) {
+        // we'll keep retrying in the background, but don't show the page again
+        self.imp().source_denied_dismissed.set(true);
+        self.set_page("device-detail");
+    }
+    #[template_callback]
+    fn handle_resolve_setting(&self, id: String, action: String) {
+        spawn_future_local(clone!(@weak self as win => async move {
+            let result = match action.as_str() {
+                "push" => win.push_setting(&id).await,
+                "adopt" => win.adopt_setting(&id).await,
+                _ => Ok(())
+            };
+            if let Err(err) = result {
+                win.show_error(&i18n_f("An error occurred while resolving the setting: {}", &[&err.to_string()]));
+            }
+            if let Err(err) = win.reload_current_device().await {
+                win.show_error(&i18n_f("An error occurred while reloading the band: {}", &[&err.to_string()]));
+            }
+        }));
+    }
+    #[template_callback]
+    fn handle_workouts_export(&self, format: String) {
+        let Some(format) = ExportFormat::from_id(&format) else { return };
+        spawn_future_local(clone!(@weak self as win => async move {
+            let contents = export_workouts(&win.imp().workouts.borrow(), format);
+            let name = format!("miband4-workouts.{}", format.extension());
+            let dialog = win.imp().workout_history_dialog.get();
+            if let Err(err) = win.save_to_file(&dialog, &gettext("Export Workouts"), &name, contents).await {
+                win.show_error(&i18n_f("An error occurred while exporting the workouts: {}", &[&err.to_string()]));
+            }
+        }));
+    }
+    #[template_callback]
+    fn handle_alert_resend(&self, id: u64) {
+        let Some(sent) = self.imp().alert_history.borrow().iter().find(|sent| sent.id == id).cloned() else { return };
+        spawn_future_local(clone!(@weak self as win => async move {
+            win.mark_active();
+            win.wake_from_idle().await;
+            match win.imp().current_device.read().await.as_ref() {
+                Some(band) => if let Err(err) = win.send_alert_to(band, &sent.as_alert()).await {
+                    win.show_error(&i18n_f("An error occurred while resending the alert: {}", &[&err.to_string()]));
+                },
+                None => win.show_toast(&gettext("Connect to a band to resend the alert"), Severity::Warning)
+            }
+        }));
+    }
+    #[template_callback]
+    fn handle_sync_cancel(&self) {
+        if let Some(cancel_tx) = self.imp().sync_cancel.take() {
+            let _ = cancel_tx.try_send(());
+        }
+    }
+    #[template_callback]
+    fn handle_sync_resume(&self) {
         spawn_future_local(clone!(@weak self as win => async move {
-            if let Err(err) = win.process_new_auth_key(value).await {
-                win.show_error(&format!("An error occurred while retriving the store: {err}"));
+            if let Err(err) = win.sync_activity(true).await {
+                win.show_error(&i18n_f("An error occurred while syncing activity: {}", &[&err.to_string()]));
             }
         }));
     }
     #[template_callback]
+    fn handle_workouts_refresh(&self) {
+        spawn_future_local(clone!(@weak self as win => async move {
+            win.refresh_workouts().await;
+        }));
+    }
+    #[template_callback]
     /// handles the click events for the buttons on the info cards
     fn handle_info_card_clicked(&self, id: String) {
+        self.mark_active();
+        // reconnect first if it was disconnected for being idle
+        if self.imp().idle_disconnected.get() {
+            spawn_future_local(clone!(@weak self as win => async move {
+                win.wake_from_idle().await;
+                win.handle_info_card_clicked(id);
+            }));
+            return;
+        }
+
         if id == "sync_time" {
             spawn_future_local(clone!(@weak self as win => async move {
                 if let Some(device) = win.imp().current_device.read().await.as_ref() {
@@ -120,33 +584,120 @@ impl MiBandWindow {
                     let current_time = Local::now();
                     // set the band time
                     if let Err(err) = device.set_band_time(current_time).await {
-                        win.show_error(&format!("An error occurred while setting the band time: {err}"));
+                        win.show_error(&i18n_f("An error occurred while setting the band time: {}", &[&err.to_string()]));
                     }
                     // refresh the time fropm the band
-                    match device.get_band_time().await {
-                        Err(err) => win.show_error(&format!("An error occurred while getting the band time: {err}")),
-                        Ok(time) => card.apply_values((time, true))
+                    match (device.get_band_time().await, win.display_format(&device.address).await) {
+                        (Err(err), _) => win.show_error(&i18n_f("An error occurred while getting the band time: {}", &[&err.to_string()])),
+                        (_, Err(err)) => win.show_error(&i18n_f("An error occurred while retriving the store: {}", &[&err.to_string()])),
+                        (Ok(time), Ok(format)) => card.apply_values((time, true, format.time_format))
                     }
                 };
             }));
+        } else if id == "verify_settings" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                match win.verify_settings().await {
+                    Ok(diffs) => {
+                        let dialog = &win.imp().verify_settings_dialog;
+                        dialog.set_diffs(diffs);
+                        dialog.present();
+                    },
+                    Err(err) => win.show_error(&i18n_f("An error occurred while verifying the settings: {}", &[&err.to_string()]))
+                }
+            }));
+        } else if id == "view_workouts" {
+            self.imp().workout_history_dialog.present();
+            spawn_future_local(clone!(@weak self as win => async move {
+                win.refresh_workouts().await;
+            }));
+        } else if id == "view_statistics" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                match win.load_statistics().await {
+                    Ok(()) => win.imp().statistics_dialog.present(),
+                    Err(err) => win.show_error(&i18n_f("An error occurred while loading the statistics: {}", &[&err.to_string()]))
+                }
+            }));
+        } else if id == "export_report" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let result = match win.weekly_report().await {
+                    Ok(Some(report)) => win.save_to_file(&win, &gettext("Export Weekly Report"), "miband4-weekly-report.html", report.to_html()).await
+                        .map_err(BandError::from),
+                    Ok(None) => Ok(()),
+                    Err(err) => Err(err.into())
+                };
+                if let Err(err) = result {
+                    win.show_error(&i18n_f("An error occurred while exporting the weekly report: {}", &[&err.to_string()]));
+                }
+            }));
+        } else if id == "print_report" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                match win.weekly_report().await {
+                    Ok(Some(report)) => win.print_report(report),
+                    Ok(None) => {},
+                    Err(err) => win.show_error(&i18n_f("An error occurred while loading the weekly report: {}", &[&err.to_string()]))
+                }
+            }));
         } else if id == "disconnect" {
             spawn_future_local(clone!(@weak self as win => async move {
                 if let Some(device) = win.imp().current_device.write().await.as_mut() {
                     if let Err(err) = device.disconnect().await {
-                        win.show_error(&format!("An error occurred while disconnecting: {err}"));
+                        win.show_error(&i18n_f("An error occurred while disconnecting: {}", &[&err.to_string()]));
                     }
+                    // don't connect right back to it
+                    win.imp().auto_connect_skip.borrow_mut().insert(device.address.clone());
                     // go back to the home screen
                     win.show_home();
                 };
             }));
+        } else if id == "bond" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                if let Some(device) = win.imp().current_device.read().await.as_ref() {
+                    match win.bond_band(device).await {
+                        Ok(state) => win.imp().info_device.apply_values(state),
+                        Err(err) => win.show_error(&i18n_f("An error occurred while bonding with the band: {}", &[&err.to_string()]))
+                    }
+                }
+            }));
+        } else if id == "remove_bond" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let confirm = AlertDialog::builder()
+                    .message(gettext("Remove the bond?"))
+                    .detail(gettext("BlueZ will forget this band and its pairing keys. You'll have to scan for it again to reconnect."))
+                    .buttons([gettext("Cancel"), gettext("Remove Bond")])
+                    .cancel_button(0)
+                    .default_button(0)
+                    .build();
+                if confirm.choose_future(Some(&win)).await != Ok(1) { return }
+
+                if let Err(err) = win.remove_current_bond().await {
+                    win.show_error(&i18n_f("An error occurred while removing the bond: {}", &[&err.to_string()]));
+                }
+            }));
+        } else if id == "save_connection" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let values = win.imp().info_device.get_values();
+                let enabled = matches!(values.get("auto_connect"), Some(InfoItemValue::Switch(true)));
+                let idle_disconnect: IdleDisconnect = values.into();
+                if let Err(err) = win.process_new_auto_connect(enabled).await {
+                    win.show_error(&i18n_f("An error occurred while saving auto-connect: {}", &[&err.to_string()]));
+                }
+                if let Err(err) = win.process_new_idle_disconnect(idle_disconnect.clone()).await {
+                    win.show_error(&i18n_f("An error occurred while saving the idle disconnect: {}", &[&err.to_string()]));
+                }
+                win.imp().info_device.apply_values(&idle_disconnect);
+            }));
         } else if id == "save_goal" {
             spawn_future_local(clone!(@weak self as win => async move {
                 let card = &win.imp().info_activity_goal;
+                // don't send anything until it's valid
+                let values = match ActivityGoal::try_from(card.get_values()) {
+                    Ok(values) => values,
+                    Err(errors) => return card.show_errors(&errors)
+                };
                 card.set_loading();
 
-                let values: ActivityGoal = card.get_values().into();
                 if let Err(err) = win.process_new_goal_config(values.clone()).await {
-                    win.show_error(&format!("An error occurred while setting the new goal config: {err}"));
+                    win.show_error(&i18n_f("An error occurred while setting the new goal config: {}", &[&err.to_string()]));
                 }
                 card.apply_values(&values);
             }));
@@ -157,66 +708,300 @@ impl MiBandWindow {
 
                 let values: BandLock = card.get_values().into();
                 if let Err(err) = win.process_new_band_lock(values.clone()).await {
-                    win.show_error(&format!("An error occurred while setting the new band lock: {err}"));
+                    win.show_error(&i18n_f("An error occurred while setting the new band lock: {}", &[&err.to_string()]));
                 }
                 card.apply_values(&values);
             }));
-        }
-    }
-
-    fn setup_device_list(&self, initial_model: ListStore) {
-        // setup the factory
-        let device_list_factory = SignalListItemFactory::new();
-        device_list_factory.connect_setup(move |_, list_item| {
-            let row = DeviceRow::new();
-            let list_item = list_item
-                .downcast_ref::<ListItem>()
-                .expect("Needs to be ListItem");
-            
-            list_item.set_child(Some(&row));
-
-            // bind list_item->item to row->device
-            list_item.property_expression("item").bind(&row, "device", Widget::NONE);
-        });
-
-        self.imp().list_devices.set_factory(Some(&device_list_factory));
-
-        // setup the model
-        self.imp().devices.replace(Some(initial_model));
-        self.imp().list_devices.set_model(Some(&NoSelection::new(Some(self.devices()))));
-
-        self.imp().list_devices.connect_activate(clone!(@weak self as win => move |list_view, idx| {
-            // get the DiscoveredDevice they clicked
-            let model = list_view.model().expect("the model must not be None at this point");
-            
-            let device: DiscoveredDevice = model
-                .item(idx)
-                .and_downcast::<DeviceRowObject>()
-                .expect("the item must exist and be a DeviceRowObject")
-                .into();
+        } else if id == "save_heart_rate" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let card = &win.imp().info_heart_rate;
+                card.set_loading();
 
-            let focused = list_view.focus_child().unwrap();
+                let values: HeartRateSharing = card.get_values().into();
+                let wear_detection: WearDetection = card.get_values().into();
+                if let Err(err) = win.process_new_heart_rate_sharing(values.clone(), wear_detection.clone()).await {
+                    win.show_error(&i18n_f("An error occurred while saving heart rate sharing: {}", &[&err.to_string()]));
+                }
+                card.apply_values(&values);
+                card.apply_values(&wear_detection);
+            }));
+        } else if id == "save_metric_sinks" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let card = &win.imp().info_metric_sinks;
+                card.set_loading();
 
-            // load the band and display it
-            spawn_future_local(async move {
-                focused.set_sensitive(false);
-                if let Err(err) = win.set_new_band(device).await {
-                    win.show_error(&format!("Error while connecting band: {err}"));
+                let values: MetricSinks = card.get_values().into();
+                if let Err(err) = win.process_new_metric_sinks(values.clone()).await {
+                    win.show_error(&i18n_f("An error occurred while saving the data exports: {}", &[&err.to_string()]));
                 }
-                focused.set_sensitive(true);
-            });
-        }));
-    }
+                card.apply_values(&values);
+            }));
+        } else if id == "save_movement" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let card = &win.imp().info_movement;
+                card.set_loading();
 
-   async fn process_new_auth_key(&self, auth_key: String) -> band::Result<()> {
-        if let Some(device) = self.imp().current_device.write().await.as_mut() {
-            // store this auth key
-            let store = self.store().await?;
+                let values: MovementGate = card.get_values().into();
+                if let Err(err) = win.process_new_movement_gate(values.clone()).await {
+                    win.show_error(&i18n_f("An error occurred while saving the movement reminders: {}", &[&err.to_string()]));
+                }
+                card.apply_values(&values);
+            }));
+        } else if id == "save_developer" {
+            let card = &self.imp().info_developer;
+            let values = card.get_values();
+            let enabled = matches!(values.get("trace_enabled"), Some(InfoItemValue::Switch(true)));
+            trace::set_enabled(enabled);
+            let soak_enabled = matches!(values.get("soak_enabled"), Some(InfoItemValue::Switch(true)));
+            spawn_future_local(clone!(@weak self as win => async move {
+                if let Err(err) = win.process_new_soak_diagnostics(soak_enabled).await {
+                    win.show_error(&i18n_f("An error occurred while saving the soak diagnostics setting: {}", &[&err.to_string()]));
+                }
+                win.refresh_developer_card();
+            }));
+        } else if id == "clear_trace" {
+            trace::clear();
+            self.refresh_developer_card();
+        } else if id == "export_trace" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let result = win.save_to_file(&win, &gettext("Export Protocol Trace"), "miband4-gtk-trace.log", trace::export_gadgetbridge()).await;
+                if let Err(err) = result {
+                    win.show_error(&i18n_f("An error occurred while exporting the trace: {}", &[&err.to_string()]));
+                }
+                win.refresh_developer_card();
+            }));
+        } else if id == "export_capture" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let result = win.save_to_file(&win, &gettext("Save Payload Capture"), "miband4-gtk-capture.txt", trace::export_capture()).await;
+                if let Err(err) = result {
+                    win.show_error(&i18n_f("An error occurred while saving the capture: {}", &[&err.to_string()]));
+                }
+            }));
+        } else if id == "replay_capture" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let result = match win.open_file(&win, &gettext("Replay Payload Capture")).await {
+                    Ok(Some((_, capture))) => {
+                        let decoded = replay(&trace::parse_capture(&capture));
+                        win.save_to_file(&win, &gettext("Save Decoded Capture"), "miband4-gtk-replay.txt", decoded).await
+                    },
+                    Ok(None) => Ok(()),
+                    Err(err) => Err(err)
+                };
+                if let Err(err) = result {
+                    win.show_error(&i18n_f("An error occurred while replaying the capture: {}", &[&err.to_string()]));
+                }
+            }));
+        } else if id == "export_activity" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let result = match win.sync_activity(true).await.and(win.recent_activity().await) {
+                    Ok(samples) => win.save_to_file(&win, &gettext("Export Minute Data"), "miband4-activity.csv", activity_to_csv(&samples)).await
+                        .map_err(BandError::from),
+                    Err(err) => Err(err)
+                };
+                if let Err(err) = result {
+                    win.show_error(&i18n_f("An error occurred while exporting the activity data: {}", &[&err.to_string()]));
+                }
+            }));
+        } else if id == "sync_now" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                if let Err(err) = win.sync_activity(true).await {
+                    win.show_error(&i18n_f("An error occurred while syncing activity: {}", &[&err.to_string()]));
+                }
+            }));
+        } else if id == "save_battery_alert" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let card = &win.imp().info_battery;
+                let values: BatteryAlert = card.get_values().into();
+                if let Err(err) = win.process_new_battery_alert(values.clone()).await {
+                    win.show_error(&i18n_f("An error occurred while saving the battery notifications: {}", &[&err.to_string()]));
+                }
+                card.apply_values(&values);
+            }));
+        } else if id == "save_sync" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let card = &win.imp().info_activity;
+                let values: SyncSchedule = card.get_values().into();
+                if let Err(err) = win.process_new_sync_schedule(values.clone()).await {
+                    win.show_error(&i18n_f("An error occurred while saving the sync schedule: {}", &[&err.to_string()]));
+                }
+                card.apply_values(&values);
+            }));
+        } else if id == "save_briefing" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let card = &win.imp().info_briefing;
+                card.set_loading();
+
+                let values: MorningBriefing = card.get_values().into();
+                if let Err(err) = win.process_new_briefing(values.clone()).await {
+                    win.show_error(&i18n_f("An error occurred while saving the morning briefing: {}", &[&err.to_string()]));
+                }
+                card.apply_values(&values);
+            }));
+        } else if id == "save_app_lock" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let card = &win.imp().info_app_lock;
+                let values = card.get_values();
+                let new_pin = match values.get("app_pin") {
+                    Some(InfoItemValue::Entry(pin)) => pin.trim().to_string(),
+                    _ => String::new()
+                };
+                card.set_loading();
+
+                match win.process_new_app_lock(values.into(), new_pin).await {
+                    Ok(lock) => card.apply_values(&lock),
+                    Err(err) => {
+                        win.show_error(&i18n_f("An error occurred while saving the app lock: {}", &[&err.to_string()]));
+                        card.apply_values(&AppLock::default());
+                    }
+                }
+            }));
+        } else if id == "lock_now" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                win.lock_app().await;
+            }));
+        } else if id == "save_travel" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let card = &win.imp().info_travel;
+                card.set_loading();
+
+                let values: TravelMode = card.get_values().into();
+                if let Err(err) = win.process_new_travel_mode(values.clone()).await {
+                    win.show_error(&i18n_f("An error occurred while applying travel mode: {}", &[&err.to_string()]));
+                }
+                card.apply_values(&values);
+            }));
+        } else if id == "save_sleep_summary" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let card = &win.imp().info_sleep_summary;
+                card.set_loading();
+
+                let values: SleepSummary = card.get_values().into();
+                if let Err(err) = win.process_new_sleep_summary(values.clone()).await {
+                    win.show_error(&i18n_f("An error occurred while saving the sleep summary: {}", &[&err.to_string()]));
+                }
+                card.apply_values(&values);
+            }));
+        } else if id == "save_forwarding" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let card = &win.imp().info_forwarding;
+                card.set_loading();
+
+                let values: Forwarding = card.get_values().into();
+                if let Err(err) = win.process_new_forwarding(values).await {
+                    win.show_error(&i18n_f("An error occurred while saving what's forwarded: {}", &[&err.to_string()]));
+                }
+                card.apply_values(&values);
+            }));
+        } else if id == "send_test_alert" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let card = &win.imp().info_test_alert;
+                let test_alert = match TestAlert::try_from(card.get_values()) {
+                    Ok(test_alert) => test_alert,
+                    Err(errors) => return card.show_errors(&errors)
+                };
+                win.mark_active();
+                win.wake_from_idle().await;
+                if let Some(band) = win.imp().current_device.read().await.as_ref() {
+                    match win.send_alert_to(band, &test_alert.alert()).await {
+                        Ok(()) => win.show_toast(&gettext("Sent the test alert"), Severity::Info),
+                        Err(err) => win.show_error(&i18n_f("An error occurred while sending the test alert: {}", &[&err.to_string()]))
+                    }
+                }
+            }));
+        } else if id == "send_briefing" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                if let Err(err) = win.send_briefing().await {
+                    win.show_error(&i18n_f("An error occurred while sending the morning briefing: {}", &[&err.to_string()]));
+                }
+            }));
+        } else if id == "save_display_format" {
+            spawn_future_local(clone!(@weak self as win => async move {
+                let card = &win.imp().info_display_format;
+                card.set_loading();
+
+                let values: DisplayFormat = card.get_values().into();
+                if let Err(err) = win.process_new_display_format(values.clone()).await {
+                    win.show_error(&i18n_f("An error occurred while setting the new display format: {}", &[&err.to_string()]));
+                }
+                card.apply_values(&values);
+
+                // everything else needs to be displayed in the new format
+                if let Err(err) = win.reload_current_device().await {
+                    win.show_error(&i18n_f("An error occurred while reloading the band: {}", &[&err.to_string()]));
+                }
+            }));
+        }
+    }
+
+    fn setup_device_list(&self, initial_model: ListStore) {
+        // setup the factory
+        let device_list_factory = SignalListItemFactory::new();
+        device_list_factory.connect_setup(move |_, list_item| {
+            let row = DeviceRow::new();
+            let list_item = list_item
+                .downcast_ref::<ListItem>()
+                .expect("Needs to be ListItem");
+            
+            list_item.set_child(Some(&row));
+
+            // bind list_item->item to row->device
+            list_item.property_expression("item").bind(&row, "device", Widget::NONE);
+        });
+
+        self.imp().list_devices.set_factory(Some(&device_list_factory));
+
+        // setup the model
+        self.imp().devices.replace(Some(initial_model));
+        // strongest signal first, and bands that aren't in range last
+        let sorter = CustomSorter::new(|a, b| {
+            let rssi = |obj: &Object| obj.downcast_ref::<DeviceRowObject>().map(|d| d.rssi()).filter(|rssi| *rssi != 0);
+            let ordering = match (rssi(a), rssi(b)) {
+                (Some(a), Some(b)) => b.cmp(&a),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal
+            };
+            ordering.into()
+        });
+        let sorted = SortListModel::new(Some(self.devices()), Some(sorter.clone()));
+        self.imp().device_sorter.replace(Some(sorter));
+        self.imp().list_devices.set_model(Some(&NoSelection::new(Some(sorted))));
+
+        self.imp().list_devices.connect_activate(clone!(@weak self as win => move |list_view, idx| {
+            // get the DiscoveredDevice they clicked
+            let model = list_view.model().expect("the model must not be None at this point");
+            
+            let device: DiscoveredDevice = model
+                .item(idx)
+                .and_downcast::<DeviceRowObject>()
+                .expect("the item must exist and be a DeviceRowObject")
+                .into();
+
+            let focused = list_view.focus_child().unwrap();
+
+            // they picked it, so auto-connect can go back to normal
+            win.imp().auto_connect_skip.borrow_mut().remove(&device.address);
+
+            // load the band and display it
+            spawn_future_local(async move {
+                focused.set_sensitive(false);
+                if let Err(err) = win.set_new_band(device).await {
+                    win.show_error(&i18n_f("Error while connecting band: {}", &[&err.to_string()]));
+                }
+                focused.set_sensitive(true);
+            });
+        }));
+    }
+
+   async fn process_new_auth_key(&self, auth_key: String) -> band::Result<()> {
+        if let Some(device) = self.imp().current_device.write().await.as_mut() {
+            // store this auth key
+            let store = self.store().await?;
 
-            let mut store_lock = store.lock().expect("can lock mutex");
+            let mut store_lock = store.lock().await;
             store_lock.get_band(device.address.clone()).auth_key = Some(auth_key.clone());
             // save
-            store_lock.save().await?;
+            Store::save_and_unlock(store_lock).await?;
             
             // actually authenticate
             self.try_band_auth(device, Some(auth_key)).await?
@@ -228,15 +1013,102 @@ impl MiBandWindow {
         Ok(())
     }
 
+    /// authenticate the current band with `auth_key`, only saving it if the band accepts it
+    async fn test_auth_key(&self, auth_key: String) -> band::Result<()> {
+        let imp = self.imp();
+        {
+            let mut device = imp.current_device.write().await;
+            let device = device.as_mut().ok_or(BandError::NotInitialized)?;
+            device.authenticate(&decode_hex(&auth_key).ok_or(BandError::InvalidAuthKey)?).await?;
+            imp.btn_auth_key.remove_css_class("suggested-action");
+
+            let mut store = self.store().await?
+                .lock().await;
+            store.get_band(device.address.clone()).auth_key = Some(auth_key);
+            Store::save_and_unlock(store).await?;
+        }
+        self.reload_current_device().await
+    }
+
+    /// move the onboarding dialog on from connecting - straight to the settings if the saved key already worked
+    async fn continue_onboarding(&self) -> band::Result<()> {
+        let imp = self.imp();
+        let (address, authenticated) = imp.current_device.read().await.as_ref()
+            .map(|device| (device.address.clone(), device.authenticated))
+            .ok_or(BandError::NotInitialized)?;
+        let (auth_key, goal) = {
+            let mut store = self.store().await?
+                .lock().await;
+            let band_conf = store.get_band(address.clone());
+            (band_conf.auth_key.clone(), band_conf.activity_goal.clone().unwrap_or_default())
+        };
+        if authenticated {
+            imp.onboarding_dialog.show_setup(&goal);
+        } else {
+            imp.onboarding_dialog.show_auth(&address, auth_key.as_deref());
+        }
+        Ok(())
+    }
+
+    /// the optional settings at the end of onboarding - `goal` is 0 to leave the band's goal alone
+    async fn finish_onboarding(&self, set_time: bool, goal: u32) -> band::Result<()> {
+        let address = {
+            let device = self.imp().current_device.read().await;
+            let device = device.as_ref().ok_or(BandError::NotInitialized)?;
+            if set_time {
+                device.set_band_time(Local::now()).await?;
+            }
+            device.address.clone()
+        };
+        if goal > 0 {
+            let mut goal_config = self.store().await?.lock().await
+                .get_band(address).activity_goal.clone().unwrap_or_default();
+            goal_config.steps = u16::try_from(goal).map_err(|_| BandError::InvalidGoal)?;
+            self.process_new_goal_config(goal_config).await?;
+        }
+        self.reload_current_device().await
+    }
+
+    /// save the keys for bands we know about (saved or in the device list)
+    /// returns the current band's key, which is left for `process_new_auth_key`
+    async fn import_auth_keys(&self, keys: Vec<(String, String)>) -> store::Result<Option<String>> {
+        let imp = self.imp();
+        let current = imp.current_device.read().await.as_ref().map(|b| b.address.clone());
+        let listed: HashSet<String> = imp.devices.borrow().as_ref()
+            .map(|devices| devices.iter::<DeviceRowObject>().filter_map(Result::ok).map(|row| row.address()).collect())
+            .unwrap_or_default();
+
+        let mut current_key = None;
+        let mut imported = 0;
+        {
+            let mut store = self.store().await?.lock().await;
+            for (address, key) in keys {
+                if Some(&address) == current.as_ref() {
+                    current_key = Some(key);
+                } else if listed.contains(&address) || store.stored_band(&address).is_some() {
+                    store.get_band(address).auth_key = Some(key);
+                    imported += 1;
+                }
+            }
+            if imported > 0 {
+                Store::save_and_unlock(store).await?;
+            }
+        }
+        if imported > 0 {
+            self.show_toast(&i18n_nf("Imported the auth key for {} other band", "Imported auth keys for {} other bands", imported, &[&imported.to_string()]), Severity::Info);
+        }
+        Ok(current_key)
+    }
+
     async fn process_new_goal_config(&self, goal_config: ActivityGoal) -> band::Result<()> {
         if let Some(device) = self.imp().current_device.read().await.as_ref() {
             // set the goal config
             device.set_activity_goal(&goal_config).await?;
             // remember it
             let mut store_lock = self.store().await?
-                .lock().expect("can lock store");
+                .lock().await;
             store_lock.get_band(device.address.clone()).activity_goal = Some(goal_config);
-            store_lock.save().await?;
+            Store::save_and_unlock(store_lock).await?;
         };
         Ok(())
     }
@@ -247,255 +1119,2698 @@ impl MiBandWindow {
             device.set_band_lock(&band_lock).await?;
             // remember it
             let mut store_lock = self.store().await?
-                .lock().expect("can lock store");
+                .lock().await;
             store_lock.get_band(device.address.clone()).band_lock = Some(band_lock);
-            store_lock.save().await?;
+            Store::save_and_unlock(store_lock).await?;
         };
         Ok(())
     }
 
-    async fn process_new_alias(&self, alias: String) -> store::Result<()> {
-        let mut store = self.store().await?.lock().expect("can lock store");
-        if let Some(band_mac) = self.imp().current_device.read().await.as_ref().map(|b| b.address.clone()){
-            let band_conf = store.get_band(band_mac);
-            band_conf.alias = Some(alias);
-            store.save().await?;
+    async fn process_new_display_format(&self, display_format: DisplayFormat) -> band::Result<()> {
+        if let Some(device) = self.imp().current_device.read().await.as_ref() {
+            // set the units on the band
+            device.set_distance_unit(display_format.distance_unit).await?;
+            device.set_time_format(display_format.time_format).await?;
+            device.set_date_display(display_format.date_display).await?;
+            // remember it
+            let mut store_lock = self.store().await?
+                .lock().await;
+            store_lock.get_band(device.address.clone()).display_format = Some(display_format);
+            Store::save_and_unlock(store_lock).await?;
+        };
+        Ok(())
+    }
+
+    async fn process_new_heart_rate_sharing(&self, sharing: HeartRateSharing, wear_detection: WearDetection) -> band::Result<()> {
+        if let Some(device) = self.imp().current_device.read().await.as_ref() {
+            let mut store_lock = self.store().await?
+                .lock().await;
+            let band_conf = store_lock.get_band(device.address.clone());
+            band_conf.heart_rate_sharing = Some(sharing.clone());
+            band_conf.wear_detection = Some(wear_detection);
+            Store::save_and_unlock(store_lock).await?;
+        };
+        // saving counts as putting it back on
+        self.imp().heart_rate_paused.set(false);
+
+        if sharing.enabled {
+            self.start_heart_rate_sharing();
+        } else {
+            self.stop_heart_rate_sharing();
         }
         Ok(())
     }
 
-    /// try to authenticate with the band
-    /// 
-    /// this method takes `device` and `auth_key` directly because in the two cases
-    /// where I use it, I already have those values
-    async fn try_band_auth<'a>(&self, device: &mut MiBand<'a>, auth_key: Option<String>) -> band::Result<()> {
-        if let Some(auth_key) = auth_key.and_then(|k| decode_hex(&k)) {
-            match device.authenticate(&auth_key).await {
-                // authed successfully
-                Ok(()) => {
-                    // unhighlight the auth key button
-                    self.imp().btn_auth_key.remove_css_class("suggested-action");
-                    return Ok(());
+    async fn process_new_metric_sinks(&self, sinks: MetricSinks) -> band::Result<()> {
+        check_webhook_url(&sinks.webhook_url)?;
+        check_webhook_url(&sinks.sleep_webhook_url)?;
+        if let Some(device) = self.imp().current_device.read().await.as_ref() {
+            let mut store_lock = self.store().await?
+                .lock().await;
+            store_lock.get_band(device.address.clone()).metric_sinks = Some(sinks.clone());
+            Store::save_and_unlock(store_lock).await?;
+        };
+
+        self.configure_metric_sinks(&sinks).await?;
+        Ok(())
+    }
+
+    async fn process_new_movement_gate(&self, gate: MovementGate) -> band::Result<()> {
+        if let Some(device) = self.imp().current_device.read().await.as_ref() {
+            let mut store_lock = self.store().await?
+                .lock().await;
+            store_lock.get_band(device.address.clone()).movement_gate = Some(gate.clone());
+            Store::save_and_unlock(store_lock).await?;
+        };
+
+        if gate.enabled {
+            self.start_movement_gate();
+        } else {
+            self.stop_movement_gate();
+        }
+        Ok(())
+    }
+
+    /// compare the settings in the store with what the band reports
+    async fn verify_settings(&self) -> band::Result<Vec<SettingDiff>> {
+        let mut diffs = vec![];
+        if let Some(device) = self.imp().current_device.read().await.as_ref() {
+            let band_time = device.get_band_time().await?;
+            let band_goal = device.get_activity_goal().await?;
+            let band_lock = device.get_band_lock().await?;
+            let band_format = device.get_display_format().await?;
+            let (activity_goal, band_lock_conf, display_format) = {
+                let mut store = self.store().await?.lock().await;
+                let band_conf = store.get_band(device.address.clone());
+                (band_conf.activity_goal.clone(), band_conf.band_lock.clone(), band_conf.display_format.clone())
+            };
+            let time_format = display_format.as_ref().map(|f| f.time_format).unwrap_or(TimeFormat::H12);
+
+            // the band's time is "stored" on this computer's clock
+            let now = Local::now();
+            let drift = (now - band_time).num_seconds().abs();
+            diffs.push(SettingDiff {
+                id: "time",
+                label: "Time",
+                stored: format_date(&now, time_format),
+                band: Some(format_date(&band_time, time_format)),
+                // anything within a minute is in sync
+                in_sync: Some(drift < 60),
+                adoptable: false
+            });
+
+            diffs.push(SettingDiff::compare("activity_goal", "Activity Goal", activity_goal, band_goal, |goal| {
+                format!("{} steps, goal notifications {}", goal.steps, if goal.notifications { "on" } else { "off" })
+            }));
+            // don't show the PIN itself - the dialog can be opened by anyone who can see the window
+            diffs.push(SettingDiff::compare("band_lock", "Band Lock", band_lock_conf, band_lock, |lock| {
+                if lock.enabled { format!("on, PIN {}", "•".repeat(lock.pin.chars().count())) } else { "off".into() }
+            }));
+            diffs.push(SettingDiff::compare("display_format", "Display Format", display_format, band_format, |format| {
+                format!(
+                    "{}, {}, {}",
+                    if format.distance_unit == DistanceUnit::Metric { "metric" } else { "imperial" },
+                    if format.time_format == TimeFormat::H24 { "24-hour" } else { "12-hour" },
+                    if format.date_display == DateDisplay::DateTime { "date and time" } else { "time only" }
+                )
+            }));
+        }
+        Ok(diffs)
+    }
+
+    /// save the band's value of a setting to the store
+    async fn adopt_setting(&self, id: &str) -> band::Result<()> {
+        if let Some(device) = self.imp().current_device.read().await.as_ref() {
+            // read it before locking the store - the band can take a while to answer
+            let mut store_lock;
+            match id {
+                "activity_goal" => {
+                    let goal = device.get_activity_goal().await?.ok_or(BandError::SettingNotAdoptable)?;
+                    store_lock = self.store().await?.lock().await;
+                    store_lock.get_band(device.address.clone()).activity_goal = Some(goal);
                 },
-                Err(BandError::InvalidAuthKey) => {
-                    // notify the user
-                    self.show_error("Invalid auth key");
+                "band_lock" => {
+                    let lock = device.get_band_lock().await?.ok_or(BandError::SettingNotAdoptable)?;
+                    store_lock = self.store().await?.lock().await;
+                    store_lock.get_band(device.address.clone()).band_lock = Some(lock);
                 },
-                Err(err) => {
-                    // propagate other errors
-                    return Err(err);
-                }
+                "display_format" => {
+                    let format = device.get_display_format().await?.ok_or(BandError::SettingNotAdoptable)?;
+                    store_lock = self.store().await?.lock().await;
+                    store_lock.get_band(device.address.clone()).display_format = Some(format);
+                },
+                // this computer's clock is always right
+                _ => return Err(BandError::SettingNotAdoptable)
+            }
+            Store::save_and_unlock(store_lock).await?;
+        }
+        Ok(())
+    }
+
+    /// write the stored value of a setting to the band
+    async fn push_setting(&self, id: &str) -> band::Result<()> {
+        if let Some(device) = self.imp().current_device.read().await.as_ref() {
+            let (activity_goal, band_lock, display_format) = {
+                let mut store = self.store().await?.lock().await;
+                let band_conf = store.get_band(device.address.clone());
+                (band_conf.activity_goal.clone(), band_conf.band_lock.clone(), band_conf.display_format.clone())
+            };
+            match id {
+                "time" => device.set_band_time(Local::now()).await?,
+                "activity_goal" => if let Some(goal) = activity_goal {
+                    device.set_activity_goal(&goal).await?;
+                },
+                "band_lock" => if let Some(lock) = band_lock {
+                    device.set_band_lock(&lock).await?;
+                },
+                "display_format" => if let Some(format) = display_format {
+                    device.set_distance_unit(format.distance_unit).await?;
+                    device.set_time_format(format.time_format).await?;
+                    device.set_date_display(format.date_display).await?;
+                },
+                _ => warn!("Unknown setting {id}")
             }
         }
+        Ok(())
+    }
+
+    /// the display format configured for a band
+    async fn display_format(&self, band_mac: &str) -> store::Result<DisplayFormat> {
+        let mut store = self.store().await?.lock().await;
+        Ok(store.get_band(band_mac.to_string()).display_format.clone().unwrap_or_default())
+    }
+
+    /// the temperature unit and language the band gets the weather in
+    async fn weather_format(&self, band_mac: &str) -> store::Result<WeatherFormat> {
+        let mut store = self.store().await?.lock().await;
+        let band_conf = store.get_band(band_mac.to_string());
+        let briefing = band_conf.morning_briefing.clone().unwrap_or_default();
+        let distance_unit = band_conf.display_format.clone().unwrap_or_default().distance_unit;
+        Ok(WeatherFormat {
+            unit: briefing.temperature_unit.unwrap_or(match distance_unit {
+                DistanceUnit::Metric => TemperatureUnit::Celsius,
+                DistanceUnit::Imperial => TemperatureUnit::Fahrenheit
+            }),
+            language: briefing.weather_language
+        })
+    }
+
+    /// forget the current band at the BlueZ level and go back to the device list
+    async fn remove_current_bond(&self) -> band::Result<()> {
+        let imp = self.imp();
+        {
+            let mut device = imp.current_device.write().await;
+            let Some(band) = device.as_mut() else { return Ok(()) };
+            band.remove_bond().await?;
+            device.take();
+        }
+        // stop everything that was running for the band
+        if let Some((tx, _rx)) = imp.band_closed.borrow_mut().take() {
+            tx.close();
+        }
+        self.show_home();
+        Ok(())
+    }
+
+    /// the band is gone once it's reset, and its saved auth key won't work anymore
+    async fn factory_reset_current_band(&self) -> band::Result<()> {
+        let imp = self.imp();
+        {
+            let mut device = imp.current_device.write().await;
+            let Some(band) = device.as_mut() else { return Ok(()) };
+            band.factory_reset().await?;
+
+            let mut store = self.store().await?
+                .lock().await;
+            store.get_band(band.address.clone()).auth_key = None;
+            Store::save_and_unlock(store).await?;
+            device.take();
+        }
+        // stop everything that was running for the band
+        if let Some((tx, _rx)) = imp.band_closed.borrow_mut().take() {
+            tx.close();
+        }
+        self.show_home();
+        self.show_toast(&gettext("The band was reset"), Severity::Info);
+        Ok(())
+    }
+
+    async fn process_new_auto_connect(&self, enabled: bool) -> store::Result<()> {
+        let mut store = self.store().await?.lock().await;
+        if let Some(band_mac) = self.imp().current_device.read().await.as_ref().map(|b| b.address.clone()) {
+            store.get_band(band_mac).auto_connect = Some(enabled);
+            Store::save_and_unlock(store).await?;
+        }
+        Ok(())
+    }
+
+    async fn process_new_idle_disconnect(&self, idle_disconnect: IdleDisconnect) -> store::Result<()> {
+        {
+            let mut store = self.store().await?.lock().await;
+            let Some(band_mac) = self.imp().current_device.read().await.as_ref().map(|b| b.address.clone()) else { return Ok(()) };
+            store.get_band(band_mac).idle_disconnect = Some(idle_disconnect.clone());
+            Store::save_and_unlock(store).await?;
+        }
+        if idle_disconnect.enabled {
+            self.start_idle_disconnect(idle_disconnect.minutes);
+        } else {
+            self.stop_idle_disconnect();
+        }
+        Ok(())
+    }
+
+    /// something happened that needs the band (they clicked something, a notification was forwarded, etc.)
+    fn mark_active(&self) {
+        self.imp().last_active.set(Some(Instant::now()));
+    }
+
+    /// disconnect from the band once nothing has needed it for `minutes`
+    /// stops when the band is closed or `stop_idle_disconnect` is called
+    fn start_idle_disconnect(&self, minutes: u16) {
+        let (stop_tx, stop_rx) = async_channel::bounded(1);
+        if let Some(old_stop_tx) = self.imp().idle_disconnect_stop.replace(Some(stop_tx)) {
+            let _ = old_stop_tx.try_send(());
+        }
+        self.mark_active();
+
+        let timeout = Duration::from_secs(minutes as u64 * 60);
+        spawn_future_local(clone!(@weak self as win => async move {
+            let _task = soak::track_task("idle disconnect");
+            let Some(band_closed_rx) = win.imp().band_closed.borrow().as_ref().map(|a| a.1.clone()) else { return };
+
+            let mut check = Timer::interval(IDLE_CHECK_INTERVAL).fuse();
+            pin_mut!(band_closed_rx);
+            pin_mut!(stop_rx);
+            loop {
+                select! {
+                    _ = band_closed_rx.next() => break,
+                    _ = stop_rx.next() => break,
+                    _ = check.next() => {
+                        let imp = win.imp();
+                        // heart rate sharing needs the connection the whole time
+                        if imp.idle_disconnected.get() || imp.heart_rate_stop.borrow().is_some() { continue }
+                        if imp.last_active.get().is_some_and(|t| t.elapsed() < timeout) { continue }
+
+                        // mark it first so this doesn't look like a dropped connection
+                        imp.idle_disconnected.set(true);
+                        if let Some(device) = imp.current_device.write().await.as_mut() {
+                            if let Err(err) = device.disconnect().await {
+                                warn!("Error while disconnecting an idle band: {err}");
+                                imp.idle_disconnected.set(false);
+                                continue;
+                            }
+                        }
+                        win.show_signal(None, false);
+                        win.show_toast(&gettext("Disconnected from the idle band - it reconnects when it's needed"), Severity::Info);
+                    }
+                }
+            }
+        }));
+    }
+
+    fn stop_idle_disconnect(&self) {
+        if let Some(stop_tx) = self.imp().idle_disconnect_stop.take() {
+            let _ = stop_tx.try_send(());
+        }
+    }
+
+    /// reconnect to the band if it was disconnected for being idle
+    async fn wake_from_idle(&self) {
+        let imp = self.imp();
+        if !imp.idle_disconnected.replace(false) { return }
+        let Some(device) = imp.current_discovered.borrow().clone() else { return };
+        if let Err(err) = self.set_new_band(device.clone()).await {
+            warn!("Error while reconnecting to the idle band: {err}");
+            // keep trying in the background
+            self.reconnect_current_band(device);
+        }
+    }
+
+    /// connect to a known band without the user picking it, if auto-connect is on for it
+    /// does nothing if another band is already connected
+    async fn auto_connect(&self, device: DiscoveredDevice) {
+        let imp = self.imp();
+        let enabled = match self.store().await {
+            Ok(store) => store.lock().await.should_auto_connect(&device.address),
+            Err(_) => false
+        };
+        if !enabled || imp.auto_connect_skip.borrow().contains(&device.address) { return }
+        // it'll be reconnected when it's needed
+        if imp.idle_disconnected.get() { return }
+        if let Some(current) = imp.current_device.read().await.as_ref() {
+            if current.is_connected().await { return }
+        }
+
+        // several bands can show up at once - only connect to the first
+        if imp.auto_connecting.replace(true) { return }
+        // this happens in the background, so don't interrupt them with an error dialog
+        if let Err(err) = self.set_new_band(device).await {
+            warn!("Error while automatically connecting to a band: {err}");
+        }
+        imp.auto_connecting.set(false);
+    }
+
+    fn spawn_auto_connect(&self, device: DiscoveredDevice) {
+        spawn_future_local(clone!(@weak self as win => async move {
+            win.auto_connect(device).await;
+        }));
+    }
+
+    async fn process_new_alias(&self, alias: String) -> store::Result<()> {
+        let mut store = self.store().await?.lock().await;
+        if let Some(band_mac) = self.imp().current_device.read().await.as_ref().map(|b| b.address.clone()){
+            let band_conf = store.get_band(band_mac);
+            band_conf.alias = Some(alias);
+            Store::save_and_unlock(store).await?;
+        }
+        Ok(())
+    }
+
+    /// try to authenticate with the band
+    /// 
+    /// this method takes `device` and `auth_key` directly because in the two cases
+    /// where I use it, I already have those values
+    async fn try_band_auth<'a>(&self, device: &mut MiBand<'a>, auth_key: Option<String>) -> band::Result<()> {
+        if let Some(auth_key) = auth_key.and_then(|k| decode_hex(&k)) {
+            match device.authenticate(&auth_key).await {
+                // authed successfully
+                Ok(()) => {
+                    // unhighlight the auth key button
+                    self.imp().btn_auth_key.remove_css_class("suggested-action");
+                    return Ok(());
+                },
+                Err(BandError::InvalidAuthKey) => {
+                    // notify the user
+                    self.show_error(&gettext("Invalid auth key"));
+                },
+                Err(err) => {
+                    // propagate other errors
+                    return Err(err);
+                }
+            }
+        }
+
+        // highlight the auth key button
+        self.imp().btn_auth_key.add_css_class("suggested-action");
+
+        Ok(())
+    }
+
+    async fn reload_current_device(&self) -> band::Result<()> {
+        let imp = self.imp();
+        if let Some(device) = imp.current_device.read().await.as_ref() {
+            {
+                // display the band alias/name
+                let store = self.store().await?
+                    .lock().await;
+                let band_alias = store.get_band_alias(&device.address);
+                imp.address_label.set_text(band_alias);
+                imp.mini_window.set_band(Some(band_alias));
+                let model = device.model().to_string();
+                if store.is_guest(&device.address) {
+                    self.set_all_titles(&i18n_f("{} (Guest) - {}", &[band_alias, &model]));
+                } else {
+                    self.set_all_titles(&i18n_f("{} - {}", &[band_alias, &model]));
+                }
+            }
+            let display_format = self.display_format(&device.address).await?;
+
+            // if not connected, show what was last read and stop here
+            if !device.is_connected().await {
+                return self.show_cached_values(&device.address).await;
+            }
+
+            // set everything to loading
+            imp.info_battery.set_loading();
+            imp.info_time.set_loading();
+            imp.info_device.set_loading();
+            imp.info_activity.set_loading();
+            imp.info_activity_goal.set_loading();
+            imp.info_band_lock.set_loading();
+            imp.info_display_format.set_loading();
+
+            // load all of the data
+            let battery = device.get_battery().await?;
+            self.publish_sample(&device.address, MetricSample::Battery {
+                time: Local::now(),
+                level: battery.battery_level,
+                charging: battery.charging
+            }).await;
+            self.set_state_class("band-charging", battery.charging);
+            self.handle_battery_alert(&device.address, &battery).await;
+            let mut cached = CachedValues { battery: Some(Cached::now((&battery).into())), ..Default::default() };
+            imp.info_battery.apply_values((battery, display_format.time_format));
+            let band_time = device.get_band_time().await?;
+            cached.time = Some(Cached::now(band_time.timestamp()));
+            imp.info_time.apply_values((band_time, device.authenticated, display_format.time_format));
+            let auto_connect = self.store().await?.lock().await.should_auto_connect(&device.address);
+            imp.info_device.apply_values((
+                device,
+                device.get_firmware_revision().await?,
+                auto_connect
+            ));
+            imp.info_device.apply_values(device.get_bond_state().await?);
+            match device.get_current_activity().await {
+                Ok(activity) => {
+                    cached.activity = Some(Cached::now((&activity).into()));
+                    imp.info_activity.apply_values((activity.clone(), display_format.distance_unit));
+                    self.publish_sample(&device.address, MetricSample::Totals { time: Local::now(), totals: activity }).await;
+                },
+                // the rest of the band can still be shown
+                Err(BandError::RequiresAuth) => imp.info_activity.set_unavailable(&gettext("Authentication required")),
+                Err(err) => return Err(err)
+            }
+
+            // we need to lock the store again so that it's not held across await
+            let mut store = self.store().await?
+                .lock().await;
+            {
+                // keep the previous activity if it needed authentication this time
+                let band_cache = store.get_band(device.address.clone()).cached.get_or_insert_with(Default::default);
+                cached.activity = cached.activity.or_else(|| band_cache.activity.take());
+                *band_cache = cached;
+            }
+            Store::save_and_unlock(store).await?;
+            let mut store = self.store().await?
+                .lock().await;
+            let band_conf = &*store.get_band(device.address.clone());
+
+            imp.info_device
+                .apply_values(band_conf.idle_disconnect.as_ref().unwrap_or(&IdleDisconnect::default()));
+
+            imp.info_battery
+                .apply_values(band_conf.battery_alert.as_ref().unwrap_or(&BatteryAlert::default()));
+
+            // activity goal
+            imp.info_activity_goal
+                .apply_values(band_conf.activity_goal.as_ref().unwrap_or(&ActivityGoal::default()));
+
+            // band lock
+            imp.info_band_lock
+                .apply_values(band_conf.band_lock.as_ref().unwrap_or(&BandLock::default()));
+
+            // display format
+            imp.info_display_format.apply_values(&display_format);
+
+            // these are written to the band, which needs authentication
+            if !device.authenticated {
+                for card in [&imp.info_activity_goal, &imp.info_band_lock, &imp.info_display_format] {
+                    card.set_unavailable(&gettext("Authentication required"));
+                }
+            }
+            imp.box_danger_zone.set_sensitive(device.authenticated);
+
+            // heart rate
+            imp.info_heart_rate
+                .apply_values(band_conf.heart_rate_sharing.as_ref().unwrap_or(&HeartRateSharing::default()));
+            imp.info_heart_rate
+                .apply_values(band_conf.wear_detection.as_ref().unwrap_or(&WearDetection::default()));
+
+            // movement reminders
+            imp.info_movement
+                .apply_values(band_conf.movement_gate.as_ref().unwrap_or(&MovementGate::default()));
+
+            // activity sync
+            imp.info_activity
+                .apply_values(band_conf.sync_schedule.as_ref().unwrap_or(&SyncSchedule::default()));
+            imp.info_activity.apply_values((
+                band_conf.last_synced.and_then(|t| Local.timestamp_opt(t, 0).single()),
+                display_format.time_format
+            ));
+
+            // morning briefing
+            imp.info_briefing
+                .apply_values(band_conf.morning_briefing.as_ref().unwrap_or(&MorningBriefing::default()));
+
+            // sleep summary
+            imp.info_sleep_summary
+                .apply_values(band_conf.sleep_summary.as_ref().unwrap_or(&SleepSummary::default()));
+
+            // travel mode
+            imp.info_travel
+                .apply_values(band_conf.travel_mode.as_ref().unwrap_or(&TravelMode::default()));
+
+            // forwarding
+            imp.info_forwarding
+                .apply_values(&band_conf.forwarding.unwrap_or_default());
+
+            // metric sinks
+            imp.info_metric_sinks
+                .apply_values(band_conf.metric_sinks.as_ref().unwrap_or(&MetricSinks::default()));
+
+            // workouts
+            imp.info_workouts
+                .apply_values((imp.workouts.borrow().last(), device.authenticated, display_format.time_format));
+
+            // app lock
+            imp.info_app_lock
+                .apply_values(store.app.app_lock.as_ref().unwrap_or(&AppLock::default()));
+
+            self.refresh_developer_card();
+        }
+
+        Ok(())
+    }
+
+    /// fetch the workouts recorded since the last one we have and add them to the history
+    async fn fetch_workouts(&self) -> band::Result<()> {
+        let imp = self.imp();
+        if let Some(device) = imp.current_device.read().await.as_ref() {
+            let mark = self.store().await?.lock().await
+                .get_band(device.address.clone()).sync_marks.as_ref()
+                .and_then(|marks| marks.workouts)
+                .and_then(|t| Local.timestamp_opt(t, 0).single());
+            let since = mark.or_else(|| imp.workouts.borrow().last().map(|workout| workout.start))
+                .map(|start| start + TimeDelta::minutes(1))
+                .unwrap_or_else(|| Local::now() - TimeDelta::days(WORKOUT_HISTORY_DAYS));
+            let new_workouts = device.fetch_workouts(since).await?;
+            let display_format = self.display_format(&device.address).await?;
+            {
+                let mut store = self.store().await?.lock().await;
+                store.get_band(device.address.clone()).sync_marks.get_or_insert_with(Default::default).record_workouts(&new_workouts);
+                Store::save_and_unlock(store).await?;
+            }
+
+            self.publish_sample(&device.address, MetricSample::Workouts(new_workouts)).await;
+            let stored_workouts = self.store().await?.lock().await
+                .history(&device.address).workouts(&device.address).map_err(store::Error::from)?;
+
+            let mut workouts = imp.workouts.borrow_mut();
+            *workouts = stored_workouts;
+            imp.workout_history_dialog.set_workouts(&workouts, &display_format);
+            imp.info_workouts.apply_values((workouts.last(), device.authenticated, display_format.time_format));
+        }
+        Ok(())
+    }
+
+    /// set up the sinks every sample from the band goes to
+    async fn configure_metric_sinks(&self, conf: &MetricSinks) -> store::Result<()> {
+        // the history views depend on the history sink, so it's always there
+        let mut sinks: Vec<Rc<dyn MetricSink>> = vec![Rc::new(HistorySink { store: self.store().await? })];
+        if conf.prometheus {
+            sinks.push(Rc::new(PrometheusSink::default()));
+        }
+        if !conf.webhook_url.is_empty() {
+            sinks.push(Rc::new(WebhookSink { url: conf.webhook_url.clone() }));
+        }
+        if !conf.sleep_webhook_url.is_empty() {
+            sinks.push(Rc::new(SleepWebhookSink { url: conf.sleep_webhook_url.clone() }));
+        }
+        if let Some(service) = self.imp().band_service.borrow().clone() {
+            sinks.push(Rc::new(service));
+        }
+        self.imp().metric_sinks.replace(sinks);
+        Ok(())
+    }
+
+    /// hand a sample from the band to every metric sink
+    async fn publish_sample(&self, band_mac: &str, sample: MetricSample) {
+        // the mini window shows the latest values too
+        match &sample {
+            MetricSample::Battery { level, charging, .. } => self.imp().mini_window.set_battery(*level, *charging),
+            MetricSample::Totals { time, totals } => {
+                self.imp().mini_window.set_steps(totals.steps);
+                self.handle_day_totals(band_mac, *time, totals).await;
+            },
+            _ => {}
+        }
+
+        // clone the list so it isn't borrowed while the sinks run
+        let (remote, local): (Vec<_>, Vec<_>) = self.imp().metric_sinks.borrow().iter().cloned().partition(|sink| sink.is_remote());
+        for sink in local {
+            if let Err(err) = sink.handle(band_mac, &sample).await {
+                warn!("{} sink failed to handle a sample: {err}", sink.name());
+            }
+        }
+
+        // an unreachable host shouldn't hold up the live heart rate, so these all run at once in the background
+        if !remote.is_empty() {
+            let band_mac = band_mac.to_string();
+            spawn_future_local(async move {
+                join_all(remote.iter().map(|sink| async {
+                    if let Err(err) = sink.handle(&band_mac, &sample).await {
+                        warn!("{} sink failed to handle a sample: {err}", sink.name());
+                    }
+                })).await;
+            });
+        }
+    }
+
+    /// fetch the activity recorded since the last sync into the history database
+    /// and show the new totals
+    ///
+    /// with `show_progress`, the transfer is shown in the sync progress dialog and can be cancelled from there
+    async fn sync_activity(&self, show_progress: bool) -> band::Result<()> {
+        let imp = self.imp();
+        let current_device = imp.current_device.read().await;
+        let Some(device) = current_device.as_ref() else { return Ok(()) };
+
+        let (last_sample, since) = self.activity_sync_start(&device.address).await?;
+
+        let dialog = imp.sync_progress_dialog.get();
+        let time_format = self.display_format(&device.address).await?.time_format;
+        let (cancel_tx, cancel_rx) = async_channel::bounded(1);
+        if show_progress {
+            imp.sync_cancel.replace(Some(cancel_tx));
+            dialog.start(since, time_format);
+            dialog.present();
+        }
+
+        let started = Instant::now();
+        let result = device.fetch_activity(since, |progress| {
+            if show_progress {
+                let rate = progress.activity_samples() as f64 / started.elapsed().as_secs_f64().max(1.0);
+                dialog.set_progress(&progress, rate, time_format);
+            }
+        }, Some(&cancel_rx)).await;
+        // the sender is dropped here if this sync owned it
+        let cancelled = show_progress && imp.sync_cancel.take().is_none();
+
+        let samples = match result {
+            Ok(samples) => samples,
+            Err(err) => {
+                if show_progress { dialog.set_failed(&err.to_string()) }
+                return Err(err);
+            }
+        };
+        let sample_count = samples.len();
+        let resume_from = samples.last().map(|sample| sample.time).or(last_sample);
+        // keep whatever was received - the next sync picks up after the last sample
+        self.record_synced_activity(&device.address, samples).await?;
+
+        if cancelled {
+            dialog.set_cancelled(resume_from, time_format);
+            return Ok(());
+        }
+        if show_progress {
+            dialog.set_finished(sample_count);
+        }
+
+        let now = self.record_sync_finished(&device.address).await?;
+
+        let display_format = self.display_format(&device.address).await?;
+        let activity = device.get_current_activity().await?;
+        imp.info_activity.apply_values((activity.clone(), display_format.distance_unit));
+        imp.info_activity.apply_values((Some(now), display_format.time_format));
+        self.publish_sample(&device.address, MetricSample::Totals { time: now, totals: activity }).await;
+
+        // the first sync of the morning brings in last night's sleep
+        let address = device.address.clone();
+        drop(current_device);
+        if let Err(err) = self.send_sleep_summary_if_due(&address).await {
+            warn!("Error while sending the sleep summary: {err}");
+        }
+        Ok(())
+    }
+
+    /// where the next activity sync starts: the last synced sample (if any) and the time to request activity from
+    async fn activity_sync_start(&self, address: &str) -> store::Result<(Option<DateTime<Local>>, DateTime<Local>)> {
+        // pick up where the last sync left off
+        let last_sample = self.store().await?.lock().await.last_synced_activity(address)?;
+        let since = last_sample
+            .map(|time| time + TimeDelta::minutes(1))
+            .unwrap_or_else(|| Local::now() - TimeDelta::days(ACTIVITY_HISTORY_DAYS));
+        Ok((last_sample, since))
+    }
+
+    /// move the sync mark past these samples and hand them (and the heart rates in them) to the metric sinks (which includes the history)
+    async fn record_synced_activity(&self, address: &str, samples: Vec<ActivitySample>) -> store::Result<()> {
+        {
+            let mut store = self.store().await?.lock().await;
+            store.get_band(address.to_string()).sync_marks.get_or_insert_with(Default::default).record_activity(&samples);
+            Store::save_and_unlock(store).await?;
+        }
+        let heart_rates: Vec<_> = samples.iter()
+            .filter_map(|sample| sample.heart_rate.map(|bpm| (sample.time, bpm as u16)))
+            .collect();
+        self.publish_sample(address, MetricSample::Activity(samples)).await;
+        if !heart_rates.is_empty() {
+            self.publish_sample(address, MetricSample::HeartRates(heart_rates)).await;
+        }
+        Ok(())
+    }
+
+    /// remember when the band was last completely synced
+    async fn record_sync_finished(&self, address: &str) -> store::Result<DateTime<Local>> {
+        let now = Local::now();
+        let mut store = self.store().await?.lock().await;
+        store.get_band(address.to_string()).last_synced = Some(now.timestamp());
+        Store::save_and_unlock(store).await?;
+        Ok(now)
+    }
+
+    /// sync a band that isn't the open one (nothing is shown)
+    async fn sync_band_history(&self, band: &MiBand<'_>) -> band::Result<usize> {
+        let (_, since) = self.activity_sync_start(&band.address).await?;
+        let samples = band.fetch_activity(since, |_| {}, None).await?;
+        let sample_count = samples.len();
+        self.record_synced_activity(&band.address, samples).await?;
+        self.record_sync_finished(&band.address).await?;
+        Ok(sample_count)
+    }
+
+    /// run an operation on every saved band that's in range (and has an auth key), a few at a time,
+    /// showing how each one went in the bulk operation dialog
+    async fn run_bulk_operation(&self, operation: BulkOperation) {
+        let imp = self.imp();
+        let Some(devices) = imp.devices.borrow().clone() else { return };
+        let bands: Vec<(DiscoveredDevice, String)> = match self.store().await {
+            Ok(store) => {
+                let store = store.lock().await;
+                devices.iter::<DeviceRowObject>()
+                    .filter_map(Result::ok)
+                    // bands that aren't connected only have a signal strength while they're advertising
+                    .filter(|row| row.connected() || row.rssi() != 0)
+                    .filter(|row| store.stored_band(&row.address()).is_some_and(|band| band.auth_key.is_some()))
+                    .map(|row| {
+                        let alias = store.get_band_alias(&row.address()).to_string();
+                        (row.into(), alias)
+                    })
+                    .collect()
+            },
+            Err(err) => {
+                self.show_error(&i18n_f("An error occurred while retrieving the store: {}", &[&err.to_string()]));
+                return;
+            }
+        };
+        if bands.is_empty() {
+            self.show_toast(&gettext("None of the saved bands are in range"), Severity::Info);
+            return;
+        }
+
+        let dialog = imp.bulk_operation_dialog.get();
+        let names: Vec<_> = bands.iter().map(|(device, alias)| (device.address.clone(), alias.clone())).collect();
+        dialog.start(operation.title(), &names);
+        dialog.present();
+
+        let failed = Cell::new(0);
+        futures::stream::iter(bands).for_each_concurrent(BULK_CONCURRENCY, |(device, _)| {
+            let (dialog, failed) = (&dialog, &failed);
+            async move {
+                let address = device.address.clone();
+                dialog.set_band_status(&address, "Connecting");
+                match self.run_bulk_on_band(device, operation).await {
+                    Ok(status) => dialog.set_band_status(&address, &status),
+                    Err(err) => {
+                        failed.set(failed.get() + 1);
+                        dialog.set_band_status(&address, &i18n_f("Failed: {}", &[&err.to_string()]));
+                    }
+                }
+            }
+        }).await;
+        dialog.set_finished(failed.get());
+    }
+
+    /// connect to the band (unless it's the open one), run the operation, and put the connection back how it was
+    async fn run_bulk_on_band(&self, device: DiscoveredDevice, operation: BulkOperation) -> Result<String, Box<dyn std::error::Error>> {
+        let is_current = self.imp().current_device.read().await.as_ref().is_some_and(|band| band.address == device.address);
+        if is_current {
+            // this also updates the open band's page
+            if operation == BulkOperation::History {
+                self.sync_activity(false).await?;
+                return Ok("Synced".to_string());
+            }
+            let current_device = self.imp().current_device.read().await;
+            let band = current_device.as_ref().ok_or(BandError::NotInitialized)?;
+            return self.run_bulk_step(band, operation).await;
+        }
+
+        let was_connected = device.connected;
+        let mut band = MiBand::from_discovered_device(self.session().await?.clone(), device).await?;
+        // anything still running for the band stops when it's closed
+        band.set_cancel(imp.band_closed.borrow().as_ref().map(|a| a.1.clone()));
+        self.watch_band_state(band.stream_state());
+        let auth_key = {
+            let store = self.store().await?.lock().await;
+            let band_conf = store.stored_band(&band.address);
+            band.set_gatt_paths(band_conf.and_then(|b| b.gatt_paths.clone()));
+            band.set_io_policy(store.app.io_policy.unwrap_or_default());
+            band.set_simplify_alerts(store.app.simplify_alerts.unwrap_or(false));
+            band_conf.and_then(|b| b.auth_key.as_deref()).and_then(decode_hex)
+        };
+
+        band.initialize().await?;
+        let result = match auth_key {
+            Some(auth_key) => match band.authenticate(&auth_key).await {
+                Ok(()) => self.run_bulk_step(&band, operation).await,
+                Err(err) => Err(err.into())
+            },
+            None => Err(BandError::RequiresAuth.into())
+        };
+        if !was_connected {
+            if let Err(err) = band.disconnect().await {
+                warn!("Error while disconnecting from {}: {err}", band.address);
+            }
+        }
+        result
+    }
+
+    async fn run_bulk_step(&self, band: &MiBand<'_>, operation: BulkOperation) -> Result<String, Box<dyn std::error::Error>> {
+        match operation {
+            BulkOperation::SyncTime => {
+                band.set_band_time(Local::now()).await?;
+                Ok("Time synced".to_string())
+            },
+            BulkOperation::Weather => {
+                let Some((latitude, longitude)) = self.weather_location(&band.address).await? else {
+                    return Ok("Skipped - no weather location set".to_string());
+                };
+                let weather = fetch_weather(latitude, longitude).await?;
+                let message = compose_weather(&weather, self.weather_format(&band.address).await?);
+                self.send_alert_to(band, &Alert { alert_type: AlertType::Message, title: "Weather", message: &message }).await?;
+                Ok("Weather sent".to_string())
+            },
+            BulkOperation::History => {
+                let samples = self.sync_band_history(band).await?;
+                Ok(format!("{samples} new samples"))
+            }
+        }
+    }
+
+    /// the last `ACTIVITY_HISTORY_DAYS` of synced activity
+    async fn recent_activity(&self) -> band::Result<Vec<ActivitySample>> {
+        let Some(address) = self.imp().current_device.read().await.as_ref().map(|d| d.address.clone()) else { return Ok(vec![]) };
+        let since = Local::now() - TimeDelta::days(ACTIVITY_HISTORY_DAYS);
+        Ok(self.store().await?.lock().await
+            .history(&address).activity(&address, since).map_err(store::Error::from)?)
+    }
+
+    /// show the stored daily history in the statistics dialog
+    async fn load_statistics(&self) -> store::Result<()> {
+        let Some(address) = self.imp().current_device.read().await.as_ref().map(|d| d.address.clone()) else { return Ok(()) };
+        let since = Local::now().date_naive() - TimeDelta::days(STATISTICS_HISTORY_DAYS);
+        let (days, goal) = {
+            let mut store = self.store().await?.lock().await;
+            let days = store.history(&address).daily_stats(&address, since)?;
+            (days, store.get_band(address.clone()).activity_goal.clone())
+        };
+        let display_format = self.display_format(&address).await?;
+        self.imp().statistics_dialog.set_history(days, goal.as_ref(), display_format.distance_unit);
+        Ok(())
+    }
+
+    /// the last `REPORT_DAYS` of stored history for the current band
+    async fn weekly_report(&self) -> store::Result<Option<WeeklyReport>> {
+        let Some(address) = self.imp().current_device.read().await.as_ref().map(|d| d.address.clone()) else { return Ok(None) };
+        let end = Local::now().date_naive();
+        let start = end - TimeDelta::days(REPORT_DAYS - 1);
+        let Some(start_time) = start.and_hms_opt(0, 0, 0).and_then(|t| t.and_local_timezone(Local).single()) else { return Ok(None) };
+
+        let display_format = self.display_format(&address).await?;
+        let mut store = self.store().await?.lock().await;
+        let days = store.history(&address).daily_stats(&address, start)?;
+        let sleep = store.history(&address).sleep_sessions(&address, start_time)?;
+        let band_name = store.get_band_alias(&address).to_string();
+        let step_goal = store.get_band(address.clone()).activity_goal.as_ref().map(|goal| goal.steps);
+        Ok(Some(WeeklyReport { band_name, end, days, sleep, step_goal, distance_unit: display_format.distance_unit }))
+    }
+
+    /// print the report with the GTK print dialog, which can also save it as a PDF
+    fn print_report(&self, report: WeeklyReport) {
+        let operation = PrintOperation::new();
+        operation.set_job_name("Weekly Report");
+        operation.set_n_pages(1);
+        operation.set_allow_async(true);
+        operation.connect_draw_page(move |_, context, _| {
+            report.draw(&context.cairo_context(), context.width());
+        });
+        operation.connect_done(clone!(@weak self as win => move |operation, result| {
+            if result == PrintOperationResult::Error {
+                if let Err(err) = operation.error() {
+                    win.show_error(&i18n_f("An error occurred while printing the weekly report: {}", &[&err.to_string()]));
+                }
+            }
+        }));
+        if let Err(err) = operation.run(PrintOperationAction::PrintDialog, Some(self)) {
+            self.show_error(&i18n_f("An error occurred while printing the weekly report: {}", &[&err.to_string()]));
+        }
+    }
+
+    /// compose the morning briefing and send it to the band
+    async fn send_briefing(&self) -> band::Result<()> {
+        let Some(address) = self.imp().current_device.read().await.as_ref().map(|d| d.address.clone()) else { return Ok(()) };
+        let display_format = self.display_format(&address).await?;
+
+        // make sure last night is in the history
+        if let Err(err) = self.sync_activity(false).await {
+            warn!("Error while syncing activity for the briefing: {err}");
+        }
+
+        let weather = match self.weather_location(&address).await? {
+            Some((latitude, longitude)) => fetch_weather(latitude, longitude).await
+                .map_err(|err| warn!("Error while fetching the weather: {err}"))
+                .ok(),
+            None => None
+        };
+
+        let today = Local::now().date_naive();
+        let end_of_today = today.and_hms_opt(23, 59, 59).and_then(|t| t.and_local_timezone(Local).single());
+        let event = match end_of_today {
+            Some(end_of_today) => next_event(end_of_today).await
+                .map_err(|err| warn!("Error while reading the calendar: {err}"))
+                .ok()
+                .flatten(),
+            None => None
+        };
+
+        let sleep = self.last_night_sleep(&address).await?;
+
+        let message = compose_briefing(
+            weather.as_ref(), event.as_ref(), sleep.as_ref(), display_format.time_format, self.weather_format(&address).await?
+        );
+        if let Some(band) = self.imp().current_device.read().await.as_ref() {
+            self.send_alert_to(band, &Alert { alert_type: AlertType::Message, title: "Good morning", message: &message }).await?;
+        }
+        Ok(())
+    }
+
+    /// the longest sleep since 6 PM yesterday in the synced history (it's stored as a sleep session too)
+    async fn last_night_sleep(&self, address: &str) -> store::Result<Option<SleepSession>> {
+        let yesterday = Local::now().date_naive() - TimeDelta::days(1);
+        let Some(since) = yesterday.and_hms_opt(18, 0, 0).and_then(|t| t.and_local_timezone(Local).single()) else { return Ok(None) };
+        let mut store = self.store().await?.lock().await;
+        let samples = store.history(address).activity(address, since)?;
+        let sleep = detect_sleep(&samples);
+        if let Some(sleep) = &sleep {
+            store.history(address).insert_sleep(address, sleep)?;
+        }
+        Ok(sleep)
+    }
+
+    /// send last night's sleep to the band and/or desktop, once a day after the configured time
+    async fn send_sleep_summary_if_due(&self, address: &str) -> band::Result<()> {
+        let summary = self.store().await?.lock().await
+            .get_band(address.to_string()).sleep_summary.clone().unwrap_or_default();
+        if !summary.enabled { return Ok(()) }
+
+        let now = Local::now();
+        let already_sent = summary.last_sent
+            .and_then(|t| Local.timestamp_opt(t, 0).single())
+            .is_some_and(|t| t.date_naive() == now.date_naive());
+        let due = (now.hour() * 60 + now.minute()) >= (summary.hour as u32 * 60 + summary.minute as u32);
+        if already_sent || !due { return Ok(()) }
+
+        // the band might not have finished recording the night yet - try again after the next sync
+        let Some(sleep) = self.last_night_sleep(address).await? else { return Ok(()) };
+        let time_format = self.display_format(address).await?.time_format;
+        let message = compose_sleep_summary(&sleep, time_format);
+
+        if summary.to_band {
+            if let Some(band) = self.imp().current_device.read().await.as_ref() {
+                self.send_alert_to(band, &Alert { alert_type: AlertType::Message, title: "Last night", message: &message }).await?;
+            }
+        }
+        if summary.to_desktop {
+            self.notify_desktop("sleep-summary", &gettext("Last night"), &message);
+        }
+
+        let mut store = self.store().await?.lock().await;
+        store.get_band(address.to_string()).sleep_summary.get_or_insert_with(Default::default).last_sent = Some(now.timestamp());
+        Store::save_and_unlock(store).await?;
+        Ok(())
+    }
+
+    /// sync at the summary time every morning, which sends the summary
+    /// (the scheduled sync sends it too, if it runs first)
+    /// stops when the band is closed or `stop_sleep_summary_scheduler` is called
+    fn start_sleep_summary_scheduler(&self) {
+        let (stop_tx, stop_rx) = async_channel::bounded(1);
+        // stop the previous scheduler
+        if let Some(old_stop_tx) = self.imp().sleep_summary_stop.replace(Some(stop_tx)) {
+            let _ = old_stop_tx.try_send(());
+        }
+
+        spawn_future_local(clone!(@weak self as win => async move {
+            let _task = soak::track_task("sleep summary");
+            let Some(band_closed_rx) = win.imp().band_closed.borrow().as_ref().map(|a| a.1.clone()) else { return };
+
+            let mut last_synced = None;
+            let mut check = Timer::interval(BRIEFING_CHECK_INTERVAL).fuse();
+            pin_mut!(band_closed_rx);
+            pin_mut!(stop_rx);
+            loop {
+                select! {
+                    _ = band_closed_rx.next() => break,
+                    _ = stop_rx.next() => break,
+                    _ = check.next() => {
+                        let now = Local::now();
+                        if last_synced == Some(now.date_naive()) { continue }
+                        let Some(address) = win.imp().current_device.read().await.as_ref().map(|d| d.address.clone()) else { continue };
+                        let summary = match win.store().await {
+                            Ok(store) => store.lock().await.get_band(address).sleep_summary.clone().unwrap_or_default(),
+                            Err(_) => continue
+                        };
+                        let due = (now.hour() * 60 + now.minute()) >= (summary.hour as u32 * 60 + summary.minute as u32);
+                        if !due { continue }
+
+                        last_synced = Some(now.date_naive());
+                        if let Err(err) = win.sync_activity(false).await {
+                            warn!("Error while syncing activity for the sleep summary: {err}");
+                        }
+                    }
+                }
+            }
+        }));
+    }
+
+    fn stop_sleep_summary_scheduler(&self) {
+        if let Some(stop_tx) = self.imp().sleep_summary_stop.take() {
+            let _ = stop_tx.try_send(());
+        }
+    }
+
+    async fn process_new_forwarding(&self, forwarding: Forwarding) -> store::Result<()> {
+        if let Some(device) = self.imp().current_device.read().await.as_ref() {
+            let mut store = self.store().await?.lock().await;
+            store.get_band(device.address.clone()).forwarding = Some(forwarding);
+            Store::save_and_unlock(store).await?;
+        }
+        if !forwarding.media {
+            // take the media off the band's music screen
+            if let Some(band) = self.imp().current_device.read().await.as_ref() {
+                if let Err(err) = band.set_media_info(&None).await {
+                    warn!("Error while clearing the media state: {err}");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// what gets forwarded to this band
+    async fn forwarding(&self, address: &str) -> Forwarding {
+        match self.store().await {
+            Ok(store) => store.lock().await.get_band(address.to_string()).forwarding.unwrap_or_default(),
+            Err(err) => {
+                warn!("Error while reading what's forwarded: {err}");
+                Forwarding::default()
+            }
+        }
+    }
+
+    async fn process_new_sleep_summary(&self, mut summary: SleepSummary) -> band::Result<()> {
+        if let Some(device) = self.imp().current_device.read().await.as_ref() {
+            let mut store_lock = self.store().await?
+                .lock().await;
+            let band_conf = store_lock.get_band(device.address.clone());
+            // don't send it again today just because the settings changed
+            summary.last_sent = band_conf.sleep_summary.as_ref().and_then(|s| s.last_sent);
+            band_conf.sleep_summary = Some(summary.clone());
+            Store::save_and_unlock(store_lock).await?;
+        };
+
+        if summary.enabled {
+            self.start_sleep_summary_scheduler();
+        } else {
+            self.stop_sleep_summary_scheduler();
+        }
+        Ok(())
+    }
+
+    /// send the briefing once a day at the configured time
+    /// stops when the band is closed or `stop_briefing_scheduler` is called
+    fn start_briefing_scheduler(&self) {
+        let (stop_tx, stop_rx) = async_channel::bounded(1);
+        // stop the previous scheduler
+        if let Some(old_stop_tx) = self.imp().briefing_stop.replace(Some(stop_tx)) {
+            let _ = old_stop_tx.try_send(());
+        }
+
+        spawn_future_local(clone!(@weak self as win => async move {
+            let _task = soak::track_task("morning briefing");
+            let Some(band_closed_rx) = win.imp().band_closed.borrow().as_ref().map(|a| a.1.clone()) else { return };
+
+            let mut last_sent = None;
+            let mut check = Timer::interval(BRIEFING_CHECK_INTERVAL).fuse();
+            pin_mut!(band_closed_rx);
+            pin_mut!(stop_rx);
+            loop {
+                select! {
+                    _ = band_closed_rx.next() => break,
+                    _ = stop_rx.next() => break,
+                    _ = check.next() => {
+                        let now = Local::now();
+                        if last_sent == Some(now.date_naive()) { continue }
+                        let Some(address) = win.imp().current_device.read().await.as_ref().map(|d| d.address.clone()) else { continue };
+                        let briefing = match win.store().await {
+                            Ok(store) => store.lock().await.get_band(address).morning_briefing.clone().unwrap_or_default(),
+                            Err(_) => continue
+                        };
+
+                        // only send it in the hour after the scheduled time (e.g. not if the app is opened in the evening)
+                        let minutes = (now.hour() * 60 + now.minute()) as i64 - (briefing.hour as i64 * 60 + briefing.minute as i64);
+                        if !(0..60).contains(&minutes) { continue }
+
+                        last_sent = Some(now.date_naive());
+                        if let Err(err) = win.send_briefing().await {
+                            warn!("Error while sending the morning briefing: {err}");
+                        }
+                    }
+                }
+            }
+        }));
+    }
+
+    fn stop_briefing_scheduler(&self) {
+        if let Some(stop_tx) = self.imp().briefing_stop.take() {
+            let _ = stop_tx.try_send(());
+        }
+    }
+
+    async fn process_new_briefing(&self, briefing: MorningBriefing) -> band::Result<()> {
+        if let Some(device) = self.imp().current_device.read().await.as_ref() {
+            let mut store_lock = self.store().await?
+                .lock().await;
+            store_lock.get_band(device.address.clone()).morning_briefing = Some(briefing.clone());
+            Store::save_and_unlock(store_lock).await?;
+        };
+
+        if briefing.enabled {
+            self.start_briefing_scheduler();
+        } else {
+            self.stop_briefing_scheduler();
+        }
+        Ok(())
+    }
+
+    /// check the app PIN against the stored hash
+    /// a hash from an older version is replaced with a stronger one once the PIN is right
+    async fn verify_app_pin(&self, pin: String) -> store::Result<bool> {
+        let hash = self.store().await?.lock().await.app.app_lock.as_ref().and_then(|lock| lock.pin.clone());
+        let Some(hash) = hash else { return Ok(false) };
+        // the hash is slow on purpose, so keep it off the main thread
+        let (correct, rehashed) = gio::spawn_blocking(move || {
+            let correct = hash.verify(&pin);
+            (correct, (correct && hash.is_outdated()).then(|| PinHash::new(&pin)))
+        }).await.unwrap_or((false, None));
+
+        if let Some(rehashed) = rehashed {
+            let mut store = self.store().await?.lock().await;
+            if let Some(lock) = store.app.app_lock.as_mut() {
+                lock.pin = Some(rehashed);
+            }
+            Store::save_and_unlock(store).await?;
+        }
+        Ok(correct)
+    }
+
+    /// returns the saved lock
+    async fn process_new_app_lock(&self, mut lock: AppLock, new_pin: String) -> store::Result<AppLock> {
+        // hash it before locking the store - it's slow on purpose
+        let new_hash = if new_pin.is_empty() {
+            None
+        } else {
+            gio::spawn_blocking(move || PinHash::new(&new_pin)).await.ok()
+        };
+        let mut store = self.store().await?.lock().await;
+        lock.pin = match new_hash {
+            Some(hash) => Some(hash),
+            None => store.app.app_lock.as_ref().and_then(|l| l.pin.clone())
+        };
+        // don't lock them out
+        if lock.enabled && lock.pin.is_none() && !lock.system_auth {
+            self.show_error(&gettext("Set a PIN or allow system authentication before locking the app"));
+            lock.enabled = false;
+        }
+        store.app.app_lock = Some(lock.clone());
+        Store::save_and_unlock(store).await?;
+        Ok(lock)
+    }
+
+    /// hide everything behind the lock page
+    async fn lock_app(&self) {
+        let lock = match STORE.get() {
+            Some(store) => store.lock().await.app.app_lock.clone().unwrap_or_default(),
+            None => AppLock::default()
+        };
+        if lock.enabled {
+            self.show_lock_page(&lock);
+        }
+    }
+
+    fn show_lock_page(&self, lock: &AppLock) {
+        let imp = self.imp();
+        for action in LOCKED_ACTIONS {
+            self.action_set_enabled(action, false);
+        }
+        imp.btn_system_unlock.set_visible(lock.system_auth);
+        imp.entry_app_pin.set_visible(lock.pin.is_some());
+        imp.entry_app_pin.set_text("");
+        imp.label_unlock_error.set_visible(false);
+        imp.lock_stack.set_visible_child_name("locked");
+        imp.btn_back.set_sensitive(false);
+        imp.btn_reload.set_sensitive(false);
+        self.set_all_titles(&gettext("Mi Band 4"));
+
+        // the dialogs show band data too
+        imp.mini_window.set_visible(false);
+        imp.workout_history_dialog.set_visible(false);
+        imp.alert_history_dialog.set_visible(false);
+        imp.alert_history_dialog.set_alerts(std::iter::empty(), DisplayFormat::default().time_format);
+        imp.preferences_window.set_visible(false);
+        imp.statistics_dialog.set_visible(false);
+        imp.verify_settings_dialog.set_visible(false);
+        imp.auth_key_dialog.set_visible(false);
+        imp.onboarding_dialog.set_visible(false);
+        imp.entry_app_pin.grab_focus();
+    }
+
+    fn is_locked(&self) -> bool {
+        self.imp().lock_stack.visible_child_name().as_deref() == Some("locked")
+    }
+
+    fn unlock_app(&self) {
+        let imp = self.imp();
+        imp.lock_stack.set_visible_child_name("unlocked");
+        for action in LOCKED_ACTIONS {
+            self.action_set_enabled(action, true);
+        }
+        imp.btn_back.set_sensitive(true);
+        imp.btn_reload.set_sensitive(true);
+        // it waited for the app to be unlocked
+        self.spawn_stale_band_check();
+        spawn_future_local(clone!(@weak self as win => async move {
+            let address = win.imp().current_device.read().await.as_ref().map(|band| band.address.clone());
+            win.refresh_alert_history(address.as_deref()).await;
+            // restore the band's title
+            if let Err(err) = win.reload_current_device().await {
+                win.show_error(&i18n_f("An error occurred while reloading the band: {}", &[&err.to_string()]));
+            }
+        }));
+    }
+
+    /// lock the app once it's been in the background for `timeout_minutes`
+    fn watch_app_lock(&self) {
+        self.connect_is_active_notify(|win| {
+            if win.is_active() {
+                // they came back before it locked
+                if let Some(stop_tx) = win.imp().lock_timer_stop.take() {
+                    let _ = stop_tx.try_send(());
+                }
+                return;
+            }
+
+            let (stop_tx, stop_rx) = async_channel::bounded(1);
+            if let Some(old_stop_tx) = win.imp().lock_timer_stop.replace(Some(stop_tx)) {
+                let _ = old_stop_tx.try_send(());
+            }
+            spawn_future_local(clone!(@weak win => async move {
+                let Some(store) = STORE.get() else { return };
+                let timeout = store.lock().await.app.app_lock.clone()
+                    .filter(|lock| lock.enabled)
+                    .map(|lock| Duration::from_secs(lock.timeout_minutes as u64 * 60));
+                let Some(timeout) = timeout else { return };
+
+                let mut timer = Timer::after(timeout).fuse();
+                pin_mut!(stop_rx);
+                select! {
+                    _ = timer => win.lock_app().await,
+                    _ = stop_rx.next() => {}
+                }
+            }));
+        });
+    }
+
+    /// travel mode sets the band to the new timezone, sends it the weather for wherever we are now,
+    /// and pauses the movement reminders until it's turned off
+    async fn process_new_travel_mode(&self, travel: TravelMode) -> band::Result<()> {
+        let imp = self.imp();
+        let device = imp.current_device.read().await;
+        let Some(device) = device.as_ref() else { return Ok(()) };
+        {
+            let mut store_lock = self.store().await?
+                .lock().await;
+            store_lock.get_band(device.address.clone()).travel_mode = Some(travel.clone());
+            Store::save_and_unlock(store_lock).await?;
+        }
+        if !travel.enabled { return Ok(()) }
+
+        // the system clock has already moved to the new timezone
+        device.set_band_time(Local::now()).await?;
+        let display_format = self.display_format(&device.address).await?;
+        imp.info_time.apply_values((device.get_band_time().await?, device.authenticated, display_format.time_format));
+
+        if let Some((latitude, longitude)) = self.weather_location(&device.address).await? {
+            match fetch_weather(latitude, longitude).await {
+                Ok(weather) => {
+                    let message = compose_weather(&weather, self.weather_format(&device.address).await?);
+                    self.send_alert_to(device, &Alert { alert_type: AlertType::Message, title: "Weather", message: &message }).await?;
+                },
+                Err(err) => self.show_error(&i18n_f("An error occurred while fetching the weather: {}", &[&err.to_string()]))
+            }
+        }
+        Ok(())
+    }
+
+    /// while traveling, this is wherever GeoClue says we are
+    /// otherwise (or if GeoClue doesn't know), it's the location set for the morning briefing
+    async fn weather_location(&self, band_mac: &str) -> store::Result<Option<(f64, f64)>> {
+        let (traveling, home) = {
+            let mut store = self.store().await?.lock().await;
+            let band_conf = store.get_band(band_mac.to_string());
+            (
+                band_conf.travel_mode.as_ref().map(|t| t.enabled).unwrap_or(false),
+                band_conf.morning_briefing.as_ref().and_then(|b| b.weather_location)
+            )
+        };
+        if traveling {
+            match current_location().await {
+                Ok(Some(location)) => return Ok(Some(location)),
+                Ok(None) => warn!("GeoClue couldn't find the current location"),
+                Err(err) => warn!("Error while getting the current location: {err}")
+            }
+        }
+        Ok(home)
+    }
+
+    /// sync activity every `interval_minutes` while the band is connected
+    /// stops when the band is closed or `stop_sync_scheduler` is called
+    fn start_sync_scheduler(&self, schedule: &SyncSchedule) {
+        let (stop_tx, stop_rx) = async_channel::bounded(1);
+        // stop the previous scheduler
+        if let Some(old_stop_tx) = self.imp().sync_stop.replace(Some(stop_tx)) {
+            let _ = old_stop_tx.try_send(());
+        }
+
+        let interval = Duration::from_secs(schedule.interval_minutes as u64 * 60);
+        let min_battery = schedule.min_battery;
+        spawn_future_local(clone!(@weak self as win => async move {
+            let _task = soak::track_task("sync schedule");
+            // only notify once each time the battery gets low
+            let mut postponed = false;
+            let Some(band_closed_rx) = win.imp().band_closed.borrow().as_ref().map(|a| a.1.clone()) else { return };
+
+            let mut sync = Timer::interval(interval).fuse();
+            pin_mut!(band_closed_rx);
+            pin_mut!(stop_rx);
+            loop {
+                select! {
+                    _ = band_closed_rx.next() => break,
+                    _ = stop_rx.next() => break,
+                    _ = sync.next() => {
+                        let battery = win.check_band_battery().await;
+                        // fetching the history keeps the radio busy for a while, so leave it until the band is charged
+                        if let Some(battery) = battery.filter(|b| !b.charging && b.battery_level < min_battery) {
+                            if !postponed {
+                                postponed = true;
+                                win.notify_sync_postponed(battery.battery_level).await;
+                            }
+                            continue;
+                        }
+                        postponed = false;
+
+                        let mut result = win.sync_activity(false).await;
+                        // the band is usually only busy for a moment
+                        if matches!(result, Err(BandError::Busy)) {
+                            Timer::after(BUSY_RETRY_DELAY).await;
+                            result = win.sync_activity(false).await;
+                        }
+                        if let Err(err) = result {
+                            warn!("Error while syncing activity: {err}");
+                        }
+                    }
+                }
+            }
+        }));
+    }
+
+    /// read the band's battery, update everything that shows it, and notify if it's low or charged
+    async fn check_band_battery(&self) -> Option<BatteryStatus> {
+        let (address, battery) = {
+            let current_device = self.imp().current_device.read().await;
+            let device = current_device.as_ref()?;
+            (device.address.clone(), device.get_battery().await.ok()?)
+        };
+        self.show_band_battery(&address, &battery).await;
+        Some(battery)
+    }
+
+    /// update everything that shows the battery, and notify if it's low or charged
+    async fn show_band_battery(&self, address: &str, battery: &BatteryStatus) {
+        self.publish_sample(address, MetricSample::Battery {
+            time: Local::now(),
+            level: battery.battery_level,
+            charging: battery.charging
+        }).await;
+        self.set_state_class("band-charging", battery.charging);
+        self.handle_battery_alert(address, battery).await;
+        self.cache_values(address, |cached| cached.battery = Some(Cached::now(battery.into()))).await;
+        if let Ok(display_format) = self.display_format(address).await {
+            self.imp().info_battery.apply_values((battery.clone(), display_format.time_format));
+        }
+    }
+
+    /// update the activity card as the band counts steps
+    async fn show_band_activity(&self, address: &str, activity: CurrentActivity) {
+        self.cache_values(address, |cached| cached.activity = Some(Cached::now((&activity).into()))).await;
+        if let Ok(display_format) = self.display_format(address).await {
+            self.imp().info_activity.apply_values((activity.clone(), display_format.distance_unit));
+        }
+        self.publish_sample(address, MetricSample::Totals { time: Local::now(), totals: activity }).await;
+    }
+
+    /// remember values the band sent, for when it can't be reached
+    /// only in memory - they're written with the next save
+    async fn cache_values(&self, address: &str, update: impl FnOnce(&mut CachedValues)) {
+        if let Ok(store) = self.store().await {
+            let mut store = store.lock().await;
+            update(store.get_band(address.to_string()).cached.get_or_insert_with(Default::default));
+        }
+    }
+
+    /// the band can't be reached: show the last battery, activity and time read from it, marked with when they were read
+    async fn show_cached_values(&self, address: &str) -> band::Result<()> {
+        let imp = self.imp();
+        let display_format = self.display_format(address).await?;
+        let cached = self.store().await?.lock().await
+            .get_band(address.to_string()).cached.clone().unwrap_or_default();
+        let unreachable = gettext("The band can't be reached");
+        let as_of = |read_at: Option<DateTime<Local>>| read_at
+            .map(|t| i18n_f("As of {} (the band can't be reached)", &[&format_recent_date(&t, display_format.time_format)]))
+            .unwrap_or_else(|| unreachable.clone());
+
+        match &cached.battery {
+            Some(battery) => {
+                imp.info_battery.apply_values((BatteryStatus::from(&battery.value), display_format.time_format));
+                imp.info_battery.set_stale(&as_of(battery.read_at()));
+            },
+            None => imp.info_battery.set_unavailable(&unreachable)
+        }
+        match cached.time.as_ref().and_then(|time| Some((Local.timestamp_opt(time.value, 0).single()?, time.read_at()))) {
+            Some((band_time, read_at)) => {
+                imp.info_time.apply_values((band_time, false, display_format.time_format));
+                imp.info_time.set_stale(&as_of(read_at));
+            },
+            None => imp.info_time.set_unavailable(&unreachable)
+        }
+        match &cached.activity {
+            Some(activity) => {
+                imp.info_activity.apply_values((CurrentActivity::from(&activity.value), display_format.distance_unit));
+                imp.info_activity.set_stale(&as_of(activity.read_at()));
+            },
+            None => imp.info_activity.set_unavailable(&unreachable)
+        }
+        Ok(())
+    }
+
+    /// keep the battery and activity cards up to date with the band's notifications until it's closed
+    fn watch_live_values(&self) {
+        spawn_future_local(clone!(@weak self as win => async move {
+            let _task = soak::track_task("band live values");
+            let band_closed_rx = win.imp().band_closed.borrow().as_ref().map(|a| a.1.clone());
+            let band = win.imp().current_device.read().await;
+            let Some((band_closed_rx, band)) = band_closed_rx.zip(band.as_ref()) else { return };
+
+            let mut battery = match band.stream_battery().await {
+                Ok(battery) => battery.fuse(),
+                Err(err) => {
+                    // the reload button still works
+                    warn!("Error while subscribing to the band's battery: {err}");
+                    return;
+                }
+            };
+            // guests can't read the activity, but the battery still updates
+            let mut activity = match band.stream_activity().await {
+                Ok(activity) => activity.boxed().fuse(),
+                Err(err) => {
+                    warn!("Error while subscribing to the band's activity: {err}");
+                    stream::pending().boxed().fuse()
+                }
+            };
+            pin_mut!(band_closed_rx);
+            loop {
+                select! {
+                    _ = band_closed_rx.next() => break,
+                    status = battery.next() => match status {
+                        Some(Some(status)) => win.show_band_battery(&band.address, &status).await,
+                        // not something we can parse
+                        Some(None) => {},
+                        None => break
+                    },
+                    totals = activity.next() => match totals {
+                        Some(Some(totals)) => win.show_band_activity(&band.address, totals).await,
+                        Some(None) => {},
+                        None => break
+                    }
+                }
+            }
+        }));
+    }
+
+    /// notify when the battery drops below the threshold or finishes charging
+    /// only changes are notified about, so this can run on every read
+    async fn handle_battery_alert(&self, address: &str, battery: &BatteryStatus) {
+        let previous = self.imp().last_battery.replace(Some((address.to_string(), battery.battery_level, battery.charging)))
+            // a different band
+            .filter(|(previous_address, _, _)| previous_address == address);
+        let (alert, alias) = match self.store().await {
+            Ok(store) => {
+                let mut store = store.lock().await;
+                let alias = store.get_band_alias(address).to_string();
+                (store.get_band(address.to_string()).battery_alert.clone().unwrap_or_default(), alias)
+            },
+            Err(_) => return
+        };
+
+        let is_low = |level: u8, charging: bool| !charging && level < alert.threshold;
+        let was_low = previous.as_ref().is_some_and(|(_, level, charging)| is_low(*level, *charging));
+        if alert.notify_low && is_low(battery.battery_level, battery.charging) && !was_low {
+            self.notify_desktop("battery-low", &gettext("Band battery low"), &i18n_f("{}'s battery is at {}%", &[&alias.to_string(), &battery.battery_level.to_string()]));
+        }
+
+        let was_full = previous.as_ref().is_some_and(|(_, level, _)| *level >= 100);
+        if alert.notify_charged && battery.charging && battery.battery_level >= 100 && previous.is_some() && !was_full {
+            self.notify_desktop("battery-charged", &gettext("Band charged"), &i18n_f("{} has finished charging", &[&alias.to_string()]));
+        }
+    }
+
+    async fn process_new_battery_alert(&self, alert: BatteryAlert) -> band::Result<()> {
+        if let Some(device) = self.imp().current_device.read().await.as_ref() {
+            let mut store_lock = self.store().await?
+                .lock().await;
+            store_lock.get_band(device.address.clone()).battery_alert = Some(alert);
+            Store::save_and_unlock(store_lock).await?;
+        };
+        Ok(())
+    }
+
+    async fn notify_sync_postponed(&self, level: u8) {
+        let Some(address) = self.imp().current_device.read().await.as_ref().map(|band| band.address.clone()) else { return };
+        let alias = match self.store().await {
+            Ok(store) => store.lock().await.get_band_alias(&address).to_string(),
+            Err(_) => return
+        };
+        self.notify_desktop("sync-postponed", &gettext("Sync postponed"), &i18n_f("{}'s battery is at {}%. Its history will be synced once it's charged.", &[&alias.to_string(), &level.to_string()]));
+    }
+
+    fn stop_sync_scheduler(&self) {
+        if let Some(stop_tx) = self.imp().sync_stop.take() {
+            let _ = stop_tx.try_send(());
+        }
+    }
+
+    async fn process_new_sync_schedule(&self, schedule: SyncSchedule) -> band::Result<()> {
+        if let Some(device) = self.imp().current_device.read().await.as_ref() {
+            let mut store_lock = self.store().await?
+                .lock().await;
+            store_lock.get_band(device.address.clone()).sync_schedule = Some(schedule.clone());
+            Store::save_and_unlock(store_lock).await?;
+        };
+
+        if schedule.enabled {
+            self.start_sync_scheduler(&schedule);
+        } else {
+            self.stop_sync_scheduler();
+        }
+        Ok(())
+    }
+
+    /// ask where to save `contents` and write it there
+    /// does nothing if they cancel
+    async fn save_to_file(&self, parent: &impl IsA<Window>, title: &str, initial_name: &str, contents: String) -> io::Result<()> {
+        let dialog = FileDialog::builder()
+            .title(title)
+            .initial_name(initial_name)
+            .modal(true)
+            .build();
+        // an error here means they cancelled
+        if let Some(path) = dialog.save_future(Some(parent)).await.ok().and_then(|f| f.path()) {
+            async_fs::write(path, contents).await?;
+        }
+        Ok(())
+    }
+
+    /// ask for a file and read it, returning its name and contents
+    /// `None` if they cancel
+    async fn open_file(&self, parent: &impl IsA<Window>, title: &str) -> io::Result<Option<(String, String)>> {
+        let dialog = FileDialog::builder()
+            .title(title)
+            .modal(true)
+            .build();
+        // an error here means they cancelled
+        match dialog.open_future(Some(parent)).await.ok().and_then(|f| f.path()) {
+            Some(path) => {
+                let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                Ok(Some((name, async_fs::read_to_string(path).await?)))
+            },
+            None => Ok(None)
+        }
+    }
+
+    async fn refresh_workouts(&self) {
+        let dialog = &self.imp().workout_history_dialog;
+        dialog.set_loading(true);
+        if let Err(err) = self.fetch_workouts().await {
+            self.show_error(&i18n_f("An error occurred while fetching workouts: {}", &[&err.to_string()]));
+        }
+        dialog.set_loading(false);
+    }
+
+    /// connect to, initialize, and show a new band
+    /// disconnects from the old connected band
+    async fn set_new_band(&self, device: DiscoveredDevice) -> band::Result<()> {
+        let imp = self.imp();
+
+        // close up the last band
+        let last_band_closed = imp.band_closed.borrow_mut().replace(async_channel::bounded(1));
+        if let Some((tx, _rx)) = last_band_closed {
+            let _ = tx.send(()).await;
+        }
+        
+        // connect to the band and store it
+        let discovered = device.clone();
+        let mut band = MiBand::from_discovered_device(self.session().await?.clone(), device).await?;
+        // anything still running for the band stops when it's closed
+        band.set_cancel(imp.band_closed.borrow().as_ref().map(|a| a.1.clone()));
+        let unknown = {
+            let mut store = self.store().await?
+                .lock().await;
+            let unknown = store.stored_band(&band.address).is_none();
+            // guests' settings and auth keys are only kept in memory
+            store.set_guest(band.address.clone(), imp.switch_guest.is_active());
+            band.set_gatt_paths(store.get_band(band.address.clone()).gatt_paths.clone());
+            band.set_io_policy(store.app.io_policy.unwrap_or_default());
+            band.set_simplify_alerts(store.app.simplify_alerts.unwrap_or(false));
+            unknown
+        };
+        
+        band.initialize().await?;
+        self.check_band_serial(&band, unknown).await?;
+        // attempt authentication with the current auth key
+        let current_auth_key = {
+            let mut store = self.store().await?
+                .lock().await;
+            let band_conf = store.get_band(band.address.clone());
+            // remember where the characteristics were for next time
+            band_conf.gatt_paths = band.gatt_paths().cloned();
+            // so it isn't offered to be forgotten
+            band_conf.last_connected = Some(Local::now().timestamp());
+            let auth_key = band_conf.auth_key.clone();
+            Store::save_and_unlock(store).await?;
+            auth_key
+        };
+        
+        // set the value of the auth key dialog to whatever they had
+        imp.auth_key_dialog.set_auth_key(current_auth_key.clone().unwrap_or_default());
+        imp.auth_key_dialog.set_address(band.address.clone());
+        
+        self.try_band_auth(&mut band, current_auth_key.clone()).await?;
+        // a band that's new to the app is walked through getting the key, instead of just highlighting the button
+        let needs_onboarding = unknown && !band.authenticated && !imp.switch_guest.is_active();
+        
+        let address = band.address.clone();
+        imp.current_device.write().await.replace(band);
+        let metric_sinks = self.store().await?.lock().await
+            .get_band(address.clone()).metric_sinks.clone().unwrap_or_default();
+        self.configure_metric_sinks(&metric_sinks).await?;
+
+        // load the workouts we've already fetched from this band
+        let workouts = self.store().await?.lock().await
+            .history(&address).workouts(&address).map_err(store::Error::from)?;
+        imp.workout_history_dialog.set_workouts(&workouts, &self.display_format(&address).await?);
+        imp.workouts.replace(workouts);
+
+        // show the device detail page
+        imp.main_stack.set_visible_child_name("device-detail");
+        // show the header buttons
+        imp.btn_back.set_visible(true);
+        imp.btn_reload.set_visible(true);
+        imp.box_band_state.set_visible(true);
+        self.reload_current_device().await?;
+
+        self.register_alert_source(NotificationSource);
+        self.register_alert_source(CalendarSource);
+        self.start_band_media();
+        self.watch_live_values();
+        imp.idle_disconnected.set(false);
+        imp.current_discovered.replace(Some(discovered.clone()));
+        self.watch_current_band_signal(discovered);
+        self.watch_metrics();
+
+        // a new connection starts measuring again even if it was taken off
+        imp.heart_rate_paused.set(false);
+        self.start_band_tasks(address.clone()).await?;
+
+        if imp.quick_toggles.get().do_not_disturb {
+            if let Some(band) = imp.current_device.read().await.as_ref() {
+                if let Err(err) = band.set_do_not_disturb(true).await {
+                    warn!("Error while turning on do not disturb: {err}");
+                }
+            }
+        }
+
+        // when it was chosen from the onboarding dialog, the dialog carries on by itself
+        if needs_onboarding && !imp.onboarding_dialog.is_visible() {
+            imp.onboarding_dialog.show_auth(&address, current_auth_key.as_deref());
+        }
+        
+        Ok(())
+    }
+
+    /// remember the band's serial number - and if it's one we haven't seen but a saved band that's out of range
+    /// has the same serial number, the band's address probably changed, so offer to move the saved one over to it
+    async fn check_band_serial(&self, band: &MiBand<'_>, unknown: bool) -> store::Result<()> {
+        let serial = match band.get_serial_number().await {
+            Ok(Some(serial)) => serial,
+            Ok(None) => return Ok(()),
+            Err(err) => {
+                warn!("Error while reading the serial number: {err}");
+                return Ok(());
+            }
+        };
+        let address = band.address.clone();
+        let in_range: HashSet<String> = self.imp().devices.borrow().as_ref()
+            .map(|devices| devices.iter::<DeviceRowObject>()
+                .filter_map(Result::ok)
+                .filter(|row| row.connected() || row.rssi() != 0)
+                .map(|row| row.address())
+                .collect())
+            .unwrap_or_default();
+
+        let old_band = {
+            let store = self.store().await?.lock().await;
+            if !unknown || store.is_guest(&address) {
+                None
+            } else {
+                store.band_with_serial(&serial, &address)
+                    .filter(|old_address| !in_range.contains(old_address))
+                    .map(|old_address| {
+                        let alias = store.get_band_alias(&old_address).to_string();
+                        (old_address, alias)
+                    })
+            }
+        };
+
+        let mut migrate = false;
+        if let Some((old_address, alias)) = &old_band {
+            let confirm = AlertDialog::builder()
+                .message(gettext("Is this the same band?"))
+                .detail(i18n_f(
+                    "This band has the same serial number as {} ({}), which hasn't been seen since. Move its name, auth key, settings, and history to the new address ({})?",
+                    &[alias, old_address, &address]
+                ))
+                .buttons([gettext("Keep Separate"), gettext("Move")])
+                .cancel_button(0)
+                .default_button(1)
+                .build();
+            migrate = confirm.choose_future(Some(self)).await == Ok(1);
+        }
+
+        let mut store = self.store().await?.lock().await;
+        if let Some((old_address, _)) = old_band.as_ref().filter(|_| migrate) {
+            store.migrate_band(old_address, address.clone())?;
+        }
+        let band_conf = store.get_band(address);
+        if migrate || band_conf.serial.as_ref() != Some(&serial) {
+            band_conf.serial = Some(serial);
+            Store::save_and_unlock(store).await?;
+        }
+        Ok(())
+    }
+
+    /// start (or stop) everything that runs in the background for the current band, according to its settings
+    async fn start_band_tasks(&self, address: String) -> band::Result<()> {
+        let (share_heart_rate, movement_gate, sync_schedule, briefing, sleep_summary, idle_disconnect) = {
+            let mut store = self.store().await?
+                .lock().await;
+            let band_conf = store.get_band(address);
+            (
+                band_conf.heart_rate_sharing.as_ref().map(|s| s.enabled).unwrap_or(false),
+                band_conf.movement_gate.as_ref().map(|g| g.enabled).unwrap_or(false),
+                band_conf.sync_schedule.clone().unwrap_or_default(),
+                band_conf.morning_briefing.as_ref().map(|b| b.enabled).unwrap_or(false),
+                band_conf.sleep_summary.as_ref().map(|s| s.enabled).unwrap_or(false),
+                band_conf.idle_disconnect.clone().unwrap_or_default()
+            )
+        };
+        // battery saver and the computer's battery override the band's own settings
+        let battery_saver = self.imp().quick_toggles.get().battery_saver;
+        let power_throttled = self.power_throttled();
+        if share_heart_rate && !battery_saver && !power_throttled && !self.imp().heart_rate_paused.get() {
+            self.start_heart_rate_sharing();
+        } else {
+            self.stop_heart_rate_sharing();
+        }
+        if movement_gate {
+            self.start_movement_gate();
+        } else {
+            self.stop_movement_gate();
+        }
+        if sync_schedule.enabled && !power_throttled {
+            self.start_sync_scheduler(&sync_schedule);
+        } else {
+            self.stop_sync_scheduler();
+        }
+        if briefing {
+            self.start_briefing_scheduler();
+        } else {
+            self.stop_briefing_scheduler();
+        }
+        if sleep_summary {
+            self.start_sleep_summary_scheduler();
+        } else {
+            self.stop_sleep_summary_scheduler();
+        }
+        if battery_saver {
+            let minutes = if idle_disconnect.enabled { idle_disconnect.minutes.min(BATTERY_SAVER_IDLE_MINUTES) } else { BATTERY_SAVER_IDLE_MINUTES };
+            self.start_idle_disconnect(minutes);
+        } else if idle_disconnect.enabled {
+            self.start_idle_disconnect(idle_disconnect.minutes);
+        } else {
+            self.stop_idle_disconnect();
+        }
+        Ok(())
+    }
+
+    fn setup_device_cards(&self) {
+        let imp = self.imp();
+        imp.info_battery.handle_items(&BATTERY_ITEMS);
+        imp.info_time.handle_items(&TIME_ITEMS);
+        imp.info_device.handle_items(&DEVICE_INFO_ITEMS);
+        imp.info_activity.handle_items(&ACTIVITY_ITEMS);
+        imp.info_activity_goal.handle_items(&ACTIVITY_GOAL_ITEMS);
+        imp.info_band_lock.handle_items(&BAND_LOCK_ITEMS);
+        imp.info_display_format.handle_items(&DISPLAY_FORMAT_ITEMS);
+        imp.info_heart_rate.handle_items(&HEART_RATE_ITEMS);
+        imp.info_movement.handle_items(&MOVEMENT_ITEMS);
+        imp.info_workouts.handle_items(&WORKOUT_ITEMS);
+        imp.info_metric_sinks.handle_items(&METRIC_SINK_ITEMS);
+        imp.info_briefing.handle_items(&BRIEFING_ITEMS);
+        imp.info_travel.handle_items(&TRAVEL_ITEMS);
+        imp.info_sleep_summary.handle_items(&SLEEP_SUMMARY_ITEMS);
+        imp.info_app_lock.handle_items(&APP_LOCK_ITEMS);
+        imp.info_forwarding.handle_items(&FORWARDING_ITEMS);
+        imp.info_test_alert.handle_items(&TEST_ALERT_ITEMS);
+        imp.info_developer.handle_items(&DEVELOPER_ITEMS);
+    }
+
+    /// forwards everything `source` produces to the current band
+    /// if a source with the same name has already been registered, it does nothing
+    ///
+    /// if the source fails to start, this retries with an exponential backoff
+    fn register_alert_source(&self, source: impl AlertSource + 'static) {
+        // only start each source once
+        if !self.imp().alert_sources.borrow_mut().insert(source.name()) { return; }
+
+        let (retry_tx, retry_rx) = async_channel::bounded(1);
+        self.imp().alert_sources_retry.borrow_mut().push(retry_tx);
+
+        spawn_future_local(clone!(@weak self as win => async move {
+            let _task = soak::track_task(source.name());
+            pin_mut!(retry_rx);
+            let mut delay = MONITOR_RETRY_INITIAL;
+            loop {
+                match source.start().await {
+                    Ok(stream) => {
+                        // the source is running now - reset the backoff
+                        delay = MONITOR_RETRY_INITIAL;
+                        win.hide_source_denied(source.name());
+                        stream.for_each(|alert| {
+                            let win = win.clone();
+                            async move {
+                                // turned off from the quick settings
+                                if !win.imp().quick_toggles.get().forwarding { return }
+                                // or just for this band
+                                let address = win.imp().current_device.read().await.as_ref().map(|band| band.address.clone());
+                                if let Some(address) = address {
+                                    let forwarding = win.forwarding(&address).await;
+                                    let forwarded = match alert.alert_type {
+                                        AlertType::Call | AlertType::MissedCall => forwarding.calls,
+                                        _ => forwarding.notifications
+                                    };
+                                    if !forwarded { return }
+                                }
+                                win.mark_active();
+                                win.wake_from_idle().await;
+                                // make sure there is a current band
+                                if let Some(band) = win.imp().current_device.read().await.as_ref() {
+                                    // send it to the band
+                                    match win.send_alert_to(band, &alert.as_alert()).await {
+                                        // it's the one on the band's screen now
+                                        Ok(()) => win.imp().shown_notification.set(alert.notification_id),
+                                        Err(err) => win.show_error(&i18n_f("An error occurred while sending an alert to the band: {}", &[&err.to_string()]))
+                                    }
+                                }
+                            }
+                        }).await;
+                        // the stream only ends when the source loses its connection, so start over
+                        warn!("{} alert source ended, restarting", source.name());
+                    },
+                    Err(AlertSourceError::Denied(err)) => {
+                        warn!("{} alert source isn't allowed to listen: {err}", source.name());
+                        win.show_source_denied(&source, &err, delay);
+                    },
+                    // nothing to listen to on this desktop - keep checking in case it shows up
+                    Err(AlertSourceError::Unavailable(err)) => {
+                        debug!("{} alert source is unavailable: {err}", source.name());
+                    },
+                    // display any other errors that occur, but only once per streak
+                    Err(err) => {
+                        let message = i18n_f("An error occurred while starting to forward {} to the band: {}", &[&source.name().to_lowercase(), &err.to_string()]);
+                        if delay == MONITOR_RETRY_INITIAL {
+                            win.show_error(&message);
+                        } else {
+                            error!("{message}");
+                        }
+                    }
+                }
+
+                // wait for the backoff timer or for the user to ask for a retry
+                let mut backoff = Timer::after(delay).fuse();
+                select! {
+                    _ = backoff.next() => {},
+                    retry = retry_rx.next() => {
+                        // the window is gone
+                        if retry.is_none() { break; }
+                    }
+                }
+                delay = (delay * 2).min(MONITOR_RETRY_MAX);
+            }
+        }));
+    }
+
+    /// show the page explaining why `source` can't be forwarded
+    fn show_source_denied(&self, source: &dyn AlertSource, error: &str, retry_delay: Duration) {
+        let imp = self.imp();
+        // another source is already being explained
+        if imp.denied_source.get().is_some_and(|name| name != source.name()) { return }
+
+        imp.denied_source.set(Some(source.name()));
+        imp.label_source_denied_title.set_label(&i18n_f("Forwarding {} to the band is blocked", &[&source.name().to_lowercase()]));
+        imp.label_source_denied_help.set_markup(&source.denied_help());
+        imp.label_source_error.set_label(error);
+        imp.label_source_retry.set_label(&i18n_f("Retrying automatically in {} seconds", &[&retry_delay.as_secs().to_string()]));
+
+        // only take over the window the first time it fails, and not if they've dismissed it
+        if retry_delay == MONITOR_RETRY_INITIAL && !imp.source_denied_dismissed.get() {
+            imp.main_stack.set_visible_child_name("source-denied");
+        }
+    }
+
+    /// go back to the device page if the denied page is shown for `source_name`
+    fn hide_source_denied(&self, source_name: &str) {
+        let imp = self.imp();
+        if imp.denied_source.get() != Some(source_name) { return }
+
+        imp.denied_source.set(None);
+        imp.source_denied_dismissed.set(false);
+        if imp.main_stack.visible_child_name().as_deref() == Some("source-denied") {
+            imp.main_stack.set_visible_child_name("device-detail");
+        }
+    }
+
+    /// gets an MPRIS controller
+    /// if this has already been called before, it returns the existing instance
+    async fn get_mpris_controller(&self) -> Sender<MusicEvent> {
+        static CONTROLLER: OnceCell<Sender<MusicEvent>> = OnceCell::new();
+        CONTROLLER.get_or_init(|| async {
+            let (mpris_tx, mut mpris_rx) = mpsc::channel(1);
+            let (controller_tx, controller_rx) = mpsc::channel(3);
+            spawn_future_local(async move {
+                let _ = watch_mpris(mpris_tx, controller_rx).await;
+            });
+            spawn_future_local(clone!(@weak self as win => async move {
+                while let Some(item) = mpris_rx.next().await {
+                    // skip it while the band is idle - it gets the current media when it reconnects
+                    if win.imp().idle_disconnected.get() { continue }
+                    // the same goes for when the music screen is closed, since opening it asks for the current media
+                    if !win.music_screen_open() { continue }
+                    win.mark_active();
+                    // make sure there is a current band
+                    if let Some(band) = win.imp().current_device.read().await.as_ref() {
+                        if !win.forwarding(&band.address).await.media { continue }
+                        // send it to the band
+                        if let Err(err) = band.set_media_info(&item).await {
+                            win.show_error(&i18n_f("An error occurred while setting the media state: {}", &[&err.to_string()]));
+                        }
+                    }
+                }
+            }));
+            controller_tx
+        }).await.clone()
+    }
+
+    /// keep track of whether the band's music screen is open
+    /// the band doesn't always say when it closes it, so any music event keeps it open for the idle timeout
+    fn handle_music_screen_event(&self, event: MusicEvent) {
+        let imp = self.imp();
+        let open_until = match event {
+            MusicEvent::Close => None,
+            _ => Some(Instant::now() + Duration::from_secs(imp.music_idle_timeout_secs.get() as u64))
+        };
+        imp.music_open_until.set(open_until);
+    }
+
+    /// whether music updates should be sent to the band
+    fn music_screen_open(&self) -> bool {
+        let imp = self.imp();
+        // no timeout means always sending them
+        if imp.music_idle_timeout_secs.get() == 0 { return true }
+        match imp.music_open_until.get() {
+            Some(open_until) if Instant::now() < open_until => true,
+            Some(_) => {
+                debug!("The band's music screen timed out");
+                imp.music_open_until.set(None);
+                false
+            },
+            None => false
+        }
+    }
+
+    fn start_band_media(&self) {
+        spawn_future_local(clone!(@weak self as win => async move {
+            let _task = soak::track_task("band media");
+            // get the Receiver for when the band is closed
+            let band_closed_rx = win.imp().band_closed.borrow().as_ref().map(|a| a.1.clone());
+            // get the current band
+            let band = win.imp().current_device.read().await;
+            if let Some((band_closed_rx, band)) = band_closed_rx.zip(band.as_ref()) {
+                // start listening to the media button events
+                match band.stream_device_events().await.map(|s| s.fuse()) {
+                    Ok(mut device_events) => {
+                        let mut mpris_controller_tx = win.get_mpris_controller().await;
+                        pin_mut!(band_closed_rx);
+                        loop {
+                            select! {
+                                // stop watching for events
+                                _ = band_closed_rx.next() => {
+                                    break;
+                                },
+                                event = device_events.next() => {
+                                    match event {
+                                        // send all music events to the mpris controller
+                                        Some(Some(DeviceEvent::Music(event))) => {
+                                            win.mark_active();
+                                            win.handle_music_screen_event(event);
+                                            // someone is pressing the buttons, so it's being worn
+                                            win.resume_heart_rate();
+                                            if win.forwarding(&band.address).await.media && mpris_controller_tx.send(event).await.is_err() {
+                                                break;
+                                            }
+                                        },
+                                        Some(Some(DeviceEvent::RemovedFromWrist)) => win.handle_band_removed().await,
+                                        Some(Some(DeviceEvent::FellAsleep)) => win.handle_sleep_change(true).await,
+                                        Some(Some(DeviceEvent::WokeUp)) => win.handle_sleep_change(false).await,
+                                        Some(Some(DeviceEvent::AlertDismissed)) => win.handle_alert_dismissed().await,
+                                        Some(Some(DeviceEvent::GoalReached)) => win.handle_goal_reached(&band.address).await,
+                                        Some(Some(DeviceEvent::ButtonLongPressed)) => {
+                                            win.mark_active();
+                                            if win.forwarding(&band.address).await.media && mpris_controller_tx.send(MusicEvent::SwitchPlayer).await.is_err() {
+                                                break;
+                                            }
+                                        },
+                                        // nothing needs these yet
+                                        Some(Some(_)) | Some(None) => {},
+                                        None => break
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    Err(err) => {
+                        win.show_error(&i18n_f("Error while starting the band media: {}", &[&err.to_string()]));
+                    }
+                }
+            } else {
+                win.show_error(&gettext("Error while starting the band media: no band found"));
+            }
+        }));
+    }
+
+    /// show what the band is doing in the header
+    /// a band that's replaced (or fails to connect) is dropped, which ends its stream, so only the newest one is shown
+    fn watch_band_state(&self, mut states: async_channel::Receiver<BandState>) {
+        spawn_future_local(clone!(@weak self as win => async move {
+            let _task = soak::track_task("band state");
+            while let Some(state) = states.next().await {
+                win.show_band_state(state);
+            }
+        }));
+    }
+
+    fn show_band_state(&self, state: BandState) {
+        let imp = self.imp();
+        let (label, tooltip) = match state {
+            BandState::Disconnected => (gettext("Disconnected"), gettext("The band isn't connected - nothing can be changed until it reconnects")),
+            BandState::Connecting => (gettext("Connecting…"), gettext("Connecting to the band")),
+            BandState::Authenticating => (gettext("Authenticating…"), gettext("Checking the auth key with the band")),
+            BandState::Connected => (gettext("Connected"), gettext("The band is connected")),
+            BandState::Syncing => (gettext("Syncing…"), gettext("Downloading data from the band - other changes wait until it's done"))
+        };
+        imp.label_band_state.set_label(&label);
+        imp.box_band_state.set_tooltip_text(Some(&tooltip));
+        imp.spinner_band_state.set_visible(state.is_busy());
+        imp.spinner_band_state.set_spinning(state.is_busy());
+        self.set_state_class("band-busy", state.is_busy());
+    }
+
+    /// show the signal strength of the current band until it's closed
+    /// and reconnect if the connection drops
+    fn watch_current_band_signal(&self, device: DiscoveredDevice) {
+        self.show_signal(None, true);
+        spawn_future_local(clone!(@weak self as win => async move {
+            let _task = soak::track_task("band signal");
+            let Some(band_closed_rx) = win.imp().band_closed.borrow().as_ref().map(|a| a.1.clone()) else { return };
+            let events = match win.session().await {
+                Ok(session) => MiBand::stream_band_events(session, device.path.clone()).await,
+                Err(err) => Err(err)
+            };
+
+            match events {
+                Ok(events) => {
+                    let events = events.fuse();
+                    pin_mut!(events);
+                    pin_mut!(band_closed_rx);
+                    loop {
+                        select! {
+                            _ = band_closed_rx.next() => break,
+                            event = events.next() => {
+                                match event {
+                                    Some((_, BandChangeEvent::RSSI(rssi))) => win.show_signal(rssi, true),
+                                    Some((_, BandChangeEvent::Connected(false))) => {
+                                        if !win.imp().idle_disconnected.get() { soak::record_disconnect(); }
+                                        win.show_signal(None, false);
+                                        if let Some(band) = win.imp().current_device.read().await.as_ref() {
+                                            band.set_disconnected();
+                                        }
+                                        if let Err(err) = win.show_cached_values(&device.address).await {
+                                            warn!("Error while showing the band's cached values: {err}");
+                                        }
+                                        win.reconnect_current_band(device);
+                                        break;
+                                    },
+                                    Some(_) => {},
+                                    None => break
+                                }
+                            }
+                        }
+                    }
+                },
+                Err(err) => warn!("Error while watching the band signal: {err}")
+            }
+        }));
+    }
+
+    /// keep trying to reconnect to the current band after it disconnected on its own
+    /// stops once the band is closed or the user disconnects it
+    fn reconnect_current_band(&self, device: DiscoveredDevice) {
+        spawn_future_local(clone!(@weak self as win => async move {
+            let _task = soak::track_task("reconnect");
+            let imp = win.imp();
+            let mut delay = RECONNECT_RETRY_INITIAL;
+            let mut toast_shown = false;
+            loop {
+                // a failed attempt replaces the channel, so get it again every time
+                let Some(band_closed_rx) = imp.band_closed.borrow().as_ref().map(|a| a.1.clone()) else { return };
+                let mut timer = Timer::after(delay).fuse();
+                pin_mut!(band_closed_rx);
+                select! {
+                    _ = band_closed_rx.next() => return,
+                    _ = timer => {}
+                }
+
+                // they disconnected it themselves, or it was idle
+                if imp.auto_connect_skip.borrow().contains(&device.address) || imp.idle_disconnected.get() { return }
+                if let Some(current) = imp.current_device.read().await.as_ref() {
+                    if current.address != device.address { return }
+                }
+                if !toast_shown {
+                    win.show_toast(&gettext("Lost the connection to the band. Reconnecting..."), Severity::Warning);
+                    toast_shown = true;
+                }
+
+                // connect, authenticate, and restart everything from scratch
+                match win.set_new_band(device.clone()).await {
+                    Ok(()) => {
+                        soak::record_reconnect(true);
+                        win.show_toast(&gettext("Reconnected to the band"), Severity::Info);
+                        return;
+                    },
+                    Err(err) => {
+                        soak::record_reconnect(false);
+                        warn!("Error while reconnecting to the band: {err}");
+                    }
+                }
+                delay = (delay * 2).min(RECONNECT_RETRY_MAX);
+            }
+        }));
+    }
+
+    fn refresh_developer_card(&self) {
+        let card = &self.imp().info_developer;
+        card.apply_values(trace::state());
+        card.apply_values(soak::state());
+        card.apply_values(metrics::snapshot());
+    }
+
+    /// append a soak sample every `SOAK_INTERVAL` until `stop_soak_diagnostics` is called
+    /// unlike the band's tasks, this keeps going across connections
+    fn start_soak_diagnostics(&self) {
+        let (stop_tx, stop_rx) = async_channel::bounded(1);
+        if let Some(old_stop_tx) = self.imp().soak_stop.replace(Some(stop_tx)) {
+            let _ = old_stop_tx.try_send(());
+        }
+        soak::set_enabled(true);
+
+        spawn_future_local(clone!(@weak self as win => async move {
+            let _task = soak::track_task("soak diagnostics");
+            let mut interval = Timer::interval(SOAK_INTERVAL).fuse();
+            pin_mut!(stop_rx);
+            loop {
+                // the first sample is written right away
+                if let Err(err) = soak::write_sample().await {
+                    warn!("Error while writing a soak sample: {err}");
+                }
+                select! {
+                    _ = stop_rx.next() => break,
+                    _ = interval.next() => {}
+                }
+            }
+        }));
+    }
+
+    fn stop_soak_diagnostics(&self) {
+        soak::set_enabled(false);
+        if let Some(stop_tx) = self.imp().soak_stop.take() {
+            let _ = stop_tx.try_send(());
+        }
+    }
+
+    async fn process_new_soak_diagnostics(&self, enabled: bool) -> band::Result<()> {
+        if enabled {
+            self.start_soak_diagnostics();
+        } else {
+            self.stop_soak_diagnostics();
+        }
+        let mut store = self.store().await?.lock().await;
+        store.app.soak_diagnostics = Some(enabled);
+        Store::save_and_unlock(store).await?;
+        Ok(())
+    }
+
+    /// keep the GATT metrics up to date until the band is closed
+    fn watch_metrics(&self) {
+        spawn_future_local(clone!(@weak self as win => async move {
+            let _task = soak::track_task("metrics");
+            let Some(band_closed_rx) = win.imp().band_closed.borrow().as_ref().map(|a| a.1.clone()) else { return };
+            let mut interval = Timer::interval(METRICS_REFRESH_INTERVAL).fuse();
+            pin_mut!(band_closed_rx);
+            loop {
+                select! {
+                    _ = band_closed_rx.next() => break,
+                    _ = interval.next() => {
+                        win.imp().info_developer.apply_values(metrics::snapshot());
+                    }
+                }
+            }
+        }));
+    }
+
+    fn show_signal(&self, rssi: Option<i16>, connected: bool) {
+        let imp = self.imp();
+        imp.image_signal.set_icon_name(Some(signal_icon_name(rssi)));
+        imp.label_signal.set_label(&match (connected, rssi) {
+            (false, _) => "Disconnected".to_string(),
+            (true, Some(rssi)) => format!("{rssi} dBm"),
+            (true, None) => "? dBm".to_string()
+        });
+        self.set_state_class("band-connected", connected);
+        imp.mini_window.set_connected(connected);
+
+        if let Some(service) = imp.band_service.borrow().clone() {
+            let address = imp.current_discovered.borrow().as_ref().map(|d| d.address.clone()).unwrap_or_default();
+            spawn_future_local(async move {
+                if let Err(err) = service.set_connected(address, connected).await {
+                    warn!("Error while updating the band's D-Bus state: {err}");
+                }
+            });
+        }
+    }
+
+    /// continuously measure the heart rate, showing it on the heart rate card
+    /// and sharing it with local apps over a socket
+    /// stops when the band is closed or `stop_heart_rate_sharing` is called
+    fn start_heart_rate_sharing(&self) {
+        let (stop_tx, stop_rx) = async_channel::bounded(1);
+        // stop the previous session
+        if let Some(old_stop_tx) = self.imp().heart_rate_stop.replace(Some(stop_tx)) {
+            let _ = old_stop_tx.try_send(());
+        }
+
+        spawn_future_local(clone!(@weak self as win => async move {
+            let _task = soak::track_task("heart rate sharing");
+            // get the Receiver for when the band is closed
+            let Some(band_closed_rx) = win.imp().band_closed.borrow().as_ref().map(|a| a.1.clone()) else { return };
+
+            // we can't hold the band across the whole loop, so only borrow it to start the stream
+            let (address, heart_rate) = match win.imp().current_device.read().await.as_ref() {
+                Some(band) => (band.address.clone(), band.stream_heart_rate().await),
+                None => return
+            };
+
+            match heart_rate.map(|s| s.fuse()) {
+                Ok(mut heart_rate) => {
+                    let (mut socket_tx, socket_rx) = mpsc::channel(4);
+                    spawn_future_local(async move {
+                        if let Err(err) = serve_heart_rate(socket_rx).await {
+                            error!("Error while sharing the heart rate: {err}");
+                        }
+                    });
+
+                    let mut ping = Timer::interval(HEART_RATE_PING_INTERVAL).fuse();
+                    pin_mut!(band_closed_rx);
+                    pin_mut!(stop_rx);
+                    loop {
+                        select! {
+                            _ = band_closed_rx.next() => break,
+                            _ = stop_rx.next() => break,
+                            _ = ping.next() => {
+                                if let Some(band) = win.imp().current_device.read().await.as_ref() {
+                                    if let Err(err) = band.ping_heart_rate().await {
+                                        warn!("Error while pinging the heart rate monitor: {err}");
+                                    }
+                                }
+                            },
+                            measurement = heart_rate.next() => {
+                                match measurement {
+                                    Some(Some(measurement)) => {
+                                        win.imp().info_heart_rate.apply_values(measurement);
+                                        win.publish_sample(&address, MetricSample::HeartRate { time: Local::now(), bpm: measurement.bpm }).await;
+                                        // if nobody is reading fast enough, just drop the measurement
+                                        let _ = socket_tx.try_send(measurement);
+                                    },
+                                    Some(None) => {},
+                                    None => break
+                                }
+                            }
+                        }
+                    }
+
+                    if let Some(band) = win.imp().current_device.read().await.as_ref() {
+                        let _ = band.stop_heart_rate().await;
+                    }
+                },
+                Err(err) => {
+                    win.show_error(&i18n_f("Error while starting heart rate monitoring: {}", &[&err.to_string()]));
+                }
+            }
+        }));
+    }
+
+    /// close the desktop notification that was last forwarded to the band
+    async fn handle_alert_dismissed(&self) {
+        let Some(id) = self.imp().shown_notification.take() else { return };
+        // it might have already been closed on the desktop
+        if let Err(err) = close_notification(id).await {
+            warn!("Error while closing notification {id}: {err}");
+        }
+    }
+
+    /// turn the band's fell asleep/woke up events into sleep events for the metric sinks
+    /// sleep only ends once they've stayed awake for `SLEEP_END_DELAY`
+    async fn handle_sleep_change(&self, asleep: bool) {
+        let Some(address) = self.imp().current_device.read().await.as_ref().map(|band| band.address.clone()) else { return };
+        let imp = self.imp();
+        let now = Local::now();
+        if asleep {
+            imp.woke_at.set(None);
+            // falling back asleep is still the same night
+            if !imp.asleep.replace(true) {
+                self.publish_sample(&address, MetricSample::Sleep { time: now, event: SleepEvent::Started }).await;
+            }
+            return;
+        }
+
+        self.publish_sample(&address, MetricSample::Sleep { time: now, event: SleepEvent::Awake }).await;
+        if !imp.asleep.get() { return }
+        imp.woke_at.set(Some(now));
+        spawn_future_local(clone!(@weak self as win => async move {
+            Timer::after(SLEEP_END_DELAY).await;
+            let imp = win.imp();
+            // they fell asleep again (or woke up again, which has its own timer)
+            if imp.woke_at.get() != Some(now) { return }
+            imp.woke_at.set(None);
+            imp.asleep.set(false);
+            win.publish_sample(&address, MetricSample::Sleep { time: Local::now(), event: SleepEvent::Ended }).await;
+        }));
+    }
+
+    /// notify and/or pause heart rate measurement, depending on the band's wear detection settings
+    async fn handle_band_removed(&self) {
+        let Some(address) = self.imp().current_device.read().await.as_ref().map(|band| band.address.clone()) else { return };
+        let (wear_detection, alias) = match self.store().await {
+            Ok(store) => {
+                let mut store = store.lock().await;
+                let alias = store.get_band_alias(&address).to_string();
+                (store.get_band(address).wear_detection.clone().unwrap_or_default(), alias)
+            },
+            Err(_) => return
+        };
+
+        if wear_detection.notify {
+            self.notify_desktop("band-removed", &gettext("Band taken off"), &i18n_f("{} is no longer being worn", &[&alias.to_string()]));
+        }
+        // only pause if it's actually measuring
+        if wear_detection.pause_heart_rate && self.imp().heart_rate_stop.borrow().is_some() {
+            self.stop_heart_rate_sharing();
+            self.imp().heart_rate_paused.set(true);
+            self.imp().info_heart_rate.apply_values(InfoItemValues::from([
+                ("heart_rate".into(), InfoItemValue::Field("Paused (band taken off)".into()))
+            ]));
+        }
+    }
 
-        // highlight the auth key button
-        self.imp().btn_auth_key.add_css_class("suggested-action");
+    /// restart heart rate measurement if it was paused because the band was taken off
+    fn resume_heart_rate(&self) {
+        if self.imp().heart_rate_paused.replace(false) && !self.imp().quick_toggles.get().battery_saver && !self.power_throttled() {
+            self.start_heart_rate_sharing();
+        }
+    }
 
-        Ok(())
+    fn stop_heart_rate_sharing(&self) {
+        if let Some(stop_tx) = self.imp().heart_rate_stop.take() {
+            let _ = stop_tx.try_send(());
+        }
     }
 
-    async fn reload_current_device(&self) -> band::Result<()> {
-        let imp = self.imp();
-        if let Some(device) = imp.current_device.read().await.as_ref() {
-            {
-                // display the band alias/name
-                let store = self.store().await?
-                    .lock().expect("can lock store");
-                let band_alias = store.get_band_alias(&device.address);
-                imp.address_label.set_text(band_alias);
-                self.set_all_titles(&format!("{} - Mi Band 4", band_alias));
-            }
+    /// periodically check the step count and remind the user to move
+    /// stops when the band is closed or `stop_movement_gate` is called
+    fn start_movement_gate(&self) {
+        let (stop_tx, stop_rx) = async_channel::bounded(1);
+        // stop the previous gate
+        if let Some(old_stop_tx) = self.imp().movement_stop.replace(Some(stop_tx)) {
+            let _ = old_stop_tx.try_send(());
+        }
 
-            // if not connected, stop here
-            if !device.is_connected().await { return Ok(()) }
+        spawn_future_local(clone!(@weak self as win => async move {
+            let _task = soak::track_task("movement reminders");
+            let Some(band_closed_rx) = win.imp().band_closed.borrow().as_ref().map(|a| a.1.clone()) else { return };
 
-            // set everything to loading
-            imp.info_battery.set_loading();
-            imp.info_time.set_loading();
-            imp.info_device.set_loading();
-            imp.info_activity.set_loading();
-            imp.info_activity_goal.set_loading();
-            imp.info_band_lock.set_loading();
+            let mut tracker = MovementTracker::default();
+            let mut check = Timer::interval(MOVEMENT_CHECK_INTERVAL).fuse();
+            pin_mut!(band_closed_rx);
+            pin_mut!(stop_rx);
+            loop {
+                select! {
+                    _ = band_closed_rx.next() => break,
+                    _ = stop_rx.next() => break,
+                    _ = check.next() => {
+                        if let Err(err) = win.check_movement(&mut tracker).await {
+                            warn!("Error while checking movement: {err}");
+                        }
+                    }
+                }
+            }
+        }));
+    }
 
-            // load all of the data
-            imp.info_battery.apply_values(device.get_battery().await?);
-            imp.info_time.apply_values((
-                device.get_band_time().await?,
-                device.authenticated
-            ));
-            imp.info_device.apply_values((
-                device,
-                device.get_firmware_revision().await?
-            ));
-            imp.info_activity.apply_values(device.get_current_activity().await?);
+    fn stop_movement_gate(&self) {
+        if let Some(stop_tx) = self.imp().movement_stop.take() {
+            let _ = stop_tx.try_send(());
+        }
+    }
 
-            // we need to lock the store again so that it's not held across await
-            let mut store = self.store().await?
-                .lock()
-                .expect("can lock store");
-            let band_conf = &*store.get_band(device.address.clone());
+    /// record the current step count, and send escalating reminders if they haven't walked enough
+    async fn check_movement(&self, tracker: &mut MovementTracker) -> band::Result<()> {
+        if let Some(band) = self.imp().current_device.read().await.as_ref() {
+            let (gate, traveling) = {
+                let mut store = self.store().await?
+                    .lock().await;
+                let band_conf = store.get_band(band.address.clone());
+                (
+                    band_conf.movement_gate.clone().unwrap_or_default(),
+                    band_conf.travel_mode.as_ref().map(|t| t.enabled).unwrap_or(false)
+                )
+            };
 
-            // activity goal
-            imp.info_activity_goal
-                .apply_values(band_conf.activity_goal.as_ref().unwrap_or(&ActivityGoal::default()));
+            let now = Local::now();
+            // travel days don't follow the usual schedule
+            if traveling || !in_schedule(&gate, &now) {
+                tracker.reset();
+                return Ok(());
+            }
 
-            // band lock
-            imp.info_band_lock
-                .apply_values(band_conf.band_lock.as_ref().unwrap_or(&BandLock::default()));
-        }
+            let activity = band.get_current_activity().await?;
+            if let Some(level) = tracker.record(&gate, now, activity.steps) {
+                if gate.skip_meetings && in_meeting().await {
+                    // don't escalate while they can't get up
+                    tracker.reset_level();
+                    return Ok(());
+                }
 
+                let message = i18n_f("You've walked fewer than {} steps in the last hour", &[&gate.steps.to_string()]);
+                // vibrate longer each time
+                let alert_type = match level {
+                    1 => AlertType::Message,
+                    2 => AlertType::MissedCall,
+                    _ => AlertType::Call
+                };
+                self.send_alert_to(band, &Alert { alert_type, title: "Time to move", message: &message }).await?;
+                self.notify_desktop("movement", &gettext("Time to move"), &message);
+            }
+        }
         Ok(())
     }
 
-    /// connect to, initialize, and show a new band
-    /// disconnects from the old connected band
-    async fn set_new_band(&self, device: DiscoveredDevice) -> band::Result<()> {
-        let imp = self.imp();
+    /// send an alert to the band and keep it in the alert history
+    async fn send_alert_to(&self, band: &MiBand<'_>, alert: &Alert<'_>) -> band::Result<()> {
+        band.send_alert(alert).await?;
 
-        let mut band_closed = imp.band_closed.borrow_mut();
-        // close up the last band
-        if let Some((tx, _rx)) = band_closed.replace(async_channel::bounded(1)) {
-            let _ = tx.send(()).await;
+        let imp = self.imp();
+        let id = imp.alert_history_next_id.get();
+        imp.alert_history_next_id.set(id + 1);
+        {
+            let mut history = imp.alert_history.borrow_mut();
+            if history.len() >= ALERT_HISTORY_LENGTH {
+                history.pop_front();
+            }
+            history.push_back(SentAlert {
+                id,
+                time: Local::now(),
+                alert_type: alert.alert_type,
+                title: alert.title.to_string(),
+                message: alert.message.to_string()
+            });
         }
-        
-        // connect to the band and store it
-        let mut band = MiBand::from_discovered_device(self.session().await?.clone(), device).await?;
-        
-        band.initialize().await?;
-        // attempt authentication with the current auth key
-        let current_auth_key = self.store().await?
-            .lock()
-            .expect("can lock store")
-            .get_band(band.address.clone()).auth_key.clone();
-        
-        // set the value of the auth key dialog to whatever they had
-        imp.auth_key_dialog.set_auth_key(current_auth_key.clone().unwrap_or_default());
-        
-        self.try_band_auth(&mut band, current_auth_key).await?;
-        
-        imp.current_device.write().await.replace(band);
-
-        // show the device detail page
-        imp.main_stack.set_visible_child_name("device-detail");
-        // show the header buttons
-        imp.btn_back.set_visible(true);
-        imp.btn_reload.set_visible(true);
-        self.reload_current_device().await?;
 
-        self.forward_notifications();
-        self.start_band_media();
-        
+        self.refresh_alert_history(Some(&band.address)).await;
         Ok(())
     }
 
-    fn setup_device_cards(&self) {
+    /// put the alert history in its dialog - it's left empty while the app is locked,
+    /// so the notifications it has in it can't be read from the window
+    async fn refresh_alert_history(&self, band_mac: Option<&str>) {
         let imp = self.imp();
-        imp.info_battery.handle_items(&BATTERY_ITEMS);
-        imp.info_time.handle_items(&TIME_ITEMS);
-        imp.info_device.handle_items(&DEVICE_INFO_ITEMS);
-        imp.info_activity.handle_items(&ACTIVITY_ITEMS);
-        imp.info_activity_goal.handle_items(&ACTIVITY_GOAL_ITEMS);
-        imp.info_band_lock.handle_items(&BAND_LOCK_ITEMS);
+        if self.is_locked() {
+            imp.alert_history_dialog.set_alerts(std::iter::empty(), DisplayFormat::default().time_format);
+            return;
+        }
+        // the history is only for display - don't fail the send over it
+        let time_format = match band_mac {
+            Some(band_mac) => self.display_format(band_mac).await.unwrap_or_default().time_format,
+            None => DisplayFormat::default().time_format
+        };
+        imp.alert_history_dialog.set_alerts(imp.alert_history.borrow().iter(), time_format);
     }
 
-    /// forwards notifs from org.freedesktop.Notifications to the current band
-    /// if this has already been called before, it does nothing
-    fn forward_notifications(&self) {
-        static START: Once = Once::new();
-        START.call_once(|| {
-            spawn_future_local(clone!(@weak self as win => async move {
-                match stream_notifications().await {
-                    Ok(stream) => {
-                        stream.for_each(|notif| {
-                            let win = win.clone();
-                            async move {
-                                // make sure there is a current band
-                                if let Some(band) = win.imp().current_device.read().await.as_ref() {
-                                    // create the alert message
-                                    let alert = Alert {
-                                        alert_type: AlertType::Message,
-                                        title: &notif.app,
-                                        message: &format!("{} - {}", notif.summary, notif.body)
-                                    };
-                                    // send it to the band
-                                    if let Err(err) = band.send_alert(&alert).await {
-                                        win.show_error(&format!("An error occurred while sending a notification to the band: {err}"));
-                                    }
-                                }
-                            }
-                        }).await;
-                    },
-                    // display any errors that occur
-                    Err(err) => {
-                        win.show_error(&format!("An error occurred while starting to forward notifications to the band: {err}"))
-                    }
+    /// keep the latest totals for the end of day summary, and notify when they go over the step goal
+    async fn handle_day_totals(&self, band_mac: &str, time: DateTime<Local>, totals: &CurrentActivity) {
+        let imp = self.imp();
+        let previous = imp.day_totals.replace(Some((band_mac.to_string(), time, totals.clone())));
+        if !imp.goal_notifications.get() { return }
+
+        // only when it goes over while the app is watching, not every time the app is opened
+        let Some((previous_band, previous_time, previous_totals)) = previous else { return };
+        if previous_band != band_mac || previous_time.date_naive() != time.date_naive() { return }
+        let goal = match self.store().await {
+            Ok(store) => {
+                let mut store = store.lock().await;
+                // the band already said so
+                match store.history(band_mac).goal_achievement(band_mac, time.date_naive()) {
+                    Ok(Some(_)) => return,
+                    Ok(None) => {},
+                    Err(err) => warn!("Error while reading goal achievements: {err}")
                 }
-            }));
-        });
+                store.get_band(band_mac.to_string()).activity_goal.clone().unwrap_or_default()
+            },
+            Err(err) => {
+                warn!("Error while reading the step goal: {err}");
+                return;
+            }
+        };
+        if previous_totals.steps < goal.steps && totals.steps >= goal.steps {
+            self.notify_desktop("step-goal", &gettext("Step goal reached"), &i18n_f("You've walked {} steps today", &[&totals.steps.to_string()]));
+        }
     }
-    
-    /// gets an MPRIS controller
-    /// if this has already been called before, it returns the existing instance
-    async fn get_mpris_controller(&self) -> Sender<MusicEvent> {
-        static CONTROLLER: OnceCell<Sender<MusicEvent>> = OnceCell::new();
-        CONTROLLER.get_or_init(|| async {
-            let (mpris_tx, mut mpris_rx) = mpsc::channel(1);
-            let (controller_tx, controller_rx) = mpsc::channel(3);
-            spawn_future_local(async move {
-                let _ = watch_mpris(mpris_tx, controller_rx).await;
-            });
-            spawn_future_local(clone!(@weak self as win => async move {
-                while let Some(item) = mpris_rx.next().await {
-                    // make sure there is a current band
-                    if let Some(band) = win.imp().current_device.read().await.as_ref() {
-                        // send it to the band
-                        if let Err(err) = band.set_media_info(&item).await {
-                            win.show_error(&format!("An error occurred while setting the media state: {err}"));
-                        }
-                    }
+
+    /// the band says the step goal was reached - remember when, and pass it on to the desktop
+    async fn handle_goal_reached(&self, band_mac: &str) {
+        let now = Local::now();
+        let (goal, alias, already_reached) = match self.store().await {
+            Ok(store) => {
+                let mut store = store.lock().await;
+                let history = store.history(band_mac);
+                let already_reached = history.goal_achievement(band_mac, now.date_naive()).ok().flatten().is_some();
+                if let Err(err) = history.insert_goal_achievement(band_mac, now) {
+                    warn!("Error while recording the goal achievement: {err}");
                 }
-            }));
-            controller_tx
-        }).await.clone()
+                (
+                    store.get_band(band_mac.to_string()).activity_goal.clone().unwrap_or_default(),
+                    store.get_band_alias(band_mac).to_string(),
+                    already_reached
+                )
+            },
+            Err(err) => {
+                warn!("Error while recording the goal achievement: {err}");
+                return;
+            }
+        };
+        // the band says it again if the goal is changed and reached again, but once a day is enough
+        if already_reached || !self.imp().goal_notifications.get() { return }
+        self.notify_desktop("step-goal", &gettext("Step goal reached"), &i18n_f("{} reached today's goal of {} steps", &[&alias, &goal.steps.to_string()]));
     }
 
-    fn start_band_media(&self) {
-        spawn_future_local(clone!(@weak self as win => async move {
-            // get the Receiver for when the band is closed
-            let band_closed_rx = win.imp().band_closed.borrow().as_ref().map(|a| a.1.clone());
-            // get the current band
-            let band = win.imp().current_device.read().await;
-            if let Some((band_closed_rx, band)) = band_closed_rx.zip(band.as_ref()) {
-                // start listening to the media button events
-                match band.stream_media_button_events().await.map(|s| s.fuse()) {
-                    Ok(mut music_events) => {
-                        let mut mpris_controller_tx = win.get_mpris_controller().await;
-                        pin_mut!(band_closed_rx);
-                        loop {
-                            select! {
-                                // stop watching for events
-                                _ = band_closed_rx.next() => {
-                                    break;
-                                },
-                                event = music_events.next() => {
-                                    // send all events to the mpris controller
-                                    if let Some(Some(event)) = event {
-                                        if mpris_controller_tx.send(event).await.is_err() {
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    },
-                    Err(err) => {
-                        win.show_error(&format!("Error while starting the band media: {err}"));
-                    }
-                }
-            } else {
-                win.show_error("Error while starting the band media: no band found");
+    /// read today's totals from the band if it's connected, and show them with the step goal
+    async fn show_day_summary(&self) {
+        if let Some(device) = self.imp().current_device.read().await.as_ref() {
+            match device.get_current_activity().await {
+                Ok(activity) => self.publish_sample(&device.address, MetricSample::Totals { time: Local::now(), totals: activity }).await,
+                Err(err) => warn!("Error while reading the activity for the day summary: {err}")
             }
-        }));
+        }
+
+        let Some((address, time, totals)) = self.imp().day_totals.borrow().clone() else { return };
+        // nothing was read from a band today
+        if time.date_naive() != Local::now().date_naive() { return }
+        let (goal, distance_unit) = match self.store().await {
+            Ok(store) => {
+                let mut store = store.lock().await;
+                let band_conf = store.get_band(address);
+                (band_conf.activity_goal.clone().unwrap_or_default(), band_conf.display_format.clone().unwrap_or_default().distance_unit)
+            },
+            Err(err) => {
+                warn!("Error while reading the step goal: {err}");
+                return;
+            }
+        };
+
+        let mut message = i18n_f(
+            "{} steps, {}, {} kcal",
+            &[&totals.steps.to_string(), &format_distance(totals.meters as u32, distance_unit), &totals.calories.to_string()]
+        );
+        if totals.steps < goal.steps {
+            message.push_str(&i18n_f(" - {} steps short of your goal", &[&(goal.steps - totals.steps).to_string()]));
+        }
+        self.notify_desktop("day-summary", &gettext("Today's activity"), &message);
+    }
+
+    /// show a desktop notification from the app
+    /// notifications with the same `id` replace each other
+    fn notify_desktop(&self, id: &str, title: &str, body: &str) {
+        if let Some(app) = self.application() {
+            let notification = Notification::new(title);
+            notification.set_body(Some(body));
+            app.send_notification(Some(id), &notification);
+        }
     }
 
     async fn watch_device_changes(&self, mut shown_devices: HashMap<OwnedObjectPath, DeviceRowObject>) -> band::Result<()> {
@@ -526,13 +3841,14 @@ impl MiBandWindow {
                             // if we already have this device, skip the event
                             if shown_devices.contains_key(&device.path) { continue; }
 
-                            let alias = self.store().await?.lock().expect("can lock store").get_band_alias(&device.address).to_string();
+                            let alias = self.store().await?.lock().await.get_band_alias(&device.address).to_string();
                             
                             let obj: DeviceRowObject = (device.clone(), alias).into();
                             // add it to the device list
                             self.devices().append(&obj);
                             // add it to our map
                             shown_devices.insert(device.path.clone(), obj);
+                            self.spawn_auto_connect(device.clone());
 
                             // if we haven't already started watching this one, start
                             if !watched_bands.contains(&device.path) {
@@ -562,11 +3878,22 @@ impl MiBandWindow {
                         Some((path, BandChangeEvent::RSSI(rssi))) => {
                             if let Some(device) = shown_devices.get(&path) {
                                 device.set_rssi(rssi.map(|r| r as i32).unwrap_or(0));
+                                if let Some(sorter) = self.imp().device_sorter.borrow().as_ref() {
+                                    sorter.changed(SorterChange::Different);
+                                }
+                                // the band just came into range
+                                if rssi.is_some() {
+                                    self.spawn_auto_connect(device.clone().into());
+                                }
                             }
                         },
                         Some((path, BandChangeEvent::Connected(connected))) => {
                             if let Some(device) = shown_devices.get(&path) {
                                 device.set_connected(connected);
+                                // BlueZ reconnected it by itself
+                                if connected {
+                                    self.spawn_auto_connect(device.clone().into());
+                                }
                             }
                         },
                         // don't break on None
@@ -585,10 +3912,274 @@ impl MiBandWindow {
                 }
             };
         }
-        band::Result::Ok(())
+        band::Result::Ok(())
+    }
+
+    /// pair with the band, answering BlueZ's pairing requests for it with the pairing dialog
+    /// the agent is only registered while pairing, so the desktop's agent handles every other device
+    async fn bond_band(&self, device: &MiBand<'static>) -> band::Result<band::BondState> {
+        let session = self.session().await?;
+        let requests = session.register_agent(OwnedObjectPath::from(device.path().to_owned())).await?;
+        spawn_future_local(clone!(@weak self as win => async move {
+            // ends once the agent is unregistered
+            while let Ok(request) = requests.recv().await {
+                // a cancel can come in while waiting for an answer, so don't wait here
+                spawn_future_local(clone!(@weak win => async move {
+                    win.handle_pairing_request(request).await;
+                }));
+            }
+        }));
+
+        let result = device.bond().await;
+        if let Err(err) = session.unregister_agent().await {
+            warn!("Error while unregistering the pairing agent: {err}");
+        }
+        result?;
+        device.get_bond_state().await
+    }
+
+    async fn handle_pairing_request(&self, request: AgentRequest) {
+        let dialog = self.imp().pairing_dialog.get();
+        match request {
+            AgentRequest::Confirm { device, passkey, reply } => {
+                let message = i18n_f("Does {} show the passkey {}?", &[&self.pairing_device_name(&device).await, &format!("{passkey:06}")]);
+                let _ = reply.send(dialog.ask(&message, PairingInput::None, "Pair").await.is_some());
+            },
+            AgentRequest::Passkey { device, reply } => {
+                let message = i18n_f("Enter the passkey shown on {}", &[&self.pairing_device_name(&device).await]);
+                let passkey = dialog.ask(&message, PairingInput::Passkey, "Pair").await.and_then(|code| code.parse().ok());
+                let _ = reply.send(passkey);
+            },
+            AgentRequest::PinCode { device, reply } => {
+                let message = i18n_f("Enter the PIN code shown on {}", &[&self.pairing_device_name(&device).await]);
+                let _ = reply.send(dialog.ask(&message, PairingInput::PinCode, "Pair").await);
+            },
+            AgentRequest::Display { device, code } => {
+                dialog.show_code(&i18n_f("Enter {} on {} to pair with it", &[&code.to_string(), &self.pairing_device_name(&device).await]));
+            },
+            AgentRequest::Authorize { device, reply } => {
+                let message = i18n_f("Allow {} to pair with this computer?", &[&self.pairing_device_name(&device).await]);
+                let _ = reply.send(dialog.ask(&message, PairingInput::None, "Allow").await.is_some());
+            },
+            AgentRequest::Cancel => dialog.cancel()
+        }
+    }
+
+    /// the alias of the device at this BlueZ path (.../dev_AA_BB_CC_DD_EE_FF)
+    async fn pairing_device_name(&self, path: &OwnedObjectPath) -> String {
+        let address = path.as_str().rsplit('/').next()
+            .and_then(|name| name.strip_prefix("dev_"))
+            .map(|address| address.replace('_', ":"));
+        match (address, self.store().await) {
+            (Some(address), Ok(store)) => store.lock().await.get_band_alias(&address).to_string(),
+            (Some(address), Err(_)) => address,
+            (None, _) => "the device".to_string()
+        }
+    }
+
+    async fn setup_disconnect_on_exit(&self) -> band::Result<()> {
+        let enabled = self.store().await?.lock().await.app.disconnect_on_exit.unwrap_or(false);
+        let switch = &self.imp().switch_disconnect_on_exit;
+        switch.set_active(enabled);
+
+        switch.connect_active_notify(clone!(@weak self as win => move |switch| {
+            let enabled = switch.is_active();
+            spawn_future_local(async move {
+                if let Err(err) = win.process_new_disconnect_on_exit(enabled).await {
+                    win.show_error(&i18n_f("Error while saving the exit setting: {}", &[&err.to_string()]));
+                }
+            });
+        }));
+        Ok(())
+    }
+
+    async fn setup_simplify_alerts(&self) -> band::Result<()> {
+        let enabled = self.store().await?.lock().await.app.simplify_alerts.unwrap_or(false);
+        let switch = &self.imp().switch_simplify_alerts;
+        switch.set_active(enabled);
+
+        switch.connect_active_notify(clone!(@weak self as win => move |switch| {
+            let enabled = switch.is_active();
+            spawn_future_local(async move {
+                if let Err(err) = win.process_new_simplify_alerts(enabled).await {
+                    win.show_error(&i18n_f("Error while saving the alert text setting: {}", &[&err.to_string()]));
+                }
+            });
+        }));
+        Ok(())
+    }
+
+    /// pick up preferences changed with gsettings or dconf-editor while the app is running
+    async fn watch_settings_changes(&self) -> band::Result<()> {
+        let Some(settings) = self.store().await?.lock().await.settings_monitor() else { return Ok(()) };
+        settings.connect_changed(None, clone!(@weak self as win => move |_, _| {
+            spawn_future_local(async move {
+                if let Err(err) = win.reload_settings().await {
+                    win.show_error(&i18n_f("Error while reloading the settings: {}", &[&err.to_string()]));
+                }
+            });
+        }));
+        self.imp().settings_monitor.replace(Some(settings));
+        Ok(())
+    }
+
+    /// apply whatever changed - this also runs after the app saves them itself, where nothing will have
+    async fn reload_settings(&self) -> band::Result<()> {
+        let (passive_discovery, disconnect_on_exit, simplify_alerts, music_idle_timeout_secs, compact_style, goal_notifications, day_summary, stale_band_days, soak_diagnostics, toggles, power_throttle) = {
+            let mut store = self.store().await?.lock().await;
+            store.reload_settings().await?;
+            let app = &store.app;
+            (
+                app.passive_discovery.unwrap_or(false),
+                app.disconnect_on_exit.unwrap_or(false),
+                app.simplify_alerts.unwrap_or(false),
+                app.music_idle_timeout_secs.unwrap_or(DEFAULT_MUSIC_IDLE_TIMEOUT_SECS),
+                app.compact_style.unwrap_or(false),
+                app.goal_notifications.unwrap_or(true),
+                app.day_summary.unwrap_or(false),
+                app.stale_band_days.unwrap_or(DEFAULT_STALE_BAND_DAYS),
+                app.soak_diagnostics.unwrap_or(false),
+                app.quick_toggles.unwrap_or_default(),
+                app.power_throttle.unwrap_or_default()
+            )
+        };
+        let imp = self.imp();
+        // the switches only save and apply it if it's different
+        imp.switch_passive_discovery.set_active(passive_discovery);
+        imp.switch_disconnect_on_exit.set_active(disconnect_on_exit);
+        imp.switch_simplify_alerts.set_active(simplify_alerts);
+        imp.spin_music_timeout.set_value(music_idle_timeout_secs as f64);
+        imp.switch_compact_style.set_active(compact_style);
+        imp.switch_goal_notifications.set_active(goal_notifications);
+        imp.switch_day_summary.set_active(day_summary);
+        imp.spin_stale_band_days.set_value(stale_band_days as f64);
+        // it's already been saved, so just apply it (before the widgets change, so they don't save half of it)
+        let was_throttled = self.power_throttled();
+        imp.power_throttle.set(power_throttle);
+        imp.spin_power_threshold.set_value(power_throttle.threshold as f64);
+        imp.switch_power_throttle.set_active(power_throttle.enabled);
+        if self.power_throttled() != was_throttled {
+            self.apply_power_throttle().await?;
+        }
+        if soak_diagnostics != soak::is_enabled() {
+            self.process_new_soak_diagnostics(soak_diagnostics).await?;
+        }
+        let current = imp.quick_toggles.get();
+        let changed = [
+            (Toggle::Forwarding, current.forwarding, toggles.forwarding),
+            (Toggle::DoNotDisturb, current.do_not_disturb, toggles.do_not_disturb),
+            (Toggle::BatterySaver, current.battery_saver, toggles.battery_saver)
+        ];
+        for (toggle, old, new) in changed {
+            if old != new {
+                self.process_new_quick_toggle(toggle, new).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn process_new_disconnect_on_exit(&self, enabled: bool) -> band::Result<()> {
+        let mut store = self.store().await?.lock().await;
+        store.app.disconnect_on_exit = Some(enabled);
+        Store::save_and_unlock(store).await?;
+        Ok(())
+    }
+
+    async fn process_new_simplify_alerts(&self, enabled: bool) -> band::Result<()> {
+        {
+            let mut store = self.store().await?.lock().await;
+            store.app.simplify_alerts = Some(enabled);
+            Store::save_and_unlock(store).await?;
+        }
+        if let Some(band) = self.imp().current_device.write().await.as_mut() {
+            band.set_simplify_alerts(enabled);
+        }
+        Ok(())
+    }
+
+    /// stop everything and write everything out before the window closes
+    /// nothing here is shown - the window is on its way out
+    async fn shutdown(&self) {
+        let imp = self.imp();
+        self.stop_soak_diagnostics();
+        // an interrupted sync keeps what it's already recorded
+        if let Some(cancel_tx) = imp.sync_cancel.take() {
+            let _ = cancel_tx.try_send(());
+        }
+        // dropping the channel stops every task for the band, which releases their notify streams
+        imp.band_closed.borrow_mut().take();
+
+        let disconnect = match self.store().await {
+            Ok(store) => {
+                let store = store.lock().await;
+                let disconnect = store.app.disconnect_on_exit.unwrap_or(false);
+                // history is written as it's synced, but the settings might not have been yet
+                if let Err(err) = Store::save_and_unlock(store).await {
+                    error!("Error while saving the settings on exit: {err}");
+                }
+                disconnect
+            },
+            Err(err) => {
+                error!("Error while opening the store on exit: {err}");
+                false
+            }
+        };
+
+        let release_band = async {
+            let Some(mut band) = imp.current_device.write().await.take() else { return band::Result::Ok(()) };
+            // it's already been let go of
+            if imp.idle_disconnected.get() { return Ok(()) }
+            // the heart rate task might not get to this before the app exits
+            if imp.heart_rate_stop.take().is_some() {
+                if let Err(err) = band.stop_heart_rate().await {
+                    warn!("Error while stopping heart rate measurement on exit: {err}");
+                }
+            }
+            // tell the band nothing is playing anymore
+            if let Err(err) = band.set_media_info(&None).await {
+                warn!("Error while closing the band's music screen on exit: {err}");
+            }
+            if disconnect {
+                if let Err(err) = band.disconnect().await {
+                    warn!("Error while disconnecting from the band on exit: {err}");
+                }
+            }
+            Ok(())
+        };
+        if let Err(err) = band::with_timeout(SHUTDOWN_TIMEOUT, release_band).await {
+            warn!("Error while letting go of the band on exit: {err}");
+        }
+    }
+
+    async fn setup_passive_discovery(&self) -> band::Result<()> {
+        let enabled = self.store().await?.lock().await.app.passive_discovery.unwrap_or(false);
+        let switch = &self.imp().switch_passive_discovery;
+        switch.set_active(enabled);
+        if enabled {
+            if let Err(err) = self.set_passive_discovery(true).await {
+                warn!("Error while starting passive discovery: {err}");
+            }
+        }
+
+        switch.connect_active_notify(clone!(@weak self as win => move |switch| {
+            let enabled = switch.is_active();
+            spawn_future_local(async move {
+                if let Err(err) = win.process_new_passive_discovery(enabled).await {
+                    win.show_error(&i18n_f("Error while changing passive discovery: {}", &[&err.to_string()]));
+                }
+            });
+        }));
+        Ok(())
     }
 
     async fn initialize(&self) -> band::Result<()> {
+        // lock before anything is shown
+        self.store().await?;
+        self.lock_app().await;
+        self.watch_app_lock();
+        // this doesn't need bluetooth
+        self.spawn_stale_band_check();
+
         let session = self.session().await?;
         
         // make sure bluetooth is on
@@ -602,26 +4193,55 @@ impl MiBandWindow {
         // get currently known devices
         let devices = MiBand::get_known_bands(&session).await?;
         let mut shown_devices = HashMap::new();
-        let store = self.store().await?.lock().expect("can lock store");
+        let mut known_devices = vec![];
+        let store = self.store().await?.lock().await;
         for device in devices.into_iter() {
             // make sure to get the configured band alias
             let alias = store.get_band_alias(&device.address).to_string();
             let obj: DeviceRowObject = (device.clone(), alias).into();
             model.append(&obj);
+            known_devices.push(device.clone());
             shown_devices.insert(device.path, obj);
         }
+        // nothing's been set up yet (the lock screen would cover it, but then they've been here before)
+        let first_run = store.saved_bands().is_empty() && !store.app.app_lock.as_ref().is_some_and(|lock| lock.enabled);
 
         self.setup_device_list(model);
 
         // now continually stream changes
         spawn_future_local(clone!(@weak self as win => async move {
             if let Err(err) = win.watch_device_changes(shown_devices).await {
-                win.show_error(&format!("Error while watching device changes: {err}"));
+                win.show_error(&i18n_f("Error while watching device changes: {}", &[&err.to_string()]));
             }
         }));
 
         self.setup_device_cards();
 
+        if first_run {
+            self.imp().onboarding_dialog.start(&self.devices());
+        }
+
+        // the cards have to be set up before connecting
+        for device in known_devices {
+            self.spawn_auto_connect(device);
+        }
+
+        self.setup_passive_discovery().await?;
+        self.setup_disconnect_on_exit().await?;
+        self.setup_simplify_alerts().await?;
+        self.setup_music_idle_timeout().await?;
+        self.setup_compact_style().await?;
+        self.setup_goal_notifications().await?;
+        self.setup_day_summary().await?;
+        self.setup_stale_band_days().await?;
+        self.setup_quick_toggles().await?;
+        self.setup_power_throttle().await?;
+        self.setup_band_service();
+        self.watch_settings_changes().await?;
+        if self.store().await?.lock().await.app.soak_diagnostics.unwrap_or(false) {
+            self.start_soak_diagnostics();
+        }
+
         // device EditableLabel
         self.imp().address_label.connect_editing_notify(clone!(@weak self as win => move |editable| {
             // if they're not editing it anymore, save it
@@ -629,7 +4249,7 @@ impl MiBandWindow {
                 let contents = editable.text().as_str().to_string();
                 spawn_future_local(async move {
                     if let Err(err) = win.process_new_alias(contents).await {
-                        win.show_error(&format!("Error while storing alias: {err}"));
+                        win.show_error(&i18n_f("Error while storing alias: {}", &[&err.to_string()]));
                     }
                 });
                 
@@ -642,11 +4262,419 @@ impl MiBandWindow {
     async fn run_scan(&self) -> band::Result<()> {
         let session = self.session().await?;
         // start the scan
-        MiBand::start_filtered_discovery(session.clone()).await?;
+        MiBand::start_filtered_discovery(session).await?;
         // wait for 10 seconds
         Timer::after(Duration::from_secs(10)).await;
-        // stop the scan
-        session.adapter.stop_discovery().await?;
+        // stop the scan (discovery keeps running if passive discovery is on)
+        MiBand::stop_filtered_discovery(session).await?;
+        Ok(())
+    }
+
+    /// keep discovery running in the background, so bands nearby keep showing up and updating
+    async fn set_passive_discovery(&self, enabled: bool) -> band::Result<()> {
+        let imp = self.imp();
+        // the switch can be flipped again before this finishes
+        if imp.passive_discovery.get() == enabled { return Ok(()) }
+        imp.passive_discovery.set(enabled);
+
+        let session = self.session().await?;
+        let result = if enabled {
+            MiBand::start_filtered_discovery(session).await
+        } else {
+            MiBand::stop_filtered_discovery(session).await
+        };
+        if result.is_err() {
+            imp.passive_discovery.set(!enabled);
+        }
+        result
+    }
+
+    /// let the desktop's quick settings flip the toggles over D-Bus
+    async fn setup_quick_toggles(&self) -> band::Result<()> {
+        let toggles = self.store().await?.lock().await.app.quick_toggles.unwrap_or_default();
+        self.imp().quick_toggles.set(toggles);
+
+        spawn_future_local(clone!(@weak self as win => async move {
+            match serve_toggles(toggles).await {
+                // the connection has to stay open for the toggles to be served
+                Ok((_connection, requests)) => {
+                    while let Ok((toggle, enabled)) = requests.recv().await {
+                        if let Err(err) = win.process_new_quick_toggle(toggle, enabled).await {
+                            win.show_error(&i18n_f("Error while changing a quick setting: {}", &[&err.to_string()]));
+                        }
+                    }
+                },
+                // only the extension needs them
+                Err(err) => warn!("Error while serving the quick settings toggles: {err}")
+            }
+        }));
+        Ok(())
+    }
+
+    async fn process_new_quick_toggle(&self, toggle: Toggle, enabled: bool) -> band::Result<()> {
+        let imp = self.imp();
+        let mut toggles = imp.quick_toggles.get();
+        match toggle {
+            Toggle::Forwarding => toggles.forwarding = enabled,
+            Toggle::DoNotDisturb => toggles.do_not_disturb = enabled,
+            Toggle::BatterySaver => toggles.battery_saver = enabled
+        }
+        imp.quick_toggles.set(toggles);
+        {
+            let mut store = self.store().await?.lock().await;
+            store.app.quick_toggles = Some(toggles);
+            Store::save_and_unlock(store).await?;
+        }
+
+        match toggle {
+            Toggle::Forwarding => {},
+            // it's turned on again when the band connects, so it's fine if there isn't one
+            Toggle::DoNotDisturb => {
+                if let Some(band) = imp.current_device.read().await.as_ref() {
+                    band.set_do_not_disturb(enabled).await?;
+                }
+            },
+            Toggle::BatterySaver => {
+                let address = imp.current_device.read().await.as_ref().map(|band| band.address.clone());
+                if let Some(address) = address {
+                    self.start_band_tasks(address).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// let other apps read the band's state and send alerts through this app
+    fn setup_band_service(&self) {
+        spawn_future_local(clone!(@weak self as win => async move {
+            match serve_band_service().await {
+                Ok((service, requests, search_requests)) => {
+                    win.imp().band_service.replace(Some(service));
+                    spawn_future_local(clone!(@weak win => async move {
+                        while let Ok(result) = search_requests.recv().await {
+                            win.show_search_result(result);
+                        }
+                    }));
+                    while let Ok(request) = requests.recv().await {
+                        win.mark_active();
+                        win.wake_from_idle().await;
+                        let result = match win.imp().current_device.read().await.as_ref() {
+                            Some(band) => win.send_alert_to(band, &Alert { alert_type: AlertType::Message, title: &request.title, message: &request.body }).await
+                                .map_err(|err| err.to_string()),
+                            None => Err("No band is connected".to_string())
+                        };
+                        let _ = request.reply.send(result);
+                    }
+                },
+                // only other apps need it
+                Err(err) => warn!("Error while serving the band's state on D-Bus: {err}")
+            }
+        }));
+    }
+
+    /// bring the window up from the GNOME Shell overview, scrolled to the card the result came from
+    fn show_search_result(&self, result: Option<SearchResult>) {
+        self.present();
+        let imp = self.imp();
+        let Some(result) = result else { return };
+        // the results are only shown while a band is open, but it might have been closed since
+        if imp.main_stack.visible_child_name().as_deref() != Some("device-detail") { return }
+        let card = match result {
+            SearchResult::Battery => &imp.info_battery,
+            SearchResult::Steps => &imp.info_activity
+        };
+        // focusing something on it scrolls it into view
+        card.child_focus(DirectionType::TabForward);
+    }
+
+    /// background work should be paused because the computer's battery is low
+    fn power_throttled(&self) -> bool {
+        let imp = self.imp();
+        let throttle = imp.power_throttle.get();
+        throttle.enabled && imp.power_state.get().is_some_and(|state| state.is_low(throttle.threshold))
+    }
+
+    async fn setup_power_throttle(&self) -> band::Result<()> {
+        let throttle = self.store().await?.lock().await.app.power_throttle.unwrap_or_default();
+        let imp = self.imp();
+        imp.power_throttle.set(throttle);
+        imp.spin_power_threshold.set_value(throttle.threshold as f64);
+        imp.switch_power_throttle.set_active(throttle.enabled);
+
+        imp.switch_power_throttle.connect_active_notify(clone!(@weak self as win => move |switch| {
+            let throttle = PowerThrottle { enabled: switch.is_active(), ..win.imp().power_throttle.get() };
+            spawn_future_local(async move {
+                if let Err(err) = win.process_new_power_throttle(throttle).await {
+                    win.show_error(&i18n_f("Error while saving the low battery setting: {}", &[&err.to_string()]));
+                }
+            });
+        }));
+        imp.spin_power_threshold.connect_value_changed(clone!(@weak self as win => move |spin| {
+            let throttle = PowerThrottle { threshold: spin.value() as u8, ..win.imp().power_throttle.get() };
+            spawn_future_local(async move {
+                if let Err(err) = win.process_new_power_throttle(throttle).await {
+                    win.show_error(&i18n_f("Error while saving the low battery setting: {}", &[&err.to_string()]));
+                }
+            });
+        }));
+
+        let (tx, rx) = async_channel::bounded(1);
+        spawn_future_local(async move {
+            // without UPower, nothing is ever throttled
+            if let Err(err) = watch_power(tx).await {
+                warn!("Error while watching the computer's power supply: {err}");
+            }
+        });
+        spawn_future_local(clone!(@weak self as win => async move {
+            while let Ok(state) = rx.recv().await {
+                let was_throttled = win.power_throttled();
+                win.imp().power_state.set(Some(state));
+                if win.power_throttled() != was_throttled {
+                    if let Err(err) = win.apply_power_throttle().await {
+                        warn!("Error while applying the low battery setting: {err}");
+                    }
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    async fn setup_goal_notifications(&self) -> band::Result<()> {
+        let enabled = self.store().await?.lock().await.app.goal_notifications.unwrap_or(true);
+        let imp = self.imp();
+        imp.goal_notifications.set(enabled);
+        imp.switch_goal_notifications.set_active(enabled);
+
+        imp.switch_goal_notifications.connect_active_notify(clone!(@weak self as win => move |switch| {
+            let enabled = switch.is_active();
+            spawn_future_local(async move {
+                if let Err(err) = win.process_new_goal_notifications(enabled).await {
+                    win.show_error(&i18n_f("Error while saving the step goal notification setting: {}", &[&err.to_string()]));
+                }
+            });
+        }));
+        Ok(())
+    }
+
+    async fn process_new_goal_notifications(&self, enabled: bool) -> band::Result<()> {
+        self.imp().goal_notifications.set(enabled);
+        let mut store = self.store().await?.lock().await;
+        store.app.goal_notifications = Some(enabled);
+        Store::save_and_unlock(store).await?;
+        Ok(())
+    }
+
+    /// checks every minute for the end of day summary while the app is running
+    async fn setup_day_summary(&self) -> band::Result<()> {
+        let enabled = self.store().await?.lock().await.app.day_summary.unwrap_or(false);
+        let imp = self.imp();
+        imp.day_summary.set(enabled);
+        imp.switch_day_summary.set_active(enabled);
+
+        imp.switch_day_summary.connect_active_notify(clone!(@weak self as win => move |switch| {
+            let enabled = switch.is_active();
+            spawn_future_local(async move {
+                if let Err(err) = win.process_new_day_summary(enabled).await {
+                    win.show_error(&i18n_f("Error while saving the day summary setting: {}", &[&err.to_string()]));
+                }
+            });
+        }));
+
+        spawn_future_local(clone!(@weak self as win => async move {
+            let _task = soak::track_task("day summary");
+            let mut last_shown = None;
+            let mut check = Timer::interval(BRIEFING_CHECK_INTERVAL);
+            while check.next().await.is_some() {
+                if !win.imp().day_summary.get() { continue }
+                let now = Local::now();
+                // only during that hour (e.g. not if the app is opened at midnight)
+                if now.hour() != DAY_SUMMARY_HOUR || last_shown == Some(now.date_naive()) { continue }
+                last_shown = Some(now.date_naive());
+                win.show_day_summary().await;
+            }
+        }));
+        Ok(())
+    }
+
+    async fn process_new_day_summary(&self, enabled: bool) -> band::Result<()> {
+        self.imp().day_summary.set(enabled);
+        let mut store = self.store().await?.lock().await;
+        store.app.day_summary = Some(enabled);
+        Store::save_and_unlock(store).await?;
+        Ok(())
+    }
+
+    async fn setup_compact_style(&self) -> band::Result<()> {
+        let enabled = self.store().await?.lock().await.app.compact_style.unwrap_or(false);
+        let switch = &self.imp().switch_compact_style;
+        switch.set_active(enabled);
+        self.apply_compact_style(enabled);
+
+        switch.connect_active_notify(clone!(@weak self as win => move |switch| {
+            let enabled = switch.is_active();
+            win.apply_compact_style(enabled);
+            spawn_future_local(async move {
+                if let Err(err) = win.process_new_compact_style(enabled).await {
+                    win.show_error(&i18n_f("Error while saving the style setting: {}", &[&err.to_string()]));
+                }
+            });
+        }));
+        Ok(())
+    }
+
+    async fn process_new_compact_style(&self, enabled: bool) -> band::Result<()> {
+        let mut store = self.store().await?.lock().await;
+        store.app.compact_style = Some(enabled);
+        Store::save_and_unlock(store).await?;
+        Ok(())
+    }
+
+    async fn setup_music_idle_timeout(&self) -> band::Result<()> {
+        let timeout_secs = self.store().await?.lock().await.app.music_idle_timeout_secs.unwrap_or(DEFAULT_MUSIC_IDLE_TIMEOUT_SECS);
+        let imp = self.imp();
+        imp.music_idle_timeout_secs.set(timeout_secs);
+        imp.spin_music_timeout.set_value(timeout_secs as f64);
+
+        imp.spin_music_timeout.connect_value_changed(clone!(@weak self as win => move |spin| {
+            let timeout_secs = spin.value() as u32;
+            spawn_future_local(async move {
+                if let Err(err) = win.process_new_music_idle_timeout(timeout_secs).await {
+                    win.show_error(&i18n_f("Error while saving the music screen timeout: {}", &[&err.to_string()]));
+                }
+            });
+        }));
+        Ok(())
+    }
+
+    async fn process_new_music_idle_timeout(&self, timeout_secs: u32) -> band::Result<()> {
+        let imp = self.imp();
+        if imp.music_idle_timeout_secs.replace(timeout_secs) == timeout_secs { return Ok(()) }
+        let mut store = self.store().await?.lock().await;
+        store.app.music_idle_timeout_secs = Some(timeout_secs);
+        Store::save_and_unlock(store).await?;
+        Ok(())
+    }
+
+    async fn setup_stale_band_days(&self) -> band::Result<()> {
+        let days = self.store().await?.lock().await.app.stale_band_days.unwrap_or(DEFAULT_STALE_BAND_DAYS);
+        let imp = self.imp();
+        imp.spin_stale_band_days.set_value(days as f64);
+
+        imp.spin_stale_band_days.connect_value_changed(clone!(@weak self as win => move |spin| {
+            let days = spin.value() as u32;
+            spawn_future_local(async move {
+                if let Err(err) = win.process_new_stale_band_days(days).await {
+                    win.show_error(&i18n_f("Error while saving when to forget bands: {}", &[&err.to_string()]));
+                }
+            });
+        }));
+        Ok(())
+    }
+
+    async fn process_new_stale_band_days(&self, days: u32) -> band::Result<()> {
+        let mut store = self.store().await?.lock().await;
+        if store.app.stale_band_days == Some(days) { return Ok(()) }
+        store.app.stale_band_days = Some(days);
+        Store::save_and_unlock(store).await?;
+        Ok(())
+    }
+
+    /// once per run, after the app is unlocked
+    fn spawn_stale_band_check(&self) {
+        let imp = self.imp();
+        if imp.stale_bands_checked.get() || self.is_locked() { return }
+        imp.stale_bands_checked.set(true);
+        spawn_future_local(clone!(@weak self as win => async move {
+            if let Err(err) = win.check_stale_bands().await {
+                win.show_error(&i18n_f("An error occurred while looking for bands that haven't been used: {}", &[&err.to_string()]));
+            }
+        }));
+    }
+
+    /// offer to forget the saved bands that haven't been connected to for `AppConf::stale_band_days`
+    async fn check_stale_bands(&self) -> store::Result<()> {
+        let (days, stale) = {
+            let mut store = self.store().await?.lock().await;
+            let days = store.app.stale_band_days.unwrap_or(DEFAULT_STALE_BAND_DAYS);
+            if days == 0 { return Ok(()) }
+            let stale = store.stale_bands(Local::now() - TimeDelta::days(days as i64));
+            let stale: Vec<(String, String)> = stale.into_iter()
+                .map(|band_mac| {
+                    let alias = store.get_band_alias(&band_mac).to_string();
+                    (band_mac, alias)
+                })
+                .collect();
+            // bands that had never been connected to start counting now
+            Store::save_and_unlock(store).await?;
+            (days, stale)
+        };
+        if stale.is_empty() { return Ok(()) }
+
+        let names = stale.iter()
+            .map(|(band_mac, alias)| if alias == band_mac { band_mac.clone() } else { format!("{alias} ({band_mac})") })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let count = stale.len() as u32;
+        let dialog = AlertDialog::builder()
+            .message(i18n_nf("Forget a band you haven't used?", "Forget bands you haven't used?", count, &[]))
+            .detail(i18n_nf(
+                "This band hasn't been connected to for over {} days:\n\n{}\n\nRemove its settings, auth key and synced history?",
+                "These bands haven't been connected to for over {} days:\n\n{}\n\nRemove their settings, auth keys and synced history?",
+                count,
+                &[&days.to_string(), &names]
+            ))
+            .buttons([gettext("Keep"), gettext("Remove")])
+            .cancel_button(0)
+            .default_button(0)
+            .build();
+        let remove = dialog.choose_future(Some(self)).await == Ok(1);
+
+        let now = Local::now().timestamp();
+        let mut store = self.store().await?.lock().await;
+        for (band_mac, _alias) in &stale {
+            if remove {
+                store.remove_band(band_mac)?;
+            } else {
+                // ask again once it's been unused for another period
+                store.get_band(band_mac.clone()).last_connected = Some(now);
+            }
+        }
+        Store::save_and_unlock(store).await?;
+        Ok(())
+    }
+
+    async fn process_new_power_throttle(&self, throttle: PowerThrottle) -> band::Result<()> {
+        let imp = self.imp();
+        let current = imp.power_throttle.get();
+        if current.enabled == throttle.enabled && current.threshold == throttle.threshold { return Ok(()) }
+
+        let was_throttled = self.power_throttled();
+        imp.power_throttle.set(throttle);
+        {
+            let mut store = self.store().await?.lock().await;
+            store.app.power_throttle = Some(throttle);
+            Store::save_and_unlock(store).await?;
+        }
+        if self.power_throttled() != was_throttled {
+            self.apply_power_throttle().await?;
+        }
+        Ok(())
+    }
+
+    /// start or stop the band's background syncs and heart rate
+    async fn apply_power_throttle(&self) -> band::Result<()> {
+        let address = self.imp().current_device.read().await.as_ref().map(|band| band.address.clone());
+        if let Some(address) = address {
+            self.start_band_tasks(address).await?;
+        }
+        Ok(())
+    }
+
+    async fn process_new_passive_discovery(&self, enabled: bool) -> band::Result<()> {
+        self.set_passive_discovery(enabled).await?;
+        let mut store = self.store().await?.lock().await;
+        store.app.passive_discovery = Some(enabled);
+        Store::save_and_unlock(store).await?;
         Ok(())
     }
 }
@@ -661,7 +4689,15 @@ pub struct MiBandWindowImpl {
     #[template_child]
     btn_reload: TemplateChild<Button>,
     #[template_child]
+    btn_bulk: TemplateChild<MenuButton>,
+    #[template_child]
     btn_back: TemplateChild<Button>,
+    #[template_child]
+    box_band_state: TemplateChild<GtkBox>,
+    #[template_child]
+    spinner_band_state: TemplateChild<Spinner>,
+    #[template_child]
+    label_band_state: TemplateChild<Label>,
 
     // device list page
     #[template_child]
@@ -669,12 +4705,52 @@ pub struct MiBandWindowImpl {
     #[template_child]
     btn_start_scan: TemplateChild<Button>,
 
+    // app lock page
+    #[template_child]
+    lock_stack: TemplateChild<Stack>,
+    #[template_child]
+    entry_app_pin: TemplateChild<PasswordEntry>,
+    #[template_child]
+    btn_unlock: TemplateChild<Button>,
+    #[template_child]
+    label_unlock_error: TemplateChild<Label>,
+    #[template_child]
+    btn_system_unlock: TemplateChild<Button>,
+    #[template_child]
+    switch_guest: TemplateChild<Switch>,
+    #[template_child]
+    switch_passive_discovery: TemplateChild<Switch>,
+    #[template_child]
+    switch_disconnect_on_exit: TemplateChild<Switch>,
+    #[template_child]
+    switch_simplify_alerts: TemplateChild<Switch>,
+    #[template_child]
+    switch_compact_style: TemplateChild<Switch>,
+    #[template_child]
+    switch_goal_notifications: TemplateChild<Switch>,
+    #[template_child]
+    switch_day_summary: TemplateChild<Switch>,
+    #[template_child]
+    spin_power_threshold: TemplateChild<SpinButton>,
+    #[template_child]
+    spin_music_timeout: TemplateChild<SpinButton>,
+    #[template_child]
+    spin_stale_band_days: TemplateChild<SpinButton>,
+    #[template_child]
+    switch_power_throttle: TemplateChild<Switch>,
+
     // device detail page
     #[template_child]
     btn_auth_key: TemplateChild<Button>,
     #[template_child]
+    box_danger_zone: TemplateChild<GtkBox>,
+    #[template_child]
     address_label: TemplateChild<EditableLabel>,
     #[template_child]
+    image_signal: TemplateChild<Image>,
+    #[template_child]
+    label_signal: TemplateChild<Label>,
+    #[template_child]
     info_battery: TemplateChild<DeviceInfoCard>,
     #[template_child]
     info_time: TemplateChild<DeviceInfoCard>,
@@ -686,13 +4762,145 @@ pub struct MiBandWindowImpl {
     info_activity_goal: TemplateChild<DeviceInfoCard>,
     #[template_child]
     info_band_lock: TemplateChild<DeviceInfoCard>,
+    #[template_child]
+    info_display_format: TemplateChild<DeviceInfoCard>,
+    #[template_child]
+    info_heart_rate: TemplateChild<DeviceInfoCard>,
+    #[template_child]
+    info_movement: TemplateChild<DeviceInfoCard>,
+    #[template_child]
+    info_workouts: TemplateChild<DeviceInfoCard>,
+    #[template_child]
+    info_metric_sinks: TemplateChild<DeviceInfoCard>,
+    #[template_child]
+    info_briefing: TemplateChild<DeviceInfoCard>,
+    #[template_child]
+    info_travel: TemplateChild<DeviceInfoCard>,
+    #[template_child]
+    info_sleep_summary: TemplateChild<DeviceInfoCard>,
+    #[template_child]
+    info_app_lock: TemplateChild<DeviceInfoCard>,
+    #[template_child]
+    info_forwarding: TemplateChild<DeviceInfoCard>,
+    #[template_child]
+    info_test_alert: TemplateChild<DeviceInfoCard>,
+    #[template_child]
+    info_developer: TemplateChild<DeviceInfoCard>,
+
+    // alert source denied page
+    #[template_child]
+    label_source_denied_title: TemplateChild<Label>,
+    #[template_child]
+    label_source_denied_help: TemplateChild<Label>,
+    #[template_child]
+    label_source_error: TemplateChild<Label>,
+    #[template_child]
+    label_source_retry: TemplateChild<Label>,
+
+    #[template_child]
+    toast_overlay: TemplateChild<ToastOverlay>,
+    #[template_child]
+    preferences_window: TemplateChild<PreferencesWindow>,
 
     // auth key
     #[template_child]
     auth_key_dialog: TemplateChild<AuthKeyDialog>,
+    #[template_child]
+    verify_settings_dialog: TemplateChild<VerifySettingsDialog>,
+    #[template_child]
+    workout_history_dialog: TemplateChild<WorkoutHistoryDialog>,
+    #[template_child]
+    alert_history_dialog: TemplateChild<AlertHistoryDialog>,
+    #[template_child]
+    statistics_dialog: TemplateChild<StatisticsDialog>,
+    #[template_child]
+    sync_progress_dialog: TemplateChild<SyncProgressDialog>,
+    #[template_child]
+    bulk_operation_dialog: TemplateChild<BulkOperationDialog>,
+    #[template_child]
+    mini_window: TemplateChild<MiniWindow>,
+    #[template_child]
+    pairing_dialog: TemplateChild<PairingDialog>,
+    #[template_child]
+    onboarding_dialog: TemplateChild<OnboardingDialog>,
     
+    /// names of the registered alert sources
+    alert_sources: RefCell<HashSet<&'static str>>,
+    source_denied_dismissed: Cell<bool>,
+    /// the source the denied page is showing
+    denied_source: Cell<Option<&'static str>>,
+    alert_sources_retry: RefCell<Vec<async_channel::Sender<()>>>,
     devices: RefCell<Option<ListStore>>,
+    /// sorts the device list by signal strength
+    device_sorter: RefCell<Option<CustomSorter>>,
+    heart_rate_stop: RefCell<Option<async_channel::Sender<()>>>,
+    idle_disconnect_stop: RefCell<Option<async_channel::Sender<()>>>,
+    /// the last time something needed the band
+    last_active: Cell<Option<Instant>>,
+    /// the band was disconnected because nothing needed it, rather than dropping the connection
+    idle_disconnected: Cell<bool>,
+    /// (band, level, charging) from the last time its battery was read, to notice changes
+    last_battery: RefCell<Option<(String, u8, bool)>>,
+    goal_notifications: Cell<bool>,
+    day_summary: Cell<bool>,
+    /// (band, read at, totals) from the last time the day's totals were read
+    day_totals: RefCell<Option<(String, DateTime<Local>, CurrentActivity)>>,
+    /// the id of the desktop notification that was last forwarded to the band
+    shown_notification: Cell<Option<u32>>,
+    /// alerts sent to the band, oldest first
+    alert_history: RefCell<VecDeque<SentAlert>>,
+    alert_history_next_id: Cell<u64>,
+    /// the band said the wearer fell asleep, and they haven't gotten up yet
+    asleep: Cell<bool>,
+    /// when they last woke up in the night
+    woke_at: Cell<Option<DateTime<Local>>>,
+    /// the current band, for reconnecting to it
+    current_discovered: RefCell<Option<DiscoveredDevice>>,
+    /// heart rate measurement was stopped because the band was taken off
+    heart_rate_paused: Cell<bool>,
+    movement_stop: RefCell<Option<async_channel::Sender<()>>>,
+    sync_stop: RefCell<Option<async_channel::Sender<()>>>,
+    /// cancels the activity sync shown in the progress dialog
+    sync_cancel: RefCell<Option<async_channel::Sender<()>>>,
+    briefing_stop: RefCell<Option<async_channel::Sender<()>>>,
+    sleep_summary_stop: RefCell<Option<async_channel::Sender<()>>>,
+    metric_sinks: RefCell<Vec<Rc<dyn MetricSink>>>,
+    /// workouts fetched from the current band
+    workouts: RefCell<Vec<WorkoutSummary>>,
     band_closed: RefCell<Option<(async_channel::Sender<()>, async_channel::Receiver<()>)>>,
+    /// an auto-connect is in progress
+    auto_connecting: Cell<bool>,
+    lock_timer_stop: RefCell<Option<async_channel::Sender<()>>>,
+    /// wrong PINs in a row - each one makes the lock page wait longer before the next try
+    failed_unlocks: Cell<u32>,
+    /// bands the user disconnected from this session
+    auto_connect_skip: RefCell<HashSet<String>>,
+    /// the toast being shown, if there is one
+    current_toast: RefCell<Option<Toast>>,
+    /// compact.css, loaded the first time it's turned on
+    compact_provider: std::cell::OnceCell<CssProvider>,
+    /// the latest warnings and errors
+    error_log: RefCell<VecDeque<(DateTime<Local>, Severity, String)>>,
+    /// we're holding a discovery reference for passive discovery
+    passive_discovery: Cell<bool>,
+    quick_toggles: Cell<QuickToggles>,
+    power_throttle: Cell<PowerThrottle>,
+    /// see `AppConf::music_idle_timeout_secs`
+    music_idle_timeout_secs: Cell<u32>,
+    /// the stale band check has run (or is running) - it only happens once per run
+    stale_bands_checked: Cell<bool>,
+    /// when the band's music screen is assumed to have closed (`None` if it's closed)
+    music_open_until: Cell<Option<Instant>>,
+    band_service: RefCell<Option<BandServiceHandle>>,
+    /// the computer's, from UPower (`None` if it isn't running)
+    power_state: Cell<Option<PowerState>>,
+    /// `shutdown` has been started
+    shutting_down: Cell<bool>,
+    /// `shutdown` is done, so the window can actually close
+    shut_down: Cell<bool>,
+    soak_stop: RefCell<Option<async_channel::Sender<()>>>,
+    /// kept around so its changed signal keeps firing
+    settings_monitor: RefCell<Option<gio::Settings>>,
     current_device: RwLock<Option<MiBand<'static>>>
 }
 
@@ -704,7 +4912,23 @@ impl ObjectSubclass for MiBandWindowImpl {
 
     fn class_init(class: &mut Self::Class) {
         class.bind_template();
-        class.bind_template_instance_callbacks()
+        class.bind_template_instance_callbacks();
+
+        // from the menu on the device list
+        class.install_action_async("win.bulk-operation", Some(VariantTy::STRING), |win, _, param| async move {
+            let Some(operation) = param.and_then(|p| p.str().and_then(BulkOperation::from_id)) else { return };
+            win.run_bulk_operation(operation).await;
+        });
+        class.install_action("win.mini-window", None, |win, _, _| {
+            let mini_window = &win.imp().mini_window;
+            mini_window.set_visible(!mini_window.is_visible());
+        });
+        class.install_action("win.alert-history", None, |win, _, _| {
+            win.imp().alert_history_dialog.present();
+        });
+        class.install_action("win.preferences", None, |win, _, _| {
+            win.imp().preferences_window.present();
+        });
     }
 
     fn instance_init(obj: &InitializingObject<Self>) {
@@ -716,6 +4940,21 @@ impl ObjectImpl for MiBandWindowImpl {
     fn constructed(&self) {
         self.parent_constructed();
         self.main_stack.set_visible_child_name("bluetooth-off");
+
+        // coming back to the window counts as needing the band
+        self.obj().connect_is_active_notify(|win| {
+            if !win.is_active() { return }
+            win.mark_active();
+            spawn_future_local(clone!(@weak win => async move {
+                win.wake_from_idle().await;
+            }));
+        });
+
+        for signal in [SIGINT, SIGTERM] {
+            unix_signal_add_local_once(signal, clone!(@weak self as win => move || {
+                win.obj().close();
+            }));
+        }
         
         spawn_future_local(clone!(@weak self as win => async move {
             if let Err(err) = win.obj().initialize().await {
@@ -727,5 +4966,54 @@ impl ObjectImpl for MiBandWindowImpl {
     }
 }
 impl WidgetImpl for MiBandWindowImpl {}
-impl WindowImpl for MiBandWindowImpl {}
+impl WindowImpl for MiBandWindowImpl {
+    // save everything and let go of the band first
+    fn close_request(&self) -> Propagation {
+        if self.shut_down.get() { return self.parent_close_request() }
+        if !self.shutting_down.replace(true) {
+            spawn_future_local(clone!(@weak self as win => async move {
+                win.obj().shutdown().await;
+                win.shut_down.set(true);
+                win.obj().close();
+            }));
+        }
+        Propagation::Stop
+    }
+}
 impl ApplicationWindowImpl for MiBandWindowImpl {}
+impl AdwApplicationWindowImpl for MiBandWindowImpl {}
+
+#[cfg(test)]
+mod tests {
+    use adw::subclass::prelude::*;
+    use gtk::{gio::resources_register_include, glib::{self, ToVariant}, prelude::*, Button};
+
+    use crate::store::AppLock;
+    use super::{MiBandWindow, LOCKED_ACTIONS};
+
+    #[test]
+    fn locked_actions_are_disabled_while_locked() {
+        // there's nothing to draw the window on
+        if gtk::init().is_err() { return }
+        adw::init().expect("can init libadwaita");
+        resources_register_include!("resources.gresource").expect("can register resources");
+
+        let win: MiBandWindow = glib::Object::new();
+        // a button's sensitivity follows its action
+        let buttons: Vec<Button> = LOCKED_ACTIONS.iter().map(|action| {
+            let button = Button::builder().action_name(*action).build();
+            if *action == "win.bulk-operation" {
+                button.set_action_target_value(Some(&"time".to_variant()));
+            }
+            win.imp().box_band_state.append(&button);
+            button
+        }).collect();
+        assert!(buttons.iter().all(Button::is_sensitive));
+
+        win.show_lock_page(&AppLock { enabled: true, ..Default::default() });
+        assert!(buttons.iter().all(|button| !button.is_sensitive()));
+
+        win.unlock_app();
+        assert!(buttons.iter().all(Button::is_sensitive));
+    }
+}
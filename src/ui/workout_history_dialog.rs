@@ -0,0 +1,144 @@
+use gtk::{glib::{self, Object}, prelude::*, subclass::prelude::*, Accessible, Align, Buildable, ConstraintTarget, Grid, Label, ListBoxRow, Native, Root, ShortcutManager, Widget, Window};
+
+use crate::{band::WorkoutSummary, store::DisplayFormat, utils::{format_date, format_distance, format_duration}};
+
+glib::wrapper! {
+    pub struct WorkoutHistoryDialog(ObjectSubclass<imp::WorkoutHistoryDialog>)
+        // https://docs.gtk.org/gtk4/class.Window.html#hierarchy
+        @extends Window, Widget,
+        @implements Accessible, Buildable, ConstraintTarget, Native, Root, ShortcutManager;
+}
+
+impl WorkoutHistoryDialog {
+    pub fn new() -> Self {
+        Object::builder().build()
+    }
+
+    /// show the spinner while workouts are being fetched from the band
+    pub fn set_loading(&self, loading: bool) {
+        let imp = self.imp();
+        imp.spinner.set_spinning(loading);
+        imp.spinner.set_visible(loading);
+        imp.btn_refresh.set_sensitive(!loading);
+    }
+
+    /// replace the history list with these workouts (newest first)
+    pub fn set_workouts(&self, workouts: &[WorkoutSummary], display_format: &DisplayFormat) {
+        let imp = self.imp();
+        let list = &imp.list_workouts;
+        while let Some(child) = list.first_child() {
+            list.remove(&child);
+        }
+        for workout in workouts.iter().rev() {
+            list.append(&build_row(workout, display_format));
+        }
+        imp.label_empty.set_visible(workouts.is_empty());
+        imp.box_export.set_sensitive(!workouts.is_empty());
+    }
+}
+
+fn build_row(workout: &WorkoutSummary, display_format: &DisplayFormat) -> ListBoxRow {
+    let grid = Grid::new();
+    grid.set_row_spacing(4);
+    grid.set_column_spacing(12);
+
+    let kind = Label::new(Some(&workout.kind.to_string()));
+    kind.set_halign(Align::Start);
+    kind.set_hexpand(true);
+    kind.add_css_class("heading");
+    grid.attach(&kind, 0, 0, 1, 1);
+
+    let start = Label::new(Some(&format_date(&workout.start, display_format.time_format)));
+    start.set_halign(Align::End);
+    start.add_css_class("dim-label");
+    grid.attach(&start, 1, 0, 1, 1);
+
+    let mut details = vec![format_duration(workout.duration)];
+    if workout.distance > 0.0 {
+        details.push(format_distance(workout.distance as u32, display_format.distance_unit));
+    }
+    details.push(format!("{} kcal", workout.calories));
+    if let Some(avg_hr) = workout.avg_hr {
+        details.push(format!("{avg_hr} bpm avg"));
+    }
+    let details = Label::new(Some(&details.join(" · ")));
+    details.set_halign(Align::Start);
+    grid.attach(&details, 0, 1, 2, 1);
+
+    let row = ListBoxRow::new();
+    row.set_activatable(false);
+    row.set_child(Some(&grid));
+    row
+}
+
+mod imp {
+    use std::sync::OnceLock;
+
+    use gtk::{glib::{self, subclass::{InitializingObject, Signal}}, prelude::*, subclass::prelude::*, template_callbacks, Box as GtkBox, Button, CompositeTemplate, Label, ListBox, Spinner, TemplateChild, Window};
+
+    #[derive(CompositeTemplate, Default)]
+    #[template(resource = "/me/grimsteel/miband4-gtk/workout_history_dialog.ui")]
+    pub struct WorkoutHistoryDialog {
+        #[template_child]
+        pub list_workouts: TemplateChild<ListBox>,
+        #[template_child]
+        pub label_empty: TemplateChild<Label>,
+        #[template_child]
+        pub spinner: TemplateChild<Spinner>,
+        #[template_child]
+        pub btn_refresh: TemplateChild<Button>,
+        #[template_child]
+        pub box_export: TemplateChild<GtkBox>
+    }
+
+    #[template_callbacks]
+    impl WorkoutHistoryDialog {
+        #[template_callback]
+        fn handle_refresh_clicked(&self) {
+            self.obj().emit_by_name::<()>("refresh", &[]);
+        }
+
+        #[template_callback]
+        fn handle_export_clicked(&self, button: &Button) {
+            // the button names are the export formats
+            self.obj().emit_by_name::<()>("export", &[&button.widget_name().to_string()]);
+        }
+
+        #[template_callback]
+        fn handle_close_clicked(&self) {
+            self.obj().close();
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for WorkoutHistoryDialog {
+        const NAME: &'static str = "MiBand4WorkoutHistoryDialog";
+        type Type = super::WorkoutHistoryDialog;
+        type ParentType = Window;
+
+        fn class_init(class: &mut Self::Class) {
+            class.bind_template();
+            class.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for WorkoutHistoryDialog {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![
+                    // fetch new workouts from the band
+                    Signal::builder("refresh").build(),
+                    // param is the export format ("gpx", "tcx", or "csv")
+                    Signal::builder("export").param_types([String::static_type()]).build()
+                ]
+            })
+        }
+    }
+    impl WidgetImpl for WorkoutHistoryDialog {}
+    impl WindowImpl for WorkoutHistoryDialog {}
+}
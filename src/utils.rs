@@ -1,11 +1,61 @@
-use std::fmt::Display;
+use std::{fmt::Display, time::Duration};
 
 use aes::{cipher::{block_padding::Pkcs7, BlockEncryptMut, KeyIvInit}, Aes128};
 use cbc::Encryptor;
-use chrono::{DateTime, TimeZone};
+use chrono::{DateTime, Local, TimeZone};
+
+use crate::store::{DistanceUnit, TimeFormat};
 
 pub const APP_ID: &'static str = "me.grimsteel.miband4-gtk";
 
+/// byte order for `encode_uint`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ByteOrder {
+    Little,
+    Big
+}
+
+/// `value` in `width` bytes (at most 8), in the given order - `None` if it doesn't fit
+/// the band uses little endian for almost everything
+pub fn encode_uint(value: u64, width: usize, order: ByteOrder) -> Option<Vec<u8>> {
+    if width > 8 || (width < 8 && value >> (width * 8) != 0) { return None }
+    let mut bytes = value.to_le_bytes()[..width].to_vec();
+    if order == ByteOrder::Big {
+        bytes.reverse();
+    }
+    Some(bytes)
+}
+
+/// the longest prefix of `string` that fits in `max_bytes` without splitting a character
+pub fn truncate_utf8(string: &str, max_bytes: usize) -> &str {
+    if string.len() <= max_bytes { return string }
+    let mut end = max_bytes;
+    while !string.is_char_boundary(end) {
+        end -= 1;
+    }
+    &string[..end]
+}
+
+/// swap typographic punctuation for plain ASCII, and drop the invisible characters emoji are built from
+/// the band's font doesn't have any of these, so they'd show up as boxes
+pub fn transliterate(string: &str) -> String {
+    let mut simplified = String::with_capacity(string.len());
+    for c in string.chars() {
+        match c {
+            '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{2032}' => simplified.push('\''),
+            '\u{201c}' | '\u{201d}' | '\u{201e}' | '\u{2033}' => simplified.push('"'),
+            '\u{2010}'..='\u{2015}' | '\u{2212}' => simplified.push('-'),
+            '\u{2026}' => simplified.push_str("..."),
+            '\u{2022}' | '\u{00b7}' => simplified.push('*'),
+            '\u{00a0}' | '\u{2002}'..='\u{200a}' | '\u{202f}' => simplified.push(' '),
+            // zero width joiners/spaces, variation selectors and skin tones
+            '\u{200b}'..='\u{200d}' | '\u{2060}' | '\u{fe00}'..='\u{fe0f}' | '\u{1f3fb}'..='\u{1f3ff}' => {},
+            c => simplified.push(c)
+        }
+    }
+    simplified
+}
+
 pub fn decode_hex(hex_string: &str) -> Option<Vec<u8>> {
     // make sure it's not odd
     if hex_string.len() & 0b1 == 0b1 { return None; }
@@ -29,12 +79,45 @@ pub fn is_hex_string(string: &str) -> bool {
     string.chars().all(|c| (c >= '0' && c <= '9') || (c >= 'A' && c <= 'F') || (c >= 'a' && c <= 'f'))
 }
 
-pub fn format_date<T: TimeZone<Offset: Display>>(date: &DateTime<T>) -> String {
-    format!("{}", date.format("%m/%d/%y %I:%M %p"))
+/// the time of day - the date is written the same way for both formats
+fn time_pattern(time_format: TimeFormat) -> &'static str {
+    match time_format {
+        TimeFormat::H12 => "%I:%M %p",
+        TimeFormat::H24 => "%H:%M"
+    }
+}
+
+pub fn format_date<T: TimeZone<Offset: Display>>(date: &DateTime<T>, time_format: TimeFormat) -> String {
+    format!("{} {}", date.format("%m/%d/%y"), date.format(time_pattern(time_format)))
+}
+
+/// just the time for today, otherwise the whole date
+pub fn format_recent_date(date: &DateTime<Local>, time_format: TimeFormat) -> String {
+    if date.date_naive() != Local::now().date_naive() {
+        return format_date(date, time_format);
+    }
+    format!("{}", date.format(time_pattern(time_format)))
+}
+
+/// returns the distance formatted in the given unit
+pub fn format_distance(meters: u32, unit: DistanceUnit) -> String {
+    match unit {
+        DistanceUnit::Metric => meters_to_metric(meters),
+        DistanceUnit::Imperial => meters_to_imperial(meters)
+    }
+}
+
+/// returns the equivalent distance in meters or kilometers
+pub fn meters_to_metric(meters: u32) -> String {
+    if meters < 1000 {
+        format!("{} m", meters)
+    } else {
+        format!("{:.3} km", (meters as f64) / 1000.0)
+    }
 }
 
 /// returns the equivalent distance in feet or miles
-pub fn meters_to_imperial(meters: u16) -> String {
+pub fn meters_to_imperial(meters: u32) -> String {
     // below 0.1 miles (528 feet, 161 meters), display in feet
     if meters < 161 {
         format!("{:.3} ft", (meters as f64) * 3.281)
@@ -42,3 +125,61 @@ pub fn meters_to_imperial(meters: u16) -> String {
         format!("{:.3} mi", (meters as f64) / 1609.344)
     }
 }
+
+/// icon showing the strength of a bluetooth signal
+pub fn signal_icon_name(rssi: Option<i16>) -> &'static str {
+    match rssi {
+        None => "network-cellular-signal-none-symbolic",
+        Some(rssi) if rssi >= -60 => "network-cellular-signal-excellent-symbolic",
+        Some(rssi) if rssi >= -70 => "network-cellular-signal-good-symbolic",
+        Some(rssi) if rssi >= -80 => "network-cellular-signal-ok-symbolic",
+        Some(_) => "network-cellular-signal-weak-symbolic"
+    }
+}
+
+/// h:mm:ss, or m:ss for anything under an hour
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, (seconds / 60) % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    #[test]
+    fn encode_uint_widths() {
+        assert_eq!(encode_uint(0, 1, ByteOrder::Little), Some(vec![0x00]));
+        assert_eq!(encode_uint(0, 0, ByteOrder::Little), Some(vec![]));
+        assert_eq!(encode_uint(u8::MAX as u64, 1, ByteOrder::Little), Some(vec![0xff]));
+        assert_eq!(encode_uint(u16::MAX as u64, 2, ByteOrder::Little), Some(vec![0xff, 0xff]));
+        assert_eq!(encode_uint(u32::MAX as u64, 4, ByteOrder::Big), Some(vec![0xff; 4]));
+        assert_eq!(encode_uint(u64::MAX, 8, ByteOrder::Little), Some(vec![0xff; 8]));
+        assert_eq!(encode_uint(0x0102, 2, ByteOrder::Little), Some(vec![0x02, 0x01]));
+        assert_eq!(encode_uint(0x0102, 2, ByteOrder::Big), Some(vec![0x01, 0x02]));
+        assert_eq!(encode_uint(0x0102, 3, ByteOrder::Big), Some(vec![0x00, 0x01, 0x02]));
+    }
+
+    #[test]
+    fn encode_uint_overflow() {
+        assert_eq!(encode_uint(u8::MAX as u64 + 1, 1, ByteOrder::Little), None);
+        assert_eq!(encode_uint(u16::MAX as u64 + 1, 2, ByteOrder::Little), None);
+        assert_eq!(encode_uint(u32::MAX as u64 + 1, 4, ByteOrder::Big), None);
+        assert_eq!(encode_uint(1, 0, ByteOrder::Little), None);
+        assert_eq!(encode_uint(0, 9, ByteOrder::Little), None);
+    }
+
+    #[test]
+    fn time_format_only_changes_the_time() {
+        let date = Utc.with_ymd_and_hms(2024, 5, 10, 15, 4, 0).unwrap();
+        assert_eq!(format_date(&date, TimeFormat::H12), "05/10/24 03:04 PM");
+        assert_eq!(format_date(&date, TimeFormat::H24), "05/10/24 15:04");
+    }
+}
@@ -0,0 +1,129 @@
+use std::{error::Error as StdError, fmt::{self, Display, Formatter}};
+
+use gtk::gio;
+use serde_json::Value;
+
+use crate::store::{TemperatureUnit, WeatherLanguage};
+
+const FORECAST_URL: &'static str = "https://api.open-meteo.com/v1/forecast";
+
+#[derive(Debug)]
+pub enum Error {
+    HttpError(Box<ureq::Error>),
+    IoError(std::io::Error),
+    InvalidResponse
+}
+
+impl From<ureq::Error> for Error {
+    fn from(value: ureq::Error) -> Self {
+        Self::HttpError(Box::new(value))
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::IoError(value)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HttpError(err) => write!(f, "HTTP error: {}", err),
+            Self::IoError(err) => write!(f, "I/O error: {}", err),
+            Self::InvalidResponse => write!(f, "The weather service sent an invalid response")
+        }
+    }
+}
+impl StdError for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WeatherCondition {
+    Clear,
+    PartlyCloudy,
+    Cloudy,
+    Fog,
+    Drizzle,
+    Rain,
+    Snow,
+    Showers,
+    Thunderstorm
+}
+
+impl WeatherCondition {
+    /// WMO weather interpretation codes
+    fn from_wmo_code(code: u64) -> Self {
+        match code {
+            0 => Self::Clear,
+            1 | 2 => Self::PartlyCloudy,
+            3 => Self::Cloudy,
+            45 | 48 => Self::Fog,
+            51..=57 => Self::Drizzle,
+            61..=67 => Self::Rain,
+            71..=77 | 85 | 86 => Self::Snow,
+            80..=82 => Self::Showers,
+            _ => Self::Thunderstorm
+        }
+    }
+
+    /// the condition in `language`, for the band
+    pub fn localized(&self, language: WeatherLanguage) -> &'static str {
+        // Clear, Partly cloudy, Cloudy, Fog, Drizzle, Rain, Snow, Showers, Thunderstorms
+        let names = match language {
+            WeatherLanguage::English => ["Clear", "Partly cloudy", "Cloudy", "Fog", "Drizzle", "Rain", "Snow", "Showers", "Thunderstorms"],
+            WeatherLanguage::German => ["Klar", "Teils bewölkt", "Bewölkt", "Nebel", "Nieselregen", "Regen", "Schnee", "Schauer", "Gewitter"],
+            WeatherLanguage::Spanish => ["Despejado", "Parcialmente nublado", "Nublado", "Niebla", "Llovizna", "Lluvia", "Nieve", "Chubascos", "Tormentas"],
+            WeatherLanguage::French => ["Dégagé", "Partiellement nuageux", "Nuageux", "Brouillard", "Bruine", "Pluie", "Neige", "Averses", "Orages"],
+            WeatherLanguage::Italian => ["Sereno", "Parzialmente nuvoloso", "Nuvoloso", "Nebbia", "Pioviggine", "Pioggia", "Neve", "Rovesci", "Temporali"],
+            WeatherLanguage::Portuguese => ["Limpo", "Parcialmente nublado", "Nublado", "Nevoeiro", "Chuvisco", "Chuva", "Neve", "Aguaceiros", "Trovoadas"],
+            WeatherLanguage::Russian => ["Ясно", "Переменная облачность", "Облачно", "Туман", "Морось", "Дождь", "Снег", "Ливни", "Гроза"],
+            WeatherLanguage::Chinese => ["晴", "多云", "阴", "雾", "毛毛雨", "雨", "雪", "阵雨", "雷暴"]
+        };
+        names[*self as usize]
+    }
+}
+
+impl Display for WeatherCondition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.localized(WeatherLanguage::English))
+    }
+}
+
+/// how the weather is written out for the band
+#[derive(Debug, Clone, Copy)]
+pub struct WeatherFormat {
+    pub unit: TemperatureUnit,
+    pub language: WeatherLanguage
+}
+
+/// today's weather - temperatures are in °C
+#[derive(Debug, Clone)]
+pub struct WeatherReport {
+    pub condition: WeatherCondition,
+    pub temperature: f64,
+    pub high: f64,
+    pub low: f64
+}
+
+/// get the current weather and today's forecast for a location from Open-Meteo
+pub async fn fetch_weather(latitude: f64, longitude: f64) -> Result<WeatherReport> {
+    let url = format!(
+        "{FORECAST_URL}?latitude={latitude}&longitude={longitude}&current=temperature_2m,weather_code\
+        &daily=temperature_2m_max,temperature_2m_min&timezone=auto&forecast_days=1"
+    );
+    // ureq blocks, so keep it off the main thread
+    let response: Value = gio::spawn_blocking(move || -> Result<Value> {
+        Ok(ureq::get(&url).call()?.into_json()?)
+    }).await.map_err(|_| Error::InvalidResponse)??;
+
+    let current = &response["current"];
+    let daily = &response["daily"];
+    Some(WeatherReport {
+        condition: WeatherCondition::from_wmo_code(current["weather_code"].as_u64()?),
+        temperature: current["temperature_2m"].as_f64()?,
+        high: daily["temperature_2m_max"][0].as_f64()?,
+        low: daily["temperature_2m_min"][0].as_f64()?
+    }).ok_or(Error::InvalidResponse)
+}